@@ -0,0 +1,175 @@
+use std::path::Path;
+
+use steam_shortcuts_util::parse_shortcuts;
+use time::{Date, Month, OffsetDateTime, PrimitiveDateTime, Time};
+
+use crate::{
+    config::get_backups_flder,
+    steam::{get_shortcuts_paths, SteamSettings},
+};
+
+use super::{Snapshot, SnapshotManifest, UserSnapshot};
+
+/// Restores a single shortcuts file, the file name has to start with the id of the Steam user
+pub fn restore_backup(steam_settings: &SteamSettings, shortcut_path: &Path) -> bool {
+    let file_name = shortcut_path.file_name();
+    let paths = get_shortcuts_paths(steam_settings);
+    if let (Ok(paths), Some(file_name)) = (paths, file_name) {
+        for user in paths {
+            if let Some(user_shortcut_path) = user.shortcut_path {
+                if file_name.to_string_lossy().starts_with(&user.user_id) {
+                    match std::fs::copy(shortcut_path, Path::new(&user_shortcut_path)) {
+                        Ok(_) => {
+                            log!("Restored shortcut to path : {user_shortcut_path}");
+                        }
+                        Err(err) => {
+                            eprintln!(
+                                "Failed to restored shortcut to path : {user_shortcut_path} gave error: {err:?}"
+                            );
+                        }
+                    }
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Older versions of BoilR saved backups as `{user_id}-{date}-shortcuts.vdf` files,
+/// these are moved into snapshots so they show up with the rest.
+pub fn migrate_legacy_backups() {
+    migrate_legacy_backups_in(&get_backups_flder());
+}
+
+fn migrate_legacy_backups_in(backup_folder: &Path) {
+    let Ok(files) = std::fs::read_dir(backup_folder) else {
+        return;
+    };
+    for file in files.flatten() {
+        let path = file.path();
+        if !path.is_file() {
+            continue;
+        }
+        let file_name = file.file_name().to_string_lossy().to_string();
+        if let Some((user_id, date)) = parse_legacy_name(&file_name) {
+            if let Err(err) = migrate_legacy_backup(backup_folder, &path, user_id, date) {
+                eprintln!("Could not move old backup {path:?}: {err}");
+            }
+        }
+    }
+}
+
+fn migrate_legacy_backup(
+    backup_folder: &Path,
+    path: &Path,
+    user_id: &str,
+    date: &str,
+) -> eyre::Result<()> {
+    let content = std::fs::read(path)?;
+    let shortcuts = parse_shortcuts(&content)
+        .map(|s| s.len())
+        .map_err(|e| eyre::eyre!("Could not parse shortcuts: {e:?}"))?;
+    let created_at = match parse_legacy_date(date) {
+        Some(created_at) => created_at,
+        None => std::fs::metadata(path)?
+            .modified()
+            .map(OffsetDateTime::from)
+            .unwrap_or_else(|_| OffsetDateTime::now_utc())
+            .unix_timestamp(),
+    };
+
+    let snapshot_path = backup_folder.join(date);
+    let mut snapshot = Snapshot {
+        id: date.to_string(),
+        manifest: std::fs::read_to_string(snapshot_path.join("snapshot.json"))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or(SnapshotManifest {
+                created_at,
+                legacy: true,
+                ..Default::default()
+            }),
+        path: snapshot_path,
+    };
+    let user_folder = snapshot.path.join("users").join(user_id);
+    std::fs::create_dir_all(&user_folder)?;
+    std::fs::rename(path, user_folder.join("shortcuts.vdf"))?;
+    snapshot.manifest.users.push(UserSnapshot {
+        user_id: user_id.to_string(),
+        shortcuts: Some(shortcuts),
+        ..Default::default()
+    });
+    snapshot.save_manifest()
+}
+
+fn parse_legacy_name(file_name: &str) -> Option<(&str, &str)> {
+    let without_suffix = file_name.strip_suffix("-shortcuts.vdf")?;
+    let (user_id, date) = without_suffix.split_once('-')?;
+    if user_id.chars().all(|c| c.is_ascii_digit()) && !date.is_empty() {
+        Some((user_id, date))
+    } else {
+        None
+    }
+}
+
+/// Parses dates like 2023-01-02-10-11-12 into a unix timestamp
+fn parse_legacy_date(date: &str) -> Option<i64> {
+    let numbers: Vec<u16> = date.split('-').map(|n| n.parse().ok()).collect::<Option<_>>()?;
+    let [year, month, day, hour, minute, second] = numbers.as_slice() else {
+        return None;
+    };
+    let month = Month::try_from(u8::try_from(*month).ok()?).ok()?;
+    let date = Date::from_calendar_date(i32::from(*year), month, u8::try_from(*day).ok()?).ok()?;
+    let time = Time::from_hms(
+        u8::try_from(*hour).ok()?,
+        u8::try_from(*minute).ok()?,
+        u8::try_from(*second).ok()?,
+    )
+    .ok()?;
+    Some(PrimitiveDateTime::new(date, time).assume_utc().unix_timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
+    use super::*;
+
+    #[test]
+    fn moves_legacy_backups_into_snapshots_kept_by_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("12345-2023-01-02-10-11-12-shortcuts.vdf"),
+            steam_shortcuts_util::shortcuts_to_bytes(&vec![]),
+        )
+        .unwrap();
+        migrate_legacy_backups_in(dir.path());
+
+        let folder = dir.path().join("2023-01-02-10-11-12");
+        assert!(folder.join("users/12345/shortcuts.vdf").exists());
+        let manifest: SnapshotManifest =
+            serde_json::from_str(&std::fs::read_to_string(folder.join("snapshot.json")).unwrap())
+                .unwrap();
+        assert!(manifest.legacy);
+        assert_eq!(Some(0), manifest.users[0].shortcuts);
+    }
+
+    #[test]
+    fn parses_legacy_names() {
+        assert_eq!(
+            Some(("12345", "2023-01-02-10-11-12")),
+            parse_legacy_name("12345-2023-01-02-10-11-12-shortcuts.vdf")
+        );
+        assert_eq!(None, parse_legacy_name("shortcuts.vdf"));
+        assert_eq!(None, parse_legacy_name("abc-2023-shortcuts.vdf"));
+    }
+
+    #[test]
+    fn parses_legacy_dates() {
+        assert_eq!(Some(1672654272), parse_legacy_date("2023-01-02-10-11-12"));
+        assert_eq!(None, parse_legacy_date("2023-13-02-10-11-12"));
+        assert_eq!(None, parse_legacy_date("yesterday"));
+    }
+}
@@ -0,0 +1,9 @@
+mod legacy;
+mod retention;
+mod settings;
+mod snapshot;
+
+pub use legacy::{migrate_legacy_backups, restore_backup};
+pub use retention::apply_retention;
+pub use settings::BackupSettings;
+pub use snapshot::*;
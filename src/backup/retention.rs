@@ -0,0 +1,124 @@
+use time::OffsetDateTime;
+
+use super::{delete_snapshot, BackupSettings, Snapshot};
+
+const SECONDS_IN_DAY: i64 = 60 * 60 * 24;
+
+/// Deletes the snapshots that fall outside of the retention settings and returns their ids.
+/// Labeled snapshots and backups of older versions of BoilR are always kept and do not count towards `keep_last`.
+pub fn apply_retention(settings: &BackupSettings, snapshots: &[Snapshot]) -> Vec<String> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    snapshots_to_remove(settings, snapshots, now)
+        .into_iter()
+        .filter(|snapshot| match delete_snapshot(snapshot) {
+            Ok(_) => true,
+            Err(err) => {
+                eprintln!("Could not delete old backup {}: {err}", snapshot.id);
+                false
+            }
+        })
+        .map(|snapshot| snapshot.id.clone())
+        .collect()
+}
+
+fn snapshots_to_remove<'a>(
+    settings: &BackupSettings,
+    snapshots: &'a [Snapshot],
+    now: i64,
+) -> Vec<&'a Snapshot> {
+    let mut unlabeled: Vec<&Snapshot> = snapshots
+        .iter()
+        .filter(|s| s.manifest.label.is_none() && !s.manifest.legacy)
+        .collect();
+    unlabeled.sort_by_key(|s| std::cmp::Reverse(s.manifest.created_at));
+    let max_age = i64::try_from(settings.keep_days)
+        .unwrap_or(i64::MAX)
+        .saturating_mul(SECONDS_IN_DAY);
+    unlabeled
+        .into_iter()
+        .enumerate()
+        .filter(|(index, snapshot)| {
+            let too_many = settings.keep_last > 0 && *index >= settings.keep_last;
+            let too_old =
+                settings.keep_days > 0 && now - snapshot.manifest.created_at > max_age;
+            too_many || too_old
+        })
+        .map(|(_, snapshot)| snapshot)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::super::SnapshotManifest;
+    use super::*;
+
+    fn snapshot(id: &str, days_old: i64, label: Option<&str>) -> Snapshot {
+        Snapshot {
+            id: id.to_string(),
+            path: PathBuf::from(id),
+            manifest: SnapshotManifest {
+                created_at: 100 * SECONDS_IN_DAY - days_old * SECONDS_IN_DAY,
+                label: label.map(|l| l.to_string()),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn ids(snapshots: Vec<&Snapshot>) -> Vec<&str> {
+        snapshots.iter().map(|s| s.id.as_str()).collect()
+    }
+
+    #[test]
+    fn keeps_the_newest() {
+        let snapshots = vec![
+            snapshot("old", 3, None),
+            snapshot("new", 1, None),
+            snapshot("middle", 2, None),
+        ];
+        let settings = BackupSettings {
+            keep_last: 2,
+            keep_days: 0,
+        };
+        let removed = snapshots_to_remove(&settings, &snapshots, 100 * SECONDS_IN_DAY);
+        assert_eq!(vec!["old"], ids(removed));
+    }
+
+    #[test]
+    fn removes_old_snapshots_but_keeps_labeled() {
+        let snapshots = vec![
+            snapshot("old", 10, None),
+            snapshot("labeled", 10, Some("Before reinstall")),
+            snapshot("new", 1, None),
+        ];
+        let settings = BackupSettings {
+            keep_last: 0,
+            keep_days: 7,
+        };
+        let removed = snapshots_to_remove(&settings, &snapshots, 100 * SECONDS_IN_DAY);
+        assert_eq!(vec!["old"], ids(removed));
+    }
+
+    #[test]
+    fn keeps_legacy_backups() {
+        let mut legacy = snapshot("legacy", 1000, None);
+        legacy.manifest.legacy = true;
+        let snapshots = vec![snapshot("new", 1, None), legacy];
+        let settings = BackupSettings {
+            keep_last: 1,
+            keep_days: 7,
+        };
+        assert!(snapshots_to_remove(&settings, &snapshots, 100 * SECONDS_IN_DAY).is_empty());
+    }
+
+    #[test]
+    fn zero_keeps_everything() {
+        let snapshots = vec![snapshot("a", 1000, None), snapshot("b", 2000, None)];
+        let settings = BackupSettings {
+            keep_last: 0,
+            keep_days: 0,
+        };
+        assert!(snapshots_to_remove(&settings, &snapshots, 100 * SECONDS_IN_DAY).is_empty());
+    }
+}
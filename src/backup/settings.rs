@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupSettings {
+    /// How many backups to keep, 0 keeps all of them
+    pub keep_last: usize,
+    /// How many days to keep backups for, 0 keeps them forever
+    pub keep_days: u64,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            keep_last: 20,
+            keep_days: 0,
+        }
+    }
+}
@@ -0,0 +1,620 @@
+use std::{
+    collections::HashSet,
+    fs::create_dir_all,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use steam_shortcuts_util::{parse_shortcuts, shortcut::ShortcutOwned};
+use time::{format_description, OffsetDateTime};
+
+use crate::{
+    config::{
+        get_adopted_file, get_app_ids_file, get_backups_flder, get_cache_file, get_collection_tags_file,
+        get_config_file, get_config_folder, get_duplicates_file, get_overrides_file, get_proton_tools_file,
+        get_renames_file,
+    },
+    settings::Settings,
+    steam::{
+        get_install_users, get_shortcuts_for_user, get_shortcuts_paths, get_vdf_path, SteamInstall, SteamSettings,
+        SteamUsersInfo,
+    },
+    steamgriddb::ImageType,
+    sync::{diff_shortcuts, IsBoilRShortcut, ShortcutChange},
+};
+
+use super::apply_retention;
+
+const MANIFEST_FILE: &str = "snapshot.json";
+const SHORTCUTS_FILE: &str = "shortcuts.vdf";
+const COLLECTIONS_FILE: &str = "localconfig.vdf";
+const GRID_FOLDER: &str = "grid";
+const CONFIG_FOLDER: &str = "boilr";
+#[cfg(target_family = "unix")]
+const PROTON_FILE: &str = "CompatToolMapping.vdf";
+const DATE_FORMAT: &str = "[year]-[month]-[day]-[hour]-[minute]-[second]";
+
+/// Describes what a snapshot contains, saved as json inside the snapshot folder
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    /// Unix timestamp of when the snapshot was taken
+    pub created_at: i64,
+    pub label: Option<String>,
+    pub users: Vec<UserSnapshot>,
+    pub proton: bool,
+    pub config_files: Vec<String>,
+    /// Moved from a backup of an older version of BoilR, these are only deleted by hand
+    #[serde(default)]
+    pub legacy: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserSnapshot {
+    pub user_id: String,
+    /// Number of shortcuts, none if the user had no shortcuts file
+    pub shortcuts: Option<usize>,
+    /// File names of the images of BoilR shortcuts
+    pub images: Vec<String>,
+    pub collections: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub id: String,
+    pub path: PathBuf,
+    pub manifest: SnapshotManifest,
+}
+
+/// The parts of a snapshot that can be restored on their own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotPart {
+    Shortcuts,
+    Images,
+    Collections,
+    Proton,
+    Config,
+}
+
+pub const ALL_PARTS: [SnapshotPart; 5] = [
+    SnapshotPart::Shortcuts,
+    SnapshotPart::Images,
+    SnapshotPart::Collections,
+    SnapshotPart::Proton,
+    SnapshotPart::Config,
+];
+
+impl SnapshotPart {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SnapshotPart::Shortcuts => "shortcuts",
+            SnapshotPart::Images => "images",
+            SnapshotPart::Collections => "collections",
+            SnapshotPart::Proton => "proton",
+            SnapshotPart::Config => "config",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        ALL_PARTS.into_iter().find(|part| part.name() == name)
+    }
+}
+
+/// The difference between the current state of a user and a snapshot,
+/// the changes are what restoring the snapshot would do
+#[derive(Debug, Clone)]
+pub struct SnapshotDiff {
+    pub user_id: String,
+    pub shortcut_changes: Vec<ShortcutChange>,
+    /// Images in the snapshot that are no longer in the grid folder
+    pub images_missing: usize,
+}
+
+impl Snapshot {
+    pub fn title(&self) -> String {
+        match &self.manifest.label {
+            Some(label) => format!("{} - {label}", self.id),
+            None => self.id.clone(),
+        }
+    }
+
+    /// A short description of what the snapshot contains
+    pub fn summary(&self) -> String {
+        let manifest = &self.manifest;
+        let shortcuts: usize = manifest.users.iter().filter_map(|u| u.shortcuts).sum();
+        let images: usize = manifest.users.iter().map(|u| u.images.len()).sum();
+        let mut parts = vec![
+            format!("{} user(s)", manifest.users.len()),
+            format!("{shortcuts} shortcuts"),
+            format!("{images} images"),
+        ];
+        if manifest.users.iter().any(|u| u.collections) {
+            parts.push("collections".to_string());
+        }
+        if manifest.proton {
+            parts.push("proton mappings".to_string());
+        }
+        if !manifest.config_files.is_empty() {
+            parts.push("BoilR config".to_string());
+        }
+        if manifest.legacy {
+            parts.push("from an older BoilR".to_string());
+        }
+        parts.join(", ")
+    }
+
+    fn user_folder(&self, user_id: &str) -> PathBuf {
+        self.path.join("users").join(user_id)
+    }
+
+    pub(super) fn save_manifest(&self) -> eyre::Result<()> {
+        let content = serde_json::to_string_pretty(&self.manifest)?;
+        std::fs::write(self.path.join(MANIFEST_FILE), content)?;
+        Ok(())
+    }
+}
+
+/// Takes a snapshot of the shortcuts, BoilR images, collections, proton mappings and BoilR config
+pub fn create_snapshot(install: &SteamInstall, label: Option<String>) -> eyre::Result<Snapshot> {
+    let config_files = [
+        get_config_file(),
+        get_renames_file(),
+        get_cache_file(),
+        get_app_ids_file(),
+        get_overrides_file(),
+        get_adopted_file(),
+        get_proton_tools_file(),
+        get_collection_tags_file(),
+        get_duplicates_file(),
+    ];
+    create_snapshot_in(&get_backups_flder(), install, label, &config_files)
+}
+
+fn create_snapshot_in(
+    backup_folder: &Path,
+    install: &SteamInstall,
+    label: Option<String>,
+    config_files: &[PathBuf],
+) -> eyre::Result<Snapshot> {
+    let previous_snapshots = load_snapshots_in(backup_folder);
+    let now = OffsetDateTime::now_utc();
+    let id = unique_id(backup_folder, &format_date(now)?);
+    let path = backup_folder.join(&id);
+    create_dir_all(&path)?;
+    let mut snapshot = Snapshot {
+        id,
+        path,
+        manifest: SnapshotManifest {
+            created_at: now.unix_timestamp(),
+            label: clean_label(label),
+            ..Default::default()
+        },
+    };
+    for user in get_install_users(install)? {
+        let previous_images = previous_images_folder(&previous_snapshots, &user.user_id);
+        let user_snapshot = snapshot_user(
+            install,
+            &user,
+            &snapshot.user_folder(&user.user_id),
+            previous_images.as_deref(),
+        )?;
+        snapshot.manifest.users.push(user_snapshot);
+    }
+    #[cfg(target_family = "unix")]
+    if let Ok(section) = crate::steam::get_compat_tool_mapping(install) {
+        std::fs::write(snapshot.path.join(PROTON_FILE), section)?;
+        snapshot.manifest.proton = true;
+    }
+    let config_folder = snapshot.path.join(CONFIG_FOLDER);
+    create_dir_all(&config_folder)?;
+    for file in config_files {
+        if let Some(file_name) = file.file_name().filter(|_| file.exists()) {
+            std::fs::copy(file, config_folder.join(file_name))?;
+            let file_name = file_name.to_string_lossy().to_string();
+            snapshot.manifest.config_files.push(file_name);
+        }
+    }
+    snapshot.save_manifest()?;
+    log!("Created backup {} with {}", snapshot.id, snapshot.summary());
+    Ok(snapshot)
+}
+
+/// Takes a snapshot and removes the snapshots that are outside the retention settings
+pub fn create_snapshot_with_retention(
+    settings: &Settings,
+    install: &SteamInstall,
+    label: Option<String>,
+) -> eyre::Result<Snapshot> {
+    let snapshot = create_snapshot(install, label)?;
+    apply_retention(&settings.backup, &load_snapshots());
+    Ok(snapshot)
+}
+
+/// The images of the newest snapshot that has images of the user
+fn previous_images_folder(snapshots: &[Snapshot], user_id: &str) -> Option<PathBuf> {
+    snapshots
+        .iter()
+        .find(|snapshot| {
+            snapshot
+                .manifest
+                .users
+                .iter()
+                .any(|user| user.user_id == user_id && !user.images.is_empty())
+        })
+        .map(|snapshot| snapshot.user_folder(user_id).join(GRID_FOLDER))
+}
+
+fn snapshot_user(
+    install: &SteamInstall,
+    user: &SteamUsersInfo,
+    folder: &Path,
+    previous_images: Option<&Path>,
+) -> eyre::Result<UserSnapshot> {
+    create_dir_all(folder)?;
+    let mut result = UserSnapshot {
+        user_id: user.user_id.clone(),
+        ..Default::default()
+    };
+    if let Some(shortcut_path) = &user.shortcut_path {
+        std::fs::copy(shortcut_path, folder.join(SHORTCUTS_FILE))?;
+        let shortcuts = get_shortcuts_for_user(user)
+            .map(|info| info.shortcuts)
+            .unwrap_or_default();
+        result.shortcuts = Some(shortcuts.len());
+        result.images = copy_boilr_images(
+            user,
+            &shortcuts,
+            &folder.join(GRID_FOLDER),
+            previous_images,
+        )?;
+    }
+    if let Some(localconfig) = get_vdf_path(install, &user.user_id) {
+        std::fs::copy(localconfig, folder.join(COLLECTIONS_FILE))?;
+        result.collections = true;
+    }
+    Ok(result)
+}
+
+fn copy_boilr_images(
+    user: &SteamUsersInfo,
+    shortcuts: &[ShortcutOwned],
+    target: &Path,
+    previous_images: Option<&Path>,
+) -> eyre::Result<Vec<String>> {
+    let image_names: HashSet<String> = shortcuts
+        .iter()
+        .filter(|s| s.is_boilr_shortcut())
+        .flat_map(|s| {
+            ImageType::all()
+                .iter()
+                .map(|image_type| image_type.file_name_no_extension(s.app_id))
+        })
+        .collect();
+    let mut images = vec![];
+    let Ok(files) = std::fs::read_dir(grid_folder(user)) else {
+        return Ok(images);
+    };
+    create_dir_all(target)?;
+    for file in files.flatten() {
+        let path = file.path();
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        if path.is_file() && image_names.contains(stem.as_ref()) {
+            let file_name = file.file_name();
+            let previous = previous_images.map(|folder| folder.join(&file_name));
+            copy_image(&path, &target.join(&file_name), previous.as_deref())?;
+            images.push(file_name.to_string_lossy().to_string());
+        }
+    }
+    images.sort();
+    Ok(images)
+}
+
+/// Images that did not change since the previous snapshot are hard linked to it instead of copied again
+fn copy_image(source: &Path, target: &Path, previous: Option<&Path>) -> eyre::Result<()> {
+    if let Some(previous) = previous.filter(|previous| same_content(source, previous)) {
+        if std::fs::hard_link(previous, target).is_ok() {
+            return Ok(());
+        }
+    }
+    std::fs::copy(source, target)?;
+    Ok(())
+}
+
+fn same_content(a: &Path, b: &Path) -> bool {
+    let same_size = match (std::fs::metadata(a), std::fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.len() == b.len(),
+        _ => false,
+    };
+    same_size && matches!((std::fs::read(a), std::fs::read(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Restores the selected parts of a snapshot, users that are no longer found in Steam are skipped
+pub fn restore_snapshot(
+    install: &SteamInstall,
+    snapshot: &Snapshot,
+    parts: &[SnapshotPart],
+) -> eyre::Result<()> {
+    let users = get_install_users(install)?;
+    for user_snapshot in &snapshot.manifest.users {
+        let Some(user) = users.iter().find(|u| u.user_id == user_snapshot.user_id) else {
+            eprintln!(
+                "Steam user {} was not found, skipping it",
+                user_snapshot.user_id
+            );
+            continue;
+        };
+        let folder = snapshot.user_folder(&user.user_id);
+        if parts.contains(&SnapshotPart::Shortcuts) && user_snapshot.shortcuts.is_some() {
+            let content = std::fs::read(folder.join(SHORTCUTS_FILE))?;
+            parse_shortcuts(&content)
+                .map_err(|e| eyre::eyre!("The backed up shortcuts could not be read: {e:?}"))?;
+            let config_folder = Path::new(&user.steam_user_data_folder).join("config");
+            create_dir_all(&config_folder)?;
+            write_atomically(&config_folder.join(SHORTCUTS_FILE), &content)?;
+            log!("Restored shortcuts for user {}", user.user_id);
+        }
+        if parts.contains(&SnapshotPart::Images) && !user_snapshot.images.is_empty() {
+            let grid = grid_folder(user);
+            create_dir_all(&grid)?;
+            for image in &user_snapshot.images {
+                std::fs::copy(folder.join(GRID_FOLDER).join(image), grid.join(image))?;
+            }
+            log!(
+                "Restored {} images for user {}",
+                user_snapshot.images.len(),
+                user.user_id
+            );
+        }
+        if parts.contains(&SnapshotPart::Collections) && user_snapshot.collections {
+            match get_vdf_path(install, &user.user_id) {
+                Some(localconfig) => {
+                    let content = std::fs::read(folder.join(COLLECTIONS_FILE))?;
+                    write_atomically(&localconfig, &content)?;
+                    log!("Restored collections for user {}", user.user_id);
+                }
+                None => eprintln!("Could not find localconfig.vdf for user {}", user.user_id),
+            }
+        }
+    }
+    #[cfg(target_family = "unix")]
+    if parts.contains(&SnapshotPart::Proton) && snapshot.manifest.proton {
+        let section = std::fs::read_to_string(snapshot.path.join(PROTON_FILE))?;
+        crate::steam::set_compat_tool_mapping(install, &section)?;
+        //The tools BoilR remembers writing belong to the mappings they were written into
+        let proton_tools = get_proton_tools_file();
+        if let Some(file_name) = proton_tools.file_name().map(|name| name.to_string_lossy()) {
+            if snapshot.manifest.config_files.iter().any(|file| *file == file_name) {
+                let content = std::fs::read(snapshot.path.join(CONFIG_FOLDER).join(&*file_name))?;
+                write_atomically(&proton_tools, &content)?;
+            }
+        }
+        log!("Restored proton mappings");
+    }
+    if parts.contains(&SnapshotPart::Config) {
+        let config_folder = get_config_folder();
+        for file_name in &snapshot.manifest.config_files {
+            let content = std::fs::read(snapshot.path.join(CONFIG_FOLDER).join(file_name))?;
+            write_atomically(&config_folder.join(file_name), &content)?;
+        }
+        log!("Restored BoilR config");
+    }
+    Ok(())
+}
+
+/// Compares the shortcuts and images of a snapshot with the current ones
+pub fn diff_snapshot(
+    steam_settings: &SteamSettings,
+    snapshot: &Snapshot,
+) -> eyre::Result<Vec<SnapshotDiff>> {
+    let users = get_shortcuts_paths(steam_settings)?;
+    let mut result = vec![];
+    for user_snapshot in &snapshot.manifest.users {
+        let user = users.iter().find(|u| u.user_id == user_snapshot.user_id);
+        let current = match user {
+            Some(user) if user.shortcut_path.is_some() => get_shortcuts_for_user(user)?.shortcuts,
+            _ => vec![],
+        };
+        let backed_up = match user_snapshot.shortcuts {
+            Some(_) => {
+                let content =
+                    std::fs::read(snapshot.user_folder(&user_snapshot.user_id).join(SHORTCUTS_FILE))?;
+                parse_shortcuts(&content)
+                    .map_err(|e| eyre::eyre!("The backed up shortcuts could not be read: {e:?}"))?
+                    .iter()
+                    .map(|s| s.to_owned())
+                    .collect()
+            }
+            None => vec![],
+        };
+        let images_missing = match user {
+            Some(user) => {
+                let grid = grid_folder(user);
+                user_snapshot
+                    .images
+                    .iter()
+                    .filter(|image| !grid.join(image).exists())
+                    .count()
+            }
+            None => user_snapshot.images.len(),
+        };
+        result.push(SnapshotDiff {
+            user_id: user_snapshot.user_id.clone(),
+            shortcut_changes: diff_shortcuts(&current, &backed_up),
+            images_missing,
+        });
+    }
+    Ok(result)
+}
+
+/// Loads all snapshots, newest first
+pub fn load_snapshots() -> Vec<Snapshot> {
+    load_snapshots_in(&get_backups_flder())
+}
+
+fn load_snapshots_in(backup_folder: &Path) -> Vec<Snapshot> {
+    let mut result = vec![];
+    if let Ok(folders) = std::fs::read_dir(backup_folder) {
+        for folder in folders.flatten() {
+            let path = folder.path();
+            if let Some(manifest) = std::fs::read_to_string(path.join(MANIFEST_FILE))
+                .ok()
+                .and_then(|content| serde_json::from_str(&content).ok())
+            {
+                result.push(Snapshot {
+                    id: folder.file_name().to_string_lossy().to_string(),
+                    path,
+                    manifest,
+                });
+            }
+        }
+    }
+    result.sort_by(|a, b| {
+        (b.manifest.created_at, &b.id).cmp(&(a.manifest.created_at, &a.id))
+    });
+    result
+}
+
+pub fn find_snapshot(id: &str) -> eyre::Result<Snapshot> {
+    load_snapshots()
+        .into_iter()
+        .find(|s| s.id == id || s.path == Path::new(id))
+        .ok_or_else(|| eyre::eyre!("No backup named {id}"))
+}
+
+pub fn set_snapshot_label(snapshot: &mut Snapshot, label: Option<String>) -> eyre::Result<()> {
+    snapshot.manifest.label = clean_label(label);
+    snapshot.save_manifest()
+}
+
+pub fn delete_snapshot(snapshot: &Snapshot) -> eyre::Result<()> {
+    std::fs::remove_dir_all(&snapshot.path)?;
+    log!("Deleted backup {}", snapshot.id);
+    Ok(())
+}
+
+fn grid_folder(user: &SteamUsersInfo) -> PathBuf {
+    Path::new(&user.steam_user_data_folder)
+        .join("config")
+        .join("grid")
+}
+
+fn clean_label(label: Option<String>) -> Option<String> {
+    label
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+}
+
+fn format_date(date: OffsetDateTime) -> eyre::Result<String> {
+    let format = format_description::parse(DATE_FORMAT)?;
+    Ok(date.format(&format)?)
+}
+
+fn unique_id(backup_folder: &Path, date_string: &str) -> String {
+    let mut id = date_string.to_string();
+    let mut counter = 2;
+    while backup_folder.join(&id).exists() {
+        id = format!("{date_string}-{counter}");
+        counter += 1;
+    }
+    id
+}
+
+fn write_atomically(path: &Path, content: &[u8]) -> eyre::Result<()> {
+    let temp_path = path.with_extension("boilr-restore");
+    std::fs::write(&temp_path, content)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
+    use steam_shortcuts_util::{shortcuts_to_bytes, Shortcut};
+
+    use super::*;
+    use crate::{steam::SteamInstallKind, sync::BOILR_TAG};
+
+    const COMPAT_CONFIG: &str = "\"InstallConfigStore\"\n{\n\t\"CompatToolMapping\"\n\t{\n\t\t\"123\"\n\t\t{\n\t\t\t\"name\"\t\t\"proton_9\"\n\t\t}\n\t}\n}\n";
+
+    #[test]
+    fn restores_what_the_snapshot_has() {
+        let dir = tempfile::tempdir().unwrap();
+        let steam = dir.path().join("steam");
+        let user_config = steam.join("userdata").join("12345").join("config");
+        let grid = user_config.join("grid");
+        create_dir_all(&grid).unwrap();
+        create_dir_all(steam.join("config")).unwrap();
+        let mut shortcut =
+            Shortcut::new("0", "Game", "/games/game", "/games", "", "", "").to_owned();
+        shortcut.dev_kit_game_id = BOILR_TAG.to_string();
+        let shortcuts = shortcuts_to_bytes(&vec![shortcut.borrow()]);
+        std::fs::write(user_config.join(SHORTCUTS_FILE), &shortcuts).unwrap();
+        let image = grid.join(ImageType::Grid.file_name(shortcut.app_id, "png"));
+        std::fs::write(&image, b"grid image").unwrap();
+        let collections = "\"UserLocalConfigStore\"\n{\n}\n";
+        std::fs::write(user_config.join(COLLECTIONS_FILE), collections).unwrap();
+        let config_vdf = steam.join("config").join("config.vdf");
+        std::fs::write(&config_vdf, COMPAT_CONFIG).unwrap();
+        let install = SteamInstall {
+            kind: SteamInstallKind::Custom,
+            path: steam.clone(),
+        };
+        let backups = dir.path().join("backups");
+
+        let snapshot = create_snapshot_in(&backups, &install, None, &[]).unwrap();
+        assert_eq!(Some(1), snapshot.manifest.users[0].shortcuts);
+
+        std::fs::write(user_config.join(SHORTCUTS_FILE), shortcuts_to_bytes(&vec![])).unwrap();
+        std::fs::remove_file(&image).unwrap();
+        std::fs::write(user_config.join(COLLECTIONS_FILE), "changed").unwrap();
+        std::fs::write(&config_vdf, COMPAT_CONFIG.replace("proton_9", "proton_8")).unwrap();
+
+        restore_snapshot(&install, &snapshot, &ALL_PARTS[..4]).unwrap();
+        assert_eq!(shortcuts, std::fs::read(user_config.join(SHORTCUTS_FILE)).unwrap());
+        assert_eq!(b"grid image".to_vec(), std::fs::read(&image).unwrap());
+        assert_eq!(
+            collections,
+            std::fs::read_to_string(user_config.join(COLLECTIONS_FILE)).unwrap()
+        );
+        #[cfg(target_family = "unix")]
+        assert!(std::fs::read_to_string(&config_vdf).unwrap().contains("proton_9"));
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn links_images_that_did_not_change() {
+        use std::os::unix::fs::MetadataExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let image = dir.path().join("image.png");
+        let previous = dir.path().join("previous.png");
+        std::fs::write(&image, b"image").unwrap();
+        std::fs::write(&previous, b"image").unwrap();
+        copy_image(&image, &dir.path().join("linked.png"), Some(&previous)).unwrap();
+        std::fs::write(&image, b"changed").unwrap();
+        copy_image(&image, &dir.path().join("copied.png"), Some(&previous)).unwrap();
+
+        let inode = |name: &str| std::fs::metadata(dir.path().join(name)).unwrap().ino();
+        assert_eq!(inode("previous.png"), inode("linked.png"));
+        assert_ne!(inode("previous.png"), inode("copied.png"));
+        assert_eq!(b"changed".to_vec(), std::fs::read(dir.path().join("copied.png")).unwrap());
+    }
+
+    #[test]
+    fn parses_part_names() {
+        for part in ALL_PARTS {
+            assert_eq!(Some(part), SnapshotPart::parse(part.name()));
+        }
+        assert_eq!(None, SnapshotPart::parse("everything"));
+    }
+
+    #[test]
+    fn empty_labels_are_removed() {
+        assert_eq!(None, clean_label(Some("  ".to_string())));
+        assert_eq!(
+            Some("Before update".to_string()),
+            clean_label(Some(" Before update ".to_string()))
+        );
+    }
+}
@@ -0,0 +1,541 @@
+use crate::{
+    backup::{SnapshotPart, ALL_PARTS},
+    sync::{DuplicateDecision, OverrideField},
+};
+
+pub const USAGE: &str = "Usage: boilr [COMMAND]
+
+Running boilr without a command opens the UI.
+
+Commands:
+  sync [--dry-run] [--json]     Import games from all enabled platforms into Steam,
+                                --dry-run only prints what would change,
+                                --json prints the sync report as JSON
+  list-games [--platform NAME]  List the games BoilR would import
+  images download               Download missing images for all shortcuts
+  backup create [--label TEXT]  Back up the shortcuts, images, collections, proton mappings and config
+  backup list                   List the available backups
+  backup show ID                Show what a backup contains and what restoring it would change
+  backup label ID TEXT          Label a backup, labeled backups are never removed automatically
+  backup delete ID              Delete a backup
+  backup prune                  Delete the backups that are outside the retention settings
+  backup restore ID [--only PARTS]
+                                Restore a backup, PARTS is a comma separated list of
+                                shortcuts, images, collections, proton and config
+  disconnect APP_ID             Stop BoilR from managing a shortcut
+  adopt APP_ID [--proton TOOL]  Let BoilR manage the images, collections and proton of a shortcut it did not make,
+                                on an adopted shortcut --proton only changes its compatibility tool
+  adopted                       List the adopted shortcuts
+  duplicates [--json]           List the shortcuts made outside BoilR for games BoilR imports
+  duplicates adopt|skip|keep-both|clear GAME
+                                Decide what to do with a duplicate, GAME is an app id from list-games
+                                or a game from duplicates
+  override list                 List the games that have overrides
+  override set GAME FIELD VALUE Override the exe, start-dir, launch-options, icon or compat-tool of a game,
+                                GAME is an app id from list-games or a game from override list,
+                                {exe} and {launch_options} are replaced with what the platform found
+  override clear GAME [FIELD]   Remove the overrides of a game
+  launch-rules [--json]         Show the games each launch rule matches and what it changes
+  collections [--json]          Show the collections the collection rules make and their games
+  collections pending           List the collections waiting for Steam to exit before they are written
+  collections apply [--wait]    Write the pending collections, --wait waits until Steam exits
+  steam-games [--json]          List the games installed by Steam, with their library, size and last update
+  tags                          List the games that have tags
+  tags add|remove GAME TAG      Tag a game for the collection rules, GAME is an app id from list-games
+                                or a game from tags
+  proton-tools [--json]         List the installed compatibility tools, the names can be used with compat-tool
+  users                         List the Steam users and what they get imported
+  users enable|disable USER     Turn imports for a Steam user on or off, USER is a user id or persona name
+  users skip|unskip USER WHAT   Stop or resume importing a game (app id) or platform for a single Steam user
+  config get [KEY]              Print a configuration value, e.g. steam.location
+  config set KEY VALUE          Change a configuration value, e.g. heroic.enabled false
+  help                          Print this message
+
+Options:
+  --no-ui                       Same as running sync";
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Sync { dry_run: bool, json: bool },
+    ListGames { platform: Option<String> },
+    DownloadImages,
+    BackupCreate { label: Option<String> },
+    BackupList,
+    BackupShow { id: String },
+    BackupLabel { id: String, label: String },
+    BackupDelete { id: String },
+    BackupPrune,
+    BackupRestore { id: String, parts: Vec<SnapshotPart> },
+    Disconnect { app_id: u32 },
+    Adopt { app_id: u32, compat_tool: Option<String> },
+    AdoptedList,
+    DuplicateList { json: bool },
+    DuplicateDecide { game: String, decision: Option<DuplicateDecision> },
+    OverrideList,
+    OverrideSet { game: String, field: OverrideField, value: String },
+    OverrideClear { game: String, field: Option<OverrideField> },
+    LaunchRules { json: bool },
+    Collections { json: bool },
+    CollectionsPending,
+    CollectionsApply { wait: bool },
+    SteamGames { json: bool },
+    TagList,
+    Tag { game: String, tag: String, add: bool },
+    ProtonTools { json: bool },
+    UserList,
+    UserEnable { user: String, enabled: bool },
+    UserSkip { user: String, what: String, skip: bool },
+    ConfigGet { key: Option<String> },
+    ConfigSet { key: String, value: String },
+    Help,
+}
+
+impl Command {
+    /// If the command prints JSON on stdout, other messages go to stderr then
+    pub fn prints_json(&self) -> bool {
+        matches!(
+            self,
+            Command::Sync { json: true, .. }
+                | Command::DuplicateList { json: true }
+                | Command::LaunchRules { json: true }
+                | Command::Collections { json: true }
+                | Command::SteamGames { json: true }
+                | Command::ProtonTools { json: true }
+        )
+    }
+}
+
+/// Parses the commandline arguments (including the executable name).
+/// Returns `None` if BoilR should start the UI.
+pub fn parse_args(args: &[String]) -> Option<Result<Command, String>> {
+    if args.iter().any(|a| a == "--no-ui") {
+        return Some(Ok(Command::Sync {
+            dry_run: args.iter().any(|a| a == "--dry-run"),
+            json: args.iter().any(|a| a == "--json"),
+        }));
+    }
+    let mut args = args.iter().skip(1).map(|a| a.as_str());
+    let command = match args.next() {
+        None => return None,
+        //Flags like --fullscreen belong to the UI
+        Some(flag) if flag.starts_with("--") && flag != "--help" => return None,
+        Some(command) => command,
+    };
+    let rest: Vec<&str> = args.collect();
+    Some(parse_command(command, &rest))
+}
+
+fn parse_command(command: &str, rest: &[&str]) -> Result<Command, String> {
+    match (command, rest) {
+        ("help" | "--help" | "-h", _) => Ok(Command::Help),
+        ("sync", flags) if flags.iter().all(|f| ["--dry-run", "--json"].contains(f)) => {
+            Ok(Command::Sync {
+                dry_run: flags.contains(&"--dry-run"),
+                json: flags.contains(&"--json"),
+            })
+        }
+        ("list-games", []) => Ok(Command::ListGames { platform: None }),
+        ("list-games", ["--platform", platform]) => Ok(Command::ListGames {
+            platform: Some(platform.to_string()),
+        }),
+        ("images", ["download"]) => Ok(Command::DownloadImages),
+        ("backup", ["create"]) => Ok(Command::BackupCreate { label: None }),
+        ("backup", ["create", "--label", label]) => Ok(Command::BackupCreate {
+            label: Some(label.to_string()),
+        }),
+        ("backup", ["list"]) => Ok(Command::BackupList),
+        ("backup", ["show", id]) => Ok(Command::BackupShow { id: id.to_string() }),
+        ("backup", ["label", id, label]) => Ok(Command::BackupLabel {
+            id: id.to_string(),
+            label: label.to_string(),
+        }),
+        ("backup", ["delete", id]) => Ok(Command::BackupDelete { id: id.to_string() }),
+        ("backup", ["prune"]) => Ok(Command::BackupPrune),
+        ("backup", ["restore", id]) => Ok(Command::BackupRestore {
+            id: id.to_string(),
+            parts: ALL_PARTS.to_vec(),
+        }),
+        ("backup", ["restore", id, "--only", parts]) => Ok(Command::BackupRestore {
+            id: id.to_string(),
+            parts: parse_parts(parts)?,
+        }),
+        ("disconnect", [app_id]) => Ok(Command::Disconnect {
+            app_id: parse_app_id(app_id)?,
+        }),
+        ("adopt", [app_id]) => Ok(Command::Adopt {
+            app_id: parse_app_id(app_id)?,
+            compat_tool: None,
+        }),
+        ("adopt", [app_id, "--proton", tool]) => Ok(Command::Adopt {
+            app_id: parse_app_id(app_id)?,
+            compat_tool: Some(tool.to_string()),
+        }),
+        ("adopted", []) => Ok(Command::AdoptedList),
+        ("duplicates", []) => Ok(Command::DuplicateList { json: false }),
+        ("duplicates", ["--json"]) => Ok(Command::DuplicateList { json: true }),
+        ("duplicates", ["clear", game]) => Ok(Command::DuplicateDecide {
+            game: game.to_string(),
+            decision: None,
+        }),
+        ("duplicates", [action, game]) => match DuplicateDecision::parse(action) {
+            Some(decision) => Ok(Command::DuplicateDecide {
+                game: game.to_string(),
+                decision: Some(decision),
+            }),
+            None => Err(format!("Unknown decision {action}, use adopt, skip, keep-both or clear")),
+        },
+        ("override", ["list"]) => Ok(Command::OverrideList),
+        ("override", ["set", game, field, value]) => Ok(Command::OverrideSet {
+            game: game.to_string(),
+            field: parse_override_field(field)?,
+            value: value.to_string(),
+        }),
+        ("override", ["clear", game]) => Ok(Command::OverrideClear {
+            game: game.to_string(),
+            field: None,
+        }),
+        ("override", ["clear", game, field]) => Ok(Command::OverrideClear {
+            game: game.to_string(),
+            field: Some(parse_override_field(field)?),
+        }),
+        ("launch-rules", []) => Ok(Command::LaunchRules { json: false }),
+        ("launch-rules", ["--json"]) => Ok(Command::LaunchRules { json: true }),
+        ("collections", []) => Ok(Command::Collections { json: false }),
+        ("collections", ["--json"]) => Ok(Command::Collections { json: true }),
+        ("collections", ["pending"]) => Ok(Command::CollectionsPending),
+        ("collections", ["apply"]) => Ok(Command::CollectionsApply { wait: false }),
+        ("collections", ["apply", "--wait"]) => Ok(Command::CollectionsApply { wait: true }),
+        ("steam-games", []) => Ok(Command::SteamGames { json: false }),
+        ("steam-games", ["--json"]) => Ok(Command::SteamGames { json: true }),
+        ("tags", []) => Ok(Command::TagList),
+        ("tags", [action @ ("add" | "remove"), game, tag]) => Ok(Command::Tag {
+            game: game.to_string(),
+            tag: tag.to_string(),
+            add: *action == "add",
+        }),
+        ("proton-tools", []) => Ok(Command::ProtonTools { json: false }),
+        ("proton-tools", ["--json"]) => Ok(Command::ProtonTools { json: true }),
+        ("users", []) => Ok(Command::UserList),
+        ("users", [action @ ("enable" | "disable"), user]) => Ok(Command::UserEnable {
+            user: user.to_string(),
+            enabled: *action == "enable",
+        }),
+        ("users", [action @ ("skip" | "unskip"), user, what]) => Ok(Command::UserSkip {
+            user: user.to_string(),
+            what: what.to_string(),
+            skip: *action == "skip",
+        }),
+        ("config", ["get"]) => Ok(Command::ConfigGet { key: None }),
+        ("config", ["get", key]) => Ok(Command::ConfigGet {
+            key: Some(key.to_string()),
+        }),
+        ("config", ["set", key, value]) => Ok(Command::ConfigSet {
+            key: key.to_string(),
+            value: value.to_string(),
+        }),
+        (
+            "sync" | "list-games" | "images" | "backup" | "disconnect" | "adopt" | "adopted"
+            | "duplicates" | "override" | "launch-rules"
+            | "collections" | "steam-games" | "tags" | "proton-tools" | "users" | "config",
+            _,
+        ) => Err(format!("Invalid arguments for {command}")),
+        _ => Err(format!("Unknown command {command}")),
+    }
+}
+
+fn parse_app_id(app_id: &str) -> Result<u32, String> {
+    app_id
+        .parse()
+        .map_err(|_| format!("{app_id} is not a valid app id"))
+}
+
+fn parse_override_field(field: &str) -> Result<OverrideField, String> {
+    OverrideField::parse(field).ok_or_else(|| {
+        format!("Unknown field {field}, use exe, start-dir, launch-options, icon or compat-tool")
+    })
+}
+
+fn parse_parts(parts: &str) -> Result<Vec<SnapshotPart>, String> {
+    parts
+        .split(',')
+        .map(|name| {
+            SnapshotPart::parse(name.trim()).ok_or_else(|| format!("Unknown backup part {name}"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    fn parse(args: &[&str]) -> Option<Result<Command, String>> {
+        let args: Vec<String> = std::iter::once("boilr")
+            .chain(args.iter().copied())
+            .map(|s| s.to_string())
+            .collect();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn no_arguments_starts_ui() {
+        assert_eq!(None, parse(&[]));
+        assert_eq!(None, parse(&["--fullscreen", "--no-vsync"]));
+    }
+
+    #[test]
+    fn no_ui_flag_is_sync() {
+        assert_eq!(
+            Some(Ok(Command::Sync {
+                dry_run: false,
+                json: false
+            })),
+            parse(&["--no-ui"])
+        );
+        assert_eq!(
+            Some(Ok(Command::Sync {
+                dry_run: false,
+                json: false
+            })),
+            parse(&["--fullscreen", "--no-ui"])
+        );
+    }
+
+    #[test]
+    fn parses_dry_run() {
+        assert_eq!(
+            Some(Ok(Command::Sync {
+                dry_run: true,
+                json: false
+            })),
+            parse(&["sync", "--dry-run"])
+        );
+        assert_eq!(
+            Some(Ok(Command::Sync {
+                dry_run: true,
+                json: false
+            })),
+            parse(&["--no-ui", "--dry-run"])
+        );
+    }
+
+    #[test]
+    fn parses_json() {
+        assert_eq!(
+            Some(Ok(Command::Sync {
+                dry_run: false,
+                json: true
+            })),
+            parse(&["sync", "--json"])
+        );
+        assert_eq!(
+            Some(Ok(Command::Sync {
+                dry_run: true,
+                json: true
+            })),
+            parse(&["sync", "--json", "--dry-run"])
+        );
+        assert!(parse(&["sync", "--jsn"]).unwrap().is_err());
+    }
+
+    #[test]
+    fn parses_list_games() {
+        assert_eq!(
+            Some(Ok(Command::ListGames { platform: None })),
+            parse(&["list-games"])
+        );
+        assert_eq!(
+            Some(Ok(Command::ListGames {
+                platform: Some("heroic".to_string())
+            })),
+            parse(&["list-games", "--platform", "heroic"])
+        );
+    }
+
+    #[test]
+    fn parses_backup() {
+        assert_eq!(Some(Ok(Command::BackupList)), parse(&["backup", "list"]));
+        assert_eq!(
+            Some(Ok(Command::BackupCreate {
+                label: Some("Before update".to_string())
+            })),
+            parse(&["backup", "create", "--label", "Before update"])
+        );
+        assert_eq!(
+            Some(Ok(Command::BackupRestore {
+                id: "2023-01-02-10-11-12".to_string(),
+                parts: ALL_PARTS.to_vec()
+            })),
+            parse(&["backup", "restore", "2023-01-02-10-11-12"])
+        );
+        assert_eq!(
+            Some(Ok(Command::BackupRestore {
+                id: "2023-01-02-10-11-12".to_string(),
+                parts: vec![SnapshotPart::Shortcuts, SnapshotPart::Images]
+            })),
+            parse(&[
+                "backup",
+                "restore",
+                "2023-01-02-10-11-12",
+                "--only",
+                "shortcuts,images"
+            ])
+        );
+        assert!(parse(&["backup", "restore"]).unwrap().is_err());
+        assert!(parse(&["backup", "restore", "x", "--only", "everything"])
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn parses_disconnect() {
+        assert_eq!(
+            Some(Ok(Command::Disconnect { app_id: 3456789012 })),
+            parse(&["disconnect", "3456789012"])
+        );
+        assert!(parse(&["disconnect", "not-a-number"]).unwrap().is_err());
+    }
+
+    #[test]
+    fn parses_adopt() {
+        assert_eq!(
+            Some(Ok(Command::Adopt {
+                app_id: 3456789012,
+                compat_tool: None
+            })),
+            parse(&["adopt", "3456789012"])
+        );
+        assert_eq!(
+            Some(Ok(Command::Adopt {
+                app_id: 42,
+                compat_tool: Some("GE-Proton9-20".to_string())
+            })),
+            parse(&["adopt", "42", "--proton", "GE-Proton9-20"])
+        );
+        assert_eq!(Some(Ok(Command::AdoptedList)), parse(&["adopted"]));
+        assert!(parse(&["adopt", "game"]).unwrap().is_err());
+    }
+
+    #[test]
+    fn parses_duplicates() {
+        assert_eq!(
+            Some(Ok(Command::DuplicateList { json: false })),
+            parse(&["duplicates"])
+        );
+        assert_eq!(
+            Some(Ok(Command::DuplicateDecide {
+                game: "Lutris/celeste".to_string(),
+                decision: Some(DuplicateDecision::KeepBoth)
+            })),
+            parse(&["duplicates", "keep-both", "Lutris/celeste"])
+        );
+        assert_eq!(
+            Some(Ok(Command::DuplicateDecide {
+                game: "123".to_string(),
+                decision: None
+            })),
+            parse(&["duplicates", "clear", "123"])
+        );
+        assert!(parse(&["duplicates", "merge", "123"]).unwrap().is_err());
+    }
+
+    #[test]
+    fn parses_tags() {
+        assert_eq!(
+            Some(Ok(Command::Collections { json: true })),
+            parse(&["collections", "--json"])
+        );
+        assert_eq!(Some(Ok(Command::CollectionsPending)), parse(&["collections", "pending"]));
+        assert_eq!(
+            Some(Ok(Command::CollectionsApply { wait: true })),
+            parse(&["collections", "apply", "--wait"])
+        );
+        assert_eq!(
+            Some(Ok(Command::SteamGames { json: true })),
+            parse(&["steam-games", "--json"])
+        );
+        assert_eq!(Some(Ok(Command::TagList)), parse(&["tags"]));
+        assert_eq!(
+            Some(Ok(Command::Tag {
+                game: "Lutris/celeste".to_string(),
+                tag: "Platformers".to_string(),
+                add: false
+            })),
+            parse(&["tags", "remove", "Lutris/celeste", "Platformers"])
+        );
+        assert!(parse(&["tags", "add", "123"]).unwrap().is_err());
+    }
+
+    #[test]
+    fn parses_users() {
+        assert_eq!(Some(Ok(Command::UserList)), parse(&["users"]));
+        assert_eq!(
+            Some(Ok(Command::UserEnable {
+                user: "12345".to_string(),
+                enabled: false
+            })),
+            parse(&["users", "disable", "12345"])
+        );
+        assert_eq!(
+            Some(Ok(Command::UserSkip {
+                user: "Kid".to_string(),
+                what: "egs".to_string(),
+                skip: true
+            })),
+            parse(&["users", "skip", "Kid", "egs"])
+        );
+        assert!(parse(&["users", "remove", "12345"]).unwrap().is_err());
+    }
+
+    #[test]
+    fn parses_override() {
+        assert_eq!(Some(Ok(Command::OverrideList)), parse(&["override", "list"]));
+        assert_eq!(
+            Some(Ok(Command::OverrideSet {
+                game: "3456789012".to_string(),
+                field: OverrideField::LaunchOptions,
+                value: "gamemoderun %command%".to_string()
+            })),
+            parse(&[
+                "override",
+                "set",
+                "3456789012",
+                "launch-options",
+                "gamemoderun %command%"
+            ])
+        );
+        assert_eq!(
+            Some(Ok(Command::OverrideClear {
+                game: "Heroic/fortnite".to_string(),
+                field: None
+            })),
+            parse(&["override", "clear", "Heroic/fortnite"])
+        );
+        assert!(parse(&["override", "set", "1", "name", "x"])
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn parses_config() {
+        assert_eq!(
+            Some(Ok(Command::ConfigGet {
+                key: Some("steam.location".to_string())
+            })),
+            parse(&["config", "get", "steam.location"])
+        );
+        assert_eq!(
+            Some(Ok(Command::ConfigSet {
+                key: "heroic.enabled".to_string(),
+                value: "false".to_string()
+            })),
+            parse(&["config", "set", "heroic.enabled", "false"])
+        );
+    }
+
+    #[test]
+    fn unknown_command_is_an_error() {
+        assert!(parse(&["frobnicate"]).unwrap().is_err());
+    }
+}
@@ -0,0 +1,617 @@
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Instant;
+
+use tokio::runtime::Runtime;
+
+use crate::{
+    backup::{
+        apply_retention, create_snapshot, create_snapshot_with_retention, delete_snapshot, diff_snapshot, find_snapshot,
+        load_snapshots, restore_backup, restore_snapshot, set_snapshot_label, SnapshotPart,
+    },
+    platforms::{get_platforms, Platforms, ShortcutToImport},
+    settings::{get_rename_map, save_settings, save_steam_users, Settings},
+    steam::{
+        apply_pending_collections, get_compat_tools, get_game_manifests, get_install_users, get_shortcuts_paths,
+        is_collections_locked, multi_library_installs, wait_for_pending_collections, PendingCollections, SteamInstall, SteamUserSettings,
+        PENDING_COLLECTIONS_POLL,
+    },
+    sync::{
+        self, AdoptedShortcuts, CollectionRules, CollectionTags, DuplicateDecisions, GameOverride, GameOverrides, PlatformShortcuts,
+        SyncReport, OVERRIDE_FIELDS,
+    },
+};
+
+use super::{
+    args::{Command, USAGE},
+    config_values::{format_value, get_config_value, set_config_value},
+};
+
+/// The command failed
+const EXIT_FAILURE: u8 = 1;
+/// The commandline arguments could not be understood
+const EXIT_USAGE: u8 = 2;
+/// The import finished, but some platforms failed to find their games
+const EXIT_PARTIAL: u8 = 3;
+
+/// Runs a parsed commandline invocation and returns the exit code for the process
+pub fn run(command: Result<Command, String>) -> ExitCode {
+    let command = match command {
+        Ok(command) => command,
+        Err(msg) => {
+            eprintln!("{msg}\n\n{USAGE}");
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+    match run_command(command) {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+fn run_command(command: Command) -> eyre::Result<ExitCode> {
+    let mut settings = Settings::new()?;
+    match command {
+        Command::Help => {
+            println!("{USAGE}");
+        }
+        Command::Sync {
+            dry_run: false,
+            json,
+        } => return run_sync(&settings, json),
+        Command::Sync {
+            dry_run: true,
+            json,
+        } => return preview_sync(&settings, json),
+        Command::ListGames { platform } => return list_games(&settings, platform.as_deref()),
+        Command::DownloadImages => download_images(&settings)?,
+        Command::BackupCreate { label } => {
+            let install = SteamInstall::find(&settings.steam)?;
+            let snapshot = create_snapshot_with_retention(&settings, &install, label)?;
+            println!("{}", snapshot.id);
+        }
+        Command::BackupList => {
+            for snapshot in load_snapshots() {
+                let label = snapshot.manifest.label.as_deref().unwrap_or_default();
+                println!("{}\t{label}\t{}", snapshot.id, snapshot.summary());
+            }
+        }
+        Command::BackupShow { id } => show_backup(&settings, &id)?,
+        Command::BackupLabel { id, label } => {
+            let mut snapshot = find_snapshot(&id)?;
+            set_snapshot_label(&mut snapshot, Some(label))?;
+        }
+        Command::BackupDelete { id } => delete_snapshot(&find_snapshot(&id)?)?,
+        Command::BackupPrune => {
+            for id in apply_retention(&settings.backup, &load_snapshots()) {
+                println!("{id}");
+            }
+        }
+        Command::BackupRestore { id, parts } => restore(&settings, &id, &parts)?,
+        Command::Disconnect { app_id } => {
+            sync::disconnect_shortcut(&settings, app_id).map_err(|e| eyre::eyre!(e))?;
+            if !settings.blacklisted_games.contains(&app_id) {
+                settings.blacklisted_games.push(app_id);
+            }
+            save_settings(&settings, &get_platforms())?;
+            println!("Disconnected {app_id}");
+        }
+        Command::Adopt {
+            app_id,
+            compat_tool,
+        } => {
+            if AdoptedShortcuts::load().get(app_id).is_none() {
+                let adopted = sync::adopt_shortcut(&settings, app_id).map_err(|e| eyre::eyre!(e))?;
+                println!("Adopted {} for {} user(s)", adopted.name, adopted.users.len());
+            }
+            if let Some(tool) = compat_tool {
+                let mut adopted = AdoptedShortcuts::load();
+                adopted.set_compat_tool(app_id, Some(tool.clone()));
+                adopted.save()?;
+                println!("{app_id}\t{tool}");
+            }
+        }
+        Command::AdoptedList => {
+            for (app_id, adopted) in AdoptedShortcuts::load().iter() {
+                println!(
+                    "{app_id}\t{}\t{}\t{}\t{}",
+                    adopted.name,
+                    adopted.users.join(","),
+                    adopted.compat_tool.as_deref().unwrap_or(""),
+                    format_timestamp(u64::try_from(adopted.adopted_at).unwrap_or_default())
+                );
+            }
+        }
+        Command::DuplicateList { json } => return list_duplicates(&settings, json),
+        Command::DuplicateDecide { game, decision } => {
+            let (key, _) = resolve_game(&game)?;
+            let mut decisions = DuplicateDecisions::load();
+            decisions.set(&key, decision);
+            decisions.save()?;
+            let name = decision.map(|decision| decision.name()).unwrap_or("ask");
+            println!("{key}\t{name}");
+        }
+        Command::OverrideList => {
+            for (game, game_override) in GameOverrides::load().iter() {
+                for field in OVERRIDE_FIELDS {
+                    if let Some(value) = game_override.get(field) {
+                        println!("{game}\t{}\t{value}", field.name());
+                    }
+                }
+            }
+        }
+        Command::OverrideSet { game, field, value } => {
+            edit_override(&game, |game_override| *game_override.get_mut(field) = Some(value))?
+        }
+        Command::OverrideClear { game, field } => edit_override(&game, |game_override| match field {
+            Some(field) => *game_override.get_mut(field) = None,
+            None => *game_override = GameOverride::default(),
+        })?,
+        Command::LaunchRules { json } => return preview_launch_rules(&settings, json),
+        Command::Collections { json } => return preview_collections(&settings, json),
+        Command::CollectionsPending => {
+            let pending = PendingCollections::load();
+            if pending.is_empty() {
+                println!("No pending collections");
+            }
+            for (user_id, user) in pending.users() {
+                let names: Vec<&str> = user.collections.iter().map(|c| c.name.as_str()).collect();
+                println!("{user_id}\t{}\t{}", user.queued_at, names.join(", "));
+            }
+        }
+        Command::CollectionsApply { wait } => {
+            let install = SteamInstall::find(&settings.steam)?;
+            let written = if wait {
+                let mut printed = false;
+                wait_for_pending_collections(&install, PENDING_COLLECTIONS_POLL, |_| {
+                    if !printed {
+                        println!("Waiting for Steam to exit");
+                        printed = true;
+                    }
+                })?
+            } else {
+                apply_pending_collections(&install).map_err(|err| {
+                    if is_collections_locked(&err) {
+                        eyre::eyre!("Steam is running, close it or use collections apply --wait")
+                    } else {
+                        err
+                    }
+                })?
+            };
+            if written.is_empty() {
+                println!("No pending collections");
+            }
+            for user_id in written {
+                println!("Wrote the collections of {user_id}");
+            }
+        }
+        Command::SteamGames { json } => {
+            let install = SteamInstall::find(&settings.steam)?;
+            let manifests = get_game_manifests(&install);
+            let several = multi_library_installs(&manifests);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&manifests)?);
+            } else {
+                for game in &manifests {
+                    let proton = if game.needs_proton { "proton" } else { "" };
+                    println!(
+                        "{}\t{}\t{}\t{} MB\t{}\t{proton}",
+                        game.appid,
+                        game.name,
+                        game.library.display(),
+                        game.size_on_disk / 1_000_000,
+                        format_timestamp(game.last_updated)
+                    );
+                }
+                for game in several {
+                    eprintln!("{} ({}) is installed in {} libraries", game.name, game.appid, game.libraries.len());
+                }
+            }
+        }
+        Command::TagList => {
+            for (game, tags) in CollectionTags::load().iter() {
+                println!("{game}\t{}", tags.join(", "));
+            }
+        }
+        Command::Tag { game, tag, add } => {
+            let (key, _) = resolve_game(&game)?;
+            let mut collection_tags = CollectionTags::load();
+            let mut tags: Vec<String> = collection_tags
+                .get(&key)
+                .iter()
+                .filter(|t| !t.eq_ignore_ascii_case(&tag))
+                .cloned()
+                .collect();
+            if add {
+                tags.push(tag);
+            }
+            println!("{key}\t{}", tags.join(", "));
+            collection_tags.set(&key, tags);
+            collection_tags.save()?;
+        }
+        Command::ProtonTools { json } => {
+            let tools = get_compat_tools(&settings.steam);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&tools)?);
+            } else {
+                for tool in tools {
+                    println!("{}\t{}\t{}", tool.name, tool.display_name, tool.path.display());
+                }
+            }
+        }
+        Command::UserList => {
+            for user in get_shortcuts_paths(&settings.steam)? {
+                let user_settings = settings.steam.user_settings(&user.user_id);
+                let status = if user_settings.enabled { "imports" } else { "disabled" };
+                println!(
+                    "{}\t{}\t{status}\tskipped platforms: {}\tskipped games: {}",
+                    user.user_id,
+                    user.persona_name.as_deref().unwrap_or_default(),
+                    user_settings.disabled_platforms.join(","),
+                    user_settings.blacklisted_games.len()
+                );
+            }
+        }
+        Command::UserEnable { user, enabled } => {
+            edit_user_settings(&mut settings, &user, |user_settings| user_settings.enabled = enabled)?
+        }
+        Command::UserSkip { user, what, skip } => {
+            edit_user_settings(&mut settings, &user, |user_settings| {
+                match what.parse::<u32>() {
+                    Ok(app_id) => {
+                        user_settings.blacklisted_games.retain(|id| *id != app_id);
+                        if skip {
+                            user_settings.blacklisted_games.push(app_id);
+                        }
+                    }
+                    Err(_) => {
+                        user_settings
+                            .disabled_platforms
+                            .retain(|platform| !platform.eq_ignore_ascii_case(&what));
+                        if skip {
+                            user_settings.disabled_platforms.push(what);
+                        }
+                    }
+                }
+            })?
+        }
+        Command::ConfigGet { key } => {
+            let platforms = get_platforms();
+            let value = match key {
+                Some(key) => get_config_value(&settings, &platforms, &key)?,
+                None => toml::Value::Table(toml::Table::try_from(&settings)?),
+            };
+            println!("{}", format_value(&value));
+        }
+        Command::ConfigSet { key, value } => {
+            let mut platforms = get_platforms();
+            set_config_value(&mut settings, &mut platforms, &key, &value)?;
+            save_settings(&settings, &platforms)?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn run_sync(settings: &Settings, json: bool) -> eyre::Result<ExitCode> {
+    let platforms = get_platforms();
+    let mut report = SyncReport::new();
+    let start_time = Instant::now();
+    let results = sync::fetch_platform_shortcuts(&platforms);
+    report.timings.find_games_ms = Some(sync::millis(start_time.elapsed()));
+    for (name, result) in &results {
+        report.add_platform(name, result);
+    }
+    let (shortcuts, failed) = split_results(results);
+    let renames = get_rename_map();
+    let rt = Runtime::new()?;
+    let settings = settings.clone();
+    let failed_platforms = failed.clone();
+    let (report, result) = rt.block_on(async move {
+        tokio::task::spawn_blocking(move || {
+            let result = sync::run_sync(
+                &settings,
+                shortcuts,
+                &failed_platforms,
+                &renames,
+                &mut None,
+                &mut report,
+            );
+            (report, result)
+        })
+        .await
+    })?;
+    if json {
+        println!("{}", report.to_json()?);
+    }
+    result?;
+    if !failed.is_empty() {
+        Ok(ExitCode::from(EXIT_PARTIAL))
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn preview_sync(settings: &Settings, json: bool) -> eyre::Result<ExitCode> {
+    let platforms = get_platforms();
+    let (shortcuts, failed) = find_games(&platforms);
+    let renames = get_rename_map();
+    let preview = sync::preview_sync(settings, &shortcuts, &failed, &renames)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&preview)?);
+    } else {
+        print!("{preview}");
+    }
+    if !failed.is_empty() {
+        Ok(ExitCode::from(EXIT_PARTIAL))
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn list_games(settings: &Settings, platform: Option<&str>) -> eyre::Result<ExitCode> {
+    let mut platforms = get_platforms();
+    if let Some(platform) = platform {
+        platforms.retain(|p| {
+            p.code_name().eq_ignore_ascii_case(platform) || p.name().eq_ignore_ascii_case(platform)
+        });
+        match platforms.first() {
+            None => return Err(eyre::eyre!("Unknown platform {platform}")),
+            Some(p) if !p.enabled() => {
+                return Err(eyre::eyre!("Platform {platform} is not enabled"))
+            }
+            _ => {}
+        }
+    }
+    let (shortcuts, failed) = find_games(&platforms);
+    let renames = get_rename_map();
+    for (platform_name, shortcuts) in shortcuts {
+        for shortcut_to_import in shortcuts {
+            let shortcut = &shortcut_to_import.shortcut;
+            let name = renames.get(&shortcut.app_id).unwrap_or(&shortcut.app_name);
+            let status = if settings.blacklisted_games.contains(&shortcut.app_id) {
+                "excluded"
+            } else {
+                "import"
+            };
+            println!("{}\t{platform_name}\t{status}\t{name}", shortcut.app_id);
+        }
+    }
+    if !failed.is_empty() {
+        Ok(ExitCode::from(EXIT_PARTIAL))
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Prints the games each launch rule matches, with the exe and launch options the rule gives them
+fn preview_launch_rules(settings: &Settings, json: bool) -> eyre::Result<ExitCode> {
+    let platforms = get_platforms();
+    let (shortcuts, failed) = find_games(&platforms);
+    let code_names = sync::platform_code_names();
+    let preview = sync::preview_launch_rules(&settings.launch_rules, &shortcuts, &code_names);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&preview)?);
+    } else {
+        for rule in &preview {
+            match &rule.error {
+                Some(error) => println!("{}: {error}", rule.rule),
+                None => println!("{}: {} game(s)", rule.rule, rule.games.len()),
+            }
+            for game in &rule.games {
+                println!(
+                    "\t{}\t{}\t{} {}",
+                    game.platform, game.name, game.exe, game.launch_options
+                );
+            }
+        }
+    }
+    if !failed.is_empty() {
+        Ok(ExitCode::from(EXIT_PARTIAL))
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// Prints the collections the collection rules make from the games found
+fn preview_collections(settings: &Settings, json: bool) -> eyre::Result<ExitCode> {
+    let platforms = get_platforms();
+    let (shortcuts, failed) = find_games(&platforms);
+    let overrides = sync::resolve_overrides(settings, &shortcuts);
+    let mut games = sync::collection_games(settings, &shortcuts, &get_rename_map(), &overrides);
+    games.extend(sync::installed_collection_games(settings));
+    let preview = CollectionRules::from_settings(settings).preview(&games);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&preview)?);
+    } else {
+        for collection in &preview {
+            let kind = if collection.existing { " (existing)" } else { "" };
+            println!("{}{kind}: {} game(s)", collection.name, collection.games.len());
+            for game in &collection.games {
+                println!("\t{}\t{}", game.platform, game.name);
+            }
+        }
+    }
+    if !failed.is_empty() {
+        Ok(ExitCode::from(EXIT_PARTIAL))
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+fn list_duplicates(settings: &Settings, json: bool) -> eyre::Result<ExitCode> {
+    let (shortcuts, failed) = find_games(&get_platforms());
+    let duplicates = sync::find_all_duplicates(settings, &shortcuts, &get_rename_map())?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&duplicates)?);
+    } else {
+        let decisions = DuplicateDecisions::load();
+        for duplicate in &duplicates {
+            let decision = decisions
+                .get(&duplicate.game)
+                .map(|decision| decision.name())
+                .unwrap_or("ask");
+            println!(
+                "{}\t{}\t{decision}\t{}\t{}",
+                duplicate.user_id, duplicate.game, duplicate.shortcut_name, duplicate.name
+            );
+        }
+    }
+    if !failed.is_empty() {
+        Ok(ExitCode::from(EXIT_PARTIAL))
+    } else {
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+/// A unix timestamp as an RFC 3339 date, or an empty string if it is not known
+fn format_timestamp(timestamp: u64) -> String {
+    i64::try_from(timestamp)
+        .ok()
+        .filter(|timestamp| *timestamp > 0)
+        .and_then(|timestamp| time::OffsetDateTime::from_unix_timestamp(timestamp).ok())
+        .and_then(|date| date.format(&time::format_description::well_known::Rfc3339).ok())
+        .unwrap_or_default()
+}
+
+/// Changes the settings of a Steam user, `user` is a user id or a persona name
+fn edit_user_settings(
+    settings: &mut Settings,
+    user: &str,
+    edit: impl FnOnce(&mut SteamUserSettings),
+) -> eyre::Result<()> {
+    let users = get_shortcuts_paths(&settings.steam)?;
+    let found = users
+        .iter()
+        .find(|info| info.user_id == user)
+        .or_else(|| {
+            users
+                .iter()
+                .find(|info| info.persona_name.as_deref() == Some(user))
+        })
+        .ok_or_else(|| eyre::eyre!("No Steam user {user}, see boilr users"))?;
+    let mut user_settings = settings.steam.user_settings(&found.user_id);
+    edit(&mut user_settings);
+    if user_settings == SteamUserSettings::default() {
+        settings.steam.users.remove(&found.user_id);
+    } else {
+        settings.steam.users.insert(found.user_id.clone(), user_settings);
+    }
+    save_steam_users(&settings.steam.users)
+}
+
+/// Changes the override of a game, `game` is either an app id from `list-games` or a game from `override list`
+fn edit_override(game: &str, edit: impl FnOnce(&mut GameOverride)) -> eyre::Result<()> {
+    let (key, found) = resolve_game(game)?;
+    let mut overrides = GameOverrides::load();
+    let mut game_override = overrides.get(&key).cloned().unwrap_or_default();
+    edit(&mut game_override);
+    if let Some(info) = found {
+        let mut shortcut = info.shortcut;
+        game_override.apply(&mut shortcut);
+        println!("{key}");
+        println!("exe: {}", shortcut.exe);
+        println!("start-dir: {}", shortcut.start_dir);
+        println!("launch-options: {}", shortcut.launch_options);
+        println!("icon: {}", shortcut.icon);
+    }
+    overrides.set(&key, game_override);
+    overrides.save()
+}
+
+/// The key of a game given as an app id from list-games or as a key like "Heroic/fortnite",
+/// with the game itself if it was found by its app id
+fn resolve_game(game: &str) -> eyre::Result<(String, Option<ShortcutToImport>)> {
+    match game.parse::<u32>() {
+        Ok(app_id) => find_game(app_id).map(|(key, info)| (key, Some(info))),
+        Err(_) if game.contains('/') => Ok((game.to_string(), None)),
+        Err(_) => Err(eyre::eyre!("Unknown game {game}")),
+    }
+}
+
+fn find_game(app_id: u32) -> eyre::Result<(String, ShortcutToImport)> {
+    let (shortcuts, _) = find_games(&get_platforms());
+    shortcuts
+        .iter()
+        .flat_map(|(platform, infos)| infos.iter().map(move |info| (platform, info)))
+        .find(|(_, info)| info.shortcut.app_id == app_id)
+        .map(|(platform, info)| (sync::game_key(platform, info), info.clone()))
+        .ok_or_else(|| eyre::eyre!("No game found with app id {app_id}"))
+}
+
+/// Finds the games of all enabled platforms, errors are reported but do not stop the rest.
+/// Also returns the names of the platforms that failed.
+fn find_games(platforms: &Platforms) -> (PlatformShortcuts, Vec<String>) {
+    split_results(sync::fetch_platform_shortcuts(platforms))
+}
+
+/// Splits the results into the games found and the names of the platforms that failed
+fn split_results(
+    results: Vec<(String, eyre::Result<Vec<ShortcutToImport>>)>,
+) -> (PlatformShortcuts, Vec<String>) {
+    let mut failed = vec![];
+    let mut result = vec![];
+    for (name, shortcuts) in results {
+        match shortcuts {
+            Ok(shortcuts) => result.push((name, shortcuts)),
+            Err(err) => {
+                eprintln!("Failed finding games for {name}: {err}");
+                failed.push(name);
+            }
+        }
+    }
+    (result, failed)
+}
+
+fn download_images(settings: &Settings) -> eyre::Result<()> {
+    if settings.steamgrid_db.auth_key.is_none() {
+        return Err(eyre::eyre!(
+            "A SteamGridDB authentication key is needed to download images"
+        ));
+    }
+    let install = SteamInstall::find(&settings.steam)?;
+    let users = get_install_users(&install)?;
+    let rt = Runtime::new()?;
+    rt.block_on(sync::download_images(settings, &users, &mut None));
+    sync::fix_all_shortcut_icons(settings, &install)
+}
+
+fn show_backup(settings: &Settings, id: &str) -> eyre::Result<()> {
+    let snapshot = find_snapshot(id)?;
+    println!("{}", snapshot.title());
+    println!("{}", snapshot.path.to_string_lossy());
+    println!("Contains {}", snapshot.summary());
+    println!("Restoring it would change:");
+    for diff in diff_snapshot(&settings.steam, &snapshot)? {
+        println!("Steam user {}:", diff.user_id);
+        for change in &diff.shortcut_changes {
+            println!("  {change}");
+        }
+        if diff.images_missing > 0 {
+            println!("  + {} images", diff.images_missing);
+        }
+        if diff.shortcut_changes.is_empty() && diff.images_missing == 0 {
+            println!("  No changes");
+        }
+    }
+    Ok(())
+}
+
+fn restore(settings: &Settings, id: &str, parts: &[SnapshotPart]) -> eyre::Result<()> {
+    //Backups from older versions of BoilR are single shortcut files
+    let path = Path::new(id);
+    let install = SteamInstall::find(&settings.steam)?;
+    if path.is_file() {
+        create_snapshot(&install, None)?;
+        return if restore_backup(&settings.steam, path) {
+            Ok(())
+        } else {
+            Err(eyre::eyre!("No Steam user matches the backup {path:?}"))
+        };
+    }
+    let snapshot = find_snapshot(id)?;
+    create_snapshot(&install, None)?;
+    restore_snapshot(&install, &snapshot, parts)
+}
@@ -0,0 +1,167 @@
+use toml::{Table, Value};
+
+use crate::{
+    platforms::{load_platform, Platforms},
+    settings::Settings,
+};
+
+/// Reads a value from the configuration, the first part of the key is either a settings section
+/// (like `steam`) or the code name of a platform (like `heroic`).
+pub fn get_config_value(
+    settings: &Settings,
+    platforms: &Platforms,
+    key: &str,
+) -> eyre::Result<Value> {
+    let (section, rest) = split_key(key);
+    let (table, path) = match platforms.iter().find(|p| p.code_name() == section) {
+        Some(platform) => (
+            toml::from_str::<Table>(&platform.get_settings_serializable())?,
+            rest,
+        ),
+        None => (settings_table(settings)?, key),
+    };
+    if path.is_empty() {
+        return Ok(Value::Table(table));
+    }
+    get_at_path(&table, path)
+        .cloned()
+        .ok_or_else(|| eyre::eyre!("No configuration value named {key}"))
+}
+
+/// Changes a value in the configuration, values are parsed as TOML, and treated as strings if that fails.
+pub fn set_config_value(
+    settings: &mut Settings,
+    platforms: &mut Platforms,
+    key: &str,
+    value: &str,
+) -> eyre::Result<()> {
+    let value = parse_value(value);
+    let (section, rest) = split_key(key);
+    if let Some(platform) = platforms.iter_mut().find(|p| p.code_name() == section) {
+        let mut table = toml::from_str::<Table>(&platform.get_settings_serializable())?;
+        set_at_path(&mut table, rest, value.clone())?;
+        let new_platform = load_platform(section, toml::to_string(&table)?)?;
+        //Platforms fall back to defaults when their settings can not be read
+        let new_table = toml::from_str::<Table>(&new_platform.get_settings_serializable())?;
+        if get_at_path(&new_table, rest) != Some(&value) {
+            return Err(eyre::eyre!("{value} is not a valid value for {key}"));
+        }
+        *platform = new_platform;
+    } else {
+        let mut table = settings_table(settings)?;
+        set_at_path(&mut table, key, value.clone())?;
+        let new_settings: Settings = Value::Table(table)
+            .try_into()
+            .map_err(|e| eyre::eyre!("{value} is not a valid value for {key}: {e}"))?;
+        //Settings ignore keys they do not know
+        let new_table = settings_table(&new_settings)?;
+        match get_at_path(&new_table, key) {
+            None => return Err(eyre::eyre!("No configuration value named {key}")),
+            Some(new_value) if new_value != &value => {
+                return Err(eyre::eyre!("{value} is not a valid value for {key}"))
+            }
+            Some(_) => {}
+        }
+        *settings = new_settings;
+    }
+    Ok(())
+}
+
+/// Formats a value for the terminal, strings are printed without quotes so they are easy to use in scripts
+pub fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Table(t) => toml::to_string(t).unwrap_or_default(),
+        v => v.to_string(),
+    }
+}
+
+fn settings_table(settings: &Settings) -> eyre::Result<Table> {
+    Ok(Table::try_from(settings)?)
+}
+
+fn split_key(key: &str) -> (&str, &str) {
+    key.split_once('.').unwrap_or((key, ""))
+}
+
+fn parse_value(value: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {value}"))
+        .ok()
+        .and_then(|mut t| t.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+fn get_at_path<'a>(table: &'a Table, path: &str) -> Option<&'a Value> {
+    let mut parts = path.split('.');
+    let first = parts.next()?;
+    let mut current = table.get(first)?;
+    for part in parts {
+        current = current.as_table()?.get(part)?;
+    }
+    Some(current)
+}
+
+fn set_at_path(table: &mut Table, path: &str, value: Value) -> eyre::Result<()> {
+    match path.split_once('.') {
+        Some((first, rest)) => match table.get_mut(first).and_then(|v| v.as_table_mut()) {
+            Some(inner) => set_at_path(inner, rest, value),
+            None => Err(eyre::eyre!("No configuration section named {first}")),
+        },
+        None if path.is_empty() => Err(eyre::eyre!("A configuration key is needed")),
+        None => {
+            table.insert(path.to_string(), value);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn parses_values_as_toml() {
+        assert_eq!(Value::Boolean(true), parse_value("true"));
+        assert_eq!(Value::Integer(42), parse_value("42"));
+        assert_eq!(
+            Value::String("/home/deck/.steam".to_string()),
+            parse_value("/home/deck/.steam")
+        );
+        assert_eq!(
+            Value::String("quoted".to_string()),
+            parse_value("\"quoted\"")
+        );
+    }
+
+    #[test]
+    fn sets_nested_values() {
+        let mut table = toml::from_str::<Table>("[steam]\nstop_steam = false").unwrap();
+        set_at_path(&mut table, "steam.stop_steam", Value::Boolean(true)).unwrap();
+        set_at_path(&mut table, "steam.location", Value::String("x".into())).unwrap();
+        assert_eq!(
+            Some(&Value::Boolean(true)),
+            get_at_path(&table, "steam.stop_steam")
+        );
+        assert_eq!(
+            Some(&Value::String("x".into())),
+            get_at_path(&table, "steam.location")
+        );
+        assert!(set_at_path(&mut table, "missing.key", Value::Boolean(true)).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_settings() {
+        let mut settings: Settings = toml::from_str(include_str!("../defaultconfig.toml")).unwrap();
+        let mut platforms: Platforms = vec![];
+        set_config_value(&mut settings, &mut platforms, "steam.stop_steam", "true").unwrap();
+        assert!(settings.steam.stop_steam);
+        set_config_value(&mut settings, &mut platforms, "steam.location", "/home/deck/.steam")
+            .unwrap();
+        assert!(set_config_value(&mut settings, &mut platforms, "steam.typo", "true").is_err());
+        assert!(set_config_value(&mut settings, &mut platforms, "typo", "1").is_err());
+        let settings_table = settings_table(&settings).unwrap();
+        assert_eq!(None, get_at_path(&settings_table, "steam.typo"));
+    }
+}
@@ -0,0 +1,19 @@
+mod args;
+mod commands;
+mod config_values;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub use args::parse_args;
+pub use commands::run;
+
+static STDOUT_FOR_JSON: AtomicBool = AtomicBool::new(false);
+
+/// Keeps stdout for the JSON a command prints, so it can be parsed
+pub fn keep_stdout_for_json() {
+    STDOUT_FOR_JSON.store(true, Ordering::Relaxed);
+}
+
+pub fn stdout_is_for_json() -> bool {
+    STDOUT_FOR_JSON.load(Ordering::Relaxed)
+}
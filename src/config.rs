@@ -3,6 +3,8 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use serde::{de::DeserializeOwned, Serialize};
+
 #[cfg(target_family = "unix")]
 pub fn get_config_folder() -> PathBuf {
     let config_home = std::env::var("XDG_CONFIG_HOME");
@@ -41,6 +43,38 @@ pub fn get_cache_file() -> PathBuf {
     get_config_folder().join("cache.json")
 }
 
+pub fn get_app_ids_file() -> PathBuf {
+    get_config_folder().join("app_ids.json")
+}
+
+pub fn get_overrides_file() -> PathBuf {
+    get_config_folder().join("overrides.json")
+}
+
+pub fn get_collection_tags_file() -> PathBuf {
+    get_config_folder().join("collection_tags.json")
+}
+
+pub fn get_pending_collections_file() -> PathBuf {
+    get_config_folder().join("pending_collections.json")
+}
+
+pub fn get_duplicates_file() -> PathBuf {
+    get_config_folder().join("duplicates.json")
+}
+
+pub fn get_adopted_file() -> PathBuf {
+    get_config_folder().join("adopted.json")
+}
+
+pub fn get_proton_tools_file() -> PathBuf {
+    get_config_folder().join("proton_tools.json")
+}
+
+pub fn get_sync_report_file() -> PathBuf {
+    get_config_folder().join("sync_report.json")
+}
+
 pub fn get_backups_flder() -> PathBuf {
     let backups_path = get_config_folder().join("backup");
     let _ = create_dir_all(&backups_path);
@@ -52,6 +86,29 @@ pub fn get_boilr_links_path() -> PathBuf {
     get_config_folder().join("links")
 }
 
+/// Reads a JSON file BoilR keeps its state in, a missing or unreadable file gives the default.
+/// `what` names the content in the message when the file can not be read.
+pub fn load_json_or_default<T: DeserializeOwned + Default>(path: &Path, what: &str) -> T {
+    if !path.exists() {
+        return T::default();
+    }
+    match std::fs::read_to_string(path)
+        .map_err(eyre::Report::from)
+        .and_then(|content| Ok(serde_json::from_str(&content)?))
+    {
+        Ok(value) => value,
+        Err(err) => {
+            eprintln!("Could not read {what} from {path:?}: {err}");
+            T::default()
+        }
+    }
+}
+
+pub fn save_json<T: Serialize>(path: &Path, value: &T) -> eyre::Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
@@ -6,6 +6,19 @@
 #![deny(clippy::panic)]
 #![deny(clippy::todo)]
 
+/// Prints a message about what BoilR is doing, on stderr when stdout is kept for JSON
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if $crate::cli::stdout_is_for_json() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+mod backup;
+mod cli;
 mod config;
 mod migration;
 mod platforms;
@@ -16,20 +29,30 @@ mod sync;
 mod ui;
 mod game_scan;
 
+use std::process::ExitCode;
+
 use color_eyre::eyre::Result;
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     color_eyre::install()?;
     ensure_config_folder();
-    migration::migrate_config();
 
     let args: Vec<String> = std::env::args().collect();
-    if args.contains(&"--no-ui".to_string()) {
-        ui::run_sync()?;
-    } else {
-        ui::run_ui(args)?;
+    let command = cli::parse_args(&args);
+    if let Some(Ok(command)) = &command {
+        if command.prints_json() {
+            cli::keep_stdout_for_json();
+        }
+    }
+    migration::migrate_config();
+
+    match command {
+        Some(command) => Ok(cli::run(command)),
+        None => {
+            ui::run_ui(args)?;
+            Ok(ExitCode::SUCCESS)
+        }
     }
-    Ok(())
 }
 
 fn ensure_config_folder() {
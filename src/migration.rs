@@ -12,9 +12,9 @@ pub fn migrate_config() {
         //Migration from 0 to 1
         let old_path = &Path::new("config.toml");
         if old_path.exists() {
-            println!("Migrating from configuration version 0 to version 1");
+            log!("Migrating from configuration version 0 to version 1");
             let new_path = crate::config::get_config_file();
-            println!("Your configuration file will be moved to {new_path:?}");
+            log!("Your configuration file will be moved to {new_path:?}");
             let _ = std::fs::copy(old_path, new_path);
             let _ = std::fs::remove_file(old_path);
         }
@@ -43,4 +43,6 @@ pub fn migrate_config() {
             }
         }
     }
+
+    crate::backup::migrate_legacy_backups();
 }
@@ -4,7 +4,8 @@ use std::path::{Path, PathBuf};
 use steam_shortcuts_util::{shortcut::ShortcutOwned, Shortcut};
 
 use crate::platforms::{
-    load_settings, to_shortcuts_simple, FromSettingsString, GamesPlatform, ShortcutToImport,
+    load_settings, to_shortcuts_simple, FromSettingsString, GamesPlatform, PlatformGameId,
+    ShortcutToImport,
 };
 
 #[derive(Clone)]
@@ -134,6 +135,12 @@ impl From<AmazonGame> for ShortcutOwned {
     }
 }
 
+impl PlatformGameId for AmazonGame {
+    fn game_id(&self) -> Option<String> {
+        Some(self.id.clone())
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct AmazonSettings {
     pub enabled: bool,
@@ -6,7 +6,8 @@ use serde::{Deserialize, Serialize};
 use steam_shortcuts_util::{shortcut::ShortcutOwned, Shortcut};
 
 use crate::platforms::{
-    load_settings, to_shortcuts_simple, FromSettingsString, GamesPlatform, ShortcutToImport,
+    load_settings, to_shortcuts_simple, FromSettingsString, GamesPlatform, PlatformGameId,
+    ShortcutToImport,
 };
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -56,6 +57,12 @@ impl From<BottlesApp> for ShortcutOwned {
     }
 }
 
+impl PlatformGameId for BottlesApp {
+    fn game_id(&self) -> Option<String> {
+        Some(format!("{}/{}", self.bottle, self.name))
+    }
+}
+
 fn get_bottles() -> eyre::Result<Vec<Bottle>> {
     let json = get_bottles_output()?;
     let map: HashMap<String, Bottle> = serde_json::from_str(json.as_str())?;
@@ -6,6 +6,8 @@ use std::{
 use serde::Deserialize;
 use steam_shortcuts_util::{shortcut::ShortcutOwned, Shortcut};
 
+use crate::platforms::PlatformGameId;
+
 #[derive(Deserialize, Debug, Clone)]
 pub(crate) struct ManifestItem {
     #[serde(alias = "LaunchExecutable")]
@@ -142,6 +144,12 @@ impl From<ManifestItem> for ShortcutOwned {
     }
 }
 
+impl PlatformGameId for ManifestItem {
+    fn game_id(&self) -> Option<String> {
+        Some(self.app_name.clone())
+    }
+}
+
 impl ManifestItem {
     fn exe(&self) -> String {
         let manifest = self;
@@ -21,58 +21,46 @@ pub fn get_locations() -> Option<EpicPaths> {
 #[cfg(target_family = "unix")]
 mod unix {
     use super::EpicPaths;
-    use std::path::Path;
+    use crate::steam::get_compatdata_prefixes;
 
     pub fn get_locations() -> Option<EpicPaths> {
-        if let Ok(home) = std::env::var("HOME") {
-            let compat_folder_path = Path::new(&home)
-                .join(".steam")
-                .join("steam")
-                .join("steamapps")
-                .join("compatdata");
+        for dir in get_compatdata_prefixes() {
+            let binary_path = dir
+                .join("pfx")
+                .join("drive_c")
+                .join("Program Files (x86)")
+                .join("Epic Games")
+                .join("Launcher")
+                .join("Portal")
+                .join("Binaries");
+            if binary_path.exists() {
+                let launcher_path = if binary_path
+                    .join("Win32")
+                    .join("EpicGamesLauncher.exe")
+                    .exists()
+                {
+                    binary_path.join("Win32").join("EpicGamesLauncher.exe")
+                } else {
+                    binary_path.join("Win64").join("EpicGamesLauncher.exe")
+                };
+                if launcher_path.exists() {
+                    //We found a launcher, lets find the manifests
 
-            if let Ok(compat_folder) = std::fs::read_dir(compat_folder_path) {
-                for dir in compat_folder.flatten() {
-                    let binary_path = dir
-                        .path()
+                    let manifest_folder_path = dir
                         .join("pfx")
                         .join("drive_c")
-                        .join("Program Files (x86)")
-                        .join("Epic Games")
-                        .join("Launcher")
-                        .join("Portal")
-                        .join("Binaries");
-                    if binary_path.exists() {
-                        let launcher_path = if binary_path
-                            .join("Win32")
-                            .join("EpicGamesLauncher.exe")
-                            .exists()
-                        {
-                            binary_path.join("Win32").join("EpicGamesLauncher.exe")
-                        } else {
-                            binary_path.join("Win64").join("EpicGamesLauncher.exe")
-                        };
-                        if launcher_path.exists() {
-                            //We found a launcher, lets find the manifests
-
-                            let manifest_folder_path = dir
-                                .path()
-                                .join("pfx")
-                                .join("drive_c")
-                                .join("ProgramData")
-                                .join("Epic")
-                                .join("EpicGamesLauncher")
-                                .join("Data")
-                                .join("Manifests");
-                            if manifest_folder_path.exists() {
-                                //We found all we need
-                                return Some(EpicPaths {
-                                    launcher_path,
-                                    compat_folder_path: Some(dir.path()),
-                                    manifest_folder_path,
-                                });
-                            }
-                        }
+                        .join("ProgramData")
+                        .join("Epic")
+                        .join("EpicGamesLauncher")
+                        .join("Data")
+                        .join("Manifests");
+                    if manifest_folder_path.exists() {
+                        //We found all we need
+                        return Some(EpicPaths {
+                            launcher_path,
+                            compat_folder_path: Some(dir),
+                            manifest_folder_path,
+                        });
                     }
                 }
             }
@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
 
 use crate::platforms::{
-    load_settings, to_shortcuts, FromSettingsString, GamesPlatform, NeedsProton, ShortcutToImport,
+    load_settings, to_shortcuts, FromSettingsString, GamesPlatform, NeedsProton, PlatformGameId,
+    ShortcutToImport,
 };
 
 use super::FlatpakSettings;
@@ -25,6 +26,12 @@ impl From<FlatpakApp> for ShortcutOwned {
     }
 }
 
+impl PlatformGameId for FlatpakApp {
+    fn game_id(&self) -> Option<String> {
+        Some(self.id.clone())
+    }
+}
+
 impl NeedsProton<FlatpakPlatform> for FlatpakApp {
     fn needs_proton(&self, _platform: &FlatpakPlatform) -> bool {
         false
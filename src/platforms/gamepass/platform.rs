@@ -70,6 +70,10 @@ impl GamesPlatform for GamePassPlatForm {
                             shortcut: shortcut.to_owned(),
                             needs_proton: false,
                             needs_symlinks: false,
+                            game_id: Some(game.aum_id()),
+                            runner: None,
+                            artwork: vec![],
+                            legacy_app_id: None,
                         }
                     })
             });
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use sqlite::{OpenFlags, State};
+
+use crate::platforms::PlatformImage;
+use crate::steamgriddb::ImageType;
+
+const WEBCACHE_QUERY: &str = "SELECT WebCache.releaseKey, WebCache.userId, WebCacheResourceTypes.type, \
+    WebCacheResources.filename \
+    FROM WebCacheResources \
+    JOIN WebCache ON WebCache.id = WebCacheResources.webCacheId \
+    JOIN WebCacheResourceTypes ON WebCacheResourceTypes.id = WebCacheResources.webCacheResourceTypeId \
+    WHERE WebCache.releaseKey LIKE 'gog\\_%' ESCAPE '\\'";
+
+/// The image type Steam uses for a kind of image Galaxy keeps in its web cache
+fn image_type(resource_type: &str) -> Option<ImageType> {
+    match resource_type {
+        "verticalCover" => Some(ImageType::Grid),
+        "background" => Some(ImageType::Hero),
+        "logo" => Some(ImageType::Logo),
+        "squareIcon" => Some(ImageType::Icon),
+        _ => None,
+    }
+}
+
+/// The images Galaxy downloaded for the GOG games, by their game id.
+/// Galaxy lists them in its database and keeps them in webcache/<user id>/gog/<game id>,
+/// older versions of Galaxy do not have the database.
+pub(crate) fn read_webcache_images(
+    galaxy_folder: &Path,
+) -> eyre::Result<HashMap<String, Vec<PlatformImage>>> {
+    let db_path = galaxy_folder.join("storage").join("galaxy-2.0.db");
+    if !db_path.exists() {
+        return Ok(HashMap::new());
+    }
+    let connection =
+        sqlite::Connection::open_with_flags(db_path, OpenFlags::new().with_read_only())?;
+    let mut statement = connection.prepare(WEBCACHE_QUERY)?;
+    let mut images: HashMap<String, Vec<PlatformImage>> = HashMap::new();
+    while let State::Row = statement.next()? {
+        let release_key = statement.read::<String, _>(0)?;
+        let user_id = statement.read::<String, _>(1)?;
+        let resource_type = statement.read::<String, _>(2)?;
+        let file_name = statement.read::<String, _>(3)?;
+        let (Some(game_id), Some(image_type)) =
+            (release_key.strip_prefix("gog_"), image_type(&resource_type))
+        else {
+            continue;
+        };
+        let path = galaxy_folder
+            .join("webcache")
+            .join(user_id)
+            .join("gog")
+            .join(game_id)
+            .join(file_name);
+        if path.is_file() {
+            images
+                .entry(game_id.to_string())
+                .or_default()
+                .push(PlatformImage::file(image_type, path));
+        }
+    }
+    Ok(images)
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
+
+    use super::*;
+
+    #[test]
+    fn reads_images_of_gog_games_that_are_cached() {
+        let dir = tempfile::tempdir().unwrap();
+        let galaxy = dir.path();
+        std::fs::create_dir_all(galaxy.join("storage")).unwrap();
+        let connection = sqlite::open(galaxy.join("storage").join("galaxy-2.0.db")).unwrap();
+        connection
+            .execute(
+                "CREATE TABLE WebCache (id INTEGER PRIMARY KEY, releaseKey TEXT, userId INTEGER);
+                CREATE TABLE WebCacheResourceTypes (id INTEGER PRIMARY KEY, type TEXT);
+                CREATE TABLE WebCacheResources (webCacheId INTEGER, webCacheResourceTypeId INTEGER, filename TEXT);
+                INSERT INTO WebCache VALUES (1, 'gog_1207658924', 4242);
+                INSERT INTO WebCache VALUES (2, 'steam_220', 4242);
+                INSERT INTO WebCacheResourceTypes VALUES (1, 'verticalCover');
+                INSERT INTO WebCacheResourceTypes VALUES (2, 'background');
+                INSERT INTO WebCacheResourceTypes VALUES (3, 'screenshot');
+                INSERT INTO WebCacheResources VALUES (1, 1, 'cover.webp');
+                INSERT INTO WebCacheResources VALUES (1, 2, 'missing.webp');
+                INSERT INTO WebCacheResources VALUES (1, 3, 'screenshot.webp');
+                INSERT INTO WebCacheResources VALUES (2, 1, 'steam.webp');",
+            )
+            .unwrap();
+        let game_folder = galaxy.join("webcache/4242/gog/1207658924");
+        std::fs::create_dir_all(&game_folder).unwrap();
+        std::fs::write(game_folder.join("cover.webp"), b"RIFF").unwrap();
+        std::fs::write(game_folder.join("screenshot.webp"), b"RIFF").unwrap();
+
+        let images = read_webcache_images(galaxy).unwrap();
+        assert_eq!(1, images.len());
+        assert_eq!(
+            vec![PlatformImage::file(ImageType::Grid, game_folder.join("cover.webp"))],
+            images["1207658924"]
+        );
+    }
+}
@@ -1,8 +1,11 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 use steam_shortcuts_util::{shortcut::ShortcutOwned, Shortcut};
 
+use crate::platforms::{PlatformGameId, PlatformImage};
+use crate::steamgriddb::ImageType;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct GogGame {
     pub name: String,
@@ -36,11 +39,17 @@ pub struct GogShortcut {
     pub arguments: String,
 }
 
+impl GogShortcut {
+    /// The icon GOG puts in the game folder
+    fn icon_path(&self) -> PathBuf {
+        Path::new(&self.game_folder).join(format!("goggame-{}.ico", self.game_id))
+    }
+}
+
 impl From<GogShortcut> for ShortcutOwned {
     fn from(gogs: GogShortcut) -> Self {
+        let icon_path = gogs.icon_path();
         let exe = Path::new(&gogs.game_folder).join(gogs.path);
-        let icon_file = format!("goggame-{}.ico", gogs.game_id);
-        let icon_path = Path::new(&gogs.game_folder).join(icon_file);
         let icon = if icon_path.exists() {
             icon_path.to_string_lossy().to_string()
         } else {
@@ -73,3 +82,18 @@ impl From<GogShortcut> for ShortcutOwned {
         owned_shortcut
     }
 }
+
+impl PlatformGameId for GogShortcut {
+    fn game_id(&self) -> Option<String> {
+        Some(self.game_id.clone())
+    }
+
+    fn artwork(&self) -> Vec<PlatformImage> {
+        let icon_path = self.icon_path();
+        if icon_path.exists() {
+            vec![PlatformImage::file(ImageType::Icon, icon_path)]
+        } else {
+            vec![]
+        }
+    }
+}
@@ -1,10 +1,12 @@
 use std::path::{Path, PathBuf};
 
 use crate::platforms::{
-    load_settings, to_shortcuts, FromSettingsString, GamesPlatform, NeedsProton, ShortcutToImport,
+    add_artwork, load_settings, to_shortcuts, FromSettingsString, GamesPlatform, NeedsProton,
+    ShortcutToImport,
 };
 
 use super::{
+    galaxy_webcache::read_webcache_images,
     gog_config::GogConfig,
     gog_game::{GogGame, GogShortcut},
     GogSettings,
@@ -16,13 +18,16 @@ pub struct GogPlatform {
 }
 
 impl GogPlatform {
-    fn get_shortcuts(&self) -> eyre::Result<Vec<GogShortcut>> {
-        let gog_location = self
-            .settings
+    fn gog_location(&self) -> PathBuf {
+        self.settings
             .location
             .as_ref()
             .map(|location| Path::new(&location).to_path_buf())
-            .unwrap_or_else(default_location);
+            .unwrap_or_else(default_location)
+    }
+
+    fn get_shortcuts(&self) -> eyre::Result<Vec<GogShortcut>> {
+        let gog_location = self.gog_location();
         if !gog_location.exists() {
             return Err(eyre::format_err!("Could not find path: {:?}", gog_location));
         }
@@ -242,7 +247,12 @@ impl GamesPlatform for GogPlatform {
     }
 
     fn get_shortcut_info(&self) -> eyre::Result<Vec<ShortcutToImport>> {
-        to_shortcuts(self, self.get_shortcuts())
+        let mut shortcuts = to_shortcuts(self, self.get_shortcuts())?;
+        match read_webcache_images(&self.gog_location()) {
+            Ok(artwork) => add_artwork(&mut shortcuts, &artwork),
+            Err(err) => eprintln!("Could not read the images cached by GOG Galaxy: {err}"),
+        }
+        Ok(shortcuts)
     }
 
     fn render_ui(&mut self, ui: &mut egui::Ui) {
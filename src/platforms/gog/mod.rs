@@ -1,3 +1,4 @@
+mod galaxy_webcache;
 mod gog_config;
 mod gog_game;
 mod gog_platform;
@@ -5,3 +6,5 @@ mod gog_settings;
 
 pub use gog_platform::GogPlatform;
 pub(crate) use gog_settings::GogSettings;
+pub(crate) use gog_game::GogShortcut;
+pub(crate) use gog_platform::get_gog_shortcuts_from_game_folders;
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// The settings Heroic keeps for a single game in GamesConfig/<app name>.json
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HeroicGameConfig {
+    #[serde(default)]
+    pub wine_version: Option<HeroicWineVersion>,
+    /// The Wine prefix, for Proton this is the compat data folder
+    #[serde(default)]
+    pub wine_prefix: Option<String>,
+    /// Heroic spells it without the n
+    #[serde(default, rename = "enviromentOptions", alias = "environmentOptions")]
+    pub environment_options: Vec<HeroicEnvironmentOption>,
+    /// Arguments given to the game after its own
+    #[serde(default)]
+    pub launcher_args: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeroicEnvironmentOption {
+    pub key: String,
+    #[serde(default)]
+    pub value: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeroicWineVersion {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub bin: String,
+    /// proton, wine, crossover or toolkit
+    #[serde(default, rename = "type")]
+    pub wine_type: String,
+}
+
+impl HeroicGameConfig {
+    fn wine_type(&self) -> Option<&str> {
+        self.wine_version
+            .as_ref()
+            .map(|version| version.wine_type.as_str())
+            .filter(|wine_type| !wine_type.is_empty())
+    }
+
+    /// Steam can not run a game with a Wine or CrossOver build Heroic has, only with Proton
+    pub fn uses_custom_wine(&self) -> bool {
+        self.wine_type()
+            .is_some_and(|wine_type| wine_type != "proton")
+    }
+
+    /// The launch options of a game Steam starts directly, with the environment and arguments Heroic gives it.
+    /// Games that run with Proton get the prefix Heroic made for them, so their saves and settings are kept.
+    pub fn launch_options(&self, launch_options: &str, needs_proton: bool) -> String {
+        let mut variables = vec![];
+        if let (true, Some("proton"), Some(prefix)) =
+            (needs_proton, self.wine_type(), &self.wine_prefix)
+        {
+            if !prefix.is_empty() {
+                variables.push(format!("STEAM_COMPAT_DATA_PATH=\"{prefix}\""));
+            }
+        }
+        variables.extend(
+            self.environment_options
+                .iter()
+                .filter(|option| !option.key.is_empty())
+                .map(|option| format!("{}=\"{}\"", option.key, option.value.replace('"', "\\\""))),
+        );
+        let arguments: Vec<&str> = [
+            launch_options,
+            self.launcher_args.as_deref().unwrap_or_default(),
+        ]
+        .into_iter()
+        .map(str::trim)
+        .filter(|arguments| !arguments.is_empty())
+        .collect();
+        let arguments = arguments.join(" ");
+        if variables.is_empty() {
+            return arguments;
+        }
+        format!("{} %command% {arguments}", variables.join(" "))
+            .trim_end()
+            .to_string()
+    }
+}
+
+/// Reads the configs Heroic has for its games, keyed by the app name of the game.
+/// Games that never had their settings opened do not have one.
+pub(crate) fn read_game_configs(heroic_folder: &Path) -> HashMap<String, HeroicGameConfig> {
+    let Ok(files) = std::fs::read_dir(heroic_folder.join("GamesConfig")) else {
+        return HashMap::new();
+    };
+    files
+        .flatten()
+        .map(|file| file.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .filter_map(|path| {
+            let app_name = path.file_stem()?.to_string_lossy().to_string();
+            let config = read_game_config(&path, &app_name)?;
+            Some((app_name, config))
+        })
+        .collect()
+}
+
+/// The file has the settings under the app name of the game, next to the version of the file.
+fn read_game_config(path: &Path, app_name: &str) -> Option<HeroicGameConfig> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let mut configs = serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(&content)
+        .map_err(|err| eprintln!("Could not read the Heroic config {path:?}: {err}"))
+        .ok()?;
+    serde_json::from_value(configs.remove(app_name)?)
+        .map_err(|err| eprintln!("Could not read the Heroic config {path:?}: {err}"))
+        .ok()
+}
+
+/// If a game Heroic runs is made for Windows. The platform Heroic has for the game decides,
+/// without one the game is a Windows game if Heroic has a Wine or Proton version for it or it starts an exe.
+pub(crate) fn is_windows_game(
+    platform: Option<&str>,
+    executable: &str,
+    config: Option<&HeroicGameConfig>,
+) -> bool {
+    match platform.map(str::to_lowercase).as_deref() {
+        Some("windows") | Some("win32") => true,
+        Some("linux") | Some("mac") | Some("osx") | Some("browser") => false,
+        _ => {
+            config.is_some_and(|config| config.wine_version.is_some())
+                || executable.to_lowercase().ends_with(".exe")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn reads_config_of_game() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("GamesConfig")).unwrap();
+        std::fs::write(
+            dir.path().join("GamesConfig/amzn1.adg.product.1234.json"),
+            r#"{"amzn1.adg.product.1234":{"wineVersion":{"bin":"/home/deck/.config/heroic/tools/proton/GE-Proton9-20/proton","name":"Proton - GE-Proton9-20","type":"proton"},"winePrefix":"/home/deck/Games/Heroic/Prefixes/game"},"version":"v0","explicit":true}"#,
+        )
+        .unwrap();
+
+        let mut configs = read_game_configs(dir.path());
+        let config = configs.remove("amzn1.adg.product.1234").unwrap();
+        assert_eq!(
+            Some("/home/deck/Games/Heroic/Prefixes/game".to_string()),
+            config.wine_prefix
+        );
+        assert!(!config.uses_custom_wine());
+        assert_eq!("proton", config.wine_version.unwrap().wine_type);
+        assert!(configs.is_empty());
+        assert!(read_game_configs(&dir.path().join("missing")).is_empty());
+    }
+
+    #[test]
+    fn platform_decides_if_game_is_for_windows() {
+        let with_wine = HeroicGameConfig {
+            wine_version: Some(HeroicWineVersion::default()),
+            ..Default::default()
+        };
+        assert!(is_windows_game(Some("Windows"), "/games/game", None));
+        assert!(!is_windows_game(
+            Some("linux"),
+            "/games/game.exe",
+            Some(&with_wine)
+        ));
+        assert!(is_windows_game(None, "/games/game", Some(&with_wine)));
+        assert!(is_windows_game(None, "/games/Game.EXE", None));
+        assert!(!is_windows_game(None, "/games/game.sh", None));
+    }
+
+    fn config(wine_type: &str) -> HeroicGameConfig {
+        serde_json::from_value(serde_json::json!({
+            "wineVersion": {"bin": "/usr/bin/wine", "name": "Wine", "type": wine_type},
+            "winePrefix": "/home/deck/Games/Heroic/Prefixes/game",
+            "enviromentOptions": [{"key": "DXVK_HUD", "value": "fps"}, {"key": "", "value": "ignored"}],
+            "launcherArgs": "-skipintro"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn launch_options_have_environment_prefix_and_arguments() {
+        assert_eq!(
+            "STEAM_COMPAT_DATA_PATH=\"/home/deck/Games/Heroic/Prefixes/game\" DXVK_HUD=\"fps\" %command% -windowed -skipintro",
+            config("proton").launch_options("-windowed", true)
+        );
+        assert_eq!(
+            "DXVK_HUD=\"fps\" %command% -skipintro",
+            config("proton").launch_options("", false)
+        );
+        let without_environment = HeroicGameConfig {
+            launcher_args: Some("-skipintro".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            "-windowed -skipintro",
+            without_environment.launch_options("-windowed", true)
+        );
+    }
+
+    #[test]
+    fn only_proton_runs_in_steam() {
+        assert!(config("wine").uses_custom_wine());
+        assert!(config("crossover").uses_custom_wine());
+        assert!(!config("proton").uses_custom_wine());
+        assert!(!HeroicGameConfig::default().uses_custom_wine());
+    }
+}
@@ -0,0 +1,56 @@
+use std::path::Path;
+
+use steam_shortcuts_util::{shortcut::ShortcutOwned, Shortcut};
+
+/// A game Heroic did not install with legendary or gog, like an Amazon game or a sideloaded app.
+/// The executable is known, so it can be launched without Heroic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeroicApp {
+    pub app_name: String,
+    pub title: String,
+    pub install_path: String,
+    /// The full path of the executable
+    pub executable: String,
+    pub working_dir: String,
+    pub launch_parameters: String,
+    /// If the executable is made for Windows, so it needs Proton outside of Windows
+    pub is_windows: bool,
+}
+
+impl HeroicApp {
+    pub fn is_installed(&self) -> bool {
+        Path::new(&self.executable).exists()
+    }
+}
+
+/// Quotes a path so Steam can start it when it has spaces
+fn quoted(path: &str) -> String {
+    #[cfg(target_family = "unix")]
+    if !path.is_empty() && !path.starts_with('\"') && !path.ends_with('\"') {
+        return format!("\"{path}\"");
+    }
+    path.to_string()
+}
+
+impl From<HeroicApp> for ShortcutOwned {
+    fn from(app: HeroicApp) -> Self {
+        let target = quoted(&app.executable);
+        let working_dir = quoted(&app.working_dir);
+        let shortcut = Shortcut::new(
+            "0",
+            app.title.as_str(),
+            &target,
+            &working_dir,
+            &target,
+            "",
+            app.launch_parameters.as_str(),
+        );
+
+        let mut owned_shortcut = shortcut.to_owned();
+        owned_shortcut.tags.push("Heroic".to_owned());
+        owned_shortcut.tags.push("Ready TO Play".to_owned());
+        owned_shortcut.tags.push("Installed".to_owned());
+
+        owned_shortcut
+    }
+}
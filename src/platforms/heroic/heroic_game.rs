@@ -10,6 +10,9 @@ pub struct HeroicGame {
     pub install_path: String,
     pub executable: String,
     pub launch_parameters: String,
+    /// The platform of the build legendary installed, like Windows or Mac
+    #[serde(default)]
+    pub platform: Option<String>,
 }
 
 impl HeroicGame {
@@ -1,17 +1,25 @@
 use steam_shortcuts_util::{shortcut::ShortcutOwned, Shortcut};
 
-use super::{HeroicGame, InstallationMode};
+use crate::platforms::{PlatformGameId, PlatformImage};
+
+use super::{HeroicApp, HeroicGame, InstallationMode};
 
 #[derive(Clone)]
 pub enum HeroicGameType {
     Epic(HeroicGame),
     //The bool is if it is windows (true) or not (false)
     Gog(crate::platforms::GogShortcut, bool),
+    /// An Amazon game installed with Nile
+    Amazon(HeroicApp),
+    /// An app added to Heroic by hand
+    Sideload(HeroicApp),
     //The string is the app name
     Heroic {
         title: String,
         app_name: String,
         install_mode: InstallationMode,
+        /// The store the game is from, "legendary" for Epic, "gog", "nile" for Amazon or "sideload"
+        runner: &'static str,
     },
 }
 
@@ -20,10 +28,9 @@ impl HeroicGameType {
         match self {
             HeroicGameType::Epic(g) => g.app_name.as_ref(),
             HeroicGameType::Gog(g, _) => g.game_id.as_ref(),
+            HeroicGameType::Amazon(a) | HeroicGameType::Sideload(a) => a.app_name.as_ref(),
             HeroicGameType::Heroic {
-                title: _,
-                app_name,
-                install_mode: _,
+                app_name, ..
             } => app_name,
         }
     }
@@ -32,10 +39,9 @@ impl HeroicGameType {
         match self {
             HeroicGameType::Epic(g) => g.title.as_ref(),
             HeroicGameType::Gog(g, _) => g.name.as_ref(),
+            HeroicGameType::Amazon(a) | HeroicGameType::Sideload(a) => a.title.as_ref(),
             HeroicGameType::Heroic {
-                title,
-                app_name: _,
-                install_mode: _,
+                title, ..
             } => title.as_ref(),
         }
     }
@@ -46,23 +52,52 @@ impl From<HeroicGameType> for ShortcutOwned {
         match heroic_game_type {
             HeroicGameType::Epic(epic) => epic.into(),
             HeroicGameType::Gog(gog, _) => gog.into(),
+            HeroicGameType::Amazon(app) | HeroicGameType::Sideload(app) => app.into(),
             HeroicGameType::Heroic {
                 title,
                 app_name,
                 install_mode,
+                ..
             } => {
                 let launch_parameter = format!("heroic://launch/{app_name}");
                 let (exe, parameter) = match install_mode {
                     InstallationMode::FlatPak => (
-                        "flatpak",
+                        "flatpak".to_string(),
                         format!(
                             "run com.heroicgameslauncher.hgl {launch_parameter} --no-gui --no-sandbox"
                         ),
                     ),
-                    InstallationMode::UserBin => ("heroic", launch_parameter),
+                    InstallationMode::UserBin => ("heroic".to_string(), launch_parameter),
+                    InstallationMode::Executable(executable) => {
+                        (format!("\"{executable}\""), launch_parameter)
+                    }
                 };
-                Shortcut::new("0", title.as_str(), exe, "", "", "", parameter.as_str()).to_owned()
+                Shortcut::new("0", title.as_str(), &exe, "", "", "", parameter.as_str()).to_owned()
             }
         }
     }
 }
+
+impl PlatformGameId for HeroicGameType {
+    fn game_id(&self) -> Option<String> {
+        Some(self.app_name().to_string())
+    }
+
+    fn runner(&self) -> Option<String> {
+        let runner = match self {
+            HeroicGameType::Epic(_) => "legendary",
+            HeroicGameType::Gog(..) => "gog",
+            HeroicGameType::Amazon(_) => "nile",
+            HeroicGameType::Sideload(_) => "sideload",
+            HeroicGameType::Heroic { runner, .. } => runner,
+        };
+        Some(runner.to_string())
+    }
+
+    fn artwork(&self) -> Vec<PlatformImage> {
+        match self {
+            HeroicGameType::Gog(gog, _) => gog.artwork(),
+            _ => vec![],
+        }
+    }
+}
@@ -1,9 +1,13 @@
 use serde::Deserialize;
 
-use super::{HeroicGame, HeroicGameType, HeroicSettings};
-use crate::platforms::{load_settings, FromSettingsString, GamesPlatform};
+use super::games_config::{is_windows_game, read_game_configs, HeroicGameConfig};
+use super::nile::get_nile_apps;
+use super::sideload::get_sideload_apps;
+use super::store_cache::read_store_cache_images;
+use super::{HeroicApp, HeroicGame, HeroicGameType, HeroicSettings};
+use crate::platforms::{add_artwork, load_settings, FromSettingsString, GamesPlatform};
 use crate::platforms::{to_shortcuts, NeedsProton, ShortcutToImport};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 use std::path::PathBuf;
@@ -11,13 +15,15 @@ use std::path::PathBuf;
 #[derive(Clone)]
 pub struct HeroicPlatform {
     pub settings: HeroicSettings,
-    pub(crate) heroic_games: Option<Vec<HeroicGameType>>,
+    pub(crate) heroic_games: Option<Vec<(HeroicConfigFolder, Vec<HeroicGameType>)>>,
 }
 
-#[derive(Deserialize, Debug, Clone, Copy)]
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum InstallationMode {
     FlatPak,
     UserBin,
+    /// A Heroic the user chose for a config folder, like a portable install
+    Executable(String),
 }
 
 #[derive(Deserialize)]
@@ -33,31 +39,64 @@ struct HeroicGogPath {
     install_path: String,
 }
 
-fn get_installed_json_location(install_mode: &InstallationMode) -> PathBuf {
-    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "".to_string());
-    match install_mode {
-        InstallationMode::FlatPak => Path::new(&home_dir)
-            .join(".var/app/com.heroicgameslauncher.hgl/config/heroic/legendaryConfig/legendary/installed.json"),
-        InstallationMode::UserBin => Path::new(&home_dir).join(".config/heroic/legendaryConfig/legendary/installed.json"),
-    }
+const FLATPAK_CONFIG_FOLDER: &str = ".var/app/com.heroicgameslauncher.hgl/config/heroic";
+
+/// A folder Heroic keeps its configuration, libraries and caches in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeroicConfigFolder {
+    pub path: PathBuf,
+    /// How Heroic is started for the games in this folder that are launched through Heroic
+    pub install_mode: InstallationMode,
+    /// If the folder is set in the settings, instead of found by BoilR
+    pub custom: bool,
 }
 
-fn get_gog_installed_location(install_mode: &InstallationMode) -> PathBuf {
-    let home_dir = std::env::var("HOME").unwrap_or_else(|_| "".to_string());
-    match install_mode {
-        InstallationMode::FlatPak => Path::new(&home_dir)
-            .join(".var/app/com.heroicgameslauncher.hgl/config/heroic/gog_store/installed.json"),
-        InstallationMode::UserBin => {
-            Path::new(&home_dir).join(".config/heroic/gog_store/installed.json")
+impl HeroicConfigFolder {
+    fn new(path: PathBuf, custom: bool, executable: Option<&str>) -> Self {
+        let install_mode = if let Some(executable) = executable {
+            InstallationMode::Executable(executable.to_string())
+        } else if path.to_string_lossy().contains("com.heroicgameslauncher.hgl") {
+            InstallationMode::FlatPak
+        } else {
+            InstallationMode::UserBin
+        };
+        Self {
+            path,
+            install_mode,
+            custom,
         }
     }
 }
 
-fn get_shortcuts_from_install_mode(
-    install_mode: &InstallationMode,
-) -> eyre::Result<Vec<HeroicGame>> {
-    let installed_path = get_installed_json_location(install_mode);
-    get_shortcuts_from_location(installed_path)
+/// The Flatpak folder, the folder in XDG_CONFIG_HOME and the default folder in .config,
+/// followed by the folders from the settings, like a portable install or the Heroic of another user
+fn find_config_folders(
+    home: &str,
+    xdg_config_home: Option<&str>,
+    settings: &HeroicSettings,
+) -> Vec<HeroicConfigFolder> {
+    let mut found = vec![Path::new(home).join(FLATPAK_CONFIG_FOLDER)];
+    if let Some(xdg_config_home) = xdg_config_home.filter(|folder| !folder.is_empty()) {
+        found.push(Path::new(xdg_config_home).join("heroic"));
+    }
+    found.push(Path::new(home).join(".config/heroic"));
+    let custom = settings
+        .config_folders
+        .iter()
+        .filter(|folder| !folder.trim().is_empty())
+        .map(|folder| PathBuf::from(folder.trim()));
+    let mut folders: Vec<HeroicConfigFolder> = vec![];
+    for (path, custom) in found
+        .into_iter()
+        .map(|path| (path, false))
+        .chain(custom.map(|path| (path, true)))
+    {
+        if !folders.iter().any(|folder| folder.path == path) {
+            let executable = settings.heroic_executable(&path);
+            folders.push(HeroicConfigFolder::new(path, custom, executable));
+        }
+    }
+    folders
 }
 
 fn get_shortcuts_from_location<P: AsRef<Path>>(path: P) -> eyre::Result<Vec<HeroicGame>> {
@@ -76,13 +115,142 @@ fn get_shortcuts_from_location<P: AsRef<Path>>(path: P) -> eyre::Result<Vec<Hero
 }
 
 impl HeroicPlatform {
-    pub fn get_heroic_games(&self) -> eyre::Result<Vec<HeroicGameType>> {
-        let install_modes = vec![InstallationMode::FlatPak, InstallationMode::UserBin];
+    /// The folders BoilR looks for Heroic games in
+    pub fn config_folders(&self) -> Vec<HeroicConfigFolder> {
+        let home = std::env::var("HOME").unwrap_or_default();
+        let xdg_config_home = std::env::var("XDG_CONFIG_HOME").ok();
+        find_config_folders(&home, xdg_config_home.as_deref(), &self.settings)
+    }
+
+    /// The games of every Heroic config folder that exists, together with the folder.
+    /// A game in several folders is only in the first of them.
+    pub fn get_heroic_games_by_folder(&self) -> Vec<(HeroicConfigFolder, Vec<HeroicGameType>)> {
+        self.get_games_by_folder(self.config_folders())
+    }
+
+    fn get_games_by_folder(
+        &self,
+        folders: Vec<HeroicConfigFolder>,
+    ) -> Vec<(HeroicConfigFolder, Vec<HeroicGameType>)> {
+        let mut seen = HashSet::new();
+        folders
+            .into_iter()
+            .filter(|folder| folder.path.exists())
+            .map(|folder| {
+                let configs = read_game_configs(&folder.path);
+                let games = self.get_games_in_folder(&folder, &configs, &mut seen);
+                (folder, games)
+            })
+            .collect()
+    }
+
+    /// The shortcuts for the games of every folder that exists, a game in several folders is imported from the first,
+    /// so its key and app id do not change between the folders on every import
+    fn get_shortcuts_of_folders(
+        &self,
+        folders: Vec<HeroicConfigFolder>,
+    ) -> eyre::Result<Vec<ShortcutToImport>> {
+        let mut shortcuts = vec![];
+        let mut seen = HashSet::new();
+        for folder in folders.into_iter().filter(|folder| folder.path.exists()) {
+            let configs = read_game_configs(&folder.path);
+            let games = self.get_games_in_folder(&folder, &configs, &mut seen);
+            shortcuts.extend(self.get_shortcuts_in_folder(&folder, &configs, games)?);
+        }
+        Ok(shortcuts)
+    }
 
-        let mut heroic_games = self.get_epic_games(&install_modes)?;
-        let gog_games = get_gog_games(&self.settings, &install_modes)?;
-        heroic_games.extend(gog_games);
-        Ok(heroic_games)
+    /// The games in a folder, leaving out the games whose app name is in `seen`
+    fn get_games_in_folder(
+        &self,
+        folder: &HeroicConfigFolder,
+        configs: &HashMap<String, HeroicGameConfig>,
+        seen: &mut HashSet<String>,
+    ) -> Vec<HeroicGameType> {
+        let mut heroic_games = self.get_epic_games(folder, configs);
+        heroic_games.extend(get_gog_games(self, folder, configs));
+        match get_nile_apps(&folder.path) {
+            Ok(apps) => heroic_games.extend(self.app_games(apps, folder, configs, "nile")),
+            Err(err) => eprintln!("Could not read the Amazon games of Heroic: {err}"),
+        }
+        match get_sideload_apps(&folder.path, configs) {
+            Ok(apps) => heroic_games.extend(self.app_games(apps, folder, configs, "sideload")),
+            Err(err) => eprintln!("Could not read the sideloaded apps of Heroic: {err}"),
+        }
+        heroic_games.retain(|game| seen.insert(game.app_name().to_string()));
+        heroic_games
+    }
+
+    /// Games the user wants to launch through Heroic, and games set up with a Wine build of Heroic,
+    /// since Steam can only run them with its own Proton
+    fn launch_through_heroic(
+        &self,
+        configs: &HashMap<String, HeroicGameConfig>,
+        app_name: &str,
+    ) -> bool {
+        self.settings.is_heroic_launch(app_name)
+            || configs
+                .get(app_name)
+                .is_some_and(|config| config.uses_custom_wine())
+    }
+
+    /// The games for Amazon or sideloaded apps, apps without an executable are launched through Heroic
+    fn app_games(
+        &self,
+        apps: Vec<HeroicApp>,
+        folder: &HeroicConfigFolder,
+        configs: &HashMap<String, HeroicGameConfig>,
+        runner: &'static str,
+    ) -> Vec<HeroicGameType> {
+        apps.into_iter()
+            .filter_map(|app| {
+                if self.launch_through_heroic(configs, &app.app_name) || app.executable.is_empty() {
+                    Some(HeroicGameType::Heroic {
+                        title: app.title,
+                        app_name: app.app_name,
+                        install_mode: folder.install_mode.clone(),
+                        runner,
+                    })
+                } else if app.is_installed() {
+                    Some(match runner {
+                        "nile" => HeroicGameType::Amazon(app),
+                        _ => HeroicGameType::Sideload(app),
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// The shortcuts for the games in a config folder, with the launch options and art from that folder
+    fn get_shortcuts_in_folder(
+        &self,
+        folder: &HeroicConfigFolder,
+        configs: &HashMap<String, HeroicGameConfig>,
+        games: Vec<HeroicGameType>,
+    ) -> eyre::Result<Vec<ShortcutToImport>> {
+        //Games launched through Heroic get their config from Heroic itself
+        let configs: HashMap<String, &HeroicGameConfig> = games
+            .iter()
+            .filter(|game| !matches!(game, HeroicGameType::Heroic { .. }))
+            .filter_map(|game| {
+                let app_name = game.app_name();
+                configs
+                    .get(app_name)
+                    .map(|config| (app_name.to_string(), config))
+            })
+            .collect();
+        let mut shortcuts = to_shortcuts(self, Ok(games))?;
+        for shortcut in &mut shortcuts {
+            let config = shortcut.game_id.as_ref().and_then(|id| configs.get(id));
+            if let Some(config) = config {
+                shortcut.shortcut.launch_options =
+                    config.launch_options(&shortcut.shortcut.launch_options, shortcut.needs_proton);
+            }
+        }
+        add_artwork(&mut shortcuts, &read_store_cache_images(&folder.path));
+        Ok(shortcuts)
     }
 }
 
@@ -95,8 +263,11 @@ impl NeedsProton<HeroicPlatform> for HeroicGameType {
     #[cfg(target_family = "unix")]
     fn needs_proton(&self, _platform: &HeroicPlatform) -> bool {
         match self {
-            HeroicGameType::Epic(_game) => true,
+            HeroicGameType::Epic(game) => {
+                is_windows_game(game.platform.as_deref(), &game.executable, None)
+            }
             HeroicGameType::Gog(_, is_windows) => *is_windows,
+            HeroicGameType::Amazon(app) | HeroicGameType::Sideload(app) => app.is_windows,
             HeroicGameType::Heroic { .. } => false,
         }
     }
@@ -107,57 +278,55 @@ impl NeedsProton<HeroicPlatform> for HeroicGameType {
 }
 
 impl HeroicPlatform {
-    pub fn get_epic_games(
+    fn get_epic_games(
         &self,
-        install_modes: &[InstallationMode],
-    ) -> eyre::Result<Vec<HeroicGameType>> {
+        folder: &HeroicConfigFolder,
+        configs: &HashMap<String, HeroicGameConfig>,
+    ) -> Vec<HeroicGameType> {
         let mut shortcuts = vec![];
-        for install_mode in install_modes {
-            if let Ok(mut games) = get_shortcuts_from_install_mode(install_mode) {
-                games.sort_by_key(|m| {
-                    format!("{}-{}-{}", m.launch_parameters, m.executable, &m.app_name)
-                });
-                games.dedup_by_key(|m| {
-                    format!("{}-{}-{}", m.launch_parameters, m.executable, &m.app_name)
-                });
-
-                for game in games {
-                    if self.settings.is_heroic_launch(&game.app_name) {
-                        shortcuts.push(HeroicGameType::Heroic {
-                            title: game.title,
-                            app_name: game.app_name,
-                            install_mode: *install_mode,
-                        });
-                    } else if game.is_installed() {
-                        shortcuts.push(HeroicGameType::Epic(game));
-                    }
+        let installed_path = folder.path.join("legendaryConfig/legendary/installed.json");
+        if let Ok(mut games) = get_shortcuts_from_location(installed_path) {
+            games.sort_by_key(|m| {
+                format!("{}-{}-{}", m.launch_parameters, m.executable, &m.app_name)
+            });
+            games.dedup_by_key(|m| {
+                format!("{}-{}-{}", m.launch_parameters, m.executable, &m.app_name)
+            });
+
+            for game in games {
+                if self.launch_through_heroic(configs, &game.app_name) {
+                    shortcuts.push(HeroicGameType::Heroic {
+                        title: game.title,
+                        app_name: game.app_name,
+                        install_mode: folder.install_mode.clone(),
+                        runner: "legendary",
+                    });
+                } else if game.is_installed() {
+                    shortcuts.push(HeroicGameType::Epic(game));
                 }
             }
         }
-        Ok(shortcuts)
+        shortcuts
     }
 }
 fn get_gog_games(
-    settings: &HeroicSettings,
-    install_modes: &[InstallationMode],
-) -> eyre::Result<Vec<HeroicGameType>> {
+    platform: &HeroicPlatform,
+    folder: &HeroicConfigFolder,
+    configs: &HashMap<String, HeroicGameConfig>,
+) -> Vec<HeroicGameType> {
     let mut gog_paths = vec![];
-    for install_mode in install_modes {
-        let config = get_gog_installed_location(install_mode);
-        if config.exists() {
-            if let Ok(config_string) = std::fs::read_to_string(config) {
-                if let Ok(config) = serde_json::from_str::<HeroicGogConfig>(&config_string) {
-                    for c in config.installed {
-                        gog_paths.push((install_mode, c));
-                    }
-                }
+    let config = folder.path.join("gog_store/installed.json");
+    if config.exists() {
+        if let Ok(config_string) = std::fs::read_to_string(config) {
+            if let Ok(config) = serde_json::from_str::<HeroicGogConfig>(&config_string) {
+                gog_paths.extend(config.installed);
             }
         }
     }
 
     let mut is_windows_map = HashMap::new();
 
-    for (_, path) in gog_paths.iter() {
+    for path in gog_paths.iter() {
         is_windows_map.insert(path.app_name.clone(), path.platform == "windows");
     }
 
@@ -165,15 +334,16 @@ fn get_gog_games(
 
     let heroic_games = gog_paths
         .iter()
-        .filter(|(_, p)| settings.is_heroic_launch(&p.app_name))
-        .filter_map(|(install_mode, p)| {
+        .filter(|p| platform.launch_through_heroic(configs, &p.app_name))
+        .filter_map(|p| {
             let path = Path::new(&p.install_path);
             if path.exists() {
                 let title = path.file_name();
                 Some(HeroicGameType::Heroic {
                     title: title.unwrap_or_default().to_string_lossy().to_string(),
                     app_name: p.app_name.clone(),
-                    install_mode: **install_mode,
+                    install_mode: folder.install_mode.clone(),
+                    runner: "gog",
                 })
             } else {
                 None
@@ -183,8 +353,8 @@ fn get_gog_games(
 
     let game_folders = gog_paths
         .iter()
-        .filter(|(_, p)| !settings.is_heroic_launch(&p.app_name))
-        .filter_map(|(_, p)| {
+        .filter(|p| !platform.launch_through_heroic(configs, &p.app_name))
+        .filter_map(|p| {
             let path = Path::new(&p.install_path);
             if path.exists() {
                 Some(path.to_path_buf())
@@ -199,7 +369,91 @@ fn get_gog_games(
         gog_shortcuts.push(HeroicGameType::Gog(shortcut, *is_windows));
     }
 
-    Ok(gog_shortcuts)
+    gog_shortcuts
+}
+
+impl HeroicPlatform {
+    /// Lists the folders BoilR found Heroic in and lets the user add their own
+    fn render_config_folders(&mut self, ui: &mut egui::Ui) {
+        let folders = self.config_folders();
+        let found_count = folders.iter().filter(|folder| folder.path.exists()).count();
+        let mut changed = false;
+        egui::CollapsingHeader::new(format!("Heroic folders ({found_count} found)"))
+            .id_salt("Heroic_config_folders")
+            .show(ui, |ui| {
+                ui.label("BoilR imports the games of every folder Heroic keeps its configuration in.");
+                for folder in folders.iter().filter(|folder| !folder.custom) {
+                    let state = if folder.path.exists() { "found" } else { "not found" };
+                    ui.label(format!("{} ({state})", folder.path.display()));
+                }
+                let mut remove = None;
+                let mut pick_executable = None;
+                let executables: Vec<Option<String>> = self
+                    .settings
+                    .config_folders
+                    .iter()
+                    .map(|folder| {
+                        let executable = self.settings.heroic_executable(Path::new(folder.trim()));
+                        executable.map(str::to_string)
+                    })
+                    .collect();
+                let mut moved = vec![];
+                let folders = self.settings.config_folders.iter_mut().zip(executables);
+                for (index, (folder, executable)) in folders.enumerate() {
+                    ui.horizontal(|ui| {
+                        let before = folder.clone();
+                        let response = ui.text_edit_singleline(folder);
+                        if response.changed() {
+                            moved.push((before, folder.clone()));
+                        }
+                        changed |= response.lost_focus();
+                        if ui.button("Remove").clicked() {
+                            remove = Some(index);
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        match executable {
+                            Some(executable) => ui.label(format!(
+                                "Games launched through Heroic start {executable}"
+                            )),
+                            None => ui.label(
+                                "Games launched through Heroic start your own Heroic, not the Heroic of this folder",
+                            ),
+                        };
+                        if ui.button("Choose Heroic").clicked() {
+                            pick_executable = Some(folder.clone());
+                        }
+                    });
+                }
+                //The Heroic chosen for a folder follows the folder when its path is edited
+                for (before, after) in moved {
+                    self.settings.move_heroic_executable(&before, &after);
+                }
+                if let Some(folder) = pick_executable {
+                    if let Some(executable) = rfd::FileDialog::new().pick_file() {
+                        self.settings
+                            .set_heroic_executable(&folder, executable.to_string_lossy().to_string());
+                        changed = true;
+                    }
+                }
+                if let Some(index) = remove {
+                    let folder = self.settings.config_folders.remove(index);
+                    self.settings.set_heroic_executable(&folder, String::new());
+                    changed = true;
+                }
+                if ui.button("Add Heroic folder").clicked() {
+                    if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                        self.settings
+                            .config_folders
+                            .push(folder.to_string_lossy().to_string());
+                        changed = true;
+                    }
+                }
+            });
+        if changed {
+            self.heroic_games = None;
+        }
+    }
 }
 
 impl FromSettingsString for HeroicPlatform {
@@ -221,12 +475,13 @@ impl GamesPlatform for HeroicPlatform {
     }
 
     fn get_shortcut_info(&self) -> eyre::Result<Vec<ShortcutToImport>> {
-        to_shortcuts(self, self.get_heroic_games())
+        self.get_shortcuts_of_folders(self.config_folders())
     }
 
     fn render_ui(&mut self, ui: &mut egui::Ui) {
         ui.heading("Heroic");
         ui.checkbox(&mut self.settings.enabled, "Import from Heroic");
+        self.render_config_folders(ui);
         ui.checkbox(
             &mut self.settings.default_launch_through_heroic,
             "Always launch games through Heroic",
@@ -251,22 +506,28 @@ impl GamesPlatform for HeroicPlatform {
                 ui.label("Some games must be started from the Heroic Launcher, select those games below and BoilR will create shortcuts that opens the games through the Heroic Launcher.");
             }
 
-            let manifests = self.heroic_games.get_or_insert_with(|| {
+            let folders = self.heroic_games.get_or_insert_with(|| {
                 let heroic_setting = self.settings.clone();
                 let heroic_platform = HeroicPlatform{ settings:heroic_setting, heroic_games:None};
-                heroic_platform.get_heroic_games().unwrap_or_default()
+                heroic_platform.get_heroic_games_by_folder()
             });
             let safe_open_games = &mut self.settings.launch_games_through_heroic;
 
-            for manifest in manifests{
-                let key = manifest.app_name();
-                let display_name = manifest.title();
-                let mut safe_open = safe_open_games.contains(&display_name.to_string()) || safe_open_games.contains(&key.to_string());
-                if ui.checkbox(&mut safe_open, display_name).clicked(){
-                    if safe_open{
-                        safe_open_games.push(key.to_string());
-                    }else{
-                        safe_open_games.retain(|m| m!= display_name && m!= key);
+            for (folder, manifests) in folders.iter() {
+                if manifests.is_empty() {
+                    continue;
+                }
+                ui.label(egui::RichText::new(format!("From {}", folder.path.display())).strong());
+                for manifest in manifests{
+                    let key = manifest.app_name();
+                    let display_name = manifest.title();
+                    let mut safe_open = safe_open_games.contains(&display_name.to_string()) || safe_open_games.contains(&key.to_string());
+                    if ui.checkbox(&mut safe_open, display_name).clicked(){
+                        if safe_open{
+                            safe_open_games.push(key.to_string());
+                        }else{
+                            safe_open_games.retain(|m| m!= display_name && m!= key);
+                        }
                     }
                 }
             }
@@ -281,3 +542,137 @@ impl GamesPlatform for HeroicPlatform {
         "heroic"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
+
+    use super::*;
+
+    #[test]
+    fn finds_config_folders_without_duplicates() {
+        let custom = vec![
+            "/home/deck/.config/heroic".to_string(),
+            " /media/usb/Heroic/config ".to_string(),
+            "/home/other/.var/app/com.heroicgameslauncher.hgl/config/heroic".to_string(),
+            "".to_string(),
+        ];
+        let settings = HeroicSettings {
+            config_folders: custom,
+            heroic_executables: vec![(
+                "/media/usb/Heroic/config".to_string(),
+                "/media/usb/Heroic/heroic".to_string(),
+            )],
+            ..Default::default()
+        };
+        let folders = find_config_folders("/home/deck", Some("/home/deck/.xdg"), &settings);
+        let paths: Vec<PathBuf> = folders.iter().map(|folder| folder.path.clone()).collect();
+        assert_eq!(
+            vec![
+                PathBuf::from("/home/deck/.var/app/com.heroicgameslauncher.hgl/config/heroic"),
+                PathBuf::from("/home/deck/.xdg/heroic"),
+                PathBuf::from("/home/deck/.config/heroic"),
+                PathBuf::from("/media/usb/Heroic/config"),
+                PathBuf::from("/home/other/.var/app/com.heroicgameslauncher.hgl/config/heroic"),
+            ],
+            paths
+        );
+        assert_eq!(InstallationMode::FlatPak, folders[0].install_mode);
+        assert_eq!(InstallationMode::UserBin, folders[1].install_mode);
+        assert!(!folders[2].custom);
+        assert!(folders[3].custom);
+        assert_eq!(
+            InstallationMode::Executable("/media/usb/Heroic/heroic".to_string()),
+            folders[3].install_mode
+        );
+        assert_eq!(InstallationMode::FlatPak, folders[4].install_mode);
+    }
+
+    /// Sideloads the app "a1" into a Heroic config folder, installed in `game`
+    fn sideload_tales(folder: &Path, game: &Path) {
+        std::fs::create_dir_all(folder.join("sideload_apps")).unwrap();
+        std::fs::create_dir_all(game).unwrap();
+        std::fs::write(game.join("tales.sh"), "").unwrap();
+        std::fs::write(
+            folder.join("sideload_apps/library.json"),
+            format!(
+                r#"{{"games":[{{"app_name":"a1","title":"Tales","install":{{"executable":"{}","platform":"linux"}}}}]}}"#,
+                game.join("tales.sh").display()
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn reads_games_of_every_folder() {
+        let dir = tempfile::tempdir().unwrap();
+        let portable = dir.path().join("portable");
+        sideload_tales(&portable, &dir.path().join("games/Tales"));
+        let platform = HeroicPlatform {
+            settings: HeroicSettings {
+                default_launch_through_heroic: false,
+                config_folders: vec![portable.to_string_lossy().to_string()],
+                ..Default::default()
+            },
+            heroic_games: None,
+        };
+
+        let home = dir.path().join("home").to_string_lossy().to_string();
+        let folders = find_config_folders(&home, None, &platform.settings);
+        let by_folder = platform.get_games_by_folder(folders);
+        assert_eq!(1, by_folder.len());
+        let (folder, games) = by_folder
+            .iter()
+            .find(|(folder, _)| folder.path == portable)
+            .unwrap();
+        assert!(folder.custom);
+        assert_eq!(1, games.len());
+        assert_eq!("Tales", games[0].title());
+    }
+
+    #[test]
+    fn heroic_of_a_folder_follows_its_path() {
+        let mut settings = HeroicSettings::default();
+        settings.set_heroic_executable(" /media/usb/Heroic/config ", "/media/usb/heroic".to_string());
+        settings.move_heroic_executable("/media/usb/Heroic/config", "/media/usb/Heroic");
+        assert_eq!(None, settings.heroic_executable(Path::new("/media/usb/Heroic/config")));
+        assert_eq!(
+            Some("/media/usb/heroic"),
+            settings.heroic_executable(Path::new("/media/usb/Heroic"))
+        );
+        settings.move_heroic_executable("/media/other", "/media/usb/Heroic/config");
+        assert_eq!(1, settings.heroic_executables.len());
+    }
+
+    #[test]
+    fn imports_a_game_of_several_folders_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let home = dir.path().join("home");
+        let flatpak_game = dir.path().join("flatpak/Tales");
+        sideload_tales(&home.join(FLATPAK_CONFIG_FOLDER), &flatpak_game);
+        sideload_tales(&home.join(".config/heroic"), &dir.path().join("native/Tales"));
+        let platform = HeroicPlatform {
+            settings: HeroicSettings {
+                default_launch_through_heroic: false,
+                ..Default::default()
+            },
+            heroic_games: None,
+        };
+        let folders = find_config_folders(&home.to_string_lossy(), None, &platform.settings);
+
+        let shortcuts = platform.get_shortcuts_of_folders(folders.clone()).unwrap();
+        assert_eq!(1, shortcuts.len());
+        assert_eq!(Some("a1".to_string()), shortcuts[0].game_id);
+        assert!(shortcuts[0]
+            .shortcut
+            .exe
+            .contains(&flatpak_game.to_string_lossy().to_string()));
+
+        let by_folder = platform.get_games_by_folder(folders);
+        assert_eq!(2, by_folder.len());
+        assert_eq!(1, by_folder[0].1.len());
+        assert!(by_folder[1].1.is_empty());
+    }
+}
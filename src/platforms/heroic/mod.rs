@@ -1,8 +1,14 @@
+mod games_config;
+mod heroic_app;
 mod heroic_game;
 mod heroic_game_type;
 mod heroic_platform;
+mod nile;
 mod settings;
+mod sideload;
+mod store_cache;
 
+pub use heroic_app::*;
 pub use heroic_game::*;
 pub use heroic_game_type::*;
 pub use heroic_platform::*;
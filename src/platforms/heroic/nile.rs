@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::HeroicApp;
+
+/// An Amazon game Nile installed, from nile_config/nile/installed.json
+#[derive(Deserialize, Debug, Clone)]
+struct NileInstall {
+    id: String,
+    path: String,
+}
+
+/// How Amazon games tell the launcher to start them, fuel.json in the install folder
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+struct Fuel {
+    main: Option<FuelMain>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "PascalCase")]
+struct FuelMain {
+    command: String,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    working_subdir_override: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct NileLibrary {
+    #[serde(default)]
+    library: Vec<NileLibraryGame>,
+}
+
+#[derive(Deserialize)]
+struct NileLibraryGame {
+    app_name: String,
+    title: String,
+}
+
+/// The Amazon games Heroic installed with Nile, their titles come from the library Heroic cached
+pub(crate) fn get_nile_apps(heroic_folder: &Path) -> eyre::Result<Vec<HeroicApp>> {
+    let installed_path = heroic_folder.join("nile_config/nile/installed.json");
+    if !installed_path.exists() {
+        return Ok(vec![]);
+    }
+    let installs: Vec<NileInstall> =
+        serde_json::from_str(&std::fs::read_to_string(installed_path)?)?;
+    let titles = read_titles(heroic_folder);
+    Ok(installs
+        .into_iter()
+        .filter_map(|install| nile_app(install, &titles))
+        .collect())
+}
+
+fn read_titles(heroic_folder: &Path) -> HashMap<String, String> {
+    std::fs::read_to_string(heroic_folder.join("store_cache/nile_library.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<NileLibrary>(&content).ok())
+        .unwrap_or_default()
+        .library
+        .into_iter()
+        .map(|game| (game.app_name, game.title))
+        .collect()
+}
+
+fn nile_app(install: NileInstall, titles: &HashMap<String, String>) -> Option<HeroicApp> {
+    let install_path = Path::new(&install.path);
+    let fuel = std::fs::read_to_string(install_path.join("fuel.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<Fuel>(&content).ok())?;
+    let main = fuel.main?;
+    let working_dir = match &main.working_subdir_override {
+        Some(subdir) if !subdir.is_empty() => install_path.join(subdir),
+        _ => install_path.to_path_buf(),
+    };
+    let title = titles.get(&install.id).cloned().unwrap_or_else(|| {
+        install_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    });
+    Some(HeroicApp {
+        title,
+        executable: install_path
+            .join(main.command.replace('\\', "/"))
+            .to_string_lossy()
+            .to_string(),
+        working_dir: working_dir.to_string_lossy().replace('\\', "/"),
+        launch_parameters: main.args.join(" "),
+        install_path: install.path,
+        app_name: install.id,
+        //Amazon only has games for Windows
+        is_windows: true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
+
+    use super::*;
+
+    #[test]
+    fn reads_installed_amazon_games() {
+        let dir = tempfile::tempdir().unwrap();
+        let heroic = dir.path().join("heroic");
+        let game = dir.path().join("Amazon/Tales");
+        std::fs::create_dir_all(heroic.join("nile_config/nile")).unwrap();
+        std::fs::create_dir_all(heroic.join("store_cache")).unwrap();
+        std::fs::create_dir_all(&game).unwrap();
+        std::fs::write(
+            heroic.join("nile_config/nile/installed.json"),
+            format!(
+                r#"[{{"id":"amzn1.adg.product.1234","version":"1","path":"{}","size":10}},{{"id":"amzn1.adg.product.9","version":"1","path":"/missing","size":10}}]"#,
+                game.display()
+            ),
+        )
+        .unwrap();
+        std::fs::write(
+            heroic.join("store_cache/nile_library.json"),
+            r#"{"library":[{"app_name":"amzn1.adg.product.1234","title":"Tales of the Sea"}]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            game.join("fuel.json"),
+            r#"{"SchemaVersion":"2","Main":{"Command":"bin\\Tales.exe","Args":["-skipintro","-dx11"],"WorkingSubdirOverride":"bin"}}"#,
+        )
+        .unwrap();
+
+        let apps = get_nile_apps(&heroic).unwrap();
+        assert_eq!(1, apps.len());
+        let app = &apps[0];
+        assert_eq!("Tales of the Sea", app.title);
+        assert_eq!(game.join("bin/Tales.exe").to_string_lossy(), app.executable);
+        assert_eq!(game.join("bin").to_string_lossy(), app.working_dir);
+        assert_eq!("-skipintro -dx11", app.launch_parameters);
+        assert!(app.is_windows);
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::Path;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
@@ -5,9 +7,47 @@ pub struct HeroicSettings {
     pub enabled: bool,
     pub launch_games_through_heroic: Vec<String>,
     pub default_launch_through_heroic: bool,
+    /// Extra folders Heroic keeps its configuration in, like a portable install or the Heroic of another user
+    #[serde(default)]
+    pub config_folders: Vec<String>,
+    /// The Heroic that starts the games of a folder in `config_folders` that are launched through Heroic,
+    /// as pairs of the folder and the executable. Without one they are started with the Heroic of the user.
+    #[serde(default)]
+    pub heroic_executables: Vec<(String, String)>,
 }
 
 impl HeroicSettings {
+    /// The Heroic the user chose for a config folder
+    pub fn heroic_executable(&self, folder: &Path) -> Option<&str> {
+        self.heroic_executables
+            .iter()
+            .find(|(path, executable)| Path::new(path.trim()) == folder && !executable.is_empty())
+            .map(|(_, executable)| executable.as_str())
+    }
+
+    /// Sets the Heroic of a config folder, an empty executable removes it
+    pub fn set_heroic_executable(&mut self, folder: &str, executable: String) {
+        self.heroic_executables
+            .retain(|(path, _)| path.trim() != folder.trim());
+        if !executable.is_empty() {
+            self.heroic_executables
+                .push((folder.trim().to_string(), executable));
+        }
+    }
+
+    /// Moves the Heroic of a config folder to the new path of the folder, when the user edits it
+    pub fn move_heroic_executable(&mut self, old_folder: &str, new_folder: &str) {
+        let executable = self
+            .heroic_executables
+            .iter()
+            .find(|(path, _)| path.trim() == old_folder.trim())
+            .map(|(_, executable)| executable.clone());
+        if let Some(executable) = executable {
+            self.set_heroic_executable(old_folder, String::new());
+            self.set_heroic_executable(new_folder, executable);
+        }
+    }
+
     pub fn is_heroic_launch<S: AsRef<str>>(&self, app_name: S) -> bool {
         let contains = self
             .launch_games_through_heroic
@@ -32,6 +72,8 @@ impl Default for HeroicSettings {
             enabled,
             launch_games_through_heroic: Default::default(),
             default_launch_through_heroic: true,
+            config_folders: vec![],
+            heroic_executables: vec![],
         }
     }
 }
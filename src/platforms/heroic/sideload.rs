@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use super::games_config::{is_windows_game, HeroicGameConfig};
+use super::HeroicApp;
+
+#[derive(Deserialize, Default)]
+struct SideloadLibrary {
+    #[serde(default)]
+    games: Vec<serde_json::Value>,
+}
+
+/// An app added to Heroic by hand, from sideload_apps/library.json
+#[derive(Deserialize, Debug)]
+struct SideloadEntry {
+    app_name: String,
+    title: String,
+    #[serde(default)]
+    install: SideloadInstall,
+    #[serde(default)]
+    folder_name: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct SideloadInstall {
+    #[serde(default)]
+    executable: Option<String>,
+    #[serde(default)]
+    platform: Option<String>,
+}
+
+/// The apps sideloaded into Heroic. Apps that open a website have no executable,
+/// they can only be launched through Heroic.
+pub(crate) fn get_sideload_apps(
+    heroic_folder: &Path,
+    configs: &HashMap<String, HeroicGameConfig>,
+) -> eyre::Result<Vec<HeroicApp>> {
+    let library_path = heroic_folder.join("sideload_apps/library.json");
+    if !library_path.exists() {
+        return Ok(vec![]);
+    }
+    let library: SideloadLibrary = serde_json::from_str(&std::fs::read_to_string(library_path)?)?;
+    Ok(library
+        .games
+        .into_iter()
+        .filter_map(|entry| serde_json::from_value::<SideloadEntry>(entry).ok())
+        .map(|entry| sideload_app(entry, configs))
+        .collect())
+}
+
+fn sideload_app(entry: SideloadEntry, configs: &HashMap<String, HeroicGameConfig>) -> HeroicApp {
+    let executable = entry.install.executable.unwrap_or_default();
+    let install_path = entry.folder_name.unwrap_or_else(|| {
+        Path::new(&executable)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .to_string_lossy()
+            .to_string()
+    });
+    let is_windows = is_windows_game(
+        entry.install.platform.as_deref(),
+        &executable,
+        configs.get(&entry.app_name),
+    );
+    let working_dir = Path::new(&executable)
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .to_string_lossy()
+        .to_string();
+    HeroicApp {
+        app_name: entry.app_name,
+        title: entry.title,
+        install_path,
+        executable,
+        working_dir,
+        launch_parameters: String::new(),
+        is_windows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
+
+    use super::super::games_config::read_game_configs;
+    use super::*;
+
+    #[test]
+    fn reads_sideloaded_apps() {
+        let dir = tempfile::tempdir().unwrap();
+        let heroic = dir.path();
+        std::fs::create_dir_all(heroic.join("sideload_apps")).unwrap();
+        std::fs::create_dir_all(heroic.join("GamesConfig")).unwrap();
+        std::fs::write(
+            heroic.join("sideload_apps/library.json"),
+            r#"{"games":[
+                {"runner":"sideload","app_name":"a1","title":"Emulator","install":{"executable":"/apps/emu/emu.AppImage","platform":"linux","is_dlc":false},"folder_name":"/apps/emu","is_installed":true},
+                {"runner":"sideload","app_name":"b2","title":"Old game","install":{"executable":"/games/old/start","is_dlc":false},"is_installed":true},
+                {"runner":"sideload","app_name":"c3","title":"Web game","install":{"platform":"Browser","is_dlc":false},"browserUrl":"https://example.com","is_installed":true},
+                {"title":"broken"}
+            ]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            heroic.join("GamesConfig/b2.json"),
+            r#"{"b2":{"wineVersion":{"bin":"/usr/bin/wine","name":"Wine Default","type":"wine"}}}"#,
+        )
+        .unwrap();
+
+        let apps = get_sideload_apps(heroic, &read_game_configs(heroic)).unwrap();
+        assert_eq!(3, apps.len());
+        assert!(!apps[0].is_windows);
+        assert_eq!("/apps/emu", apps[0].install_path);
+        assert_eq!("/apps/emu", apps[0].working_dir);
+        assert!(apps[1].is_windows);
+        assert_eq!("/games/old", apps[1].install_path);
+        assert_eq!("", apps[2].executable);
+    }
+}
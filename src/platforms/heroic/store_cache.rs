@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::platforms::PlatformImage;
+use crate::steamgriddb::ImageType;
+
+/// The libraries Heroic caches from the stores and the library of sideloaded apps, they have the art of every game
+const LIBRARY_FILES: [&str; 4] = [
+    "store_cache/legendary_library.json",
+    "store_cache/gog_library.json",
+    "store_cache/nile_library.json",
+    "sideload_apps/library.json",
+];
+
+#[derive(Deserialize, Default)]
+struct HeroicLibrary {
+    /// The Epic and Amazon libraries call their games "library", the others call them "games"
+    #[serde(default, alias = "games")]
+    library: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct HeroicLibraryGame {
+    app_name: String,
+    #[serde(default)]
+    art_cover: Option<String>,
+    #[serde(default)]
+    art_square: Option<String>,
+    #[serde(default)]
+    art_logo: Option<String>,
+    #[serde(default)]
+    art_background: Option<String>,
+}
+
+impl HeroicLibraryGame {
+    fn art(&self) -> impl Iterator<Item = (ImageType, &str)> {
+        [
+            (ImageType::Grid, &self.art_square),
+            (ImageType::WideGrid, &self.art_cover),
+            (ImageType::Logo, &self.art_logo),
+            (ImageType::Hero, &self.art_background),
+        ]
+        .into_iter()
+        .filter_map(|(image_type, url)| {
+            url.as_deref()
+                .filter(|url| !url.is_empty())
+                .map(|url| (image_type, url))
+        })
+    }
+}
+
+/// The art of the games in the store libraries Heroic cached, by their app name.
+/// Heroic keeps the images it showed in images-cache, named by the sha256 of their url,
+/// those are used instead of the url when they are there.
+pub(crate) fn read_store_cache_images(
+    heroic_folder: &Path,
+) -> HashMap<String, Vec<PlatformImage>> {
+    let images_cache = heroic_folder.join("images-cache");
+    let mut images = HashMap::new();
+    for file in LIBRARY_FILES {
+        let path = heroic_folder.join(file);
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let library = match serde_json::from_str::<HeroicLibrary>(&content) {
+            Ok(library) => library,
+            Err(err) => {
+                eprintln!("Could not read the Heroic library {path:?}: {err}");
+                continue;
+            }
+        };
+        let games = library
+            .library
+            .into_iter()
+            .filter_map(|game| serde_json::from_value::<HeroicLibraryGame>(game).ok());
+        for game in games {
+            let art = game
+                .art()
+                .map(|(image_type, url)| {
+                    //Sideloaded apps can have art on disk
+                    if let Some(path) = url.strip_prefix("file://") {
+                        return PlatformImage::file(image_type, path);
+                    }
+                    let cached = images_cache.join(cache_file_name(url));
+                    if cached.is_file() {
+                        PlatformImage::file(image_type, cached)
+                    } else {
+                        PlatformImage::url(image_type, url)
+                    }
+                })
+                .collect::<Vec<_>>();
+            if !art.is_empty() {
+                images.insert(game.app_name, art);
+            }
+        }
+    }
+    images
+}
+
+fn cache_file_name(url: &str) -> String {
+    Sha256::digest(url.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
+
+    use super::*;
+
+    #[test]
+    fn names_cached_images_by_the_hash_of_their_url() {
+        assert_eq!(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            cache_file_name("abc")
+        );
+    }
+
+    #[test]
+    fn prefers_cached_images_over_urls() {
+        let dir = tempfile::tempdir().unwrap();
+        let heroic = dir.path();
+        std::fs::create_dir_all(heroic.join("store_cache")).unwrap();
+        std::fs::create_dir_all(heroic.join("images-cache")).unwrap();
+        std::fs::write(
+            heroic.join("store_cache/legendary_library.json"),
+            r#"{"library":[{"app_name":"Fortnite","art_cover":"https://cdn/cover.jpg","art_square":"https://cdn/square.jpg","art_logo":""},{"title":"no app name"}]}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            heroic.join("store_cache/gog_library.json"),
+            r#"{"games":[{"app_name":"1207658924","art_square":"https://gog/square.jpg"}]}"#,
+        )
+        .unwrap();
+        let cached = heroic
+            .join("images-cache")
+            .join(cache_file_name("https://cdn/square.jpg"));
+        std::fs::write(&cached, b"image").unwrap();
+
+        let images = read_store_cache_images(heroic);
+        assert_eq!(2, images.len());
+        assert_eq!(
+            vec![
+                PlatformImage::file(ImageType::Grid, cached),
+                PlatformImage::url(ImageType::WideGrid, "https://cdn/cover.jpg"),
+            ],
+            images["Fortnite"]
+        );
+        assert_eq!(
+            vec![PlatformImage::url(ImageType::Grid, "https://gog/square.jpg")],
+            images["1207658924"]
+        );
+    }
+
+    #[test]
+    fn reads_art_of_sideloaded_apps_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let heroic = dir.path();
+        std::fs::create_dir_all(heroic.join("sideload_apps")).unwrap();
+        std::fs::write(
+            heroic.join("sideload_apps/library.json"),
+            r#"{"games":[{"app_name":"a1","title":"Emulator","art_square":"file:///apps/emu/cover.png"}]}"#,
+        )
+        .unwrap();
+
+        let images = read_store_cache_images(heroic);
+        assert_eq!(
+            vec![PlatformImage::file(ImageType::Grid, "/apps/emu/cover.png")],
+            images["a1"]
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use sqlite::{OpenFlags, State};
+
+/// An installed game from the caves table of butler.db, together with its game and install location
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Cave {
+    pub(crate) game_id: i64,
+    pub(crate) title: Option<String>,
+    pub(crate) cover_url: Option<String>,
+    pub(crate) still_cover_url: Option<String>,
+    pub(crate) classification: Option<String>,
+    /// The install location joined with the install folder, `None` if the location is not known
+    pub(crate) install_folder: Option<PathBuf>,
+    pub(crate) verdict: Option<Verdict>,
+}
+
+/// What butler found in the install folder of a game, the candidates are the files it can launch
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Verdict {
+    pub(crate) base_path: String,
+    #[serde(default)]
+    pub(crate) candidates: Vec<Candidate>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Candidate {
+    pub(crate) path: String,
+    #[serde(default)]
+    pub(crate) flavor: String,
+    #[serde(default)]
+    pub(crate) depth: u32,
+    #[serde(default)]
+    pub(crate) size: u64,
+}
+
+const CAVES_QUERY: &str = "SELECT caves.game_id, games.title, games.cover_url, games.still_cover_url, \
+    games.classification, install_locations.path, caves.install_folder_name, caves.verdict \
+    FROM caves \
+    LEFT JOIN games ON games.id = caves.game_id \
+    LEFT JOIN install_locations ON install_locations.id = caves.install_location_id";
+
+/// Reads the installed games from butler.db, sqlite reads the pages that are still in the WAL as well
+pub(crate) fn read_caves(db_path: &Path) -> eyre::Result<Vec<Cave>> {
+    let connection = sqlite::Connection::open_with_flags(db_path, OpenFlags::new().with_read_only())?;
+    let mut statement = connection.prepare(CAVES_QUERY)?;
+    let mut caves = vec![];
+    while let State::Row = statement.next()? {
+        let location = statement.read::<Option<String>, _>(5)?;
+        let folder = statement.read::<Option<String>, _>(6)?;
+        let install_folder = match (location, folder) {
+            (Some(location), Some(folder)) if !location.is_empty() && !folder.is_empty() => {
+                Some(Path::new(&location).join(folder))
+            }
+            _ => None,
+        };
+        let verdict = statement
+            .read::<Option<String>, _>(7)?
+            .and_then(|verdict| serde_json::from_str(&verdict).ok());
+        caves.push(Cave {
+            game_id: statement.read::<i64, _>(0)?,
+            title: statement.read::<Option<String>, _>(1)?,
+            cover_url: statement.read::<Option<String>, _>(2)?,
+            still_cover_url: statement.read::<Option<String>, _>(3)?,
+            classification: statement.read::<Option<String>, _>(4)?,
+            install_folder,
+            verdict,
+        });
+    }
+    Ok(caves)
+}
+
+/// The flavors of candidates this system can launch, best first.
+/// Windows executables come after the native ones on Linux, since they need Proton.
+#[cfg(target_os = "linux")]
+const LAUNCH_FLAVORS: &[&str] = &["linux", "script", "windows", "windows-script", "jar", "love"];
+#[cfg(target_os = "macos")]
+const LAUNCH_FLAVORS: &[&str] = &["app-macos", "macos", "script", "jar", "love"];
+#[cfg(target_os = "windows")]
+const LAUNCH_FLAVORS: &[&str] = &["windows", "windows-script", "jar", "love"];
+#[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+const LAUNCH_FLAVORS: &[&str] = &["script", "jar"];
+
+impl Verdict {
+    /// The candidate itch would launch: the best flavor for this system, then the one closest to the
+    /// install folder and then the biggest, like butler picks between the candidates it found
+    pub(crate) fn launch_target(&self) -> Option<&Candidate> {
+        self.candidates
+            .iter()
+            .filter_map(|candidate| {
+                LAUNCH_FLAVORS
+                    .iter()
+                    .position(|flavor| *flavor == candidate.flavor)
+                    .map(|rank| (rank, candidate))
+            })
+            .min_by(|(rank_a, a), (rank_b, b)| {
+                rank_a
+                    .cmp(rank_b)
+                    .then(a.depth.cmp(&b.depth))
+                    .then(b.size.cmp(&a.size))
+            })
+            .map(|(_, candidate)| candidate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
+
+    use super::*;
+
+    fn candidate(path: &str, flavor: &str, depth: u32, size: u64) -> Candidate {
+        Candidate {
+            path: path.to_string(),
+            flavor: flavor.to_string(),
+            depth,
+            size,
+        }
+    }
+
+    fn create_db(path: &Path) {
+        let connection = sqlite::open(path).unwrap();
+        connection
+            .execute(
+                "CREATE TABLE games (id INTEGER PRIMARY KEY, title TEXT, cover_url TEXT, still_cover_url TEXT, classification TEXT);
+                CREATE TABLE install_locations (id TEXT PRIMARY KEY, path TEXT);
+                CREATE TABLE caves (id TEXT PRIMARY KEY, game_id INTEGER, install_location_id TEXT, install_folder_name TEXT, verdict TEXT);
+                INSERT INTO games VALUES (42, 'Islands', 'https://img.itch.zone/cover.png', NULL, 'game');
+                INSERT INTO games VALUES (43, 'Level editor', NULL, NULL, 'tool');
+                INSERT INTO install_locations VALUES ('default', '/home/deck/.config/itch/apps');
+                INSERT INTO caves VALUES ('a', 42, 'default', 'islands', '{\"basePath\":\"/old/islands\",\"totalSize\":10,\"candidates\":[{\"path\":\"Islands_Linux.x86_64\",\"depth\":1,\"flavor\":\"linux\",\"arch\":\"amd64\",\"size\":8}]}');
+                INSERT INTO caves VALUES ('b', 43, 'missing', 'editor', NULL);",
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn reads_caves_with_their_game_and_location() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("butler.db");
+        create_db(&path);
+
+        let caves = read_caves(&path).unwrap();
+        assert_eq!(2, caves.len());
+        let islands = &caves[0];
+        assert_eq!(42, islands.game_id);
+        assert_eq!(Some("Islands".to_string()), islands.title);
+        assert_eq!(Some("https://img.itch.zone/cover.png".to_string()), islands.cover_url);
+        assert_eq!(Some("game".to_string()), islands.classification);
+        assert_eq!(
+            Some(PathBuf::from("/home/deck/.config/itch/apps/islands")),
+            islands.install_folder
+        );
+        let verdict = islands.verdict.as_ref().unwrap();
+        assert_eq!("/old/islands", verdict.base_path);
+        assert_eq!("Islands_Linux.x86_64", verdict.candidates[0].path);
+
+        let editor = &caves[1];
+        assert_eq!(Some("tool".to_string()), editor.classification);
+        assert_eq!(None, editor.install_folder);
+        assert_eq!(None, editor.verdict);
+    }
+
+    #[test]
+    fn missing_db_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(read_caves(&dir.path().join("butler.db")).is_err());
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn launch_target_prefers_native_then_shallow_then_big() {
+        let verdict = Verdict {
+            base_path: "/games/game".to_string(),
+            candidates: vec![
+                candidate("game.exe", "windows", 0, 100),
+                candidate("tools/crash_reporter", "linux", 1, 5),
+                candidate("bin/game", "linux", 1, 50),
+                candidate("readme.html", "html", 0, 1),
+            ],
+        };
+        assert_eq!("bin/game", verdict.launch_target().unwrap().path);
+
+        let windows_only = Verdict {
+            base_path: "/games/game".to_string(),
+            candidates: vec![candidate("game.exe", "windows", 0, 100)],
+        };
+        assert_eq!("game.exe", windows_only.launch_target().unwrap().path);
+
+        let nothing_to_launch = Verdict {
+            base_path: "/games/game".to_string(),
+            candidates: vec![candidate("index.html", "html", 0, 1)],
+        };
+        assert_eq!(None, nothing_to_launch.launch_target());
+    }
+}
@@ -3,7 +3,8 @@ use std::path::Path;
 use serde::{Deserialize, Serialize};
 use steam_shortcuts_util::{shortcut::ShortcutOwned, Shortcut};
 
-use crate::platforms::NeedsProton;
+use crate::platforms::{NeedsProton, PlatformGameId, PlatformImage};
+use crate::steamgriddb::ImageType;
 
 use super::ItchPlatform;
 
@@ -13,6 +14,20 @@ pub struct ItchGame {
     pub install_path: String,
     pub executable: String,
     pub title: String,
+    /// The id of the game on itch.io
+    pub game_id: Option<i64>,
+    /// The still cover of the game if it has one, otherwise its (maybe animated) cover
+    pub cover_url: Option<String>,
+    /// What kind of project it is on itch.io, like game, tool or assets
+    pub classification: Option<String>,
+    /// The app id older versions of BoilR gave the game, they launched the first executable itch found
+    pub legacy_app_id: Option<u32>,
+}
+
+/// The app id of a shortcut to an executable in the install folder of a game
+pub(super) fn launch_app_id(install_path: &str, executable: &str, title: &str) -> u32 {
+    let exe = Path::new(install_path).join(executable);
+    Shortcut::new("0", title, &exe.to_string_lossy(), install_path, "", "", "").app_id
 }
 
 impl From<ItchGame> for ShortcutOwned {
@@ -33,11 +48,43 @@ impl From<ItchGame> for ShortcutOwned {
         owned_shortcut.tags.push("Itch".to_owned());
         owned_shortcut.tags.push("Ready TO Play".to_owned());
         owned_shortcut.tags.push("Installed".to_owned());
+        if let Some(classification) = game.classification.as_deref() {
+            owned_shortcut.tags.push(classification_tag(classification));
+        }
 
         owned_shortcut
     }
 }
 
+/// The classification of an itch.io project as a tag, "game_mod" becomes "Game mod"
+fn classification_tag(classification: &str) -> String {
+    let words = classification.replace('_', " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => words,
+    }
+}
+
+impl PlatformGameId for ItchGame {
+    fn game_id(&self) -> Option<String> {
+        self.game_id.map(|id| id.to_string())
+    }
+
+    fn legacy_app_id(&self) -> Option<u32> {
+        self.legacy_app_id
+    }
+
+    /// Covers on itch.io are landscape, so they are used as the wide grid image
+    fn artwork(&self) -> Vec<PlatformImage> {
+        self.cover_url
+            .iter()
+            .filter(|url| !url.is_empty())
+            .map(|url| PlatformImage::url(ImageType::WideGrid, url.as_str()))
+            .collect()
+    }
+}
+
 impl NeedsProton<ItchPlatform> for ItchGame {
     fn needs_proton(&self, _platform: &ItchPlatform) -> bool {
         self.executable.ends_with("exe")
@@ -53,3 +100,31 @@ impl NeedsProton<ItchPlatform> for ItchGame {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_the_classification() {
+        let game = ItchGame {
+            install_path: "/games/editor".to_string(),
+            executable: "editor".to_string(),
+            title: "Editor".to_string(),
+            game_id: Some(43),
+            cover_url: Some("https://img.itch.zone/cover.png".to_string()),
+            classification: Some("game_mod".to_string()),
+            legacy_app_id: None,
+        };
+        assert_eq!(Some("43".to_string()), game.game_id());
+        assert_eq!(
+            vec![PlatformImage::url(
+                ImageType::WideGrid,
+                "https://img.itch.zone/cover.png"
+            )],
+            game.artwork()
+        );
+        let shortcut: ShortcutOwned = game.into();
+        assert!(shortcut.tags.contains(&"Game mod".to_string()));
+    }
+}
@@ -1,5 +1,6 @@
+use super::butler_db::{read_caves, Cave, Verdict};
 use super::butler_db_parser::*;
-use super::itch_game::ItchGame;
+use super::itch_game::{launch_app_id, ItchGame};
 use super::receipt::Receipt;
 use super::ItchSettings;
 use crate::platforms::{
@@ -20,58 +21,134 @@ impl ItchPlatform {
     fn get_itch_games(&self) -> eyre::Result<Vec<ItchGame>> {
         let itch_location = self.settings.location.clone();
         let itch_location = itch_location.unwrap_or_else(get_default_location);
-
-        let itch_db_location = Path::new(&itch_location).join("db").join("butler.db-wal");
-        if !itch_db_location.exists() {
-            return Err(eyre::format_err!(
-                "Path not found: {:?}",
-                itch_db_location.to_str()
-            ));
+        let db_folder = Path::new(&itch_location).join("db");
+
+        let butler_db_location = db_folder.join("butler.db");
+        if butler_db_location.exists() {
+            match read_caves(&butler_db_location) {
+                Ok(caves) => return Ok(dedupe(caves.iter().filter_map(cave_to_game).collect())),
+                Err(err) => eprintln!(
+                    "Could not read {butler_db_location:?}, looking for games in its WAL: {err}"
+                ),
+            }
         }
+        get_itch_games_from_wal(&db_folder)
+    }
+}
 
-        let shortcut_bytes = std::fs::read(&itch_db_location)?;
+/// Finds the games in the WAL of butler.db without sqlite, games that were checkpointed into butler.db are missed
+fn get_itch_games_from_wal(db_folder: &Path) -> eyre::Result<Vec<ItchGame>> {
+    let itch_db_location = db_folder.join("butler.db-wal");
+    if !itch_db_location.exists() {
+        return Err(eyre::format_err!(
+            "Path not found: {:?}",
+            itch_db_location.to_str()
+        ));
+    }
 
-        let paths = match parse_butler_db(&shortcut_bytes) {
-            Ok((_, shortcuts)) => Ok(shortcuts),
-            Err(e) => Err(eyre::format_err!(
-                "Could not parse path: {:?} , error: {:?}",
-                itch_db_location.to_str(),
-                e
-            )),
-        }?;
+    let shortcut_bytes = std::fs::read(&itch_db_location)?;
+
+    let paths = match parse_butler_db(&shortcut_bytes) {
+        Ok((_, shortcuts)) => Ok(shortcuts),
+        Err(e) => Err(eyre::format_err!(
+            "Could not parse path: {:?} , error: {:?}",
+            itch_db_location.to_str(),
+            e
+        )),
+    }?;
+
+    //This is done to paths dedupe
+    let paths: HashSet<&DbPaths> = paths.iter().collect();
+    let res = paths.iter().filter_map(|e| dbpath_to_game(e)).collect();
+    Ok(dedupe(res))
+}
 
-        //This is done to paths dedupe
-        let paths: HashSet<&DbPaths> = paths.iter().collect();
-        let res = paths.iter().filter_map(|e| dbpath_to_game(e)).collect();
-        Ok(res)
-    }
+/// A game can have several caves, for instance one per upload, only the first of each launch target is kept
+fn dedupe(games: Vec<ItchGame>) -> Vec<ItchGame> {
+    let mut seen = HashSet::new();
+    games
+        .into_iter()
+        .filter(|game| seen.insert((game.install_path.clone(), game.executable.clone())))
+        .collect()
 }
 
-fn dbpath_to_game(paths: &DbPaths) -> Option<ItchGame> {
-    let recipt = Path::new(paths.base_path.as_str())
-        .join(".itch")
-        .join("receipt.json.gz");
-    if !&recipt.exists() {
+/// The game of a cave, launched with the candidate itch would pick from its verdict
+fn cave_to_game(cave: &Cave) -> Option<ItchGame> {
+    let verdict = cave.verdict.as_ref()?;
+    let target = verdict.launch_target()?;
+    //The install folder is where the game is now, the base path is where it was when it was configured
+    let install_path = cave
+        .install_folder
+        .as_ref()
+        .filter(|folder| folder.join(&target.path).exists())
+        .map(|folder| folder.to_string_lossy().to_string())
+        .unwrap_or_else(|| verdict.base_path.clone());
+    if !Path::new(&install_path).join(&target.path).exists() {
         return None;
     }
+    let receipt = read_receipt(Path::new(&install_path));
+    let legacy_app_id = receipt
+        .as_ref()
+        .and_then(|receipt| legacy_app_id(verdict, &receipt.game.title));
+    let title = cave
+        .title
+        .clone()
+        .or_else(|| receipt.as_ref().map(|receipt| receipt.game.title.clone()))?;
+    Some(ItchGame {
+        install_path,
+        executable: target.path.clone(),
+        title,
+        game_id: Some(cave.game_id),
+        cover_url: cave.still_cover_url.clone().or_else(|| cave.cover_url.clone()),
+        classification: cave.classification.clone(),
+        legacy_app_id,
+    })
+}
+
+/// The app id older versions of BoilR gave a game, they launched the first executable candidate
+/// in the base path and used the title from the receipt
+fn legacy_app_id(verdict: &Verdict, title: &str) -> Option<u32> {
+    let base_path = Path::new(&verdict.base_path);
+    let executable = verdict
+        .candidates
+        .iter()
+        .find(|candidate| base_path.join(&candidate.path).is_executable())?;
+    Some(launch_app_id(&verdict.base_path, &executable.path, title))
+}
+
+fn read_receipt(install_path: &Path) -> Option<Receipt> {
+    let recipt = install_path.join(".itch").join("receipt.json.gz");
+    let gz_bytes = std::fs::read(recipt).ok()?;
+    let mut d = GzDecoder::new(gz_bytes.as_slice());
+    let mut s = String::new();
+    d.read_to_string(&mut s).ok()?;
+    serde_json::from_str(&s).ok()
+}
+
+fn dbpath_to_game(paths: &DbPaths) -> Option<ItchGame> {
+    let base_path = Path::new(paths.base_path.as_str());
+    let receipt = read_receipt(base_path)?;
     paths
         .paths
         .iter()
-        .filter(|p| Path::new(&paths.base_path).join(p).is_executable())
-        .find_map(|executable| {
-            if let Ok(gz_bytes) = std::fs::read(&recipt) {
-                let mut d = GzDecoder::new(gz_bytes.as_slice());
-                let mut s = String::new();
-                if d.read_to_string(&mut s).is_ok() {
-                    let receipt_op: Option<Receipt> = serde_json::from_str(&s).ok();
-                    return receipt_op.map(|re| ItchGame {
-                        install_path: paths.base_path.to_owned(),
-                        executable: executable.to_owned(),
-                        title: re.game.title,
-                    });
-                }
-            }
-            None
+        .find(|p| base_path.join(p).is_executable())
+        .map(|executable| ItchGame {
+            install_path: paths.base_path.to_owned(),
+            executable: executable.to_owned(),
+            title: receipt.game.title.clone(),
+            game_id: receipt.game.id,
+            cover_url: receipt
+                .game
+                .still_cover_url
+                .clone()
+                .or_else(|| receipt.game.cover_url.clone()),
+            classification: receipt.game.classification.clone(),
+            //Older versions of BoilR found the game the same way
+            legacy_app_id: Some(launch_app_id(
+                &paths.base_path,
+                executable,
+                &receipt.game.title,
+            )),
         })
 }
 
@@ -1,3 +1,4 @@
+mod butler_db;
 mod butler_db_parser;
 mod itch_game;
 mod itch_platform;
@@ -6,6 +6,15 @@ pub(crate) struct Receipt {
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
 pub(crate) struct Game {
+    #[serde(default)]
+    pub id: Option<i64>,
     pub title: String,
+    #[serde(default)]
+    pub cover_url: Option<String>,
+    #[serde(default)]
+    pub still_cover_url: Option<String>,
+    #[serde(default)]
+    pub classification: Option<String>,
 }
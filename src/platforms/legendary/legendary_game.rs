@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use steam_shortcuts_util::{shortcut::ShortcutOwned, Shortcut};
 
+use crate::platforms::PlatformGameId;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LegendaryGame {
     pub app_name: String,
@@ -36,3 +38,9 @@ impl From<LegendaryGame> for ShortcutOwned {
         owned_shortcut
     }
 }
+
+impl PlatformGameId for LegendaryGame {
+    fn game_id(&self) -> Option<String> {
+        Some(self.app_name.clone())
+    }
+}
@@ -1,5 +1,7 @@
 use steam_shortcuts_util::{shortcut::ShortcutOwned, Shortcut};
 
+use crate::platforms::PlatformGameId;
+
 use serde::Deserialize;
 
 use super::LutrisSettings;
@@ -34,6 +36,16 @@ impl From<LutrisGame> for ShortcutOwned {
     }
 }
 
+impl PlatformGameId for LutrisGame {
+    fn game_id(&self) -> Option<String> {
+        Some(self.slug.clone())
+    }
+
+    fn runner(&self) -> Option<String> {
+        self.runner.clone()
+    }
+}
+
 impl LutrisGame {
     pub fn get_options(&self) -> String {
         let is_flatpak = self
@@ -40,6 +40,7 @@ pub(crate) use gog::GogShortcut;
 
 
 pub use platforms_load::get_platforms;
+pub(crate) use platforms_load::load_platform;
 pub(crate) use platforms_load::load_settings;
 pub(crate) use platforms_load::FromSettingsString;
 pub use platforms_load::Platforms;
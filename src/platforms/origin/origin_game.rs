@@ -2,6 +2,8 @@ use std::path::PathBuf;
 
 use steam_shortcuts_util::{shortcut::ShortcutOwned, Shortcut};
 
+use crate::platforms::PlatformGameId;
+
 #[derive(Clone)]
 pub struct OriginGame {
     pub id: String,
@@ -36,3 +38,9 @@ impl From<OriginGame> for ShortcutOwned {
         owned_shortcut
     }
 }
+
+impl PlatformGameId for OriginGame {
+    fn game_id(&self) -> Option<String> {
+        Some(self.id.clone())
+    }
+}
@@ -100,38 +100,28 @@ struct OriginPathData {
 
 #[cfg(target_family = "unix")]
 fn get_default_locations() -> Option<OriginPathData> {
+    use crate::steam::get_compatdata_prefixes;
+
     let mut res = OriginPathData::default();
-    if let Ok(home) = std::env::var("HOME") {
-        let compat_folder_path = Path::new(&home)
-            .join(".steam")
-            .join("steam")
-            .join("steamapps")
-            .join("compatdata");
-
-        if let Ok(compat_folder) = std::fs::read_dir(compat_folder_path) {
-            for dir in compat_folder.flatten() {
-                let origin_exe_path = dir
-                    .path()
-                    .join("pfx")
-                    .join("drive_c")
-                    .join("Program Files (x86)")
-                    .join("Origin")
-                    .join("Origin.exe");
-
-                let origin_local_content = dir
-                    .path()
-                    .join("pfx")
-                    .join("drive_c")
-                    .join("ProgramData")
-                    .join("Origin");
-
-                if origin_exe_path.exists() && origin_local_content.exists() {
-                    res.exe_path = origin_exe_path;
-                    res.local_content_path = origin_local_content;
-                    res.compat_folder = Some(dir.path());
-                    return Some(res);
-                }
-            }
+    for dir in get_compatdata_prefixes() {
+        let origin_exe_path = dir
+            .join("pfx")
+            .join("drive_c")
+            .join("Program Files (x86)")
+            .join("Origin")
+            .join("Origin.exe");
+
+        let origin_local_content = dir
+            .join("pfx")
+            .join("drive_c")
+            .join("ProgramData")
+            .join("Origin");
+
+        if origin_exe_path.exists() && origin_local_content.exists() {
+            res.exe_path = origin_exe_path;
+            res.local_content_path = origin_local_content;
+            res.compat_folder = Some(dir);
+            return Some(res);
         }
     }
     None
@@ -1,6 +1,11 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
 use dyn_clone::DynClone;
 use steam_shortcuts_util::shortcut::ShortcutOwned;
 
+use crate::steamgriddb::ImageType;
+
 pub trait GamesPlatform
 where
     Self: std::marker::Send,
@@ -27,6 +32,70 @@ pub struct ShortcutToImport {
     pub shortcut: ShortcutOwned,
     pub needs_proton: bool,
     pub needs_symlinks: bool,
+    /// The id of the game on its platform, see [`PlatformGameId`]
+    pub game_id: Option<String>,
+    /// The runner of the game, like wine for a Lutris game or gog for a Heroic game
+    pub runner: Option<String>,
+    /// Art the platform has for the game, it is used before images are searched for on SteamGridDB
+    pub artwork: Vec<PlatformImage>,
+    /// The app id BoilR followed the game by before it knew the id of the game, see [`PlatformGameId::legacy_app_id`]
+    pub legacy_app_id: Option<u32>,
+}
+
+/// An image a platform provides for one of its games
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlatformImage {
+    pub image_type: ImageType,
+    pub source: ImageSource,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageSource {
+    /// An image the platform keeps on disk, like its image cache
+    File(PathBuf),
+    /// An image on the web, like the cover of the game in the store
+    Url(String),
+}
+
+impl PlatformImage {
+    pub fn file<P: Into<PathBuf>>(image_type: ImageType, path: P) -> Self {
+        Self {
+            image_type,
+            source: ImageSource::File(path.into()),
+        }
+    }
+
+    pub fn url<S: Into<String>>(image_type: ImageType, url: S) -> Self {
+        Self {
+            image_type,
+            source: ImageSource::Url(url.into()),
+        }
+    }
+}
+
+impl std::fmt::Display for ImageSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageSource::File(path) => write!(f, "{}", path.display()),
+            ImageSource::Url(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+/// Adds the art a platform found apart from its games, by the id of the game on the platform
+pub(crate) fn add_artwork(
+    shortcuts: &mut [ShortcutToImport],
+    artwork: &HashMap<String, Vec<PlatformImage>>,
+) {
+    for shortcut in shortcuts {
+        let images = shortcut
+            .game_id
+            .as_ref()
+            .and_then(|game_id| artwork.get(game_id));
+        if let Some(images) = images {
+            shortcut.artwork.extend(images.iter().cloned());
+        }
+    }
 }
 
 pub(crate) fn to_shortcuts<T, P>(
@@ -36,17 +105,26 @@ pub(crate) fn to_shortcuts<T, P>(
 where
     T: Into<ShortcutOwned>,
     T: NeedsProton<P>,
+    T: PlatformGameId,
 {
     let shortcuts = into_shortcuts?;
     let mut shortcut_info = vec![];
     for m in shortcuts {
         let needs_proton = m.needs_proton(platform);
         let needs_symlinks = m.create_symlinks(platform);
+        let game_id = m.game_id();
+        let runner = m.runner();
+        let artwork = m.artwork();
+        let legacy_app_id = m.legacy_app_id();
         let shortcut = m.into();
         shortcut_info.push(ShortcutToImport {
             shortcut,
             needs_proton,
             needs_symlinks,
+            game_id,
+            runner,
+            artwork,
+            legacy_app_id,
         });
     }
     Ok(shortcut_info)
@@ -57,17 +135,26 @@ pub(crate) fn to_shortcuts_simple<T>(
 ) -> eyre::Result<Vec<ShortcutToImport>>
 where
     T: Into<ShortcutOwned>,
+    T: PlatformGameId,
 {
     let shortcuts = into_shortcuts?;
     let mut shortcut_info = vec![];
     for m in shortcuts {
         let needs_proton = false;
         let needs_symlinks = false;
+        let game_id = m.game_id();
+        let runner = m.runner();
+        let artwork = m.artwork();
+        let legacy_app_id = m.legacy_app_id();
         let shortcut = m.into();
         shortcut_info.push(ShortcutToImport {
             shortcut,
             needs_proton,
             needs_symlinks,
+            game_id,
+            runner,
+            artwork,
+            legacy_app_id,
         });
     }
     Ok(shortcut_info)
@@ -78,3 +165,25 @@ pub trait NeedsProton<P> {
 
     fn create_symlinks(&self, platform: &P) -> bool;
 }
+
+/// The id of a game on its platform, like the app name on Epic or the game id on GOG.
+/// It does not change when the game is renamed or moved, so BoilR can follow a game when its app id changes.
+pub trait PlatformGameId {
+    fn game_id(&self) -> Option<String>;
+
+    /// The runner or store of the game, for launchers that run games from several sources
+    fn runner(&self) -> Option<String> {
+        None
+    }
+
+    /// The art the game comes with, like its cover or icon
+    fn artwork(&self) -> Vec<PlatformImage> {
+        vec![]
+    }
+
+    /// The app id older versions of BoilR gave the game, for platforms that did not have an id for their games.
+    /// The settings BoilR kept under that app id are moved to the id of the game once.
+    fn legacy_app_id(&self) -> Option<u32> {
+        None
+    }
+}
@@ -5,7 +5,9 @@ use std::{
 };
 use steam_shortcuts_util::{shortcut::ShortcutOwned, Shortcut};
 
-use crate::platforms::{load_settings, to_shortcuts_simple, FromSettingsString, GamesPlatform};
+use crate::platforms::{
+    load_settings, to_shortcuts_simple, FromSettingsString, GamesPlatform, PlatformGameId,
+};
 
 use super::playnite_parser::parse_db;
 
@@ -153,6 +155,12 @@ impl From<PlayniteGame> for ShortcutOwned {
     }
 }
 
+impl PlatformGameId for PlayniteGame {
+    fn game_id(&self) -> Option<String> {
+        Some(self.id.clone())
+    }
+}
+
 pub struct PlayniteGame {
     pub name: String,
     pub id: String,
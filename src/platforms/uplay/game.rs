@@ -2,6 +2,9 @@ use std::path::{Path, PathBuf};
 
 use steam_shortcuts_util::shortcut::{Shortcut, ShortcutOwned};
 
+use crate::platforms::{PlatformGameId, PlatformImage};
+use crate::steamgriddb::ImageType;
+
 #[derive(Clone)]
 pub(crate) struct UplayGame {
     pub(crate) name: String,
@@ -29,3 +32,19 @@ impl From<UplayGame> for ShortcutOwned {
         Shortcut::new("0", &game.name, &exe, &start_dir, &game.icon, "", &launch).to_owned()
     }
 }
+
+impl PlatformGameId for UplayGame {
+    fn game_id(&self) -> Option<String> {
+        Some(self.id.clone())
+    }
+
+    /// The icon Ubisoft Connect keeps in its data/games folder
+    fn artwork(&self) -> Vec<PlatformImage> {
+        let icon = Path::new(&self.icon);
+        if !self.icon.is_empty() && icon.is_file() {
+            vec![PlatformImage::file(ImageType::Icon, icon)]
+        } else {
+            vec![]
+        }
+    }
+}
@@ -58,41 +58,31 @@ struct UplayPathData {
 
 #[cfg(target_family = "unix")]
 fn get_launcher_path() -> eyre::Result<UplayPathData> {
+    use crate::steam::get_compatdata_prefixes;
+
     let mut res = UplayPathData::default();
-    if let Ok(home) = std::env::var("HOME") {
-        let compat_folder_path = Path::new(&home)
-            .join(".steam")
-            .join("steam")
-            .join("steamapps")
-            .join("compatdata");
-
-        if let Ok(compat_folder) = std::fs::read_dir(compat_folder_path) {
-            for dir in compat_folder.flatten() {
-                let uplay_exe_path = dir
-                    .path()
-                    .join("pfx")
-                    .join("drive_c")
-                    .join("Program Files (x86)")
-                    .join("Ubisoft")
-                    .join("Ubisoft Game Launcher")
-                    .join("upc.exe");
-
-                let uplay_games = dir
-                    .path()
-                    .join("pfx")
-                    .join("drive_c")
-                    .join("Program Files (x86)")
-                    .join("Ubisoft")
-                    .join("Ubisoft Game Launcher")
-                    .join("games");
-
-                if uplay_exe_path.exists() && uplay_games.exists() {
-                    res.exe_path = uplay_exe_path;
-                    res.games_path = uplay_games;
-                    res.compat_folder = Some(dir.path());
-                    return Ok(res);
-                }
-            }
+    for dir in get_compatdata_prefixes() {
+        let uplay_exe_path = dir
+            .join("pfx")
+            .join("drive_c")
+            .join("Program Files (x86)")
+            .join("Ubisoft")
+            .join("Ubisoft Game Launcher")
+            .join("upc.exe");
+
+        let uplay_games = dir
+            .join("pfx")
+            .join("drive_c")
+            .join("Program Files (x86)")
+            .join("Ubisoft")
+            .join("Ubisoft Game Launcher")
+            .join("games");
+
+        if uplay_exe_path.exists() && uplay_games.exists() {
+            res.exe_path = uplay_exe_path;
+            res.games_path = uplay_games;
+            res.compat_folder = Some(dir);
+            return Ok(res);
         }
     }
     Err(eyre::eyre!("Could not find uplay launcher"))
@@ -206,7 +196,7 @@ struct GameConfig<'a> {
     launch_id: usize,
 }
 
-fn parse_game_config(split: &str) -> Vec<GameConfig> {
+fn parse_game_config(split: &str) -> Vec<GameConfig<'_>> {
     let mut res = vec![];
     let mut icon_image = "";
     let mut shortcut_name = "";
@@ -323,7 +313,7 @@ mod test {
         let splits = get_file_splits(content);
         let games:Vec<_> = splits.iter().flat_map(|split| parse_game_config(split)).collect();
         assert_eq!(2, games.len());
-        assert_eq!(Some("For Honor"),games.get(0).map(|h|h.shortcut_name));
+        assert_eq!(Some("For Honor"),games.first().map(|h|h.shortcut_name));
         assert_eq!(Some("WATCH_DOGS® 2"),games.get(1).map(|h|h.shortcut_name));
     }
 }
@@ -1,11 +1,18 @@
 use crate::{
-    config::get_config_file, platforms::Platforms, steam::SteamSettings,
+    backup::BackupSettings,
+    config::{get_config_file, get_renames_file},
+    platforms::Platforms,
+    steam::{SteamSettings, SteamUserSettings},
     steamgriddb::SteamGridDbSettings,
+    sync::{CollectionRule, LaunchRule},
 };
 
 use config::{Config, ConfigError, Environment, File};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, env};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Settings {
@@ -14,6 +21,12 @@ pub struct Settings {
     pub blacklisted_games: Vec<u32>,
     pub steamgrid_db: SteamGridDbSettings,
     pub steam: SteamSettings,
+    #[serde(default)]
+    pub backup: BackupSettings,
+    #[serde(default)]
+    pub launch_rules: Vec<LaunchRule>,
+    #[serde(default)]
+    pub collection_rules: Vec<CollectionRule>,
 }
 
 impl Settings {
@@ -47,6 +60,13 @@ impl Settings {
 pub fn load_setting_sections() -> eyre::Result<HashMap<String, String>> {
     let config_file_path = get_config_file();
     let content = std::fs::read_to_string(config_file_path)?;
+    Ok(parse_setting_sections(&content))
+}
+
+/// Tables of the settings themselves, all other tables are the settings of a platform
+const SETTINGS_TABLES: [&str; 5] = ["steamgrid_db", "steam", "backup", "launch_rules", "collection_rules"];
+
+fn parse_setting_sections(content: &str) -> HashMap<String, String> {
     let mut result = HashMap::new();
     let lines = content.lines();
     let mut current_section_lines: Vec<String> = vec![];
@@ -62,11 +82,17 @@ pub fn load_setting_sections() -> eyre::Result<HashMap<String, String>> {
     }
     add_sections(&current_section_name, &current_section_lines, &mut result);
 
-    let blacklisted_sections = ["steamgrid_db", "steam"];
-    for section in blacklisted_sections {
-        let _ = result.remove(section);
+    result.retain(|section, _| is_platform_section(section));
+    result
+}
+
+/// Array tables like `[[launch_rules]]` and sub tables like `[steam.users.123]` are never platforms
+fn is_platform_section(section: &str) -> bool {
+    if section.starts_with('[') {
+        return false;
     }
-    Ok(result)
+    let table = section.split('.').next().unwrap_or_default();
+    !SETTINGS_TABLES.contains(&table)
 }
 
 pub fn save_settings(settings: &Settings, platforms: &Platforms) -> eyre::Result<()>{
@@ -86,6 +112,55 @@ pub fn save_settings(settings: &Settings, platforms: &Platforms) -> eyre::Result
     Ok(())
 }
 
+/// Writes the blacklist to the config file, leaving the rest of the file as it is
+pub fn save_blacklisted_games(blacklisted_games: &[u32]) -> eyre::Result<()> {
+    let config_path = get_config_file();
+    let mut table = match std::fs::read_to_string(&config_path) {
+        Ok(content) => content.parse::<toml::Table>()?,
+        Err(_) => toml::Table::new(),
+    };
+    let games = blacklisted_games
+        .iter()
+        .map(|id| toml::Value::Integer(i64::from(*id)))
+        .collect();
+    table.insert("blacklisted_games".to_string(), toml::Value::Array(games));
+    std::fs::write(config_path, toml::to_string(&table)?)?;
+    Ok(())
+}
+
+/// Writes the settings of the Steam users to the config file, leaving the rest of the file as it is
+pub fn save_steam_users(users: &BTreeMap<String, SteamUserSettings>) -> eyre::Result<()> {
+    let config_path = get_config_file();
+    let mut table = match std::fs::read_to_string(&config_path) {
+        Ok(content) => content.parse::<toml::Table>()?,
+        Err(_) => toml::Table::new(),
+    };
+    let steam = table
+        .entry("steam")
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    if let toml::Value::Table(steam) = steam {
+        steam.insert("users".to_string(), toml::Value::try_from(users)?);
+    }
+    std::fs::write(config_path, toml::to_string(&table)?)?;
+    Ok(())
+}
+
+pub fn save_rename_map(renames: &HashMap<u32, String>) -> eyre::Result<()> {
+    std::fs::write(get_renames_file(), serde_json::to_string(renames)?)?;
+    Ok(())
+}
+
+pub fn get_rename_map() -> HashMap<u32, String> {
+    try_get_rename_map().unwrap_or_default()
+}
+
+fn try_get_rename_map() -> eyre::Result<HashMap<u32, String>> {
+    let rename_map = get_renames_file();
+    let file_content = std::fs::read_to_string(rename_map)?;
+    let deserialized = serde_json::from_str(&file_content)?;
+    Ok(deserialized)
+}
+
 fn add_sections(
     current_section_name: &Option<String>,
     current_section_lines: &Vec<String>,
@@ -108,3 +183,43 @@ fn sanitize_auth_key(result: &mut Settings) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_platforms_are_setting_sections() {
+        let content = r#"debug = false
+
+[steam]
+stop_steam = true
+
+[steam.users.123]
+blacklisted_games = [1]
+
+[steam.proton.platform_tools]
+heroic = "proton"
+
+[[launch_rules]]
+platform = "heroic"
+
+[[collection_rules]]
+name = "Favorites"
+
+[backup]
+keep_last = 20
+
+[heroic]
+enabled = true
+
+[lutris]
+enabled = false
+"#;
+        let sections = parse_setting_sections(content);
+        let mut names: Vec<&String> = sections.keys().collect();
+        names.sort();
+        assert_eq!(vec!["heroic", "lutris"], names);
+        assert_eq!(Some(&"enabled = true\n\n".to_string()), sections.get("heroic"));
+    }
+}
@@ -1,13 +1,15 @@
-use nom::FindSubstring;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use rusty_leveldb::{LdbIterator, Options, WriteBatch, DB};
 
+use super::{SteamInstall, VdfDocument};
+
 const BOILR_TAG: &str = "boilr";
+const USER_COLLECTIONS_KEY: &str = "user-collections";
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
@@ -64,6 +66,41 @@ impl ActualSteamCollection {
         self.key
             .contains(&format!("user-collections.{BOILR_TAG}"))
     }
+
+    /// Adds the games of the matching collection to this collection, keeping everything else in its value as it is.
+    /// Returns the id of this collection and the collection it matched.
+    fn add_games<'a>(&mut self, existing: &[&'a Collection]) -> Option<(String, &'a Collection)> {
+        let mut value = serde_json::from_str::<serde_json::Value>(&self.value).ok()?;
+        let name = value.get("name")?.as_str()?;
+        let collection = *existing
+            .iter()
+            .find(|collection| collection.name.eq_ignore_ascii_case(name))?;
+        let id = value.get("id")?.as_str()?.to_string();
+        let mut changed = false;
+        if let Some(removed) = value.get_mut("removed").and_then(|r| r.as_array_mut()) {
+            let before = removed.len();
+            removed.retain(|id| {
+                id.as_u64()
+                    .is_none_or(|id| !collection.game_ids.contains(&(id as usize)))
+            });
+            changed |= removed.len() != before;
+        }
+        let added = value.get_mut("added")?.as_array_mut()?;
+        for game_id in &collection.game_ids {
+            if !added.iter().any(|id| id.as_u64() == Some(*game_id as u64)) {
+                added.push(serde_json::Value::from(*game_id));
+                changed = true;
+            }
+        }
+        if changed {
+            self.value = serde_json::to_string(&value).ok()?;
+            let since_the_epoch = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            self.timestamp = since_the_epoch.as_secs();
+        }
+        Some((id, collection))
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -97,16 +134,86 @@ struct DeletedCollection {
     version: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct Collection {
     pub name: String,
     pub game_ids: Vec<usize>,
+    /// A collection made in Steam that BoilR only adds games to
+    #[serde(default)]
+    pub existing: bool,
 }
 
+/// Steam has the collections database locked, it only lets go of it when it exits
+#[derive(Debug)]
+pub struct CollectionsLocked;
+
+impl std::fmt::Display for CollectionsLocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Could not lock the steam level database, make sure steam is turned off when running synchronizations")
+    }
+}
+
+impl Error for CollectionsLocked {}
+
+/// True if the error is because Steam is running and has the collections database locked
+pub fn is_collections_locked(err: &eyre::Report) -> bool {
+    err.downcast_ref::<CollectionsLocked>().is_some()
+}
+
+/// Reads the collections BoilR has previously written for a user
+pub fn get_boilr_collections<S: AsRef<str>>(
+    install: &SteamInstall,
+    steam_user_id: S,
+) -> eyre::Result<Vec<Collection>> {
+    read_collections(install, steam_user_id.as_ref(), false)
+}
+
+/// Reads the collections of a user that BoilR does not own, like the ones made in Steam
+pub fn get_existing_collections<S: AsRef<str>>(
+    install: &SteamInstall,
+    steam_user_id: S,
+) -> eyre::Result<Vec<Collection>> {
+    read_collections(install, steam_user_id.as_ref(), true)
+}
+
+fn read_collections(
+    install: &SteamInstall,
+    steam_user_id: &str,
+    existing: bool,
+) -> eyre::Result<Vec<Collection>> {
+    let mut db = open_db(install)?;
+    let categories = get_categories(steam_user_id, &mut db)
+        .map_err(|e| eyre::eyre!("Could not read collections: {e}"))?;
+    let mut result: Vec<Collection> = vec![];
+    for collection in categories.values().flatten().map(|(_key, c)| c) {
+        if let SteamCollection::Actual(actual) = collection {
+            if actual.is_boilr_collection() == existing {
+                continue;
+            }
+            if let Ok(value) = serde_json::from_str::<ValueCollection>(&actual.value) {
+                if !result.iter().any(|c| c.name == value.name) {
+                    result.push(Collection {
+                        name: value.name,
+                        game_ids: value.added,
+                        existing,
+                    });
+                }
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Replaces the collections BoilR owns with the new collections and adds the games of the existing collections
+/// to the collection with that name made in Steam. Other collections are left as they are.
 pub fn write_collections<S: AsRef<str>>(
+    install: &SteamInstall,
     steam_user_id: S,
-    collections_to_add: &[Collection],
-) -> Result<(), Box<dyn Error>> {
+    collections: &[Collection],
+) -> eyre::Result<()> {
     let steam_user_id = steam_user_id.as_ref();
+    let (existing, collections_to_add): (Vec<&Collection>, Vec<&Collection>) =
+        collections.iter().partition(|c| c.existing);
     let new_collections: Vec<(String, SteamCollection)> = collections_to_add
         .iter()
         .map(|c| {
@@ -115,22 +222,31 @@ pub fn write_collections<S: AsRef<str>>(
         })
         .collect();
 
-    let mut db = open_db()?;
+    let mut db = open_db(install)?;
 
-    let current_categories = get_categories(steam_user_id, &mut db)?;
+    let current_categories = get_categories(steam_user_id, &mut db)
+        .map_err(|e| eyre::eyre!("Could not read collections: {e}"))?;
     //this is a collection of collections, known as a category
     let mut write_batch = WriteBatch::new();
+    let mut found_existing: HashMap<String, &Collection> = HashMap::new();
 
     for (category_key, mut collections) in current_categories {
         collections.retain(|(_key, collection)| !collection.is_boilr_collection());
+        for (_key, collection) in collections.iter_mut() {
+            if let SteamCollection::Actual(actual) = collection {
+                if let Some((id, collection)) = actual.add_games(&existing) {
+                    found_existing.insert(id, collection);
+                }
+            }
+        }
         collections.extend(new_collections.clone());
-        save_category(category_key, collections, &mut write_batch)?;
+        save_category(category_key, collections, &mut write_batch)
+            .map_err(|e| eyre::eyre!("Could not write collections: {e}"))?;
 
-        if let Some(path) = get_vdf_path(steam_user_id) {
-            let content = std::fs::read_to_string(&path)
-                .ok()
-                .and_then(parse_vdf_collection);
-            if let Some(mut vdf_collections) = content {
+        if let Some(path) = get_vdf_path(install, steam_user_id) {
+            let content = std::fs::read_to_string(&path).ok();
+            let collections = content.as_ref().and_then(parse_vdf_collection);
+            if let (Some(content), Some(mut vdf_collections)) = (content, collections) {
                 let boilr_keys: Vec<String> = vdf_collections
                     .keys()
                     .filter(|k| k.contains(BOILR_TAG))
@@ -152,11 +268,13 @@ pub fn write_collections<S: AsRef<str>>(
                 for new_vdf in new_vdfs {
                     vdf_collections.insert(new_vdf.id.clone(), new_vdf.clone());
                 }
+                for (id, collection) in &found_existing {
+                    if let Some(vdf_collection) = vdf_collections.get_mut(id) {
+                        vdf_collection.add_games(&collection.game_ids);
+                    }
+                }
 
-                let new_string = write_vdf_collection_to_string(
-                    path.clone().to_string_lossy(),
-                    &vdf_collections,
-                );
+                let new_string = write_vdf_collection_to_string(content, &vdf_collections);
                 if let Some(new_string) = new_string {
                     std::fs::write(path, new_string)?;
                 }
@@ -166,46 +284,25 @@ pub fn write_collections<S: AsRef<str>>(
 
     db.write(write_batch, true)?;
 
-    Ok(())
-}
-
-#[cfg(target_family = "unix")]
-fn get_vdf_path<S: AsRef<str>>(steamid: S) -> Option<PathBuf> {
-    match std::env::var("HOME") {
-        Ok(home) => {
-            let path = Path::new(&home)
-                .join(".steam")
-                .join("steam")
-                .join("userdata")
-                .join(steamid.as_ref())
-                .join("config")
-                .join("localconfig.vdf");
-            if path.exists() {
-                return Some(path);
-            }
-            None
+    for collection in existing {
+        if !found_existing.values().any(|found| found.name == collection.name) {
+            eprintln!(
+                "Could not find the collection {} in Steam, create it in Steam first",
+                collection.name
+            );
         }
-        Err(_e) => None,
     }
+
+    Ok(())
 }
 
-#[cfg(target_os = "windows")]
-fn get_vdf_path<S: AsRef<str>>(steamid: S) -> Option<PathBuf> {
-    match std::env::var("PROGRAMFILES(X86)") {
-        Ok(program_files) => {
-            let path = Path::new(&program_files)
-                .join("Steam")
-                .join("userdata")
-                .join(steamid.as_ref())
-                .join("config")
-                .join("localconfig.vdf");
-            if path.exists() {
-                Some(path)
-            } else {
-                None
-            }
-        }
-        Err(_e) => None,
+/// The path of the localconfig.vdf file of a user, if it exists
+pub fn get_vdf_path<S: AsRef<str>>(install: &SteamInstall, steamid: S) -> Option<PathBuf> {
+    let path = install.localconfig_path(steamid.as_ref());
+    if path.exists() {
+        Some(path)
+    } else {
+        None
     }
 }
 
@@ -242,15 +339,15 @@ fn get_categories<S: AsRef<str>>(
     Ok(res)
 }
 
-fn open_db() -> eyre::Result<DB> {
+fn open_db(install: &SteamInstall) -> eyre::Result<DB> {
     use eyre::eyre;
-    let location = get_level_db_location().ok_or(eyre!("Collections db not found"))?;
+    let location = get_level_db_location(install).ok_or(eyre!("Collections db not found"))?;
     let options = Options::default();
     let open_res = DB::open(location, options);
     open_res.map_err(|e|{
         use rusty_leveldb::StatusCode::*;
         match e.code{
-            LockError => eyre!("Could not lock the steam level database, make sure steam is turned off when running synchronizations"),
+            LockError => eyre::Report::new(CollectionsLocked),
             NotFound => eyre!("Could not find the steam level database, try to open and close steam once and synchronize again"),
             _ => eyre!("Failed opening collections file: {}",e.err),
         }
@@ -279,42 +376,12 @@ fn get_namespaces(db: &mut DB, key_bytes: &[u8]) -> Option<Vec<(i32, String)>> {
     }
 }
 
-#[cfg(target_family = "unix")]
-fn get_level_db_location() -> Option<PathBuf> {
-    match std::env::var("HOME") {
-        Ok(home) => {
-            let path = Path::new(&home)
-                .join(".steam")
-                .join("steam")
-                .join("config")
-                .join("htmlcache")
-                .join("Local Storage")
-                .join("leveldb");
-            if path.exists() {
-                return Some(path);
-            }
-            None
-        }
-        Err(_e) => None,
-    }
-}
-
-#[cfg(target_os = "windows")]
-fn get_level_db_location() -> Option<PathBuf> {
-    match std::env::var("LOCALAPPDATA") {
-        Ok(localdata) => {
-            let path = Path::new(&localdata)
-                .join("Steam")
-                .join("htmlcache")
-                .join("Local Storage")
-                .join("leveldb");
-            if path.exists() {
-                Some(path)
-            } else {
-                None
-            }
-        }
-        Err(_e) => None,
+fn get_level_db_location(install: &SteamInstall) -> Option<PathBuf> {
+    let path = install.level_db_path();
+    if path.exists() {
+        Some(path)
+    } else {
+        None
     }
 }
 
@@ -351,34 +418,22 @@ fn get_steam_user_prefix<S: AsRef<str>>(steamid: S) -> String {
     keyprefix
 }
 
+/// Reads the collections from the content of a localconfig.vdf file
 pub fn parse_vdf_collection<S: AsRef<str>>(input: S) -> Option<HashMap<String, VdfCollection>> {
-    let input = input.as_ref();
-    serde_json::from_str(input).ok()
+    let document = VdfDocument::parse(input.as_ref()).ok()?;
+    let collections = document.root.find(USER_COLLECTIONS_KEY)?.as_str()?;
+    serde_json::from_str(collections).ok()
 }
 
+/// Replaces the collections in the content of a localconfig.vdf file
 pub fn write_vdf_collection_to_string<S: AsRef<str>>(
     input: S,
     vdf: &HashMap<String, VdfCollection>,
 ) -> Option<String> {
-    let input = input.as_ref();
-    if let Ok(str) = serde_json::to_string(vdf) {
-        let encoded_json = format!("\"{}\"", str.replace('\\', "\\\""));
-        let key = "\t\"user-collections\"\t\t";
-        if let Some(start_index) = input.find_substring(key) {
-            let start_index_plus_key = start_index + key.len();
-            if let Some(line_index) = input.get(start_index_plus_key..).and_then(|i| i.find('\n')) {
-                let end_index_in_full = line_index + start_index_plus_key;
-                if let (Some(before), Some(after)) = (
-                    input.get(..start_index_plus_key),
-                    input.get(end_index_in_full..),
-                ) {
-                    let result = format!("{before}{encoded_json}{after}");
-                    return Some(result);
-                }
-            }
-        }
-    }
-    None
+    let mut document = VdfDocument::parse(input.as_ref()).ok()?;
+    let json = serde_json::to_string(vdf).ok()?;
+    document.root.find_mut(USER_COLLECTIONS_KEY)?.set_str(&json);
+    Some(document.to_string())
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -388,12 +443,24 @@ pub struct VdfCollection {
     removed: Vec<usize>,
 }
 
+impl VdfCollection {
+    fn add_games(&mut self, game_ids: &[usize]) {
+        self.removed.retain(|id| !game_ids.contains(id));
+        for id in game_ids {
+            if !self.added.contains(id) {
+                self.added.push(*id);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     //Allow unwraps in test
     #![allow(clippy::unwrap_in_result)]
     #![allow(clippy::get_unwrap)]
     #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
     use super::*;
 
     #[test]
@@ -414,10 +481,143 @@ mod tests {
         assert_eq!(res, expected);
     }
 
+    #[test]
+    fn writes_collections_to_localconfig() {
+        let collections: HashMap<String, VdfCollection> =
+            serde_json::from_str(include_str!("../testdata/vdf/user-collections.json")).unwrap();
+        let input = "\"UserLocalConfigStore\"\n{\n\t\"WebStorage\"\n\t{\n\t\t\"user-collections\"\t\t\"{}\"\n\t}\n}\n";
+        assert_eq!(0, parse_vdf_collection(input).unwrap().len());
+        let output = write_vdf_collection_to_string(input, &collections).unwrap();
+        assert!(output.contains("\"{\\\"from-tag-"));
+        let parsed = parse_vdf_collection(&output).unwrap();
+        assert_eq!(collections.len(), parsed.len());
+        assert_eq!(
+            collections.get("from-tag-Itch").map(|c| &c.added),
+            parsed.get("from-tag-Itch").map(|c| &c.added)
+        );
+    }
+
     #[test]
     fn can_parse_categories() {
         let input = include_str!("../testdata/leveldb/testcollections.json");
         let collection = parse_steam_collections(input).unwrap();
         assert_eq!(28, collection.len())
     }
+
+    #[test]
+    fn adds_games_to_existing_collection() {
+        let value = r#"{"id":"uc-abc","name":"Favorites","added":[10,20],"removed":[30],"filterSpec":{"nFormatVersion":2}}"#;
+        let mut collection = ActualSteamCollection {
+            key: "user-collections.uc-abc".to_string(),
+            timestamp: 1,
+            value: value.to_string(),
+            conflict_resolution_method: None,
+            str_method_id: None,
+            version: None,
+        };
+        let favorites = Collection {
+            name: "favorites".to_string(),
+            game_ids: vec![20, 30, 40],
+            existing: true,
+        };
+        let other = Collection {
+            name: "Other".to_string(),
+            game_ids: vec![50],
+            existing: true,
+        };
+        assert!(collection.add_games(&[&other]).is_none());
+        let (id, found) = collection.add_games(&[&other, &favorites]).unwrap();
+        assert_eq!("uc-abc", id);
+        assert_eq!("favorites", found.name);
+        assert!(collection.timestamp > 1);
+        let value: serde_json::Value = serde_json::from_str(&collection.value).unwrap();
+        assert_eq!(serde_json::json!([10, 20, 30, 40]), value["added"]);
+        assert_eq!(serde_json::json!([]), value["removed"]);
+        assert_eq!(serde_json::json!({"nFormatVersion": 2}), value["filterSpec"]);
+
+        let mut vdf = VdfCollection {
+            id,
+            added: vec![10],
+            removed: vec![40],
+        };
+        vdf.add_games(&favorites.game_ids);
+        assert_eq!(vec![10, 20, 30, 40], vdf.added);
+        assert!(vdf.removed.is_empty());
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn write_collections_keeps_user_collections() {
+        let dir = tempfile::tempdir().unwrap();
+        let install = SteamInstall {
+            kind: super::super::SteamInstallKind::Custom,
+            path: dir.path().to_path_buf(),
+        };
+        std::fs::create_dir_all(install.level_db_path()).unwrap();
+        let user_collection = |id: &str, name: &str, added: &[usize]| {
+            let value = serde_json::json!({"id": id, "name": name, "added": added, "removed": []});
+            let collection = ActualSteamCollection {
+                key: format!("user-collections.{id}"),
+                timestamp: 1,
+                value: value.to_string(),
+                conflict_resolution_method: None,
+                str_method_id: None,
+                version: None,
+            };
+            (collection.key.clone(), SteamCollection::Actual(collection))
+        };
+        let old_boilr = ActualSteamCollection::new("Old platform", &[1]);
+        let category = vec![
+            user_collection("uc-fav", "Favorites", &[10]),
+            user_collection("uc-other", "Other", &[11]),
+            (old_boilr.key.clone(), SteamCollection::Actual(old_boilr)),
+        ];
+        let prefix = get_steam_user_prefix("42");
+        {
+            let mut db = DB::open(install.level_db_path(), Options::default()).unwrap();
+            db.put(format!("{prefix}s").as_bytes(), "\u{1}[[1,\"a\"]]".as_bytes())
+                .unwrap();
+            let mut batch = WriteBatch::new();
+            save_category(format!("{prefix}-1"), category, &mut batch).unwrap();
+            db.write(batch, true).unwrap();
+        }
+        let localconfig = install.localconfig_path("42");
+        std::fs::create_dir_all(localconfig.parent().unwrap()).unwrap();
+        let vdf_json = r#"{\"uc-fav\":{\"id\":\"uc-fav\",\"added\":[10],\"removed\":[]},\"uc-other\":{\"id\":\"uc-other\",\"added\":[11],\"removed\":[]}}"#;
+        std::fs::write(
+            &localconfig,
+            format!("\"UserLocalConfigStore\"\n{{\n\t\"WebStorage\"\n\t{{\n\t\t\"user-collections\"\t\t\"{vdf_json}\"\n\t}}\n}}\n"),
+        )
+        .unwrap();
+
+        let collections = vec![
+            Collection {
+                name: "Flatpak".to_string(),
+                game_ids: vec![20],
+                existing: false,
+            },
+            Collection {
+                name: "Favorites".to_string(),
+                game_ids: vec![20],
+                existing: true,
+            },
+        ];
+        write_collections(&install, "42", &collections).unwrap();
+
+        let boilr = get_boilr_collections(&install, "42").unwrap();
+        assert_eq!(vec![collections[0].clone()], boilr);
+        let mut existing = get_existing_collections(&install, "42").unwrap();
+        existing.sort_by(|a, b| a.name.cmp(&b.name));
+        let existing: Vec<(&str, &[usize])> = existing
+            .iter()
+            .map(|c| (c.name.as_str(), c.game_ids.as_slice()))
+            .collect();
+        assert_eq!(vec![("Favorites", &[10, 20][..]), ("Other", &[11][..])], existing);
+
+        let vdf = parse_vdf_collection(std::fs::read_to_string(&localconfig).unwrap()).unwrap();
+        assert_eq!(vec![10, 20], vdf.get("uc-fav").unwrap().added);
+        assert_eq!(vec![11], vdf.get("uc-other").unwrap().added);
+        assert_eq!(vec![20], vdf.get(&name_to_key("Flatpak")).unwrap().added);
+        assert_eq!(3, vdf.len());
+    }
 }
@@ -0,0 +1,206 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::config::{get_pending_collections_file, load_json_or_default, save_json};
+
+use super::{is_collections_locked, write_collections, Collection, SteamInstall};
+
+/// How often the watcher checks if Steam has let go of the collections database
+pub const PENDING_COLLECTIONS_POLL: Duration = Duration::from_secs(5);
+
+/// Collections that could not be written because Steam was running,
+/// they are written by the next import or once Steam exits
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PendingCollections {
+    users: BTreeMap<String, PendingUserCollections>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PendingUserCollections {
+    pub queued_at: String,
+    pub collections: Vec<Collection>,
+}
+
+/// What happened to the collections of a user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionsWrite {
+    Written,
+    /// Steam was running, the collections are written later
+    Queued,
+}
+
+impl PendingCollections {
+    pub fn load() -> Self {
+        load_json_or_default(&get_pending_collections_file(), "pending collections")
+    }
+
+    /// Saves the pending collections, the file is removed when nothing is pending
+    pub fn save(&self) -> eyre::Result<()> {
+        let path = get_pending_collections_file();
+        if self.users.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+        save_json(&path, self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+
+    pub fn users(&self) -> impl Iterator<Item = (&String, &PendingUserCollections)> {
+        self.users.iter()
+    }
+
+    /// Replaces what is pending for the user, only the newest collections of a user matter
+    pub fn queue(&mut self, user_id: &str, collections: &[Collection]) {
+        let queued_at = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default();
+        self.users.insert(
+            user_id.to_string(),
+            PendingUserCollections {
+                queued_at,
+                collections: collections.to_vec(),
+            },
+        );
+    }
+
+    pub fn remove(&mut self, user_id: &str) -> bool {
+        self.users.remove(user_id).is_some()
+    }
+
+    /// True if these collections are still the newest of the user.
+    /// An import writes or queues newer collections by itself, and then these are stale.
+    pub fn is_current(&self, user_id: &str, user: &PendingUserCollections) -> bool {
+        self.users.get(user_id) == Some(user)
+    }
+}
+
+/// Writes the collections of a user, or queues them if Steam has the database locked.
+/// Writing the collections of a user drops what was pending for that user.
+pub fn write_or_queue_collections(
+    install: &SteamInstall,
+    user_id: &str,
+    collections: &[Collection],
+) -> eyre::Result<CollectionsWrite> {
+    let mut pending = PendingCollections::load();
+    match write_collections(install, user_id, collections) {
+        Ok(()) => {
+            if pending.remove(user_id) {
+                pending.save()?;
+            }
+            Ok(CollectionsWrite::Written)
+        }
+        Err(err) if is_collections_locked(&err) => {
+            pending.queue(user_id, collections);
+            pending.save()?;
+            Ok(CollectionsWrite::Queued)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Writes the pending collections, returns the users whose collections were written.
+/// The pending collections are read again before and after each user is written,
+/// so collections an import wrote or queued in the meantime are never replaced by older ones.
+/// Fails with a locked error (see [`is_collections_locked`]) if Steam is still running.
+pub fn apply_pending_collections(install: &SteamInstall) -> eyre::Result<Vec<String>> {
+    let mut written = vec![];
+    let users: Vec<(String, PendingUserCollections)> = PendingCollections::load()
+        .users()
+        .map(|(user_id, user)| (user_id.clone(), user.clone()))
+        .collect();
+    for (user_id, user) in users {
+        if !PendingCollections::load().is_current(&user_id, &user) {
+            continue;
+        }
+        write_collections(install, &user_id, &user.collections)?;
+        let mut pending = PendingCollections::load();
+        if pending.is_current(&user_id, &user) {
+            pending.remove(&user_id);
+            pending.save()?;
+        }
+        written.push(user_id);
+    }
+    Ok(written)
+}
+
+/// Waits until Steam exits and writes the pending collections.
+/// `waiting` is called every time the database is still locked.
+pub fn wait_for_pending_collections(
+    install: &SteamInstall,
+    poll: Duration,
+    mut waiting: impl FnMut(&PendingCollections),
+) -> eyre::Result<Vec<String>> {
+    let mut written = vec![];
+    loop {
+        match apply_pending_collections(install) {
+            Ok(users) => {
+                written.extend(users);
+                return Ok(written);
+            }
+            Err(err) if is_collections_locked(&err) => {
+                waiting(&PendingCollections::load());
+                std::thread::sleep(poll);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn queue_keeps_newest_collections_per_user() {
+        let collection = |name: &str| Collection {
+            name: name.to_string(),
+            game_ids: vec![1],
+            existing: false,
+        };
+        let mut pending = PendingCollections::default();
+        pending.queue("1", &[collection("Old")]);
+        pending.queue("2", &[collection("Other user")]);
+        pending.queue("1", &[collection("New")]);
+        let names: Vec<(&str, &str)> = pending
+            .users()
+            .flat_map(|(user, p)| p.collections.iter().map(move |c| (user.as_str(), c.name.as_str())))
+            .collect();
+        assert_eq!(vec![("1", "New"), ("2", "Other user")], names);
+        assert!(pending.remove("1"));
+        assert!(!pending.remove("1"));
+        assert!(pending.remove("2"));
+        assert!(pending.is_empty());
+    }
+
+    #[test]
+    fn newer_collections_make_pending_ones_stale() {
+        let collection = |name: &str| Collection {
+            name: name.to_string(),
+            game_ids: vec![1],
+            existing: false,
+        };
+        let mut pending = PendingCollections::default();
+        pending.queue("1", &[collection("Old")]);
+        pending.queue("2", &[collection("Other user")]);
+        let old = pending.users().next().map(|(_, user)| user.clone()).unwrap();
+        assert!(pending.is_current("1", &old));
+
+        pending.queue("1", &[collection("New")]);
+        assert!(!pending.is_current("1", &old));
+        assert!(!pending.is_current("2", &old));
+        pending.remove("1");
+        assert!(!pending.is_current("1", &old));
+        assert!(pending.remove("2"));
+        assert!(pending.is_empty());
+    }
+}
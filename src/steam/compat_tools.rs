@@ -0,0 +1,143 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::{SteamInstall, SteamSettings, VdfDocument};
+
+/// A compatibility tool Steam can run games with, like Proton or GE-Proton
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CompatTool {
+    /// The name Steam uses for the tool in the CompatToolMapping
+    pub name: String,
+    pub display_name: String,
+    pub path: PathBuf,
+}
+
+/// Finds the compatibility tools in compatibilitytools.d and the Proton versions installed in the Steam libraries
+pub fn get_compat_tools(settings: &SteamSettings) -> Vec<CompatTool> {
+    let Ok(install) = SteamInstall::find(settings) else {
+        return vec![];
+    };
+    let mut tools = get_custom_tools(&install.path.join("compatibilitytools.d"));
+    for steamapps in install.library_folders() {
+        tools.extend(get_official_tools(&steamapps.join("common")));
+    }
+    tools.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+    tools.dedup_by(|a, b| a.name == b.name);
+    tools
+}
+
+/// Tools like GE-Proton, each folder has a compatibilitytool.vdf with the name of the tool
+fn get_custom_tools(folder: &Path) -> Vec<CompatTool> {
+    let Ok(dirs) = std::fs::read_dir(folder) else {
+        return vec![];
+    };
+    let mut tools = vec![];
+    for dir in dirs.flatten() {
+        let manifest = dir.path().join("compatibilitytool.vdf");
+        let Ok(content) = std::fs::read_to_string(&manifest) else {
+            continue;
+        };
+        match parse_custom_tool(&content, &dir.path()) {
+            Ok(found) => tools.extend(found),
+            Err(err) => eprintln!("Could not read compatibility tool {manifest:?}: {err}"),
+        }
+    }
+    tools
+}
+
+fn parse_custom_tool(content: &str, path: &Path) -> eyre::Result<Vec<CompatTool>> {
+    let document = VdfDocument::parse(content)?;
+    let compat_tools = document
+        .root
+        .find("compat_tools")
+        .and_then(|tools| tools.as_object())
+        .ok_or_else(|| eyre::eyre!("Missing compat_tools"))?;
+    Ok(compat_tools
+        .entries()
+        .filter_map(|entry| {
+            let tool = entry.value().as_object()?;
+            Some(CompatTool {
+                name: entry.key().to_string(),
+                display_name: tool.get_str("display_name").unwrap_or(entry.key()).to_string(),
+                path: path.to_path_buf(),
+            })
+        })
+        .collect())
+}
+
+/// The Proton versions Valve ships as apps, installed in steamapps/common
+fn get_official_tools(common: &Path) -> Vec<CompatTool> {
+    let Ok(dirs) = std::fs::read_dir(common) else {
+        return vec![];
+    };
+    dirs.flatten()
+        .filter(|dir| dir.path().join("proton").exists())
+        .filter_map(|dir| {
+            let display_name = dir.file_name().to_string_lossy().to_string();
+            official_tool_name(&display_name).map(|name| CompatTool {
+                name,
+                display_name,
+                path: dir.path(),
+            })
+        })
+        .collect()
+}
+
+/// The name Steam uses for an official Proton install folder,
+/// "Proton - Experimental" is proton_experimental, "Proton 8.0" is proton_8 and "Proton 6.3" is proton_63
+pub(crate) fn official_tool_name(folder_name: &str) -> Option<String> {
+    let version = folder_name.strip_prefix("Proton")?.trim();
+    match version {
+        "- Experimental" => return Some("proton_experimental".to_string()),
+        "Hotfix" => return Some("proton_hotfix".to_string()),
+        _ => {}
+    }
+    let number = version.split_whitespace().next()?;
+    let (major, minor) = number.split_once('.').unwrap_or((number, "0"));
+    if major.is_empty() || !major.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    if minor.is_empty() || !minor.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    if minor.trim_start_matches('0').is_empty() {
+        Some(format!("proton_{major}"))
+    } else {
+        Some(format!("proton_{major}{minor}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn names_official_tools() {
+        let name = |folder| official_tool_name(folder);
+        assert_eq!(Some("proton_experimental".to_string()), name("Proton - Experimental"));
+        assert_eq!(Some("proton_hotfix".to_string()), name("Proton Hotfix"));
+        assert_eq!(Some("proton_9".to_string()), name("Proton 9.0 (Beta)"));
+        assert_eq!(Some("proton_8".to_string()), name("Proton 8.0"));
+        assert_eq!(Some("proton_63".to_string()), name("Proton 6.3"));
+        assert_eq!(Some("proton_513".to_string()), name("Proton 5.13"));
+        assert_eq!(None, name("Proton EasyAntiCheat Runtime"));
+        assert_eq!(None, name("Steam Linux Runtime"));
+    }
+
+    #[test]
+    fn reads_custom_tools() {
+        let content = "\"compatibilitytools\"\n{\n  \"compat_tools\"\n  {\n    \"GE-Proton9-20\"\n    {\n      \"install_path\" \".\"\n      \"display_name\" \"GE-Proton9-20\"\n      \"from_oslist\"  \"windows\"\n      \"to_oslist\"    \"linux\"\n    }\n  }\n}\n";
+        let tools = parse_custom_tool(content, Path::new("/tools/GE-Proton9-20")).unwrap();
+        assert_eq!(
+            vec![CompatTool {
+                name: "GE-Proton9-20".to_string(),
+                display_name: "GE-Proton9-20".to_string(),
+                path: PathBuf::from("/tools/GE-Proton9-20"),
+            }],
+            tools
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use crate::config::get_config_file;
+
+use super::{SteamSettings, VdfDocument, VdfValue};
+
+pub(super) const FLATPAK_ID: &str = "com.valvesoftware.Steam";
+
+/// How Steam was installed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SteamInstallKind {
+    Native,
+    Flatpak,
+    /// The location set in the settings
+    Custom,
+}
+
+impl SteamInstallKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SteamInstallKind::Native => "native",
+            SteamInstallKind::Flatpak => "Flatpak",
+            SteamInstallKind::Custom => "custom",
+        }
+    }
+}
+
+/// The Steam installation BoilR reads from and writes to.
+/// Every path into the Steam folder should come from here, so Flatpak and custom installs behave like native ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SteamInstall {
+    pub kind: SteamInstallKind,
+    pub path: PathBuf,
+}
+
+impl SteamInstall {
+    /// Uses the location from the settings, or the first default location that exists
+    pub fn find(settings: &SteamSettings) -> eyre::Result<Self> {
+        if let Some(location) = settings.location.as_ref().filter(|l| !l.trim().is_empty()) {
+            let path = PathBuf::from(location);
+            let kind = if is_flatpak_path(&path) {
+                SteamInstallKind::Flatpak
+            } else {
+                SteamInstallKind::Custom
+            };
+            return Ok(Self { kind, path });
+        }
+        let candidates = default_locations()?;
+        let found = candidates
+            .iter()
+            .find(|install| install.path.exists())
+            .or_else(|| candidates.first())
+            .cloned();
+        found.ok_or_else(|| eyre::eyre!("Could not find a default Steam location"))
+    }
+
+    /// The install from the saved settings, for code that does not get the settings passed in, like the platforms.
+    /// The settings are only read again when the config file changed.
+    pub fn from_config() -> eyre::Result<Self> {
+        static CONFIGURED: Mutex<Option<(Option<SystemTime>, SteamInstall)>> = Mutex::new(None);
+        let modified = std::fs::metadata(get_config_file())
+            .and_then(|meta| meta.modified())
+            .ok();
+        let mut configured = CONFIGURED.lock().map_err(|_| eyre::eyre!("Could not read the Steam location"))?;
+        if let Some((_, install)) = configured.as_ref().filter(|(when, _)| *when == modified) {
+            return Ok(install.clone());
+        }
+        let settings = crate::settings::Settings::new()?;
+        let install = Self::find(&settings.steam)?;
+        *configured = Some((modified, install.clone()));
+        Ok(install)
+    }
+
+    pub fn userdata_folder(&self) -> PathBuf {
+        self.path.join("userdata")
+    }
+
+    /// The localconfig.vdf of a user, which holds the collections
+    pub fn localconfig_path(&self, user_id: &str) -> PathBuf {
+        self.userdata_folder()
+            .join(user_id)
+            .join("config")
+            .join("localconfig.vdf")
+    }
+
+    /// The config.vdf that holds the CompatToolMapping
+    pub fn config_vdf_path(&self) -> PathBuf {
+        self.path.join("config").join("config.vdf")
+    }
+
+    /// The LevelDB database Steam keeps the collections in
+    pub fn level_db_path(&self) -> PathBuf {
+        #[cfg(target_os = "windows")]
+        if let Ok(local_data) = std::env::var("LOCALAPPDATA") {
+            return Path::new(&local_data)
+                .join("Steam")
+                .join("htmlcache")
+                .join("Local Storage")
+                .join("leveldb");
+        }
+        self.path
+            .join("config")
+            .join("htmlcache")
+            .join("Local Storage")
+            .join("leveldb")
+    }
+
+    /// The steamapps folders of all Steam libraries
+    pub fn library_folders(&self) -> Vec<PathBuf> {
+        let vdf_path = self.path.join("steamapps").join("libraryfolders.vdf");
+        if !vdf_path.exists() {
+            return vec![self.path.join("steamapps")];
+        }
+        match std::fs::read_to_string(&vdf_path) {
+            Ok(vdf_file) => match parse_library_folders(&vdf_file) {
+                Ok(folders) => return folders,
+                Err(err) => eprintln!("Could not read {vdf_path:?}: {err}"),
+            },
+            Err(err) => eprintln!("Could not read {vdf_path:?}: {err}"),
+        }
+        //The library of the install itself is always there
+        vec![self.path.join("steamapps")]
+    }
+
+    /// The compatdata folders of all Steam libraries, each game that runs with proton has its prefix in one of them
+    pub fn compatdata_folders(&self) -> Vec<PathBuf> {
+        self.library_folders()
+            .into_iter()
+            .map(|steamapps| steamapps.join("compatdata"))
+            .filter(|folder| folder.exists())
+            .collect()
+    }
+}
+
+/// The prefixes in the compatdata folders of the configured Steam install
+pub fn get_compatdata_prefixes() -> Vec<PathBuf> {
+    let install = match SteamInstall::from_config() {
+        Ok(install) => install,
+        Err(err) => {
+            eprintln!("Could not find Steam: {err}");
+            return vec![];
+        }
+    };
+    install
+        .compatdata_folders()
+        .iter()
+        .filter_map(|folder| std::fs::read_dir(folder).ok())
+        .flat_map(|dirs| dirs.flatten().map(|dir| dir.path()))
+        .collect()
+}
+
+fn is_flatpak_path(path: &Path) -> bool {
+    path.to_string_lossy()
+        .contains(&format!(".var/app/{FLATPAK_ID}"))
+}
+
+#[cfg(target_os = "linux")]
+fn default_locations() -> eyre::Result<Vec<SteamInstall>> {
+    let home = PathBuf::from(std::env::var("HOME")?);
+    let flatpak_home = home.join(".var").join("app").join(FLATPAK_ID);
+    let install = |kind, path| SteamInstall { kind, path };
+    Ok(vec![
+        install(SteamInstallKind::Native, home.join(".steam").join("steam")),
+        install(SteamInstallKind::Native, home.join(".local").join("share").join("Steam")),
+        install(SteamInstallKind::Flatpak, flatpak_home.join(".steam").join("steam")),
+        install(
+            SteamInstallKind::Flatpak,
+            flatpak_home.join(".local").join("share").join("Steam"),
+        ),
+    ])
+}
+
+#[cfg(target_os = "windows")]
+fn default_locations() -> eyre::Result<Vec<SteamInstall>> {
+    let program_files = std::env::var("PROGRAMFILES(X86)")?;
+    Ok(vec![SteamInstall {
+        kind: SteamInstallKind::Native,
+        path: Path::new(&program_files).join("Steam"),
+    }])
+}
+
+#[cfg(target_os = "macos")]
+fn default_locations() -> eyre::Result<Vec<SteamInstall>> {
+    let home = std::env::var("HOME")?;
+    Ok(vec![SteamInstall {
+        kind: SteamInstallKind::Native,
+        path: Path::new(&home)
+            .join("Library")
+            .join("Application Support")
+            .join("Steam"),
+    }])
+}
+
+/// The steamapps folders of the libraries in libraryfolders.vdf.
+/// Libraries are objects with a path, older versions of Steam only wrote the path.
+fn parse_library_folders(vdf_file: &str) -> eyre::Result<Vec<PathBuf>> {
+    let document = VdfDocument::parse(vdf_file)?;
+    let libraries = document
+        .root
+        .get_object("libraryfolders")
+        .ok_or_else(|| eyre::eyre!("Missing libraryfolders"))?;
+    Ok(libraries
+        .entries()
+        .filter(|entry| entry.key().parse::<u32>().is_ok())
+        .filter_map(|entry| match entry.value() {
+            VdfValue::Object(library) => library.get_str("path"),
+            VdfValue::String(path) => Some(path.as_str()),
+        })
+        .map(|path| Path::new(path).join("steamapps"))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+
+    use super::*;
+
+    #[test]
+    fn parse_library_folders_test() {
+        let new_format = "\"libraryfolders\"\n{\n\t\"0\"\n\t{\n\t\t\"path\"\t\t\"/home/user/.local/share/Steam\"\n\t\t\"apps\"\n\t\t{\n\t\t\t\"763890\"\t\t\"2035740746\"\n\t\t}\n\t}\n\t\"1\"\n\t{\n\t\t\"path\"  \"D:\\\\Games\"\n\t}\n}\n";
+        let folders = parse_library_folders(new_format).unwrap();
+        assert_eq!(
+            vec![
+                Path::new("/home/user/.local/share/Steam").join("steamapps"),
+                Path::new("D:\\Games").join("steamapps")
+            ],
+            folders
+        );
+        let old_format = "\"LibraryFolders\"\n{\n\t\"TimeNextStatsReport\"\t\t\"1\"\n\t\"1\"\t\t\"/mnt/games\"\n}\n";
+        let folders = parse_library_folders(old_format).unwrap();
+        assert_eq!(vec![Path::new("/mnt/games").join("steamapps")], folders);
+    }
+
+    #[test]
+    fn custom_locations_can_be_flatpak() {
+        let settings = |location: &str| SteamSettings {
+            location: Some(location.to_string()),
+            ..Default::default()
+        };
+        let flatpak = SteamInstall::find(&settings(
+            "/home/user/.var/app/com.valvesoftware.Steam/.local/share/Steam",
+        ))
+        .unwrap();
+        assert_eq!(SteamInstallKind::Flatpak, flatpak.kind);
+        assert_eq!(
+            Path::new("/home/user/.var/app/com.valvesoftware.Steam/.local/share/Steam/config/config.vdf"),
+            flatpak.config_vdf_path()
+        );
+        let custom = SteamInstall::find(&settings("/mnt/steam")).unwrap();
+        assert_eq!(SteamInstallKind::Custom, custom.kind);
+        assert_eq!(
+            Path::new("/mnt/steam/userdata/42/config/localconfig.vdf"),
+            custom.localconfig_path("42")
+        );
+    }
+
+    #[test]
+    fn unreadable_library_folders_fall_back_to_the_install() {
+        let dir = tempfile::tempdir().unwrap();
+        let steamapps = dir.path().join("steamapps");
+        std::fs::create_dir_all(&steamapps).unwrap();
+        std::fs::write(steamapps.join("libraryfolders.vdf"), "\"libraryfolders\"\n{").unwrap();
+        let install = SteamInstall {
+            kind: SteamInstallKind::Custom,
+            path: dir.path().to_path_buf(),
+        };
+        assert_eq!(vec![steamapps], install.library_folders());
+    }
+}
@@ -1,21 +1,50 @@
 use std::{
+    collections::BTreeMap,
     ffi::OsStr,
     path::{Path, PathBuf},
 };
 
-use super::{get_steam_path, SteamSettings};
+use serde::Serialize;
 
-#[derive(Debug, Clone)]
+use super::{SteamInstall, SteamSettings, VdfDocument};
+
+/// A game installed by Steam, read from its appmanifest_<appid>.acf
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct SteamGameInfo {
     pub appid: u32,
     pub name: String,
+    /// The folder in steamapps/common the game is installed in
+    pub install_dir: String,
+    pub size_on_disk: u64,
+    /// Unix timestamp of the last update
+    pub last_updated: u64,
+    /// The library the game is installed in, the folder that holds steamapps
+    pub library: PathBuf,
+    /// The game has a proton prefix in the compatdata folder of its library
+    pub needs_proton: bool,
+}
+
+/// A game that has a manifest in more than one Steam library
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MultiLibraryInstall {
+    pub appid: u32,
+    pub name: String,
+    pub libraries: Vec<PathBuf>,
 }
 
+/// The installed Steam games, a game installed in several libraries is listed once
 pub fn get_installed_games(settings: &SteamSettings) -> Vec<SteamGameInfo> {
-    let install_folders = get_install_folders(settings);
+    match SteamInstall::find(settings) {
+        Ok(install) => unique_installs(get_game_manifests(&install)),
+        Err(_) => vec![],
+    }
+}
+
+/// Every manifest in every library, sorted by name
+pub fn get_game_manifests(install: &SteamInstall) -> Vec<SteamGameInfo> {
     let mut games = vec![];
-    for apps_path in install_folders {
-        if let Ok(files) = std::fs::read_dir(apps_path) {
+    for apps_path in install.library_folders() {
+        if let Ok(files) = std::fs::read_dir(&apps_path) {
             for file in files.flatten() {
                 if let Some(game_info) = parse_manifest_file(&file.path()) {
                     games.push(game_info);
@@ -23,32 +52,41 @@ pub fn get_installed_games(settings: &SteamSettings) -> Vec<SteamGameInfo> {
             }
         }
     }
-    games.sort_by_key(|g| g.name.clone());
+    games.sort_by(|a, b| a.name.cmp(&b.name).then(a.library.cmp(&b.library)));
     games
 }
 
-fn get_install_folders(settings: &SteamSettings) -> Vec<PathBuf> {
-    let mut result = vec![];
-    if let Ok(path) = get_steam_path(settings) {
-        let path = Path::new(&path);
-
-        let vdf_path = path.join("steamapps").join("libraryfolders.vdf");
-        if !vdf_path.exists() {
-            result.push(path.join("steamapps"));
-            return result;
-        }
-        if let Ok(vdf_file) = std::fs::read_to_string(vdf_path) {
-            for line in vdf_file.lines() {
-                if line.contains("\"path\"") {
-                    if let Some(path_string) = line.get(11..line.len() - 1) {
-                        result.push(Path::new(&path_string).join("steamapps").to_path_buf());
-                    }
-                }
-            }
+/// Keeps the install Steam updated last of each game
+pub fn unique_installs(games: Vec<SteamGameInfo>) -> Vec<SteamGameInfo> {
+    let mut unique: Vec<SteamGameInfo> = vec![];
+    for game in games {
+        match unique.iter_mut().find(|g| g.appid == game.appid) {
+            Some(found) if found.last_updated < game.last_updated => *found = game,
+            Some(_) => (),
+            None => unique.push(game),
         }
     }
+    unique
+}
 
-    result
+/// The games that have a manifest in more than one library
+pub fn multi_library_installs(games: &[SteamGameInfo]) -> Vec<MultiLibraryInstall> {
+    let mut by_app: BTreeMap<u32, MultiLibraryInstall> = BTreeMap::new();
+    for game in games {
+        by_app
+            .entry(game.appid)
+            .or_insert_with(|| MultiLibraryInstall {
+                appid: game.appid,
+                name: game.name.clone(),
+                libraries: vec![],
+            })
+            .libraries
+            .push(game.library.clone());
+    }
+    by_app
+        .into_values()
+        .filter(|install| install.libraries.len() > 1)
+        .collect()
 }
 
 fn parse_manifest_file(path: &Path) -> Option<SteamGameInfo> {
@@ -56,28 +94,39 @@ fn parse_manifest_file(path: &Path) -> Option<SteamGameInfo> {
     if let Some("acf") = extension {
         let file_content = std::fs::read_to_string(path);
         if let Ok(file_content) = file_content {
-            return parse_manifest_string(file_content);
+            let mut game = parse_manifest_string(file_content)?;
+            if let Some(steamapps) = path.parent() {
+                game.needs_proton = steamapps
+                    .join("compatdata")
+                    .join(game.appid.to_string())
+                    .exists();
+                game.library = steamapps.parent().unwrap_or(steamapps).to_path_buf();
+            }
+            return Some(game);
         }
     }
     None
 }
 
 fn parse_manifest_string<S: AsRef<str>>(string: S) -> Option<SteamGameInfo> {
-    let mut lines = string.as_ref().lines();
-    let appid: Option<u32> = lines
-        .find(|l| l.contains("\"appid\""))
-        .and_then(|line| line.get(11..line.len() - 1))
-        .and_then(|app_id_str| app_id_str.parse().ok());
-    let name_line = lines
-        .find(|l| l.contains("\"name\""))
-        .and_then(|line| line.get(10..line.len() - 1));
-    match (appid, name_line) {
-        (Some(appid), Some(name)) => Some(SteamGameInfo {
-            name: name.to_string(),
-            appid,
-        }),
-        _ => None,
-    }
+    let document = VdfDocument::parse(string.as_ref()).ok()?;
+    let app_state = document.root.get_object("AppState")?;
+    let appid = app_state.get_str("appid")?.parse().ok()?;
+    let name = app_state.get_str("name")?.to_string();
+    let number = |key: &str| {
+        app_state
+            .get_str(key)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    };
+    Some(SteamGameInfo {
+        name,
+        appid,
+        install_dir: app_state.get_str("installdir").unwrap_or_default().to_string(),
+        size_on_disk: number("SizeOnDisk"),
+        last_updated: number("LastUpdated"),
+        ..Default::default()
+    })
 }
 
 #[cfg(test)]
@@ -85,6 +134,7 @@ mod tests {
     //Okay to unwrap in tests
     #![allow(clippy::unwrap_in_result)]
     #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
 
     use super::*;
 
@@ -96,6 +146,34 @@ mod tests {
         let game_info = game_info.unwrap();
         assert_eq!("Wildermyth", game_info.name);
         assert_eq!(763890, game_info.appid);
+        assert_eq!("Wildermyth", game_info.install_dir);
+        assert_eq!(2035740746, game_info.size_on_disk);
+        assert_eq!(1651783287, game_info.last_updated);
+    }
+
+    #[test]
+    fn finds_games_in_several_libraries() {
+        let game = |appid: u32, library: &str, last_updated: u64| SteamGameInfo {
+            appid,
+            name: format!("Game {appid}"),
+            library: PathBuf::from(library),
+            last_updated,
+            ..Default::default()
+        };
+        let games = vec![
+            game(1, "/home/steam", 10),
+            game(1, "/run/media/sdcard", 20),
+            game(2, "/home/steam", 10),
+        ];
+        let multi = multi_library_installs(&games);
+        assert_eq!(1, multi.len());
+        assert_eq!(
+            vec![PathBuf::from("/home/steam"), PathBuf::from("/run/media/sdcard")],
+            multi[0].libraries
+        );
+        let unique = unique_installs(games);
+        assert_eq!(2, unique.len());
+        assert_eq!(PathBuf::from("/run/media/sdcard"), unique[0].library);
     }
 
     // #[test]
@@ -1,15 +1,23 @@
 mod collections;
+mod collections_queue;
+mod compat_tools;
+mod install;
 mod installed_games;
 #[cfg(target_family = "unix")]
 mod proton_vdf_util;
 mod restarter;
 mod settings;
 mod utils;
+mod vdf;
 
 pub use collections::*;
+pub use collections_queue::*;
+pub use compat_tools::*;
+pub use install::*;
 pub use installed_games::*;
 #[cfg(target_family = "unix")]
 pub use proton_vdf_util::*;
 pub use restarter::*;
-pub use settings::SteamSettings;
+pub use settings::{FieldOwnership, SteamSettings, SteamUserSettings};
 pub use utils::*;
+pub use vdf::*;
@@ -1,100 +1,215 @@
 use std::path::Path;
 
-use nom::FindSubstring;
-
-pub fn setup_proton_games<B: AsRef<str>>(games: &[B]) -> eyre::Result<()> {
-    if let Ok(home) = std::env::var("HOME") {
-        let config_file = Path::new(&home).join(".local/share/Steam/config/config.vdf");
-        if config_file.exists() {
-            if let Ok(config_content) = std::fs::read_to_string(&config_file) {
-                let new_string = enable_proton_games(config_content, games);
-                std::fs::write(config_file, new_string)?;
-            }
+use super::{SteamInstall, VdfDocument, VdfObject, VdfValue};
+
+const COMPAT_KEY: &str = "CompatToolMapping";
+
+/// The compatibility tool a game should run with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProtonMapping {
+    pub app_id: u32,
+    pub tool: String,
+    /// Replace the tool the game already has, otherwise only games without a tool get one
+    pub replace: bool,
+    /// The tool BoilR gave the game before, it is replaced as long as the user did not choose another tool
+    pub written_tool: Option<String>,
+}
+
+/// Sets the compatibility tool of the games, the CompatToolMapping section is created if Steam has none yet
+pub fn setup_proton_games(install: &SteamInstall, mappings: &[ProtonMapping]) -> eyre::Result<()> {
+    let config_file = install.config_vdf_path();
+    let Some(config_folder) = config_file.parent().filter(|folder| folder.exists()) else {
+        return Ok(());
+    };
+    let config_content = std::fs::read_to_string(&config_file).unwrap_or_default();
+    let new_content = enable_proton_games(&config_content, mappings)?;
+    if new_content != config_content {
+        std::fs::create_dir_all(config_folder)?;
+        write_config(&config_file, &new_content)?;
+    }
+    Ok(())
+}
+
+/// Removes the proton mappings of shortcuts that no longer exist
+pub fn remove_compat_tool_mappings(install: &SteamInstall, app_ids: &[u32]) -> eyre::Result<()> {
+    let config_file = install.config_vdf_path();
+    if app_ids.is_empty() || !config_file.exists() {
+        return Ok(());
+    }
+    let config_content = std::fs::read_to_string(&config_file)?;
+    let mut document = VdfDocument::parse(&config_content)?;
+    if let Some(mappings) = find_compat_tool_mapping(&mut document) {
+        for app_id in app_ids {
+            mappings.remove(&app_id.to_string());
         }
     }
+    let new_content = document.to_string();
+    if new_content != config_content {
+        write_config(&config_file, &new_content)?;
+    }
     Ok(())
 }
 
-fn enable_proton_games<S: AsRef<str>, B: AsRef<str>>(vdf_content: S, games: &[B]) -> String {
-    let vdf_content = vdf_content.as_ref();
-    if let Some(section_info) = find_indexes(vdf_content) {
-        let (base_indent_string, field_indent_string) = {
-            let mut a = String::new();
-            let mut b = String::new();
-            for _i in 0..=section_info.base_indentation {
-                a.push('\t');
-                b.push('\t');
-            }
-            b.push('\t');
-            (a, b)
-        };
+/// Reads the CompatToolMapping section of the steam config
+pub fn get_compat_tool_mapping(install: &SteamInstall) -> eyre::Result<String> {
+    let config_content = std::fs::read_to_string(install.config_vdf_path())?;
+    compat_tool_mapping_section(&config_content)
+        .ok_or_else(|| eyre::eyre!("Could not find proton section in steam"))
+}
 
-        let proton_replace_string = include_str!("proton_string.txt");
-        let section_str = vdf_content.get(section_info.start..section_info.append_end);
-        if let Some(section_str) = section_str {
-            let games_strings_to_add = games
-                .iter()
-                .filter(|g| {
-                    let game_section_start = format!("\"{}\"\n", g.as_ref());
-                    !section_str.contains(&game_section_start)
-                })
-                .map(|game_id| {
-                    let res = proton_replace_string.to_string();
-                    let res = res.replace("\"X\"", &format!("\"{}\"", game_id.as_ref()));
-                    let res = res.replace('=', &base_indent_string);
-                    res.replace('+', &field_indent_string)
-                });
-            let mut new_section = section_str.to_string();
-            for game_string in games_strings_to_add {
-                new_section.push_str(&game_string);
-            }
-            new_section.push_str(&section_info.end_key);
+/// Replaces the CompatToolMapping section of the steam config with a section from `get_compat_tool_mapping`
+pub fn set_compat_tool_mapping(install: &SteamInstall, section: &str) -> eyre::Result<()> {
+    let config_file = install.config_vdf_path();
+    let config_content = std::fs::read_to_string(&config_file)?;
+    let new_content = replace_compat_tool_mapping(&config_content, section)
+        .ok_or_else(|| eyre::eyre!("Could not find proton section in steam"))?;
+    write_config(&config_file, &new_content)?;
+    Ok(())
+}
 
-            if let Some(before_section) = vdf_content.get(..section_info.start) {
-                if let Some(after_section) = vdf_content.get(section_info.end..) {
-                    return format!("{before_section}{new_section}{after_section}");
-                }
-            }
+/// Writes the config to a temporary file first and renames it in place, so Steam never reads a half written config
+fn write_config(config_file: &Path, content: &str) -> eyre::Result<()> {
+    let temp_path = config_file.with_extension("vdf.tmp");
+    std::fs::write(&temp_path, content)?;
+    std::fs::rename(&temp_path, config_file)?;
+    Ok(())
+}
+
+/// Moves the proton mappings of games whose app id changed from the old to the new app id.
+/// With `replace` the mapping of the old app id replaces a mapping the new app id already has,
+/// otherwise the mapping of the new app id is kept.
+pub fn move_compat_tool_mappings(
+    install: &SteamInstall,
+    changes: &[(u32, u32)],
+    replace: bool,
+) -> eyre::Result<()> {
+    let config_file = install.config_vdf_path();
+    if !config_file.exists() {
+        return Ok(());
+    }
+    let config_content = std::fs::read_to_string(&config_file)?;
+    let new_content = rename_compat_tool_mappings(&config_content, changes, replace)
+        .ok_or_else(|| eyre::eyre!("Could not find proton section in steam"))?;
+    if new_content != config_content {
+        write_config(&config_file, &new_content)?;
+    }
+    Ok(())
+}
+
+fn rename_compat_tool_mappings(
+    vdf_content: &str,
+    changes: &[(u32, u32)],
+    replace: bool,
+) -> Option<String> {
+    let mut document = VdfDocument::parse(vdf_content).ok()?;
+    let mappings = find_compat_tool_mapping(&mut document)?;
+    for (old_app_id, new_app_id) in changes {
+        let (old_key, new_key) = (old_app_id.to_string(), new_app_id.to_string());
+        if mappings.get(&old_key).is_none() || old_key == new_key {
+            continue;
         }
-    } else {
-        //TODO make this an error instead?
-        println!("Could not find proton section in steam, try to manually set proton on at least one game and then rerun");
-    }
-    vdf_content.to_string()
-}
-
-struct SectionInfo {
-    start: usize,
-    end: usize,
-    append_end: usize,
-    base_indentation: usize,
-    end_key: String,
-}
-
-fn find_indexes<S: AsRef<str>>(vdf_content: S) -> Option<SectionInfo> {
-    let compat_key = "\"CompatToolMapping\"\n";
-    let vdf_content = vdf_content.as_ref();
-    if let Some(compat_index) = vdf_content.find_substring(compat_key) {
-        let compat_index = compat_index + compat_key.len();
-        let after_key = vdf_content.get(compat_index..);
-        if let Some(base_indentation) = after_key.and_then(|k| k.find('{')) {
-            let mut end_key = "\n".to_string();
-            for _i in 0..base_indentation {
-                end_key.push('\t');
-            }
-            end_key.push('}');
-            if let Some(end_index) = after_key.and_then(|a| a.find_substring(&end_key)) {
-                return Some(SectionInfo {
-                    start: compat_index,
-                    end: compat_index + end_index + end_key.len(),
-                    append_end: compat_index + end_index,
-                    base_indentation,
-                    end_key: end_key.to_string(),
-                });
+        if mappings.get(&new_key).is_some() {
+            if !replace {
+                continue;
             }
+            mappings.remove(&new_key);
+        }
+        mappings.rename(&old_key, &new_key);
+    }
+    Some(document.to_string())
+}
+
+/// The CompatToolMapping section as it is written in the config, from the line after its key to the closing `}`
+fn compat_tool_mapping_section(vdf_content: &str) -> Option<String> {
+    let document = VdfDocument::parse(vdf_content).ok()?;
+    let entry = document.root.find_entry(COMPAT_KEY)?;
+    entry.value().as_object()?;
+    let separator = entry.separator();
+    let indentation = separator
+        .rfind('\n')
+        .and_then(|index| separator.get(index + 1..))
+        .unwrap_or(separator);
+    Some(format!("{indentation}{}", entry.value()))
+}
+
+fn replace_compat_tool_mapping(vdf_content: &str, section: &str) -> Option<String> {
+    let mut document = VdfDocument::parse(vdf_content).ok()?;
+    let section = VdfDocument::parse(&format!("\"{COMPAT_KEY}\"\n{section}")).ok()?;
+    *find_compat_tool_mapping(&mut document)? = section.root.get_object(COMPAT_KEY)?.clone();
+    Some(document.to_string())
+}
+
+/// The mappings `setup_proton_games` would change, together with the tool the game has now
+pub fn get_proton_changes(
+    install: &SteamInstall,
+    mappings: &[ProtonMapping],
+) -> eyre::Result<Vec<(ProtonMapping, Option<String>)>> {
+    let config_content = std::fs::read_to_string(install.config_vdf_path()).unwrap_or_default();
+    find_proton_changes(&config_content, mappings)
+}
+
+fn find_proton_changes(
+    vdf_content: &str,
+    mappings: &[ProtonMapping],
+) -> eyre::Result<Vec<(ProtonMapping, Option<String>)>> {
+    let mut document = VdfDocument::parse(vdf_content)?;
+    let current = find_compat_tool_mapping(&mut document);
+    Ok(mappings
+        .iter()
+        .filter_map(|mapping| {
+            let current_tool = current
+                .as_ref()
+                .and_then(|current| current.get_object(&mapping.app_id.to_string()))
+                .map(|current| current.get_str("name").unwrap_or_default().to_string());
+            let changes = match &current_tool {
+                None => true,
+                Some(tool) => {
+                    let replace = mapping.replace || mapping.written_tool.as_ref() == Some(tool);
+                    replace && *tool != mapping.tool
+                }
+            };
+            changes.then(|| (mapping.clone(), current_tool))
+        })
+        .collect())
+}
+
+fn enable_proton_games(vdf_content: &str, mappings: &[ProtonMapping]) -> eyre::Result<String> {
+    let mut document = VdfDocument::parse(vdf_content)?;
+    let changes = find_proton_changes(vdf_content, mappings)?;
+    if changes.is_empty() {
+        return Ok(vdf_content.to_string());
+    }
+    let mappings = match find_compat_tool_mapping(&mut document) {
+        Some(mappings) => mappings,
+        None => create_compat_tool_mapping(&mut document)
+            .ok_or_else(|| eyre::eyre!("Could not add a proton section to the steam config"))?,
+    };
+    for (change, _) in changes {
+        if let Some(mapping) = mappings.get_or_insert_object(&change.app_id.to_string()) {
+            mapping.set_str("name", &change.tool);
+            mapping.set_str("config", "");
+            mapping.set_str("Priority", "250");
         }
     }
-    None
+    Ok(document.to_string())
+}
+
+/// Adds the CompatToolMapping section where Steam keeps it
+fn create_compat_tool_mapping(document: &mut VdfDocument) -> Option<&mut VdfObject> {
+    document
+        .root
+        .get_or_insert_object("InstallConfigStore")?
+        .get_or_insert_object("Software")?
+        .get_or_insert_object("Valve")?
+        .get_or_insert_object("Steam")?
+        .get_or_insert_object(COMPAT_KEY)
+}
+
+fn find_compat_tool_mapping(document: &mut VdfDocument) -> Option<&mut VdfObject> {
+    document
+        .root
+        .find_mut(COMPAT_KEY)
+        .and_then(VdfValue::as_object_mut)
 }
 
 #[cfg(test)]
@@ -110,32 +225,118 @@ mod tests {
     #[test]
     pub fn can_find_index_test() {
         let input = include_str!("../testdata/vdf/testconfig.vdf");
-        let SectionInfo {
-            start,
-            end,
-            base_indentation,
-            ..
-        } = find_indexes(input).unwrap();
-
-        let actual = input[start..end].to_string();
+        let actual = compat_tool_mapping_section(input).unwrap();
         let expected = include_str!("../testdata/vdf/compatmappingsection.vdf");
         assert_eq!(expected, actual);
-        assert_eq!(4, base_indentation);
+    }
+
+    fn mappings(app_ids: &[u32]) -> Vec<ProtonMapping> {
+        app_ids
+            .iter()
+            .map(|app_id| ProtonMapping {
+                app_id: *app_id,
+                tool: "proton_experimental".to_string(),
+                replace: false,
+                written_tool: None,
+            })
+            .collect()
     }
 
     #[test]
     pub fn enable_proton_test() {
         let input = include_str!("../testdata/vdf/testconfig.vdf");
-        let output = enable_proton_games(input, &["42", "43", "44"]);
+        let output = enable_proton_games(input, &mappings(&[42, 43, 44])).unwrap();
         let expected = include_str!("../testdata/vdf/testconfig_expected.vdf");
         assert_eq!(expected, output);
     }
 
+    #[test]
+    pub fn replaces_compat_tool_mapping() {
+        let original = include_str!("../testdata/vdf/testconfig.vdf");
+        let changed = enable_proton_games(original, &mappings(&[42])).unwrap();
+        assert_ne!(original, changed);
+        let section = compat_tool_mapping_section(original).unwrap();
+        let restored = replace_compat_tool_mapping(&changed, &section).unwrap();
+        assert_eq!(original, restored);
+    }
+
+    #[test]
+    pub fn renames_compat_tool_mappings() {
+        let input = include_str!("../testdata/vdf/testconfig.vdf");
+        let renamed = rename_compat_tool_mappings(input, &[(2719403116, 42)], false).unwrap();
+        let changes = find_proton_changes(&renamed, &mappings(&[42, 2719403116])).unwrap();
+        assert_eq!(vec![(mappings(&[2719403116])[0].clone(), None)], changes);
+        let restored = rename_compat_tool_mappings(&renamed, &[(42, 2719403116)], false).unwrap();
+        assert_eq!(input, restored);
+    }
+
+    #[test]
+    pub fn moved_mappings_replace_only_when_asked() {
+        let input = include_str!("../testdata/vdf/testconfig.vdf");
+        let with_default = enable_proton_games(input, &mappings(&[42])).unwrap();
+        let kept = rename_compat_tool_mappings(&with_default, &[(2719403116, 42)], false).unwrap();
+        assert_eq!(with_default, kept);
+
+        let replaced = rename_compat_tool_mappings(&with_default, &[(2719403116, 42)], true).unwrap();
+        let moved = rename_compat_tool_mappings(input, &[(2719403116, 42)], false).unwrap();
+        assert_eq!(moved, replaced);
+    }
+
+    #[test]
+    pub fn finds_proton_changes() {
+        let input = include_str!("../testdata/vdf/testconfig.vdf");
+        let changes = find_proton_changes(input, &mappings(&[42, 2719403116])).unwrap();
+        assert_eq!(vec![(mappings(&[42])[0].clone(), None)], changes);
+    }
+
     #[test]
     pub fn enable_proton_test_empty() {
         let input = include_str!("../testdata/vdf/testconfig.vdf");
-        let output = enable_proton_games(input, &["2719403116"]);
+        let output = enable_proton_games(input, &mappings(&[2719403116])).unwrap();
         let expected = include_str!("../testdata/vdf/testconfig.vdf");
         assert_eq!(expected, output);
     }
+
+    #[test]
+    pub fn replaces_tools_chosen_for_a_game() {
+        let input = include_str!("../testdata/vdf/testconfig.vdf");
+        let game = ProtonMapping {
+            app_id: 2719403116,
+            tool: "GE-Proton9-20".to_string(),
+            replace: true,
+            written_tool: None,
+        };
+        let output = enable_proton_games(input, std::slice::from_ref(&game)).unwrap();
+        assert!(find_proton_changes(&output, &[game]).unwrap().is_empty());
+    }
+
+    #[test]
+    pub fn replaces_only_tools_written_by_boilr() {
+        let input = include_str!("../testdata/vdf/testconfig.vdf");
+        let mut game = ProtonMapping {
+            app_id: 42,
+            tool: "proton_8".to_string(),
+            replace: false,
+            written_tool: None,
+        };
+        let output = enable_proton_games(input, std::slice::from_ref(&game)).unwrap();
+
+        game.tool = "proton_9".to_string();
+        assert!(find_proton_changes(&output, std::slice::from_ref(&game)).unwrap().is_empty());
+
+        game.written_tool = Some("proton_7".to_string());
+        assert!(find_proton_changes(&output, std::slice::from_ref(&game)).unwrap().is_empty());
+
+        game.written_tool = Some("proton_8".to_string());
+        let changes = find_proton_changes(&output, std::slice::from_ref(&game)).unwrap();
+        assert_eq!(vec![(game, Some("proton_8".to_string()))], changes);
+    }
+
+    #[test]
+    pub fn creates_compat_tool_mapping() {
+        let input = "\"InstallConfigStore\"\n{\n\t\"Software\"\n\t{\n\t}\n}\n";
+        let output = enable_proton_games(input, &mappings(&[42])).unwrap();
+        assert!(find_proton_changes(&output, &mappings(&[42])).unwrap().is_empty());
+        assert!(output.contains("\t\t\t\t\"CompatToolMapping\"\n\t\t\t\t{\n\t\t\t\t\t\"42\"\n"));
+    }
 }
@@ -1,78 +1,275 @@
-use std::{ffi::OsStr, process::Command, thread::sleep, time::Duration};
+use std::{
+    ffi::{OsStr, OsString},
+    process::{Command, Stdio},
+    thread::sleep,
+    time::{Duration, Instant},
+};
 
-use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+use sysinfo::{Process, ProcessStatus, ProcessesToUpdate, System, Uid};
 
-pub fn ensure_steam_stopped() {
-    #[cfg(target_os = "windows")]
-    let steam_name = "steam.exe";
-    #[cfg(target_family = "unix")]
-    let steam_name = "steam";
+use super::{SteamInstall, SteamInstallKind, SteamSettings, FLATPAK_ID};
+
+#[cfg(target_os = "windows")]
+const STEAM_NAME: &str = "steam.exe";
+#[cfg(not(target_os = "windows"))]
+const STEAM_NAME: &str = "steam";
 
-    let os_steam_name = OsStr::new(steam_name);
+/// Arguments that pick the interface of Steam, they are passed again when Steam is started after the import
+const MODE_ARGS: [&str; 4] = ["-gamepadui", "-bigpicture", "-tenfoot", "-steamdeck"];
 
-    let s = System::new_all();
-    let processes = s.processes_by_name(os_steam_name);
-    for process in processes {
-        let mut s = System::new();
+/// Steam was started by a session, like the game mode of the Steam Deck, which starts it again by itself
+const SESSION_ARG: &str = "-steamos3";
 
-        let quit_res = process.kill_with(sysinfo::Signal::Quit);
-        let kill_res = process.kill_with(sysinfo::Signal::Kill);
+const POLL: Duration = Duration::from_millis(500);
 
-        if quit_res == Some(false) && kill_res == Some(false) {
-            // Couldn't kill the process, this could be because it was already killed or we don't have permissions
-            // For instance, the process "steamos-manager" in the Steam Deck is owned by root
-            continue;
-        }
-        
-        let pid = process.pid();
-        let process_name = process.name();
-        let pid_arr = [pid];
-        let process_to_update = ProcessesToUpdate::Some(&pid_arr);
-
-        while s.refresh_processes_specifics(process_to_update, true,ProcessRefreshKind::everything()) > 0 
-                // The process is still alive
-                && s.process(pid).is_some()
-                 {
-            println!("Waiting for steam to stop. PID: {pid:?} Name: {process_name:?}");
-            sleep(Duration::from_millis(500));
-            process.kill_with(sysinfo::Signal::Quit);
-            process.kill_with(sysinfo::Signal::Kill);
+/// How Steam was running before BoilR stopped it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunningSteam {
+    pub kind: SteamInstallKind,
+    pub mode_args: Vec<String>,
+    pub session: bool,
+}
+
+/// Asks Steam to shut down and waits for it, if it is still running after the timeout in the settings it is killed.
+/// Returns how Steam was running, so it can be started the same way after the import.
+/// Fails if Steam is still running after it was killed, since Steam would overwrite the shortcuts when it exits.
+pub fn ensure_steam_stopped(
+    settings: &SteamSettings,
+    install: &SteamInstall,
+) -> eyre::Result<Option<RunningSteam>> {
+    let system = System::new_all();
+    let Some(running) = find_running_steam(&system) else {
+        log!("Steam is not running");
+        return Ok(None);
+    };
+    if is_flatpak(running.kind) != is_flatpak(install.kind) {
+        eprintln!(
+            "The running Steam is a {} install, but BoilR imports into the {} install at {:?}",
+            running.kind.name(),
+            install.kind.name(),
+            install.path
+        );
+    }
+    log!("Asking Steam to shut down, waiting for it to stop");
+    if let Err(err) = request_shutdown(install, running.kind) {
+        eprintln!("Could not ask Steam to shut down: {err}");
+    }
+    let timeout = Duration::from_secs(settings.shutdown_timeout);
+    if !wait_for_steam_exit(timeout) {
+        log!(
+            "Steam did not shut down within {} seconds, stopping it",
+            timeout.as_secs()
+        );
+        kill_steam();
+        if !wait_for_steam_exit(timeout.max(Duration::from_secs(5))) {
+            return Err(eyre::eyre!("Steam could not be stopped, close Steam and import again"));
         }
     }
-    println!("Steam is stopped");
+    log!("Steam is stopped");
+    Ok(Some(running))
+}
+
+/// Starts Steam if it is not running, the way it was running before it was stopped
+pub fn ensure_steam_started(install: &SteamInstall, previous: Option<&RunningSteam>) {
+    let system = System::new_all();
+    if find_running_steam(&system).is_some() {
+        return;
+    }
+    if previous.is_some_and(|steam| steam.session) {
+        log!("Steam is started again by its session");
+        return;
+    }
+    let kind = previous.map_or(install.kind, |steam| steam.kind);
+    let mode_args = previous.map(|steam| steam.mode_args.as_slice()).unwrap_or_default();
+    log!("Starting steam");
+    let mut command = steam_command(install, kind);
+    command.args(mode_args);
+    if let Err(e) = command.spawn() {
+        log!("Failed to start steam: {e:?}");
+    };
+}
+
+fn is_flatpak(kind: SteamInstallKind) -> bool {
+    kind == SteamInstallKind::Flatpak
+}
+
+/// The command that runs Steam of the given kind, Steam does not write to the output of BoilR
+fn steam_command(install: &SteamInstall, kind: SteamInstallKind) -> Command {
+    let mut command = if is_flatpak(kind) {
+        let mut command = Command::new("flatpak");
+        command.args(["run", FLATPAK_ID]);
+        command
+    } else {
+        steam_executable(install)
+    };
+    command.stdout(Stdio::null()).stderr(Stdio::null());
+    command
 }
+
 #[cfg(target_os = "windows")]
-pub fn ensure_steam_started(settings: &super::SteamSettings) {
-    let steam_name = "steam.exe";
-    let os_steam_name = OsStr::new(steam_name);
-    let s = System::new_all();
-    let mut processes = s.processes_by_name(os_steam_name);
-    if processes.next().is_none() {
-        //no steam, we need to start it
-        println!("Starting steam");
-        let folder = super::get_steam_path(settings);
-        if let Ok(folder) = folder {
-            let path = std::path::Path::new(&folder).join(steam_name);
-            let mut command = Command::new(path);
-            if let Err(e) = command.spawn() {
-                println!("Failed to start steam: {:?}", e);
-            };
+fn steam_executable(install: &SteamInstall) -> Command {
+    Command::new(install.path.join(STEAM_NAME))
+}
+
+#[cfg(not(target_os = "windows"))]
+fn steam_executable(_install: &SteamInstall) -> Command {
+    Command::new(STEAM_NAME)
+}
+
+/// Runs `steam -shutdown`, or opens steam://exit if that could not be started
+fn request_shutdown(install: &SteamInstall, kind: SteamInstallKind) -> std::io::Result<()> {
+    let mut command = steam_command(install, kind);
+    command.arg("-shutdown");
+    match command.spawn() {
+        Ok(mut child) => {
+            //The child is also named steam, it is waited on so it does not linger as a zombie
+            std::thread::spawn(move || child.wait());
+            Ok(())
         }
+        Err(_) => open_url("steam://exit"),
     }
 }
 
-#[cfg(target_family = "unix")]
-pub fn ensure_steam_started(_settings: &super::SteamSettings) {
-    let steam_name = "steam";
-    let os_steam_name = OsStr::new(steam_name);
-    let s = System::new_all();
-    let mut processes = s.processes_by_name(os_steam_name);
-    if processes.next().is_none() {
-        //no steam, we need to start it
-        println!("Starting steam");
-        let mut command = Command::new(steam_name);
-        if let Err(e) = command.spawn() {
-            println!("Failed to start steam: {e:?}");
+fn open_url(url: &str) -> std::io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(all(target_family = "unix", not(target_os = "macos")))]
+    let mut command = Command::new("xdg-open");
+    command.arg(url).spawn().map(|_| ())
+}
+
+/// Waits until no Steam process of this user is left, returns false if Steam still runs after the timeout
+fn wait_for_steam_exit(timeout: Duration) -> bool {
+    let start = Instant::now();
+    let mut system = System::new_all();
+    loop {
+        if find_running_steam(&system).is_none() {
+            return true;
+        }
+        if start.elapsed() >= timeout {
+            return false;
+        }
+        sleep(POLL);
+        system.refresh_processes(ProcessesToUpdate::All, true);
+    }
+}
+
+/// Kills the Steam processes of this user, processes of other users like root are left alone
+fn kill_steam() {
+    let system = System::new_all();
+    let user = current_user(&system);
+    for process in system
+        .processes()
+        .values()
+        .filter(|process| is_steam_process(process, user.as_ref()))
+    {
+        if !process.kill() {
+            eprintln!("Could not stop Steam process {}", process.pid());
+        }
+    }
+}
+
+fn current_user(system: &System) -> Option<Uid> {
+    let pid = sysinfo::get_current_pid().ok()?;
+    system.process(pid)?.user_id().cloned()
+}
+
+fn is_steam_process(process: &Process, user: Option<&Uid>) -> bool {
+    process.thread_kind().is_none()
+        && process.status() != ProcessStatus::Zombie
+        && process.name() == OsStr::new(STEAM_NAME)
+        && (user.is_none() || process.user_id() == user)
+}
+
+fn find_running_steam(system: &System) -> Option<RunningSteam> {
+    let user = current_user(system);
+    let processes: Vec<&Process> = system
+        .processes()
+        .values()
+        .filter(|process| is_steam_process(process, user.as_ref()))
+        .collect();
+    if processes.is_empty() {
+        return None;
+    }
+    let kind = if processes
+        .iter()
+        .any(|process| is_flatpak_process(process.cmd(), process.environ()))
+    {
+        SteamInstallKind::Flatpak
+    } else {
+        SteamInstallKind::Native
+    };
+    let cmd: Vec<OsString> = processes
+        .iter()
+        .flat_map(|process| process.cmd().iter().cloned())
+        .collect();
+    Some(RunningSteam {
+        kind,
+        mode_args: mode_args(&cmd),
+        session: cmd.iter().any(|arg| arg == SESSION_ARG),
+    })
+}
+
+fn is_flatpak_process(cmd: &[OsString], environ: &[OsString]) -> bool {
+    let flatpak_env = format!("FLATPAK_ID={FLATPAK_ID}");
+    environ.iter().any(|var| var == flatpak_env.as_str())
+        || cmd
+            .iter()
+            .any(|arg| arg.to_string_lossy().contains(&format!(".var/app/{FLATPAK_ID}")))
+}
+
+fn mode_args(cmd: &[OsString]) -> Vec<String> {
+    MODE_ARGS
+        .iter()
+        .filter(|mode| cmd.iter().any(|arg| arg == **mode))
+        .map(|mode| mode.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os_strings(values: &[&str]) -> Vec<OsString> {
+        values.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn finds_the_mode_steam_runs_in() {
+        let deck = os_strings(&["steam", "-steamos3", "-steampal", "-steamdeck", "-gamepadui"]);
+        assert_eq!(vec!["-gamepadui", "-steamdeck"], mode_args(&deck));
+        assert!(mode_args(&os_strings(&["steam", "-silent"])).is_empty());
+    }
+
+    #[test]
+    fn finds_flatpak_steam() {
+        let environ = os_strings(&["HOME=/home/deck", "FLATPAK_ID=com.valvesoftware.Steam"]);
+        assert!(is_flatpak_process(&os_strings(&["steam"]), &environ));
+        let cmd = os_strings(&["/home/deck/.var/app/com.valvesoftware.Steam/data/Steam/ubuntu12_32/steam"]);
+        assert!(is_flatpak_process(&cmd, &[]));
+        assert!(!is_flatpak_process(
+            &os_strings(&["/usr/lib/steam/steam"]),
+            &os_strings(&["HOME=/home/deck"])
+        ));
+    }
+
+    #[test]
+    fn starts_flatpak_steam_with_flatpak() {
+        let install = SteamInstall {
+            kind: SteamInstallKind::Flatpak,
+            path: "/home/deck/.var/app/com.valvesoftware.Steam/data/Steam".into(),
         };
+        let command = steam_command(&install, SteamInstallKind::Flatpak);
+        assert_eq!("flatpak", command.get_program());
+        assert_eq!(
+            vec!["run", FLATPAK_ID],
+            command.get_args().collect::<Vec<_>>()
+        );
     }
 }
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
@@ -7,4 +9,122 @@ pub struct SteamSettings {
     pub optimize_for_big_picture: bool,
     pub stop_steam: bool,
     pub start_steam: bool,
+    /// Seconds to wait for Steam to shut down before it is stopped forcefully
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout: u64,
+    #[serde(default)]
+    pub field_ownership: FieldOwnership,
+    #[serde(default)]
+    pub proton: ProtonSettings,
+    /// Puts the games installed by Steam in the collections of the collection rules too
+    #[serde(default)]
+    pub collect_installed_games: bool,
+    /// Settings of single Steam users by their user id, users without settings get all games
+    #[serde(default)]
+    pub users: BTreeMap<String, SteamUserSettings>,
+}
+
+fn default_shutdown_timeout() -> u64 {
+    30
+}
+
+impl SteamSettings {
+    pub fn user_settings(&self, user_id: &str) -> SteamUserSettings {
+        self.users.get(user_id).cloned().unwrap_or_default()
+    }
+}
+
+/// What BoilR imports for a single Steam user
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct SteamUserSettings {
+    /// Users that are not enabled get no games, and their shortcuts are left alone
+    pub enabled: bool,
+    /// Games this user does not get, by the app id the platform gave them like the global blacklist
+    pub blacklisted_games: Vec<u32>,
+    /// The name or code name of the platforms this user does not get games from
+    pub disabled_platforms: Vec<String>,
+}
+
+impl Default for SteamUserSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            blacklisted_games: vec![],
+            disabled_platforms: vec![],
+        }
+    }
+}
+
+impl SteamUserSettings {
+    /// If this user gets the games of a platform, `platform` and `code_name` are the names from `GamesPlatform`
+    pub fn imports_platform(&self, platform: &str, code_name: &str) -> bool {
+        !self.disabled_platforms.iter().any(|disabled| {
+            disabled.eq_ignore_ascii_case(platform) || disabled.eq_ignore_ascii_case(code_name)
+        })
+    }
+}
+
+/// Which compatibility tool the games that need proton get.
+/// A tool chosen for a single game in its overrides is used before these.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct ProtonSettings {
+    pub default_tool: String,
+    /// Tools by the name or code name of the platform
+    pub platform_tools: BTreeMap<String, String>,
+}
+
+impl Default for ProtonSettings {
+    fn default() -> Self {
+        Self {
+            default_tool: "proton_experimental".to_string(),
+            platform_tools: BTreeMap::new(),
+        }
+    }
+}
+
+impl ProtonSettings {
+    /// The tool for games of a platform, `platform` and `code_name` are the names from `GamesPlatform`
+    pub fn tool_for(&self, platform: &str, code_name: &str) -> &str {
+        self.platform_tools
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(platform) || key.eq_ignore_ascii_case(code_name))
+            .map(|(_, tool)| tool.as_str())
+            .filter(|tool| !tool.is_empty())
+            .unwrap_or(&self.default_tool)
+    }
+}
+
+/// Which fields of a shortcut BoilR overwrites on import.
+/// Fields BoilR does not own keep the value the user set in Steam.
+/// The name and target are always owned by BoilR, since they decide the app id.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct FieldOwnership {
+    pub start_dir: bool,
+    pub launch_options: bool,
+    pub icon: bool,
+    pub tags: bool,
+    pub is_hidden: bool,
+    pub allow_overlay: bool,
+    pub allow_desktop_config: bool,
+    pub open_vr: bool,
+    pub last_play_time: bool,
+}
+
+impl Default for FieldOwnership {
+    fn default() -> Self {
+        Self {
+            start_dir: true,
+            launch_options: true,
+            icon: true,
+            tags: false,
+            is_hidden: false,
+            allow_overlay: false,
+            allow_desktop_config: false,
+            open_vr: false,
+            last_play_time: false,
+        }
+    }
 }
@@ -1,12 +1,11 @@
-#[cfg(target_os = "windows")]
-use std::env::{self};
+use std::collections::HashMap;
 use std::error::Error;
 use std::path::PathBuf;
 use std::{fmt, path::Path};
 
 use steam_shortcuts_util::{parse_shortcuts, shortcut::ShortcutOwned};
 
-use super::SteamSettings;
+use super::{SteamInstall, SteamSettings, VdfDocument};
 
 pub fn get_shortcuts_for_user(user: &SteamUsersInfo) -> eyre::Result<ShortcutInfo> {
     let mut shortcuts = vec![];
@@ -22,7 +21,7 @@ pub fn get_shortcuts_for_user(user: &SteamUsersInfo) -> eyre::Result<ShortcutInf
             Path::new(&shortcut_path).to_path_buf()
         }
         None => {
-            println!(
+            log!(
                 "Did not find a shortcut file for user {}, creating a new",
                 user.steam_user_data_folder
             );
@@ -48,12 +47,40 @@ pub struct SteamUsersInfo {
     pub steam_user_data_folder: String,
     pub shortcut_path: Option<String>,
     pub user_id: String,
+    /// The name the user goes by in Steam, from loginusers.vdf
+    pub persona_name: Option<String>,
+}
+
+impl SteamUsersInfo {
+    /// The persona name with the user id, or only the user id if the name is not known
+    pub fn display_name(&self) -> String {
+        match &self.persona_name {
+            Some(name) => format!("{name} ({})", self.user_id),
+            None => self.user_id.clone(),
+        }
+    }
+}
+
+/// The users that get games imported, the users that are turned off in the settings are left out
+pub fn get_import_users(
+    settings: &SteamSettings,
+    install: &SteamInstall,
+) -> eyre::Result<Vec<SteamUsersInfo>> {
+    let users = get_install_users(install)?;
+    Ok(users
+        .into_iter()
+        .filter(|user| settings.user_settings(&user.user_id).enabled)
+        .collect())
 }
 
 /// Get the paths to the steam users shortcuts (one for each user)
 pub fn get_shortcuts_paths(settings: &SteamSettings) -> eyre::Result<Vec<SteamUsersInfo>> {
-    let steam_path_str = get_steam_path(settings)?;
-    let steam_path = Path::new(&steam_path_str);
+    get_install_users(&SteamInstall::find(settings)?)
+}
+
+/// The users of a Steam install, with the paths to their shortcuts
+pub fn get_install_users(install: &SteamInstall) -> eyre::Result<Vec<SteamUsersInfo>> {
+    let steam_path = install.path.as_path();
     if !steam_path.exists() {
         return Err(eyre::format_err!(
             "Steam folder not found at: {:?}",
@@ -69,6 +96,7 @@ pub fn get_shortcuts_paths(settings: &SteamSettings) -> eyre::Result<Vec<SteamUs
         ));
     }
 
+    let persona_names = get_persona_names(steam_path);
     let user_folders = std::fs::read_dir(&user_data_path)?;
     let users_info = user_folders
         .filter_map(|f| f.ok())
@@ -89,17 +117,20 @@ pub fn get_shortcuts_paths(settings: &SteamSettings) -> eyre::Result<Vec<SteamUs
             let path = format!("{folder_str}//config//shortcuts.vdf");
             let shortcuts_path = Path::new(path.as_str());
             let folder_string = folder_str.to_string();
+            let persona_name = persona_names.get(&user_id).cloned();
             if shortcuts_path.exists() {
                 SteamUsersInfo {
                     steam_user_data_folder: folder_string,
                     shortcut_path: Some(shortcuts_path.to_string_lossy().to_string()),
                     user_id,
+                    persona_name,
                 }
             } else {
                 SteamUsersInfo {
                     steam_user_data_folder: folder_string,
                     shortcut_path: None,
                     user_id,
+                    persona_name,
                 }
             }
         })
@@ -107,60 +138,40 @@ pub fn get_shortcuts_paths(settings: &SteamSettings) -> eyre::Result<Vec<SteamUs
     Ok(users_info)
 }
 
-pub fn get_steam_path(settings: &SteamSettings) -> eyre::Result<String> {
-    let user_location = settings.location.clone();
-    let steam_path_str = match user_location {
-        Some(location) => location,
-        None => get_default_location()?,
-    };
-    Ok(steam_path_str)
+/// The difference between a 64 bit steam id and the account id used for the userdata folders
+const STEAM_ID_OFFSET: u64 = 76561197960265728;
+
+/// The persona names of the users that logged in to Steam, keyed by their userdata folder name
+fn get_persona_names(steam_path: &Path) -> HashMap<String, String> {
+    let path = steam_path.join("config").join("loginusers.vdf");
+    match std::fs::read_to_string(&path) {
+        Ok(content) => parse_login_users(&content).unwrap_or_else(|err| {
+            eprintln!("Could not read {path:?}: {err}");
+            HashMap::new()
+        }),
+        Err(_) => HashMap::new(),
+    }
 }
 
-pub fn get_default_location() -> eyre::Result<String> {
-    #[cfg(target_os = "windows")]
-    let path_string = {
-        let key = "PROGRAMFILES(X86)";
-        let program_files = env::var(key)?;
-        String::from(
-            Path::new(&program_files)
-                .join("Steam")
-                .to_str()
-                .unwrap_or(""),
-        )
-    };
-    #[cfg(target_os = "linux")]
-    let path_string = {
-        let home = std::env::var("HOME")?;
-        let default_path = Path::new(&home).join(".steam").join("steam");
-        if default_path.exists() {
-            default_path.to_string_lossy().to_string()
-        } else {
-            Path::new(&home)
-                .join(".var")
-                .join("app")
-                .join("com.valvesoftware.Steam")
-                .join(".steam")
-                .join("steam")
-                .to_string_lossy()
-                .to_string()
-        }
-    };
-    #[cfg(target_os = "macos")]
-    let path_string = {
-        let home = std::env::var("HOME")?;
-        String::from(
-            Path::new(&home)
-                .join("Library")
-                .join("Application Support")
-                .join("Steam")
-                .to_str()
-                .unwrap_or(""),
-        )
-    };
-    Ok(path_string)
+fn parse_login_users(content: &str) -> eyre::Result<HashMap<String, String>> {
+    let document = VdfDocument::parse(content)?;
+    let users = document
+        .root
+        .get_object("users")
+        .ok_or_else(|| eyre::eyre!("Missing users"))?;
+    Ok(users
+        .entries()
+        .filter_map(|entry| {
+            let steam_id = entry.key().parse::<u64>().ok()?;
+            let account_id = steam_id.checked_sub(STEAM_ID_OFFSET)?;
+            let name = entry.value().as_object()?.get_str("PersonaName")?;
+            Some((account_id.to_string(), name.to_string()))
+        })
+        .collect())
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 struct SteamFolderNotFound {
     location_tried: String,
 }
@@ -182,6 +193,7 @@ impl Error for SteamFolderNotFound {
 }
 
 #[derive(Debug)]
+#[allow(dead_code)]
 struct SteamUsersDataEmpty {
     location_tried: String,
 }
@@ -220,3 +232,17 @@ pub fn get_users_images(data_folder: &str) -> Result<Vec<String>, Box<dyn Error>
         .collect();
     Ok(file_names)
 }
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn reads_persona_names() {
+        let content = "\"users\"\n{\n\t\"76561197960287930\"\n\t{\n\t\t\"AccountName\"\t\t\"gaben\"\n\t\t\"PersonaName\"\t\t\"Gabe\"\n\t\t\"MostRecent\"\t\t\"1\"\n\t}\n}\n";
+        let names = parse_login_users(content).unwrap();
+        assert_eq!(Some(&"Gabe".to_string()), names.get("22202"));
+    }
+}
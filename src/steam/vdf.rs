@@ -0,0 +1,472 @@
+//! Text VDF (KeyValues), the format of config.vdf, localconfig.vdf, libraryfolders.vdf and app manifests.
+//! Parsing keeps the whitespace, comments and quoting of the file,
+//! so writing a document gives back the same text except for the values that were changed.
+
+use std::fmt;
+
+/// A parsed VDF file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VdfDocument {
+    pub root: VdfObject,
+}
+
+/// The entries between `{` and `}`, or all entries at the top of a file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VdfObject {
+    entries: Vec<VdfEntry>,
+    /// The whitespace and comments before the closing `}` or the end of the file
+    trailing: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VdfEntry {
+    /// The whitespace and comments before the key
+    leading: String,
+    key: VdfString,
+    /// The whitespace between the key and the value
+    separator: String,
+    value: VdfValue,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VdfValue {
+    String(VdfString),
+    Object(VdfObject),
+}
+
+/// A key or value, `raw` is the text it was parsed from and is used when writing it unchanged
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VdfString {
+    value: String,
+    raw: Option<String>,
+}
+
+impl VdfDocument {
+    pub fn parse(input: &str) -> eyre::Result<Self> {
+        let mut parser = Parser { input, pos: 0 };
+        let root = parser.object(false)?;
+        Ok(Self { root })
+    }
+}
+
+impl fmt::Display for VdfDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.root.write_entries(f)
+    }
+}
+
+impl VdfString {
+    pub fn new<S: Into<String>>(value: S) -> Self {
+        Self {
+            value: value.into(),
+            raw: None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+}
+
+impl fmt::Display for VdfString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.raw {
+            Some(raw) => f.write_str(raw),
+            None => write!(f, "\"{}\"", escape(&self.value)),
+        }
+    }
+}
+
+impl VdfEntry {
+    pub fn key(&self) -> &str {
+        self.key.as_str()
+    }
+
+    pub fn value(&self) -> &VdfValue {
+        &self.value
+    }
+
+    /// The whitespace between the key and the value
+    pub fn separator(&self) -> &str {
+        &self.separator
+    }
+}
+
+impl VdfValue {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            VdfValue::String(value) => Some(value.as_str()),
+            VdfValue::Object(_) => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&VdfObject> {
+        match self {
+            VdfValue::Object(object) => Some(object),
+            VdfValue::String(_) => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut VdfObject> {
+        match self {
+            VdfValue::Object(object) => Some(object),
+            VdfValue::String(_) => None,
+        }
+    }
+
+    /// Replaces the value with a string, an unchanged string keeps how it was written
+    pub fn set_str(&mut self, value: &str) {
+        if self.as_str() != Some(value) {
+            *self = VdfValue::String(VdfString::new(value));
+        }
+    }
+}
+
+impl VdfObject {
+    pub fn entries(&self) -> impl Iterator<Item = &VdfEntry> {
+        self.entries.iter()
+    }
+
+    /// Keys are compared without case, like Steam does
+    fn position(&self, key: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.key().eq_ignore_ascii_case(key))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&VdfValue> {
+        self.position(key)
+            .and_then(|index| self.entries.get(index))
+            .map(|entry| &entry.value)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut VdfValue> {
+        self.position(key)
+            .and_then(|index| self.entries.get_mut(index))
+            .map(|entry| &mut entry.value)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(VdfValue::as_str)
+    }
+
+    pub fn get_object(&self, key: &str) -> Option<&VdfObject> {
+        self.get(key).and_then(VdfValue::as_object)
+    }
+
+    pub fn get_object_mut(&mut self, key: &str) -> Option<&mut VdfObject> {
+        self.get_mut(key).and_then(VdfValue::as_object_mut)
+    }
+
+    /// Finds the first entry with this key, in this object or any object below it
+    pub fn find_entry(&self, key: &str) -> Option<&VdfEntry> {
+        if let Some(entry) = self.position(key).and_then(|index| self.entries.get(index)) {
+            return Some(entry);
+        }
+        self.entries.iter().find_map(|entry| match &entry.value {
+            VdfValue::Object(object) => object.find_entry(key),
+            VdfValue::String(_) => None,
+        })
+    }
+
+    /// Finds the value of the first entry with this key, in this object or any object below it
+    pub fn find(&self, key: &str) -> Option<&VdfValue> {
+        self.find_entry(key).map(|entry| &entry.value)
+    }
+
+    /// Like [`VdfObject::find`], but mutable
+    pub fn find_mut(&mut self, key: &str) -> Option<&mut VdfValue> {
+        if let Some(index) = self.position(key) {
+            return self.entries.get_mut(index).map(|entry| &mut entry.value);
+        }
+        self.entries
+            .iter_mut()
+            .find_map(|entry| match &mut entry.value {
+                VdfValue::Object(object) => object.find_mut(key),
+                VdfValue::String(_) => None,
+            })
+    }
+
+    /// Sets the value of a key, the entry is added at the end if it does not exist yet
+    pub fn set_str(&mut self, key: &str, value: &str) {
+        match self.get_mut(key) {
+            Some(current) => current.set_str(value),
+            None => {
+                let leading = self.child_indent();
+                self.entries.push(VdfEntry {
+                    leading,
+                    key: VdfString::new(key),
+                    separator: "\t\t".to_string(),
+                    value: VdfValue::String(VdfString::new(value)),
+                });
+            }
+        }
+    }
+
+    /// Gets the object of a key, an empty object is added at the end if the key does not exist yet.
+    /// Returns `None` if the key has a string value.
+    pub fn get_or_insert_object(&mut self, key: &str) -> Option<&mut VdfObject> {
+        if self.position(key).is_none() {
+            let indent = self.child_indent();
+            self.entries.push(VdfEntry {
+                leading: indent.clone(),
+                key: VdfString::new(key),
+                separator: indent.clone(),
+                value: VdfValue::Object(VdfObject {
+                    entries: vec![],
+                    trailing: indent,
+                }),
+            });
+        }
+        self.get_object_mut(key)
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<VdfValue> {
+        self.position(key)
+            .map(|index| self.entries.remove(index).value)
+    }
+
+    /// Changes the key of an entry, returns false if there was no entry with the old key
+    pub fn rename(&mut self, old_key: &str, new_key: &str) -> bool {
+        match self
+            .position(old_key)
+            .and_then(|index| self.entries.get_mut(index))
+        {
+            Some(entry) => {
+                entry.key = VdfString::new(new_key);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The whitespace before a new entry, copied from the last entry or the closing `}`
+    fn child_indent(&self) -> String {
+        if let Some(last) = self.entries.last() {
+            return last_line(&last.leading).unwrap_or("\n").to_string();
+        }
+        match last_line(&self.trailing) {
+            Some(indent) => format!("{indent}\t"),
+            None => "\n".to_string(),
+        }
+    }
+
+    fn write_entries(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            write!(
+                f,
+                "{}{}{}{}",
+                entry.leading, entry.key, entry.separator, entry.value
+            )?;
+        }
+        f.write_str(&self.trailing)
+    }
+}
+
+impl fmt::Display for VdfValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VdfValue::String(value) => write!(f, "{value}"),
+            VdfValue::Object(object) => {
+                f.write_str("{")?;
+                object.write_entries(f)?;
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// The part of the whitespace from the last line break on
+fn last_line(whitespace: &str) -> Option<&str> {
+    whitespace
+        .rfind('\n')
+        .and_then(|index| whitespace.get(index..))
+}
+
+fn escape(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        self.input.get(self.pos..).unwrap_or_default()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Whitespace, `//` comments and conditions like `[$WIN32]`
+    fn trivia(&mut self) -> String {
+        let start = self.pos;
+        loop {
+            let rest = self.rest();
+            if let Some(c) = rest.chars().next().filter(|c| c.is_whitespace()) {
+                self.pos += c.len_utf8();
+            } else if rest.starts_with("//") {
+                self.pos += rest.find('\n').unwrap_or(rest.len());
+            } else if rest.starts_with("[$") || rest.starts_with("[!$") {
+                self.pos += rest.find(']').map_or(rest.len(), |end| end + 1);
+            } else {
+                break;
+            }
+        }
+        self.input.get(start..self.pos).unwrap_or_default().to_string()
+    }
+
+    fn object(&mut self, nested: bool) -> eyre::Result<VdfObject> {
+        let mut entries = vec![];
+        loop {
+            let leading = self.trivia();
+            match self.peek() {
+                None if nested => return Err(eyre::eyre!("Missing }} at the end of the file")),
+                None => {
+                    return Ok(VdfObject {
+                        entries,
+                        trailing: leading,
+                    })
+                }
+                Some('}') if nested => {
+                    self.pos += 1;
+                    return Ok(VdfObject {
+                        entries,
+                        trailing: leading,
+                    });
+                }
+                Some(c @ ('}' | '{')) => {
+                    return Err(eyre::eyre!("Unexpected {c} at position {}", self.pos))
+                }
+                Some(_) => {}
+            }
+            let key = self.string()?;
+            let separator = self.trivia();
+            let value = match self.peek() {
+                Some('{') => {
+                    self.pos += 1;
+                    VdfValue::Object(self.object(true)?)
+                }
+                None | Some('}') => {
+                    return Err(eyre::eyre!("Missing value for key {}", key.value))
+                }
+                Some(_) => VdfValue::String(self.string()?),
+            };
+            entries.push(VdfEntry {
+                leading,
+                key,
+                separator,
+                value,
+            });
+        }
+    }
+
+    fn string(&mut self) -> eyre::Result<VdfString> {
+        let start = self.pos;
+        let rest = self.rest();
+        let (value, length) = match rest.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = None;
+                while let Some((index, c)) = chars.next() {
+                    match c {
+                        '"' => {
+                            end = Some(index);
+                            break;
+                        }
+                        '\\' => match chars.next() {
+                            Some((_, 'n')) => value.push('\n'),
+                            Some((_, 't')) => value.push('\t'),
+                            Some((_, escaped @ ('\\' | '"'))) => value.push(escaped),
+                            Some((_, other)) => {
+                                value.push('\\');
+                                value.push(other);
+                            }
+                            None => value.push('\\'),
+                        },
+                        c => value.push(c),
+                    }
+                }
+                let end = end.ok_or_else(|| eyre::eyre!("Missing \" after position {start}"))?;
+                (value, end + 2)
+            }
+            None => {
+                let length = rest
+                    .find(|c: char| c.is_whitespace() || matches!(c, '{' | '}' | '"'))
+                    .unwrap_or(rest.len());
+                (rest.get(..length).unwrap_or_default().to_string(), length)
+            }
+        };
+        self.pos += length;
+        let raw = self.input.get(start..self.pos).map(str::to_string);
+        Ok(VdfString { value, raw })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    use super::*;
+
+    #[test]
+    fn round_trips_files() {
+        let files = [
+            include_str!("../testdata/vdf/testconfig.vdf"),
+            include_str!("../testdata/vdf/testconfig_expected.vdf"),
+            include_str!("../testdata/acf/appmanifest_763890.acf"),
+        ];
+        for file in files {
+            assert_eq!(file, VdfDocument::parse(file).unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn reads_nested_values() {
+        let input = include_str!("../testdata/acf/appmanifest_763890.acf");
+        let document = VdfDocument::parse(input).unwrap();
+        let app_state = document.root.get_object("AppState").unwrap();
+        assert_eq!(Some("Wildermyth"), app_state.get_str("name"));
+        assert_eq!(Some("english"), document.root.find("language").and_then(VdfValue::as_str));
+    }
+
+    #[test]
+    fn keeps_formatting_when_changing_values() {
+        let input = "// comment\n\"root\"\n{\n\t\"key\"   \"value\"\n\t\"path\"\t\t\"C:\\\\Steam\"\n}\n";
+        let mut document = VdfDocument::parse(input).unwrap();
+        let root = document.root.get_object_mut("ROOT").unwrap();
+        assert_eq!(Some("C:\\Steam"), root.get_str("path"));
+        root.set_str("key", "say \"hi\"");
+        root.get_or_insert_object("new").unwrap().set_str("a", "b");
+        assert_eq!(
+            "// comment\n\"root\"\n{\n\t\"key\"   \"say \\\"hi\\\"\"\n\t\"path\"\t\t\"C:\\\\Steam\"\n\t\"new\"\n\t{\n\t\t\"a\"\t\t\"b\"\n\t}\n}\n",
+            document.to_string()
+        );
+        let reparsed = VdfDocument::parse(&document.to_string()).unwrap();
+        assert_eq!(
+            Some("say \"hi\""),
+            reparsed.root.find("key").and_then(VdfValue::as_str)
+        );
+    }
+
+    #[test]
+    fn reports_broken_files() {
+        assert!(VdfDocument::parse("\"root\"\n{\n\t\"key\"\t\"value\"\n").is_err());
+        assert!(VdfDocument::parse("\"root\"\n{\n\t\"key\"\n}").is_err());
+        assert!(VdfDocument::parse("\"key\" \"value").is_err());
+    }
+}
@@ -44,7 +44,7 @@ impl<'a> CachedSearch<'a> {
         if let Some(result) = cached_result {
             return Ok(Some(result.1));
         }
-        println!("Searching for {}", query.as_ref());
+        log!("Searching for {}", query.as_ref());
         let search = self.client.search(query.as_ref()).await?;
         let first_id = search.first().map(|f| f.id);
         match first_id {
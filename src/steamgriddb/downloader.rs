@@ -21,7 +21,7 @@ use crate::steamgriddb::ImageType;
 use crate::sync::IsBoilRShortcut;
 use crate::sync::SyncProgress;
 
-const CONCURRENT_REQUESTS: usize = 10;
+pub(super) const CONCURRENT_REQUESTS: usize = 10;
 
 impl SearchSettings for Settings {
     fn download_animated(&self) -> bool {
@@ -49,10 +49,11 @@ pub async fn download_images_for_users(
     settings: &Settings,
     users: &[SteamUsersInfo],
     sender: &mut Option<Sender<SyncProgress>>,
-) {
+) -> Vec<ImageDownload> {
+    let mut downloads = vec![];
     let auth_key = &settings.steamgrid_db.auth_key;
     if let Some(auth_key) = auth_key {
-        println!("Checking for game images");
+        log!("Checking for game images");
         let start_time = std::time::Instant::now();
         let client = steamgriddb_api::Client::new(auth_key);
         let search = CachedSearch::new(&client);
@@ -95,21 +96,23 @@ pub async fn download_images_for_users(
                 let _ = sender.send(SyncProgress::DownloadingImages { to_download: total });
             }
             search.save();
-            stream::iter(&to_downloads)
+            let results = stream::iter(&to_downloads)
                 .map(|to_download| async move {
-                    if let Err(e) = download_to_download(to_download).await {
-                        println!("Error downloading {:?}: {}", &to_download.path, e);
+                    let result = download_to_download(to_download).await;
+                    if let Err(e) = &result {
+                        log!("Error downloading {:?}: {}", &to_download.path, e);
                         dbg!(&e);
                     }
+                    (*to_download, result.err().map(|e| e.to_string()))
                 })
                 .buffer_unordered(CONCURRENT_REQUESTS)
-                .collect::<Vec<()>>()
+                .collect::<Vec<(&ToDownload, Option<String>)>>()
                 .await;
             let duration = start_time.elapsed();
-            println!("Finished getting images in: {duration:?}");
+            log!("Finished getting images in: {duration:?}");
 
             //Validate that the downloads where ok
-            for to_download in to_downloads {
+            for (to_download, mut error) in results {
                 let file_length = std::fs::metadata(&to_download.path)
                     .map(|m| m.len())
                     .unwrap_or_default();
@@ -117,14 +120,23 @@ pub async fn download_images_for_users(
                     // Image is too small, something went wrong
                     //Try to delete file again, don't care if it fails
                     let _ = std::fs::remove_file(&to_download.path);
+                    error.get_or_insert_with(|| "Downloaded image was empty".to_string());
                 }
+                downloads.push(ImageDownload {
+                    app_name: to_download.app_name.clone(),
+                    image_type: to_download.image_type,
+                    url: to_download.url.clone(),
+                    path: to_download.path.clone(),
+                    error,
+                });
             }
         } else {
-            println!("No images needed");
+            log!("No images needed");
         }
     } else {
-        println!("Steamgrid DB Auth Key not found, please add one as described here:  https://github.com/PhilipK/steam_shortcuts_sync#configuration");
+        log!("Steamgrid DB Auth Key not found, please add one as described here:  https://github.com/PhilipK/steam_shortcuts_sync#configuration");
     }
+    downloads
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -318,7 +330,7 @@ pub fn get_query_type(
     download_animated: bool,
     image_type: &ImageType,
     allow_nsfw: bool,
-) -> steamgriddb_api::QueryType {
+) -> steamgriddb_api::QueryType<'_> {
     let anymation_type = if download_animated {
         Some(&[steamgriddb_api::query_parameters::AnimtionType::Animated][..])
     } else {
@@ -391,7 +403,7 @@ async fn get_steam_image_url(game_id: usize, image_type: &ImageType) -> Option<S
             if let (Some(Some(Some(steam_app_id))), Some(Some(Some(Some(mtime))))) =
                 (game_id, mtime)
             {
-                return Some(image_type.steam_url(steam_app_id.to_string(), mtime?));
+                return Some(image_type.steam_url(steam_app_id, mtime?));
             }
         }
     }
@@ -429,7 +441,7 @@ fn icon_url(steam_app_id: &str, icon_id: &str) -> String {
 }
 
 pub async fn download_to_download(to_download: &ToDownload) -> eyre::Result<()> {
-    println!(
+    log!(
         "Downloading {:?} for {} to {:?}",
         to_download.image_type, to_download.app_name, to_download.path
     );
@@ -442,6 +454,15 @@ pub async fn download_to_download(to_download: &ToDownload) -> eyre::Result<()>
     Ok(())
 }
 
+/// The outcome of downloading a single image
+pub struct ImageDownload {
+    pub app_name: String,
+    pub image_type: ImageType,
+    pub url: String,
+    pub path: PathBuf,
+    pub error: Option<String>,
+}
+
 pub struct ToDownload {
     pub path: PathBuf,
     pub url: String,
@@ -1,9 +1,11 @@
 mod cached_search;
 mod downloader;
 mod image_type;
+mod platform_images;
 mod settings;
 
 pub use cached_search::CachedSearch;
 pub use downloader::*;
 pub use image_type::ImageType;
+pub use platform_images::copy_platform_images;
 pub use settings::SteamGridDbSettings;
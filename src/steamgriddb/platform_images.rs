@@ -0,0 +1,264 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use futures::{stream, StreamExt};
+use steam_shortcuts_util::shortcut::ShortcutOwned;
+
+use super::{ImageDownload, ImageType, SearchSettings, CONCURRENT_REQUESTS};
+use crate::platforms::{ImageSource, PlatformImage};
+use crate::settings::Settings;
+use crate::steam::{get_shortcuts_for_user, get_users_images, SteamUsersInfo};
+
+/// An image type a shortcut is missing that its platform has
+#[derive(Debug, PartialEq, Eq)]
+struct PlatformImageToCopy {
+    app_id: u32,
+    app_name: String,
+    image_type: ImageType,
+    grid_folder: PathBuf,
+    /// The images of this type the platform has, the first one that can be read is used
+    sources: Vec<ImageSource>,
+}
+
+/// Puts the art the platforms have for their games in the grid folder of every user.
+/// This runs before SteamGridDB is searched, so only the image types no platform had are downloaded from there.
+/// Images a shortcut already has are never replaced.
+pub async fn copy_platform_images(
+    settings: &Settings,
+    users: &[SteamUsersInfo],
+    artwork: &HashMap<u32, Vec<PlatformImage>>,
+) -> Vec<ImageDownload> {
+    if !settings.steamgrid_db.use_platform_images || artwork.is_empty() {
+        return vec![];
+    }
+    let mut to_copy = vec![];
+    for user in users {
+        let Ok(shortcut_info) = get_shortcuts_for_user(user) else {
+            continue;
+        };
+        let data_folder = &user.steam_user_data_folder;
+        let known_images = get_users_images(data_folder).unwrap_or_default();
+        let grid_folder = Path::new(data_folder).join("config").join("grid");
+        to_copy.extend(images_to_copy(
+            &known_images,
+            &grid_folder,
+            &shortcut_info.shortcuts,
+            artwork,
+            settings,
+        ));
+    }
+    if to_copy.is_empty() {
+        return vec![];
+    }
+    log!("Using {} images from the platforms", to_copy.len());
+    stream::iter(to_copy)
+        .map(|to_copy| async move { copy_platform_image(to_copy).await })
+        .buffer_unordered(CONCURRENT_REQUESTS)
+        .collect()
+        .await
+}
+
+fn images_to_copy<T: SearchSettings>(
+    known_images: &[String],
+    grid_folder: &Path,
+    shortcuts: &[ShortcutOwned],
+    artwork: &HashMap<u32, Vec<PlatformImage>>,
+    search_settings: &T,
+) -> Vec<PlatformImageToCopy> {
+    let mut to_copy = vec![];
+    for shortcut in shortcuts {
+        let Some(images) = artwork.get(&shortcut.app_id) else {
+            continue;
+        };
+        for image_type in ImageType::all() {
+            let known = known_images.contains(&image_type.file_name_no_extension(shortcut.app_id));
+            if known || search_settings.is_image_banned(image_type, shortcut.app_id) {
+                continue;
+            }
+            let sources: Vec<ImageSource> = images
+                .iter()
+                .filter(|image| image.image_type == *image_type)
+                .map(|image| image.source.clone())
+                .collect();
+            if !sources.is_empty() {
+                to_copy.push(PlatformImageToCopy {
+                    app_id: shortcut.app_id,
+                    app_name: shortcut.app_name.clone(),
+                    image_type: *image_type,
+                    grid_folder: grid_folder.to_path_buf(),
+                    sources,
+                });
+            }
+        }
+    }
+    to_copy
+}
+
+async fn copy_platform_image(to_copy: PlatformImageToCopy) -> ImageDownload {
+    let mut error = None;
+    let mut path = to_copy.grid_folder.join(to_copy.image_type.file_name_no_extension(to_copy.app_id));
+    let mut url = String::new();
+    for source in &to_copy.sources {
+        url = source.to_string();
+        let result = read_source(source).await.and_then(|content| {
+            let extension = image_extension(&content, source)
+                .ok_or_else(|| eyre::eyre!("Unknown image format"))?;
+            path = to_copy
+                .grid_folder
+                .join(to_copy.image_type.file_name(to_copy.app_id, extension));
+            std::fs::write(&path, content)?;
+            Ok(())
+        });
+        match result {
+            Ok(()) => {
+                error = None;
+                break;
+            }
+            Err(err) => {
+                log!("Could not use image {source} for {}: {err}", to_copy.app_name);
+                error = Some(err.to_string());
+            }
+        }
+    }
+    ImageDownload {
+        app_name: to_copy.app_name,
+        image_type: to_copy.image_type,
+        url,
+        path,
+        error,
+    }
+}
+
+async fn read_source(source: &ImageSource) -> eyre::Result<Vec<u8>> {
+    let content = match source {
+        ImageSource::File(path) => std::fs::read(path)?,
+        ImageSource::Url(url) => reqwest::get(url)
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?
+            .to_vec(),
+    };
+    if content.len() < 2 {
+        return Err(eyre::eyre!("Image was empty"));
+    }
+    Ok(content)
+}
+
+/// The extension for an image, caches often store images without one so the content is checked first
+fn image_extension(content: &[u8], source: &ImageSource) -> Option<String> {
+    if let Some(extension) = image::guess_format(content)
+        .ok()
+        .and_then(|format| format.extensions_str().first())
+    {
+        return Some(extension.to_string());
+    }
+    let name = match source {
+        ImageSource::File(path) => path.to_string_lossy().to_string(),
+        ImageSource::Url(url) => url.split(['?', '#']).next().unwrap_or_default().to_string(),
+    };
+    Path::new(&name)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .filter(|extension| ["png", "jpg", "jpeg", "webp", "ico", "gif"].contains(&extension.as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
+
+    use steam_shortcuts_util::Shortcut;
+
+    use super::*;
+
+    struct Banned(Vec<(ImageType, u32)>);
+
+    impl SearchSettings for Banned {
+        fn download_animated(&self) -> bool {
+            false
+        }
+
+        fn download_big_picture(&self) -> bool {
+            false
+        }
+
+        fn allow_nsfw(&self) -> bool {
+            false
+        }
+
+        fn only_download_boilr_images(&self) -> bool {
+            false
+        }
+
+        fn is_image_banned(&self, image_type: &ImageType, app_id: u32) -> bool {
+            self.0.contains(&(*image_type, app_id))
+        }
+    }
+
+    #[test]
+    fn only_copies_image_types_the_shortcut_is_missing() {
+        let mut shortcut = Shortcut::new("0", "Game", "/games/game", "", "", "", "").to_owned();
+        shortcut.app_id = 42;
+        let artwork = HashMap::from([(
+            42,
+            vec![
+                PlatformImage::url(ImageType::Grid, "https://cdn/grid.jpg"),
+                PlatformImage::url(ImageType::WideGrid, "https://cdn/wide.jpg"),
+                PlatformImage::file(ImageType::Icon, "/games/game/icon.ico"),
+                PlatformImage::url(ImageType::Logo, "https://cdn/logo.png"),
+                PlatformImage::url(ImageType::Icon, "https://cdn/icon.png"),
+            ],
+        )]);
+        let known_images = vec!["42p".to_string()];
+        let banned = Banned(vec![(ImageType::Logo, 42)]);
+
+        let to_copy = images_to_copy(
+            &known_images,
+            Path::new("grid"),
+            &[shortcut],
+            &artwork,
+            &banned,
+        );
+        assert_eq!(2, to_copy.len());
+        assert_eq!(ImageType::WideGrid, to_copy[0].image_type);
+        assert_eq!(ImageType::Icon, to_copy[1].image_type);
+        assert_eq!(
+            vec![
+                ImageSource::File(PathBuf::from("/games/game/icon.ico")),
+                ImageSource::Url("https://cdn/icon.png".to_string())
+            ],
+            to_copy[1].sources
+        );
+    }
+
+    #[test]
+    fn copies_files_with_the_extension_of_their_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("5d1f0c");
+        std::fs::write(&source, b"\x89PNG\r\n\x1a\nrest").unwrap();
+        let to_copy = PlatformImageToCopy {
+            app_id: 42,
+            app_name: "Game".to_string(),
+            image_type: ImageType::Hero,
+            grid_folder: dir.path().to_path_buf(),
+            sources: vec![
+                ImageSource::File(dir.path().join("missing.png")),
+                ImageSource::File(source),
+            ],
+        };
+        let copied = futures::executor::block_on(copy_platform_image(to_copy));
+        assert_eq!(None, copied.error);
+        assert_eq!(dir.path().join("42_hero.png"), copied.path);
+        assert!(copied.path.exists());
+    }
+
+    #[test]
+    fn falls_back_to_the_extension_of_the_source() {
+        let source = ImageSource::Url("https://cdn/cover.JPG?width=300".to_string());
+        assert_eq!(Some("jpg".to_string()), image_extension(b"data", &source));
+        let unknown = ImageSource::Url("https://cdn/cover".to_string());
+        assert_eq!(None, image_extension(b"data", &unknown));
+    }
+}
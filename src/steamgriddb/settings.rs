@@ -10,6 +10,16 @@ pub struct SteamGridDbSettings {
     pub banned_images: Vec<String>,
     pub only_download_boilr_images: bool,
     pub allow_nsfw: bool,
+    /// Removes the downloaded images of games that are no longer installed
+    #[serde(default)]
+    pub remove_images_of_uninstalled_games: bool,
+    /// Uses the art the platforms have for their games, SteamGridDB is only searched for the images they do not have
+    #[serde(default = "default_use_platform_images")]
+    pub use_platform_images: bool,
+}
+
+fn default_use_platform_images() -> bool {
+    true
 }
 
 impl SteamGridDbSettings {
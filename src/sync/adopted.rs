@@ -0,0 +1,227 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use steam_shortcuts_util::shortcut::ShortcutOwned;
+use time::OffsetDateTime;
+
+use crate::{
+    config::{get_adopted_file, load_json_or_default, save_json},
+    settings::Settings,
+    steam::{get_shortcuts_for_user, get_shortcuts_paths},
+};
+
+use super::{owner_tag, save_shortcuts, CollectionGame, IsBoilRShortcut};
+
+/// The owner of shortcuts that were made outside BoilR and adopted later,
+/// they are never removed by an import and go in the collections with this platform name
+pub const ADOPTED_PLATFORM: &str = "Adopted";
+
+/// A shortcut made outside BoilR that BoilR manages now
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct AdoptedShortcut {
+    pub name: String,
+    pub exe: String,
+    /// The dev kit game id the shortcut had before it was adopted, it gets it back when it is disconnected
+    #[serde(default)]
+    pub origin: String,
+    /// The Steam users that have the shortcut
+    #[serde(default)]
+    pub users: Vec<String>,
+    /// A unix timestamp
+    pub adopted_at: i64,
+    /// The compatibility tool the shortcut runs with, `None` leaves the tool Steam has for it alone
+    #[serde(default)]
+    pub compat_tool: Option<String>,
+}
+
+/// The adopted shortcuts by their app id, saved to the config folder
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AdoptedShortcuts {
+    shortcuts: BTreeMap<u32, AdoptedShortcut>,
+}
+
+impl AdoptedShortcuts {
+    pub fn load() -> Self {
+        load_json_or_default(&get_adopted_file(), "adopted shortcuts")
+    }
+
+    pub fn save(&self) -> eyre::Result<()> {
+        save_json(&get_adopted_file(), self)
+    }
+
+    pub fn get(&self, app_id: u32) -> Option<&AdoptedShortcut> {
+        self.shortcuts.get(&app_id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&u32, &AdoptedShortcut)> {
+        self.shortcuts.iter()
+    }
+
+    pub fn insert(&mut self, app_id: u32, adopted: AdoptedShortcut) {
+        self.shortcuts.insert(app_id, adopted);
+    }
+
+    pub fn remove(&mut self, app_id: u32) -> Option<AdoptedShortcut> {
+        self.shortcuts.remove(&app_id)
+    }
+
+    /// Sets the compatibility tool of an adopted shortcut, `None` stops BoilR from setting one
+    pub fn set_compat_tool(&mut self, app_id: u32, tool: Option<String>) -> bool {
+        match self.shortcuts.get_mut(&app_id) {
+            Some(adopted) => {
+                adopted.compat_tool = tool.filter(|tool| !tool.is_empty());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The compatibility tools chosen for adopted shortcuts, they replace the tool Steam has
+    #[cfg(target_family = "unix")]
+    pub fn proton_mappings(&self) -> Vec<(crate::steam::ProtonMapping, String)> {
+        self.shortcuts
+            .iter()
+            .filter_map(|(app_id, adopted)| {
+                let tool = adopted.compat_tool.clone()?;
+                Some((
+                    crate::steam::ProtonMapping {
+                        app_id: *app_id,
+                        tool,
+                        replace: true,
+                        written_tool: None,
+                    },
+                    adopted.name.clone(),
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Marks a shortcut that BoilR did not make as adopted, returns false if it is already a BoilR shortcut
+fn adopt(shortcut: &mut ShortcutOwned) -> bool {
+    if shortcut.is_boilr_shortcut() {
+        return false;
+    }
+    shortcut.dev_kit_game_id = owner_tag(ADOPTED_PLATFORM);
+    true
+}
+
+/// Lets BoilR manage the images, collections and proton of a shortcut it did not make.
+/// This is the opposite of [`super::disconnect_shortcut`], the shortcut is changed for every Steam user that has it.
+pub fn adopt_shortcut(settings: &Settings, app_id: u32) -> Result<AdoptedShortcut, String> {
+    let mut userinfo_shortcuts = get_shortcuts_paths(&settings.steam)
+        .map_err(|e| format!("Getting shortcut paths failed: {e}"))?;
+    let mut adopted: Option<AdoptedShortcut> = None;
+    for user in userinfo_shortcuts.iter_mut() {
+        let Ok(mut shortcut_info) = get_shortcuts_for_user(user) else {
+            continue;
+        };
+        let mut changed = false;
+        for shortcut in shortcut_info.shortcuts.iter_mut() {
+            if shortcut.app_id != app_id {
+                continue;
+            }
+            let origin = shortcut.dev_kit_game_id.clone();
+            if !adopt(shortcut) {
+                continue;
+            }
+            changed = true;
+            adopted
+                .get_or_insert_with(|| AdoptedShortcut {
+                    name: shortcut.app_name.clone(),
+                    exe: shortcut.exe.clone(),
+                    origin,
+                    users: vec![],
+                    adopted_at: OffsetDateTime::now_utc().unix_timestamp(),
+                    compat_tool: None,
+                })
+                .users
+                .push(user.user_id.clone());
+        }
+        if changed {
+            save_shortcuts(&shortcut_info.shortcuts, Path::new(&shortcut_info.path))
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    let adopted = adopted.ok_or_else(|| format!("No shortcut with app id {app_id} that BoilR can adopt"))?;
+    let mut store = AdoptedShortcuts::load();
+    store.insert(app_id, adopted.clone());
+    store.save().map_err(|e| e.to_string())?;
+    Ok(adopted)
+}
+
+/// The adopted shortcuts of a user for the collection rules, keyed by their app id for tags
+pub fn adopted_collection_games(shortcuts: &[ShortcutOwned]) -> Vec<CollectionGame> {
+    shortcuts
+        .iter()
+        .filter(|shortcut| shortcut.owner() == Some(ADOPTED_PLATFORM))
+        .map(|shortcut| CollectionGame {
+            platform: ADOPTED_PLATFORM.to_string(),
+            runner: None,
+            needs_proton: false,
+            key: format!("{ADOPTED_PLATFORM}/#{}", shortcut.app_id),
+            app_id: shortcut.app_id,
+            name: shortcut.app_name.clone(),
+            library: None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
+
+    use steam_shortcuts_util::Shortcut;
+
+    use super::*;
+
+    #[test]
+    fn adopts_only_shortcuts_boilr_did_not_make() {
+        let mut manual = Shortcut::new("0", "Game", "/games/game", "", "", "", "").to_owned();
+        assert!(adopt(&mut manual));
+        assert!(manual.is_boilr_shortcut());
+        assert_eq!(Some(ADOPTED_PLATFORM), manual.owner());
+        assert!(!adopt(&mut manual));
+
+        let mut imported = manual.clone();
+        imported.dev_kit_game_id = owner_tag("Lutris");
+        assert!(!adopt(&mut imported));
+        assert_eq!(Some("Lutris"), imported.owner());
+    }
+
+    #[test]
+    fn adopted_shortcuts_go_in_collections() {
+        let mut adopted = Shortcut::new("0", "Game", "/games/game", "", "", "", "").to_owned();
+        adopted.app_id = 42;
+        adopt(&mut adopted);
+        let manual = Shortcut::new("0", "Other", "/games/other", "", "", "", "").to_owned();
+        let games = adopted_collection_games(&[adopted, manual]);
+        assert_eq!(1, games.len());
+        assert_eq!("Adopted/#42", games[0].key);
+    }
+
+    #[test]
+    #[cfg(target_family = "unix")]
+    fn maps_chosen_tools() {
+        let mut store = AdoptedShortcuts::default();
+        let adopted = AdoptedShortcut {
+            name: "Game".to_string(),
+            exe: "/games/game".to_string(),
+            origin: String::new(),
+            users: vec!["1".to_string()],
+            adopted_at: 0,
+            compat_tool: None,
+        };
+        store.insert(1, adopted.clone());
+        store.insert(2, adopted);
+        assert!(store.set_compat_tool(2, Some("GE-Proton9-20".to_string())));
+        assert!(!store.set_compat_tool(3, Some("proton_9".to_string())));
+        let mappings = store.proton_mappings();
+        assert_eq!(1, mappings.len());
+        assert_eq!(2, mappings[0].0.app_id);
+        assert!(mappings[0].0.replace);
+    }
+}
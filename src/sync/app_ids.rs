@@ -0,0 +1,390 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{get_app_ids_file, load_json_or_default, save_json},
+    platforms::ShortcutToImport,
+    settings::{save_blacklisted_games, save_rename_map, save_steam_users, Settings},
+    steam::{get_install_users, SteamInstall, SteamUserSettings, SteamUsersInfo},
+    steamgriddb::ImageType,
+};
+
+use super::{
+    customize_shortcut, CollectionTags, DuplicateDecisions, GameOverride, GameOverrides,
+    PlatformShortcuts,
+};
+
+/// The app ids BoilR gave a game.
+/// `found_app_id` is the id before renames, this is the id renames and the blacklist use.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownAppId {
+    pub found_app_id: u32,
+    pub app_id: u32,
+}
+
+/// Remembers the app ids of games, keyed by the platform and the id of the game on that platform
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct AppIdStore {
+    games: BTreeMap<String, KnownAppId>,
+}
+
+/// A game that got a new app id, because it was renamed or its path changed
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AppIdChange {
+    pub game: String,
+    pub name: String,
+    pub old: KnownAppId,
+    pub new: KnownAppId,
+}
+
+impl AppIdStore {
+    pub fn load() -> Self {
+        load_json_or_default(&get_app_ids_file(), "known app ids")
+    }
+
+    pub fn save(&self) -> eyre::Result<()> {
+        save_json(&get_app_ids_file(), self)
+    }
+
+    /// Records the app ids of the games found.
+    /// Renames and blacklist entries of games whose found app id changed are moved to the new id.
+    pub fn update(
+        &mut self,
+        shortcuts: &PlatformShortcuts,
+        renames: &mut HashMap<u32, String>,
+        blacklist: &mut Vec<u32>,
+        overrides: &HashMap<u32, GameOverride>,
+    ) -> Vec<AppIdChange> {
+        let mut changes = vec![];
+        for (platform, infos) in shortcuts {
+            for info in infos {
+                let key = game_key(platform, info);
+                let found_app_id = info.shortcut.app_id;
+                let old = self.games.get(&key).copied();
+                if let Some(old) = old.filter(|old| old.found_app_id != found_app_id) {
+                    if !renames.contains_key(&found_app_id) {
+                        if let Some(name) = renames.remove(&old.found_app_id) {
+                            renames.insert(found_app_id, name);
+                        }
+                    }
+                    if blacklist.contains(&old.found_app_id) && !blacklist.contains(&found_app_id)
+                    {
+                        blacklist.retain(|id| *id != old.found_app_id);
+                        blacklist.push(found_app_id);
+                    }
+                }
+                let mut shortcut = info.shortcut.clone();
+                customize_shortcut(&mut shortcut, renames, overrides);
+                let name = shortcut.app_name;
+                let new = KnownAppId {
+                    found_app_id,
+                    app_id: shortcut.app_id,
+                };
+                if let Some(old) = old.filter(|old| *old != new) {
+                    changes.push(AppIdChange {
+                        game: key.clone(),
+                        name,
+                        old,
+                        new,
+                    });
+                }
+                self.games.insert(key, new);
+            }
+        }
+        changes
+    }
+
+    /// Moves the app ids known under an old key of a game to its key now, returns true if any moved
+    pub fn rename_games(&mut self, renamed: &[(String, String)]) -> bool {
+        rename_game_keys(&mut self.games, renamed)
+    }
+}
+
+/// Games without an id on their platform are followed by their app id before renames,
+/// so they still keep their images when they are renamed
+pub(crate) fn game_key(platform: &str, info: &ShortcutToImport) -> String {
+    match &info.game_id {
+        Some(game_id) => format!("{platform}/{game_id}"),
+        None => format!("{platform}/#{}", info.shortcut.app_id),
+    }
+}
+
+/// The keys games had in older versions of BoilR paired with their key now.
+/// Itch games were keyed by their app id, before BoilR knew their id on itch.io.
+pub(crate) fn legacy_game_keys(shortcuts: &PlatformShortcuts) -> Vec<(String, String)> {
+    shortcuts
+        .iter()
+        .flat_map(|(platform, infos)| {
+            infos
+                .iter()
+                .filter(|info| info.game_id.is_some())
+                .filter_map(move |info| {
+                    let legacy_app_id = info.legacy_app_id?;
+                    Some((
+                        format!("{platform}/#{legacy_app_id}"),
+                        game_key(platform, info),
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// Moves the entries of the settings kept under an old key of a game to its key now, returns true if any moved.
+/// An entry already kept under the key now wins.
+pub(crate) fn rename_game_keys<T>(
+    games: &mut BTreeMap<String, T>,
+    renamed: &[(String, String)],
+) -> bool {
+    let mut moved = false;
+    for (old, new) in renamed {
+        if let Some(value) = games.remove(old) {
+            games.entry(new.clone()).or_insert(value);
+            moved = true;
+        }
+    }
+    moved
+}
+
+/// Moves the known app ids, overrides, collection tags and duplicate decisions of games
+/// kept under their key from older versions of BoilR, the app id change is followed afterwards
+pub fn migrate_legacy_game_keys(shortcuts: &PlatformShortcuts) {
+    let renamed = legacy_game_keys(shortcuts);
+    if renamed.is_empty() {
+        return;
+    }
+    let mut store = AppIdStore::load();
+    if store.rename_games(&renamed) {
+        if let Err(err) = store.save() {
+            eprintln!("Could not save the known app ids: {err}");
+        }
+    }
+    let mut overrides = GameOverrides::load();
+    if overrides.rename_games(&renamed) {
+        if let Err(err) = overrides.save() {
+            eprintln!("Could not save the game overrides: {err}");
+        }
+    }
+    let mut tags = CollectionTags::load();
+    if tags.rename_games(&renamed) {
+        if let Err(err) = tags.save() {
+            eprintln!("Could not save the collection tags: {err}");
+        }
+    }
+    let mut decisions = DuplicateDecisions::load();
+    if decisions.rename_games(&renamed) {
+        if let Err(err) = decisions.save() {
+            eprintln!("Could not save the duplicate decisions: {err}");
+        }
+    }
+}
+
+/// Finds games whose app id changed since the last import and moves their renames, blacklist entries,
+/// images and proton settings to the new app id. The changes are saved, and also applied to `settings` and `renames`.
+pub fn follow_app_id_changes(
+    settings: &mut Settings,
+    install: &SteamInstall,
+    renames: &mut HashMap<u32, String>,
+    shortcuts: &PlatformShortcuts,
+    overrides: &HashMap<u32, GameOverride>,
+) -> Vec<AppIdChange> {
+    let mut store = AppIdStore::load();
+    let renames_before = renames.clone();
+    let blacklist_before = settings.blacklisted_games.clone();
+    let changes = store.update(shortcuts, renames, &mut settings.blacklisted_games, overrides);
+    for change in &changes {
+        log!(
+            "App id of {} changed from {} to {}",
+            change.name, change.old.app_id, change.new.app_id
+        );
+    }
+    if *renames != renames_before {
+        if let Err(err) = save_rename_map(renames) {
+            eprintln!("Could not save the renames: {err}");
+        }
+    }
+    if settings.blacklisted_games != blacklist_before {
+        if let Err(err) = save_blacklisted_games(&settings.blacklisted_games) {
+            eprintln!("Could not save the blacklist: {err}");
+        }
+    }
+    if move_user_blacklists(&mut settings.steam.users, &changes) {
+        if let Err(err) = save_steam_users(&settings.steam.users) {
+            eprintln!("Could not save the blacklists of the Steam users: {err}");
+        }
+    }
+    if let Err(err) = migrate_app_ids(install, &changes) {
+        eprintln!("Could not move images and proton settings to the new app ids: {err}");
+    }
+    if let Err(err) = store.save() {
+        eprintln!("Could not save the known app ids: {err}");
+    }
+    changes
+}
+
+/// Moves the entries of the per user blacklists to the new found app ids, returns true if any moved
+fn move_user_blacklists(
+    users: &mut BTreeMap<String, SteamUserSettings>,
+    changes: &[AppIdChange],
+) -> bool {
+    let mut moved = false;
+    for change in changes {
+        let (old, new) = (change.old.found_app_id, change.new.found_app_id);
+        if old == new {
+            continue;
+        }
+        for user in users.values_mut() {
+            if user.blacklisted_games.contains(&old) && !user.blacklisted_games.contains(&new) {
+                user.blacklisted_games.retain(|id| *id != old);
+                user.blacklisted_games.push(new);
+                moved = true;
+            }
+        }
+    }
+    moved
+}
+
+/// Moves the images and proton settings of games whose app id changed
+fn migrate_app_ids(install: &SteamInstall, changes: &[AppIdChange]) -> eyre::Result<()> {
+    let moved: Vec<(u32, u32)> = changes
+        .iter()
+        .filter(|change| change.old.app_id != change.new.app_id)
+        .map(|change| (change.old.app_id, change.new.app_id))
+        .collect();
+    if moved.is_empty() {
+        return Ok(());
+    }
+    for user in get_install_users(install)? {
+        move_grid_images(&user, &moved);
+    }
+    #[cfg(target_family = "unix")]
+    {
+        crate::steam::move_compat_tool_mappings(install, &moved, false)?;
+        let mut written_tools = super::WrittenProtonTools::load();
+        written_tools.move_app_ids(&moved);
+        written_tools.save()?;
+    }
+    Ok(())
+}
+
+pub(super) fn move_grid_images(user: &SteamUsersInfo, moved: &[(u32, u32)]) {
+    let grid_folder = Path::new(&user.steam_user_data_folder)
+        .join("config")
+        .join("grid");
+    let Ok(files) = std::fs::read_dir(&grid_folder) else {
+        return;
+    };
+    for file in files.flatten() {
+        let path = file.path();
+        let (Some(stem), Some(extension)) = (path.file_stem(), path.extension()) else {
+            continue;
+        };
+        let stem = stem.to_string_lossy();
+        for (old_app_id, new_app_id) in moved {
+            let image_type = ImageType::all()
+                .iter()
+                .find(|image_type| image_type.file_name_no_extension(*old_app_id) == stem);
+            if let Some(image_type) = image_type {
+                let target =
+                    grid_folder.join(image_type.file_name(*new_app_id, extension.to_string_lossy()));
+                if !target.exists() {
+                    if let Err(err) = std::fs::rename(&path, &target) {
+                        eprintln!("Could not move image {path:?} to {target:?}: {err}");
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to index in tests
+    #![allow(clippy::indexing_slicing)]
+    use steam_shortcuts_util::Shortcut;
+
+    use crate::sync::calculate_app_id;
+
+    use super::*;
+
+    fn shortcuts(name: &str, exe: &str) -> PlatformShortcuts {
+        vec![(
+            "Heroic".to_string(),
+            vec![ShortcutToImport {
+                shortcut: Shortcut::new("0", name, exe, "", "", "", "").to_owned(),
+                needs_proton: false,
+                needs_symlinks: false,
+                game_id: Some("fortnite".to_string()),
+                runner: None,
+                artwork: vec![],
+                legacy_app_id: None,
+            }],
+        )]
+    }
+
+    #[test]
+    fn follows_games_when_their_path_changes() {
+        let mut store = AppIdStore::default();
+        let mut renames = HashMap::new();
+        let mut blacklist = vec![];
+        let first = shortcuts("Game", "/old/game");
+        let old_id = calculate_app_id("Game", "/old/game");
+        assert!(store.update(&first, &mut renames, &mut blacklist, &HashMap::new()).is_empty());
+
+        renames.insert(old_id, "Renamed".to_string());
+        blacklist.push(old_id);
+        let changes = store.update(&first, &mut renames, &mut blacklist, &HashMap::new());
+        assert_eq!(1, changes.len());
+        assert_eq!(calculate_app_id("Renamed", "/old/game"), changes[0].new.app_id);
+
+        let moved = shortcuts("Game", "/new/game");
+        let new_id = calculate_app_id("Game", "/new/game");
+        let changes = store.update(&moved, &mut renames, &mut blacklist, &HashMap::new());
+        assert_eq!(1, changes.len());
+        assert_eq!("Renamed", changes[0].name);
+        assert_eq!(new_id, changes[0].new.found_app_id);
+        assert_eq!(calculate_app_id("Renamed", "/new/game"), changes[0].new.app_id);
+        assert_eq!(Some(&"Renamed".to_string()), renames.get(&new_id));
+        assert!(!renames.contains_key(&old_id));
+        assert_eq!(vec![new_id], blacklist);
+    }
+
+    #[test]
+    fn follows_itch_games_keyed_by_their_old_app_id() {
+        let old_id = calculate_app_id("Game", "/games/game/old.exe");
+        let mut store = AppIdStore::default();
+        let mut renames = HashMap::new();
+        let mut blacklist = vec![old_id];
+        let mut old = shortcuts("Game", "/games/game/old.exe");
+        old[0].0 = "Itch".to_string();
+        old[0].1[0].game_id = None;
+        store.update(&old, &mut renames, &mut blacklist, &HashMap::new());
+
+        let mut found = shortcuts("Game", "/games/game/game.exe");
+        found[0].0 = "Itch".to_string();
+        found[0].1[0].game_id = Some("1234".to_string());
+        found[0].1[0].legacy_app_id = Some(old_id);
+        let renamed = legacy_game_keys(&found);
+        assert_eq!(
+            vec![(format!("Itch/#{old_id}"), "Itch/1234".to_string())],
+            renamed
+        );
+
+        let mut tags = BTreeMap::new();
+        tags.insert(format!("Itch/#{old_id}"), vec!["Old".to_string()]);
+        tags.insert("Itch/5678".to_string(), vec!["Other".to_string()]);
+        assert!(rename_game_keys(&mut tags, &renamed));
+        assert_eq!(Some(&vec!["Old".to_string()]), tags.get("Itch/1234"));
+        assert!(!tags.contains_key(&format!("Itch/#{old_id}")));
+        assert!(!rename_game_keys(&mut tags, &renamed));
+
+        assert!(store.rename_games(&renamed));
+        let changes = store.update(&found, &mut renames, &mut blacklist, &HashMap::new());
+        assert_eq!(1, changes.len());
+        assert_eq!(old_id, changes[0].old.app_id);
+        let new_id = calculate_app_id("Game", "/games/game/game.exe");
+        assert_eq!(new_id, changes[0].new.app_id);
+        assert_eq!(vec![new_id], blacklist);
+    }
+}
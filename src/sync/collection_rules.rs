@@ -0,0 +1,571 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use steam_shortcuts_util::shortcut::ShortcutOwned;
+
+use crate::{
+    config::{get_collection_tags_file, load_json_or_default, save_json},
+    settings::Settings,
+    steam::{get_installed_games, Collection, SteamGameInfo},
+};
+
+use super::{
+    customize_shortcut, game_key, launch_rules::default_enabled, platform_code_names,
+    rename_game_keys, GameOverride, PlatformShortcuts,
+};
+
+/// Puts every game that matches all the conditions of the rule in a Steam collection.
+/// `name` is a template, `{platform}`, `{runner}` and `{tag}` are replaced with the values of each game,
+/// so "{platform}" makes a collection per platform and "Lutris {runner}" one per Lutris runner.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CollectionRule {
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// The name or code name of the platform, e.g. "Heroic" or "egs"
+    pub platform: Option<String>,
+    /// The runner of the game, like "wine" on Lutris or "gog" on Heroic
+    pub runner: Option<String>,
+    pub needs_proton: Option<bool>,
+    /// Matched against the name the game gets in Steam
+    pub name_regex: Option<String>,
+    /// A tag given to the game on the import page
+    pub tag: Option<String>,
+    /// Only Steam games installed in this library folder, e.g. the mount point of an SD card
+    #[serde(default)]
+    pub library: Option<String>,
+    /// Adds the games to a collection made in Steam instead of a collection BoilR owns,
+    /// BoilR never removes that collection or the games already in it
+    #[serde(default)]
+    pub existing: bool,
+}
+
+impl Default for CollectionRule {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            enabled: true,
+            platform: None,
+            runner: None,
+            needs_proton: None,
+            name_regex: None,
+            tag: None,
+            library: None,
+            existing: false,
+        }
+    }
+}
+
+impl CollectionRule {
+    /// The rule used when none are configured, one collection per platform
+    pub fn per_platform() -> Self {
+        Self {
+            name: "{platform}".to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// What the collection rules know about an imported game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollectionGame {
+    pub platform: String,
+    pub runner: Option<String>,
+    pub needs_proton: bool,
+    /// The key of the game for its tags, see [`game_key`]
+    pub key: String,
+    /// The app id and name the game has in Steam
+    pub app_id: u32,
+    pub name: String,
+    /// The Steam library of an installed Steam game
+    pub library: Option<PathBuf>,
+}
+
+/// The platform name installed Steam games have in the collection rules
+pub const STEAM_GAMES_PLATFORM: &str = "Steam";
+
+/// The games of the platforms as they are written to Steam, after renames and overrides
+pub fn collection_games(
+    settings: &Settings,
+    shortcuts: &PlatformShortcuts,
+    renames: &HashMap<u32, String>,
+    overrides: &HashMap<u32, GameOverride>,
+) -> Vec<CollectionGame> {
+    shortcuts
+        .iter()
+        .flat_map(|(platform, infos)| {
+            infos
+                .iter()
+                .filter(|info| !settings.blacklisted_games.contains(&info.shortcut.app_id))
+                .map(move |info| {
+                    let mut shortcut = info.shortcut.clone();
+                    customize_shortcut(&mut shortcut, renames, overrides);
+                    CollectionGame {
+                        platform: platform.clone(),
+                        runner: info.runner.clone(),
+                        needs_proton: info.needs_proton,
+                        key: game_key(platform, info),
+                        app_id: shortcut.app_id,
+                        name: shortcut.app_name,
+                        library: None,
+                    }
+                })
+        })
+        .collect()
+}
+
+/// The shortcuts kept because their platform failed, only their platform and name are known
+pub fn kept_collection_games(kept: &[(String, Vec<ShortcutOwned>)]) -> Vec<CollectionGame> {
+    kept.iter()
+        .flat_map(|(platform, shortcuts)| {
+            shortcuts.iter().map(move |shortcut| CollectionGame {
+                platform: platform.clone(),
+                runner: None,
+                needs_proton: false,
+                key: format!("{platform}/#{}", shortcut.app_id),
+                app_id: shortcut.app_id,
+                name: shortcut.app_name.clone(),
+                library: None,
+            })
+        })
+        .collect()
+}
+
+/// The installed Steam games, if they should be in the collections
+pub fn installed_collection_games(settings: &Settings) -> Vec<CollectionGame> {
+    if !settings.steam.collect_installed_games {
+        return vec![];
+    }
+    steam_collection_games(&get_installed_games(&settings.steam))
+}
+
+/// The games Steam installed, with the platform [`STEAM_GAMES_PLATFORM`] and keyed by their app id for tags
+pub fn steam_collection_games(games: &[SteamGameInfo]) -> Vec<CollectionGame> {
+    games
+        .iter()
+        .map(|game| CollectionGame {
+            platform: STEAM_GAMES_PLATFORM.to_string(),
+            runner: None,
+            needs_proton: game.needs_proton,
+            key: format!("{STEAM_GAMES_PLATFORM}/{}", game.appid),
+            app_id: game.appid,
+            name: game.name.clone(),
+            library: Some(game.library.clone()),
+        })
+        .collect()
+}
+
+/// A collection rule with its regex compiled
+struct CompiledRule {
+    rule: CollectionRule,
+    name_regex: Option<Regex>,
+}
+
+impl CompiledRule {
+    fn new(rule: CollectionRule) -> eyre::Result<Self> {
+        let name_regex = match rule.name_regex.as_deref().filter(|regex| !regex.is_empty()) {
+            Some(regex) => Some(Regex::new(regex)?),
+            None => None,
+        };
+        Ok(Self { rule, name_regex })
+    }
+
+    fn matches(&self, game: &CollectionGame, code_name: &str, tags: &[String]) -> bool {
+        let platform_matches = match non_empty(&self.rule.platform) {
+            Some(wanted) => {
+                wanted.eq_ignore_ascii_case(&game.platform) || wanted.eq_ignore_ascii_case(code_name)
+            }
+            None => true,
+        };
+        let runner_matches = match non_empty(&self.rule.runner) {
+            Some(wanted) => game
+                .runner
+                .as_ref()
+                .is_some_and(|runner| wanted.eq_ignore_ascii_case(runner)),
+            None => true,
+        };
+        let proton_matches = self
+            .rule
+            .needs_proton
+            .is_none_or(|needs_proton| needs_proton == game.needs_proton);
+        let name_matches = self
+            .name_regex
+            .as_ref()
+            .is_none_or(|regex| regex.is_match(&game.name));
+        let tag_matches = match non_empty(&self.rule.tag) {
+            Some(wanted) => tags.iter().any(|tag| wanted.eq_ignore_ascii_case(tag)),
+            None => true,
+        };
+        let library_matches = match non_empty(&self.rule.library) {
+            Some(wanted) => game
+                .library
+                .as_ref()
+                .is_some_and(|library| library.starts_with(Path::new(wanted.trim()))),
+            None => true,
+        };
+        platform_matches
+            && runner_matches
+            && proton_matches
+            && name_matches
+            && tag_matches
+            && library_matches
+    }
+
+    /// The names of the collections the game goes in, a game without a runner or tag gets none
+    /// if the name needs them
+    fn collection_names(&self, game: &CollectionGame, tags: &[String]) -> Vec<String> {
+        let name = self.rule.name.replace("{platform}", &game.platform);
+        let name = if name.contains("{runner}") {
+            match &game.runner {
+                Some(runner) => name.replace("{runner}", runner),
+                None => return vec![],
+            }
+        } else {
+            name
+        };
+        let names = if name.contains("{tag}") {
+            match non_empty(&self.rule.tag) {
+                Some(tag) => vec![name.replace("{tag}", tag)],
+                None => tags.iter().map(|tag| name.replace("{tag}", tag)).collect(),
+            }
+        } else {
+            vec![name]
+        };
+        names
+            .into_iter()
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().filter(|value| !value.trim().is_empty())
+}
+
+/// The enabled collection rules, with the tags of the games and the code names of the platforms
+pub struct CollectionRules {
+    rules: Vec<CompiledRule>,
+    tags: CollectionTags,
+    code_names: HashMap<String, String>,
+}
+
+impl CollectionRules {
+    pub fn new(
+        rules: &[CollectionRule],
+        tags: CollectionTags,
+        code_names: HashMap<String, String>,
+    ) -> Self {
+        let per_platform = [CollectionRule::per_platform()];
+        let rules = if rules.is_empty() { &per_platform } else { rules };
+        let rules = rules
+            .iter()
+            .filter(|rule| rule.enabled)
+            .filter_map(|rule| match CompiledRule::new(rule.clone()) {
+                Ok(compiled) => Some(compiled),
+                Err(err) => {
+                    eprintln!("Skipping collection rule {}: {err}", rule.name);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            rules,
+            tags,
+            code_names,
+        }
+    }
+
+    /// The rules of the settings, with the saved tags
+    pub fn from_settings(settings: &Settings) -> Self {
+        Self::new(
+            &settings.collection_rules,
+            CollectionTags::load(),
+            platform_code_names(),
+        )
+    }
+
+    /// The collections the games go in, in the order of the rules.
+    /// A game can be in several collections, rules with the same collection name add to the same collection.
+    pub fn collections(&self, games: &[CollectionGame]) -> Vec<Collection> {
+        let mut collections: Vec<Collection> = vec![];
+        for rule in &self.rules {
+            for game in games {
+                let code_name = self.code_names.get(&game.platform).map_or("", String::as_str);
+                let tags = self.tags.get(&game.key);
+                if !rule.matches(game, code_name, tags) {
+                    continue;
+                }
+                for name in rule.collection_names(game, tags) {
+                    let game_id = game.app_id as usize;
+                    match collections.iter_mut().find(|c| c.name == name) {
+                        Some(collection) => {
+                            if !collection.game_ids.contains(&game_id) {
+                                collection.game_ids.push(game_id);
+                            }
+                        }
+                        None => collections.push(Collection {
+                            name,
+                            game_ids: vec![game_id],
+                            existing: rule.rule.existing,
+                        }),
+                    }
+                }
+            }
+        }
+        collections
+    }
+
+    /// The collections with the names of their games
+    pub fn preview(&self, games: &[CollectionGame]) -> Vec<CollectionPreview> {
+        self.collections(games)
+            .into_iter()
+            .map(|collection| CollectionPreview {
+                games: games
+                    .iter()
+                    .filter(|game| collection.game_ids.contains(&(game.app_id as usize)))
+                    .map(|game| CollectionPreviewGame {
+                        platform: game.platform.clone(),
+                        name: game.name.clone(),
+                    })
+                    .collect(),
+                name: collection.name,
+                existing: collection.existing,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionPreview {
+    pub name: String,
+    pub existing: bool,
+    pub games: Vec<CollectionPreviewGame>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionPreviewGame {
+    pub platform: String,
+    pub name: String,
+}
+
+/// The tags the user gave games on the import page, keyed like the overrides of a game
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct CollectionTags {
+    games: BTreeMap<String, Vec<String>>,
+}
+
+impl CollectionTags {
+    pub fn load() -> Self {
+        load_json_or_default(&get_collection_tags_file(), "collection tags")
+    }
+
+    pub fn save(&self) -> eyre::Result<()> {
+        save_json(&get_collection_tags_file(), self)
+    }
+
+    pub fn get(&self, game: &str) -> &[String] {
+        self.games.get(game).map_or(&[], Vec::as_slice)
+    }
+
+    /// Sets the tags of a game, empty tags are dropped and a game without tags is removed
+    pub fn set(&mut self, game: &str, tags: Vec<String>) {
+        let mut tags: Vec<String> = tags
+            .into_iter()
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        tags.dedup();
+        if tags.is_empty() {
+            self.games.remove(game);
+        } else {
+            self.games.insert(game.to_string(), tags);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Vec<String>)> {
+        self.games.iter()
+    }
+
+    /// Moves the tags kept under an old key of a game to its key now, returns true if any moved
+    pub fn rename_games(&mut self, renamed: &[(String, String)]) -> bool {
+        rename_game_keys(&mut self.games, renamed)
+    }
+
+    /// All tags in use, sorted and without duplicates
+    pub fn all_tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = self.games.values().flatten().cloned().collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game(platform: &str, name: &str, runner: Option<&str>, needs_proton: bool) -> CollectionGame {
+        CollectionGame {
+            platform: platform.to_string(),
+            runner: runner.map(str::to_string),
+            needs_proton,
+            key: format!("{platform}/{name}"),
+            app_id: super::super::calculate_app_id(name, platform),
+            name: name.to_string(),
+            library: None,
+        }
+    }
+
+    fn games() -> Vec<CollectionGame> {
+        vec![
+            game("Lutris", "Wine game", Some("wine"), false),
+            game("Lutris", "Emulated game", Some("snes9x"), false),
+            game("Heroic", "Epic game", Some("legendary"), true),
+            game("Itch", "Itch game long name", None, true),
+        ]
+    }
+
+    fn names(collections: &[Collection]) -> Vec<(&str, usize)> {
+        collections
+            .iter()
+            .map(|c| (c.name.as_str(), c.game_ids.len()))
+            .collect()
+    }
+
+    #[test]
+    fn without_rules_there_is_a_collection_per_platform() {
+        let rules = CollectionRules::new(&[], CollectionTags::default(), HashMap::new());
+        let collections = rules.collections(&games());
+        assert_eq!(
+            vec![("Lutris", 2), ("Heroic", 1), ("Itch", 1)],
+            names(&collections)
+        );
+        assert!(collections.iter().all(|c| !c.existing));
+    }
+
+    #[test]
+    fn fills_in_runner_and_skips_games_without_one() {
+        let rules = [CollectionRule {
+            name: "{platform} - {runner}".to_string(),
+            ..Default::default()
+        }];
+        let rules = CollectionRules::new(&rules, CollectionTags::default(), HashMap::new());
+        assert_eq!(
+            vec![
+                ("Lutris - wine", 1),
+                ("Lutris - snes9x", 1),
+                ("Heroic - legendary", 1)
+            ],
+            names(&rules.collections(&games()))
+        );
+    }
+
+    #[test]
+    fn matches_all_conditions() {
+        let code_names = HashMap::from([("Heroic".to_string(), "heroic".to_string())]);
+        let rules = [
+            CollectionRule {
+                name: "Proton".to_string(),
+                needs_proton: Some(true),
+                ..Default::default()
+            },
+            CollectionRule {
+                name: "Epic".to_string(),
+                platform: Some("heroic".to_string()),
+                runner: Some("Legendary".to_string()),
+                ..Default::default()
+            },
+            CollectionRule {
+                name: "Games".to_string(),
+                name_regex: Some(" game$".to_string()),
+                existing: true,
+                ..Default::default()
+            },
+            CollectionRule {
+                name: "Disabled".to_string(),
+                enabled: false,
+                ..Default::default()
+            },
+        ];
+        let rules = CollectionRules::new(&rules, CollectionTags::default(), code_names);
+        let collections = rules.collections(&games());
+        assert_eq!(
+            vec![("Proton", 2), ("Epic", 1), ("Games", 3)],
+            names(&collections)
+        );
+        assert_eq!(
+            vec![false, false, true],
+            collections.iter().map(|c| c.existing).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn uses_tags_of_games() {
+        let mut tags = CollectionTags::default();
+        tags.set("Lutris/Wine game", vec!["Co-op ".to_string(), "Retro".to_string()]);
+        tags.set("Itch/Itch game long name", vec!["Co-op".to_string()]);
+        tags.set("Heroic/Epic game", vec![" ".to_string()]);
+        assert!(tags.get("Heroic/Epic game").is_empty());
+        let rules = [
+            CollectionRule {
+                name: "{tag}".to_string(),
+                ..Default::default()
+            },
+            CollectionRule {
+                name: "Favorites".to_string(),
+                tag: Some("retro".to_string()),
+                existing: true,
+                ..Default::default()
+            },
+        ];
+        let rules = CollectionRules::new(&rules, tags, HashMap::new());
+        let collections = rules.collections(&games());
+        assert_eq!(
+            vec![("Co-op", 2), ("Retro", 1), ("Favorites", 1)],
+            names(&collections)
+        );
+    }
+
+    #[test]
+    fn matches_steam_games_by_library() {
+        let steam_game = |appid: u32, library: &str, needs_proton: bool| SteamGameInfo {
+            appid,
+            name: format!("Steam game {appid}"),
+            library: PathBuf::from(library),
+            needs_proton,
+            ..Default::default()
+        };
+        let mut all_games = games();
+        all_games.extend(steam_collection_games(&[
+            steam_game(10, "/run/media/mmcblk0p1", true),
+            steam_game(20, "/home/deck/.local/share/Steam", false),
+        ]));
+        let rules = [
+            CollectionRule {
+                name: "Installed on SD card".to_string(),
+                library: Some("/run/media/mmcblk0p1".to_string()),
+                ..Default::default()
+            },
+            CollectionRule {
+                name: "All Proton games".to_string(),
+                needs_proton: Some(true),
+                ..Default::default()
+            },
+            CollectionRule {
+                name: "{platform}".to_string(),
+                platform: Some(STEAM_GAMES_PLATFORM.to_string()),
+                ..Default::default()
+            },
+        ];
+        let rules = CollectionRules::new(&rules, CollectionTags::default(), HashMap::new());
+        let collections = rules.collections(&all_games);
+        assert_eq!(
+            vec![("Installed on SD card", 1), ("All Proton games", 3), ("Steam", 2)],
+            names(&collections)
+        );
+        assert_eq!(Some(&vec![10]), collections.first().map(|c| &c.game_ids));
+    }
+}
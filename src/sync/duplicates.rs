@@ -0,0 +1,396 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use steam_shortcuts_util::shortcut::ShortcutOwned;
+
+use crate::{
+    config::{get_duplicates_file, load_json_or_default, save_json},
+    settings::Settings,
+    steam::{get_import_users, get_shortcuts_for_user, SteamInstall},
+};
+
+use super::{
+    customize_shortcut, game_key, owner_tag, platform_code_names, rename_game_keys,
+    resolve_overrides, shortcuts_for_user, GameOverride, IsBoilRShortcut, PlatformShortcuts,
+};
+
+/// What to do with a shortcut made outside BoilR for a game BoilR imports
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateDecision {
+    /// BoilR takes over the shortcut, it keeps its images and the fields BoilR does not own
+    Adopt,
+    /// The game is not imported for users that have their own shortcut for it
+    Skip,
+    KeepBoth,
+}
+
+impl DuplicateDecision {
+    pub const ALL: [DuplicateDecision; 3] = [
+        DuplicateDecision::Adopt,
+        DuplicateDecision::Skip,
+        DuplicateDecision::KeepBoth,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DuplicateDecision::Adopt => "adopt",
+            DuplicateDecision::Skip => "skip",
+            DuplicateDecision::KeepBoth => "keep-both",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|decision| decision.name() == name)
+    }
+}
+
+/// A shortcut of a Steam user that was not made by BoilR, for a game BoilR imports
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateShortcut {
+    pub user_id: String,
+    /// The key of the imported game, see [`game_key`]
+    pub game: String,
+    pub platform: String,
+    /// The app id and name the imported game gets in Steam
+    pub app_id: u32,
+    pub name: String,
+    pub shortcut_app_id: u32,
+    pub shortcut_name: String,
+    pub shortcut_exe: String,
+    pub same_name: bool,
+    /// The shortcut starts the same executable with the same launch options
+    pub same_target: bool,
+}
+
+impl std::fmt::Display for DuplicateShortcut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let matches = match (self.same_name, self.same_target) {
+            (true, true) => "same name and target",
+            (true, false) => "same name",
+            _ => "same target",
+        };
+        write!(
+            f,
+            "{} ({}) looks like {} from {} ({matches})",
+            self.shortcut_name, self.shortcut_app_id, self.name, self.platform
+        )
+    }
+}
+
+/// A game found by a platform, as it is written to Steam
+#[derive(Debug, Clone)]
+pub struct ImportedGame {
+    pub platform: String,
+    pub key: String,
+    pub shortcut: ShortcutOwned,
+}
+
+/// The games of the platforms after renames and overrides, without blacklisted games
+pub fn imported_games(
+    settings: &Settings,
+    shortcuts: &PlatformShortcuts,
+    renames: &HashMap<u32, String>,
+    overrides: &HashMap<u32, GameOverride>,
+) -> Vec<ImportedGame> {
+    shortcuts
+        .iter()
+        .flat_map(|(platform, infos)| {
+            infos
+                .iter()
+                .filter(|info| !settings.blacklisted_games.contains(&info.shortcut.app_id))
+                .map(move |info| {
+                    let mut shortcut = info.shortcut.clone();
+                    customize_shortcut(&mut shortcut, renames, overrides);
+                    ImportedGame {
+                        platform: platform.clone(),
+                        key: game_key(platform, info),
+                        shortcut,
+                    }
+                })
+        })
+        .collect()
+}
+
+/// The shortcuts of a user not made by BoilR that have the name or target of an imported game.
+/// Shortcuts with the same app id are not duplicates, the import replaces them.
+pub fn find_duplicates(
+    user_id: &str,
+    shortcuts: &[ShortcutOwned],
+    games: &[ImportedGame],
+) -> Vec<DuplicateShortcut> {
+    let mut duplicates = vec![];
+    for shortcut in shortcuts.iter().filter(|s| !s.is_boilr_shortcut()) {
+        let name = normalize_name(&shortcut.app_name);
+        let target = normalize_target(&shortcut.exe, &shortcut.launch_options);
+        for game in games.iter().filter(|g| g.shortcut.app_id != shortcut.app_id) {
+            let same_name = !name.is_empty() && name == normalize_name(&game.shortcut.app_name);
+            let same_target = target.is_some()
+                && target == normalize_target(&game.shortcut.exe, &game.shortcut.launch_options);
+            if same_name || same_target {
+                duplicates.push(DuplicateShortcut {
+                    user_id: user_id.to_string(),
+                    game: game.key.clone(),
+                    platform: game.platform.clone(),
+                    app_id: game.shortcut.app_id,
+                    name: game.shortcut.app_name.clone(),
+                    shortcut_app_id: shortcut.app_id,
+                    shortcut_name: shortcut.app_name.clone(),
+                    shortcut_exe: shortcut.exe.clone(),
+                    same_name,
+                    same_target,
+                });
+            }
+        }
+    }
+    duplicates
+}
+
+/// The duplicates of all Steam users that get imports
+pub fn find_all_duplicates(
+    settings: &Settings,
+    shortcuts: &PlatformShortcuts,
+    renames: &HashMap<u32, String>,
+) -> eyre::Result<Vec<DuplicateShortcut>> {
+    let overrides = resolve_overrides(settings, shortcuts);
+    let code_names = platform_code_names();
+    let mut duplicates = vec![];
+    let install = SteamInstall::find(&settings.steam)?;
+    for user in get_import_users(&settings.steam, &install)? {
+        let Ok(shortcut_info) = get_shortcuts_for_user(&user) else {
+            continue;
+        };
+        let user_settings = settings.steam.user_settings(&user.user_id);
+        let user_shortcuts = shortcuts_for_user(shortcuts, &user_settings, &code_names);
+        let games = imported_games(settings, &user_shortcuts, renames, &overrides);
+        duplicates.extend(find_duplicates(&user.user_id, &shortcut_info.shortcuts, &games));
+    }
+    Ok(duplicates)
+}
+
+/// Lowercase letters and digits only, so "DOOM: Eternal™" and "Doom Eternal" are the same
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The target of a shortcut without an executable is `None`, it is never the same as another target
+fn normalize_target(exe: &str, launch_options: &str) -> Option<String> {
+    let exe = exe.trim().trim_matches('"').replace('\\', "/").to_lowercase();
+    if exe.is_empty() {
+        return None;
+    }
+    let launch_options: Vec<&str> = launch_options.split_whitespace().collect();
+    Some(format!("{exe} {}", launch_options.join(" ")))
+}
+
+/// What the decisions changed for the shortcuts of a user
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DuplicateResolution {
+    /// The app ids of the adopted shortcuts and the app ids of the games that took them over
+    pub adopted: Vec<(u32, u32)>,
+    /// The app ids of the games that are not imported
+    pub skipped: Vec<u32>,
+}
+
+/// Applies the decisions to the shortcuts of a user before they are merged.
+/// Skipped games are taken out of the shortcuts to write. Adopted shortcuts get the app id and owner of their game,
+/// so the import updates them like any other BoilR shortcut. If the game already has a BoilR shortcut,
+/// the adopted shortcut is replaced by it.
+pub fn resolve_duplicates(
+    shortcuts: &mut Vec<ShortcutOwned>,
+    to_write: &mut Vec<ShortcutOwned>,
+    duplicates: &[DuplicateShortcut],
+    decisions: &DuplicateDecisions,
+) -> DuplicateResolution {
+    let mut resolution = DuplicateResolution::default();
+    for duplicate in duplicates {
+        match decisions.get(&duplicate.game) {
+            Some(DuplicateDecision::Skip) => {
+                to_write.retain(|shortcut| shortcut.app_id != duplicate.app_id);
+                resolution.skipped.push(duplicate.app_id);
+            }
+            Some(DuplicateDecision::Adopt) => {
+                let is_manual = |s: &ShortcutOwned| {
+                    s.app_id == duplicate.shortcut_app_id && !s.is_boilr_shortcut()
+                };
+                if !shortcuts.iter().any(is_manual) {
+                    continue;
+                }
+                log!(
+                    "Adopting the shortcut {} ({}) for {}",
+                    duplicate.shortcut_name, duplicate.shortcut_app_id, duplicate.name
+                );
+                if shortcuts.iter().any(|s| s.app_id == duplicate.app_id) {
+                    shortcuts.retain(|s| !is_manual(s));
+                } else if let Some(shortcut) = shortcuts.iter_mut().find(|s| is_manual(s)) {
+                    shortcut.app_id = duplicate.app_id;
+                    shortcut.dev_kit_game_id = owner_tag(&duplicate.platform);
+                }
+                resolution
+                    .adopted
+                    .push((duplicate.shortcut_app_id, duplicate.app_id));
+            }
+            Some(DuplicateDecision::KeepBoth) | None => {}
+        }
+    }
+    resolution
+}
+
+/// The decisions the user made about duplicates, keyed like the overrides of a game
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct DuplicateDecisions {
+    games: BTreeMap<String, DuplicateDecision>,
+}
+
+impl DuplicateDecisions {
+    pub fn load() -> Self {
+        load_json_or_default(&get_duplicates_file(), "duplicate decisions")
+    }
+
+    pub fn save(&self) -> eyre::Result<()> {
+        save_json(&get_duplicates_file(), self)
+    }
+
+    pub fn get(&self, game: &str) -> Option<DuplicateDecision> {
+        self.games.get(game).copied()
+    }
+
+    /// Sets the decision for a game, `None` asks again
+    pub fn set(&mut self, game: &str, decision: Option<DuplicateDecision>) {
+        match decision {
+            Some(decision) => {
+                self.games.insert(game.to_string(), decision);
+            }
+            None => {
+                self.games.remove(game);
+            }
+        }
+    }
+
+    /// Moves the decision kept under an old key of a game to its key now, returns true if any moved
+    pub fn rename_games(&mut self, renamed: &[(String, String)]) -> bool {
+        rename_game_keys(&mut self.games, renamed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
+
+    use steam_shortcuts_util::Shortcut;
+
+    use super::super::calculate_app_id;
+    use super::*;
+
+    fn shortcut(name: &str, exe: &str, launch_options: &str) -> ShortcutOwned {
+        let mut shortcut = Shortcut::new("0", name, exe, "", "", "", launch_options).to_owned();
+        shortcut.app_id = calculate_app_id(name, exe);
+        shortcut
+    }
+
+    fn game(platform: &str, shortcut: ShortcutOwned) -> ImportedGame {
+        ImportedGame {
+            platform: platform.to_string(),
+            key: format!("{platform}/{}", shortcut.app_name),
+            shortcut,
+        }
+    }
+
+    fn games() -> Vec<ImportedGame> {
+        vec![
+            game("Lutris", shortcut("DOOM Eternal", "lutris", "lutris:rungame/doom")),
+            game("Lutris", shortcut("Celeste", "lutris", "lutris:rungame/celeste")),
+            game("Itch", shortcut("Baba Is You", "\"/games/baba/Baba Is You\"", "")),
+        ]
+    }
+
+    #[test]
+    fn finds_shortcuts_with_the_same_name_or_target() {
+        let mut boilr = shortcut("Celeste", "other", "");
+        boilr.dev_kit_game_id = "boilr-Lutris".to_string();
+        let shortcuts = vec![
+            shortcut("Doom: Eternal™", "/home/user/doom.sh", ""),
+            shortcut("Baba", "/GAMES/baba/Baba Is You", ""),
+            shortcut("Other game", "lutris", "lutris:rungame/other"),
+            boilr,
+        ];
+        let duplicates = find_duplicates("1", &shortcuts, &games());
+        let found: Vec<(&str, bool, bool)> = duplicates
+            .iter()
+            .map(|d| (d.game.as_str(), d.same_name, d.same_target))
+            .collect();
+        assert_eq!(
+            vec![
+                ("Lutris/DOOM Eternal", true, false),
+                ("Itch/Baba Is You", false, true)
+            ],
+            found
+        );
+    }
+
+    #[test]
+    fn empty_targets_are_never_the_same() {
+        let shortcuts = vec![shortcut("Manual", "", "")];
+        let games = vec![game("Flatpak", shortcut("Other", "  ", ""))];
+        assert!(find_duplicates("1", &shortcuts, &games).is_empty());
+    }
+
+    #[test]
+    fn applies_decisions() {
+        let mut shortcuts = vec![
+            shortcut("Doom Eternal", "/home/user/doom.sh", ""),
+            shortcut("Celeste", "/home/user/celeste", ""),
+            shortcut("Baba Is You", "/home/user/baba", ""),
+        ];
+        let games = games();
+        let mut to_write: Vec<ShortcutOwned> = games.iter().map(|g| g.shortcut.clone()).collect();
+        let duplicates = find_duplicates("1", &shortcuts, &games);
+        assert_eq!(3, duplicates.len());
+        let mut decisions = DuplicateDecisions::default();
+        decisions.set("Lutris/DOOM Eternal", Some(DuplicateDecision::Adopt));
+        decisions.set("Lutris/Celeste", Some(DuplicateDecision::Skip));
+        decisions.set("Itch/Baba Is You", Some(DuplicateDecision::KeepBoth));
+        let resolution = resolve_duplicates(&mut shortcuts, &mut to_write, &duplicates, &decisions);
+
+        let doom = &games[0].shortcut;
+        assert_eq!(
+            vec![(calculate_app_id("Doom Eternal", "/home/user/doom.sh"), doom.app_id)],
+            resolution.adopted
+        );
+        assert_eq!(doom.app_id, shortcuts[0].app_id);
+        assert_eq!(Some("Lutris"), shortcuts[0].owner());
+        assert_eq!(vec![games[1].shortcut.app_id], resolution.skipped);
+        assert_eq!(2, to_write.len());
+        assert!(!shortcuts[2].is_boilr_shortcut());
+    }
+
+    #[test]
+    fn adopting_removes_the_shortcut_if_the_game_is_imported() {
+        let games = games();
+        let mut imported = games[1].shortcut.clone();
+        imported.dev_kit_game_id = "boilr-Lutris".to_string();
+        let mut shortcuts = vec![shortcut("celeste", "/home/user/celeste", ""), imported];
+        let mut to_write: Vec<ShortcutOwned> = games.iter().map(|g| g.shortcut.clone()).collect();
+        let duplicates = find_duplicates("1", &shortcuts, &games);
+        let mut decisions = DuplicateDecisions::default();
+        decisions.set("Lutris/Celeste", Some(DuplicateDecision::Adopt));
+        let resolution = resolve_duplicates(&mut shortcuts, &mut to_write, &duplicates, &decisions);
+        assert_eq!(1, resolution.adopted.len());
+        assert_eq!(1, shortcuts.len());
+        assert_eq!(games[1].shortcut.app_id, shortcuts[0].app_id);
+        assert_eq!(3, to_write.len());
+    }
+
+    #[test]
+    fn parses_decisions() {
+        for decision in DuplicateDecision::ALL {
+            assert_eq!(Some(decision), DuplicateDecision::parse(decision.name()));
+        }
+        assert_eq!(None, DuplicateDecision::parse("replace"));
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    platforms::{get_platforms, ShortcutToImport},
+    settings::Settings,
+};
+
+use super::{GameOverride, GameOverrides, PlatformShortcuts};
+
+/// Changes the launch of every game that matches all the conditions of the rule.
+/// `exe` and `launch_options` are templates like the ones of [`GameOverride`],
+/// so `mangohud %command% {launch_options}` runs all matching games with mangohud.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LaunchRule {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// The name or code name of the platform, e.g. "Heroic" or "egs"
+    pub platform: Option<String>,
+    pub needs_proton: Option<bool>,
+    /// Matched against the name the platform found
+    pub name_regex: Option<String>,
+    pub exe_regex: Option<String>,
+    pub exe: Option<String>,
+    pub launch_options: Option<String>,
+}
+
+pub(super) fn default_enabled() -> bool {
+    true
+}
+
+impl Default for LaunchRule {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            enabled: true,
+            platform: None,
+            needs_proton: None,
+            name_regex: None,
+            exe_regex: None,
+            exe: None,
+            launch_options: None,
+        }
+    }
+}
+
+impl LaunchRule {
+    fn as_override(&self) -> GameOverride {
+        GameOverride {
+            exe: self.exe.clone(),
+            launch_options: self.launch_options.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A launch rule with its regexes compiled
+struct CompiledRule<'a> {
+    rule: &'a LaunchRule,
+    name_regex: Option<Regex>,
+    exe_regex: Option<Regex>,
+}
+
+impl<'a> CompiledRule<'a> {
+    fn new(rule: &'a LaunchRule) -> eyre::Result<Self> {
+        let compile = |regex: &Option<String>| -> eyre::Result<Option<Regex>> {
+            match regex.as_deref().filter(|regex| !regex.is_empty()) {
+                Some(regex) => Ok(Some(Regex::new(regex)?)),
+                None => Ok(None),
+            }
+        };
+        Ok(Self {
+            rule,
+            name_regex: compile(&rule.name_regex)?,
+            exe_regex: compile(&rule.exe_regex)?,
+        })
+    }
+
+    fn matches(&self, platform: &str, code_name: &str, info: &ShortcutToImport) -> bool {
+        let platform_matches = match self.rule.platform.as_deref() {
+            Some(wanted) if !wanted.is_empty() => {
+                wanted.eq_ignore_ascii_case(platform) || wanted.eq_ignore_ascii_case(code_name)
+            }
+            _ => true,
+        };
+        let proton_matches = self
+            .rule
+            .needs_proton
+            .is_none_or(|needs_proton| needs_proton == info.needs_proton);
+        let name_matches = self
+            .name_regex
+            .as_ref()
+            .is_none_or(|regex| regex.is_match(&info.shortcut.app_name));
+        let exe_matches = self
+            .exe_regex
+            .as_ref()
+            .is_none_or(|regex| regex.is_match(&info.shortcut.exe));
+        platform_matches && proton_matches && name_matches && exe_matches
+    }
+}
+
+/// The games a rule matches, with the exe and launch options they would get
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LaunchRulePreview {
+    pub rule: String,
+    pub error: Option<String>,
+    pub games: Vec<LaunchRuleGame>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchRuleGame {
+    pub platform: String,
+    pub name: String,
+    pub exe: String,
+    pub launch_options: String,
+}
+
+/// Maps the names of the platforms to their code names, the names used in config.toml
+pub fn platform_code_names() -> HashMap<String, String> {
+    get_platforms()
+        .iter()
+        .map(|platform| (platform.name().to_string(), platform.code_name().to_string()))
+        .collect()
+}
+
+/// The combined override of the enabled rules matching each game, keyed by the app id the platform gave it.
+/// Rules are applied in order, so a later rule's `{launch_options}` is the result of the earlier rules.
+pub fn launch_rule_overrides(
+    rules: &[LaunchRule],
+    shortcuts: &PlatformShortcuts,
+    code_names: &HashMap<String, String>,
+) -> HashMap<u32, GameOverride> {
+    let compiled = compile_rules(rules);
+    let mut result = HashMap::new();
+    for (platform, infos) in shortcuts {
+        let code_name = code_names.get(platform).map_or("", String::as_str);
+        for info in infos {
+            let combined = compiled
+                .iter()
+                .filter(|rule| rule.matches(platform, code_name, info))
+                .fold(None, |combined: Option<GameOverride>, rule| {
+                    let rule_override = rule.rule.as_override();
+                    Some(match combined {
+                        Some(combined) => combined.then(&rule_override),
+                        None => rule_override,
+                    })
+                });
+            if let Some(combined) = combined {
+                result.insert(info.shortcut.app_id, combined);
+            }
+        }
+    }
+    result
+}
+
+fn compile_rules(rules: &[LaunchRule]) -> Vec<CompiledRule<'_>> {
+    rules
+        .iter()
+        .filter(|rule| rule.enabled)
+        .filter_map(|rule| match CompiledRule::new(rule) {
+            Ok(compiled) => Some(compiled),
+            Err(err) => {
+                eprintln!("Skipping launch rule {}: {err}", rule.name);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The launch rules and per game overrides of the games found, keyed by the app id the platform gave them.
+/// Per game overrides are applied after the rules.
+pub fn resolve_overrides(
+    settings: &Settings,
+    shortcuts: &PlatformShortcuts,
+) -> HashMap<u32, GameOverride> {
+    let mut overrides =
+        launch_rule_overrides(&settings.launch_rules, shortcuts, &platform_code_names());
+    for (app_id, game_override) in GameOverrides::load().for_found_games(shortcuts) {
+        let combined = match overrides.get(&app_id) {
+            Some(rule_override) => rule_override.then(&game_override),
+            None => game_override,
+        };
+        overrides.insert(app_id, combined);
+    }
+    overrides
+}
+
+/// Shows which games each rule matches, and what the rule alone would make of them
+pub fn preview_launch_rules(
+    rules: &[LaunchRule],
+    shortcuts: &PlatformShortcuts,
+    code_names: &HashMap<String, String>,
+) -> Vec<LaunchRulePreview> {
+    rules
+        .iter()
+        .map(|rule| {
+            let compiled = match CompiledRule::new(rule) {
+                Ok(compiled) => compiled,
+                Err(err) => {
+                    return LaunchRulePreview {
+                        rule: rule.name.clone(),
+                        error: Some(err.to_string()),
+                        games: vec![],
+                    }
+                }
+            };
+            let rule_override = rule.as_override();
+            let games = shortcuts
+                .iter()
+                .flat_map(|(platform, infos)| {
+                    let code_name = code_names.get(platform).map_or("", String::as_str);
+                    let compiled = &compiled;
+                    let rule_override = &rule_override;
+                    infos
+                        .iter()
+                        .filter(move |info| compiled.matches(platform, code_name, info))
+                        .map(move |info| {
+                            let mut shortcut = info.shortcut.clone();
+                            rule_override.apply(&mut shortcut);
+                            LaunchRuleGame {
+                                platform: platform.clone(),
+                                name: shortcut.app_name,
+                                exe: shortcut.exe,
+                                launch_options: shortcut.launch_options,
+                            }
+                        })
+                })
+                .collect();
+            LaunchRulePreview {
+                rule: rule.name.clone(),
+                error: None,
+                games,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to index in tests
+    #![allow(clippy::indexing_slicing)]
+    use steam_shortcuts_util::Shortcut;
+
+    use super::*;
+
+    fn game(name: &str, exe: &str, needs_proton: bool) -> ShortcutToImport {
+        ShortcutToImport {
+            shortcut: Shortcut::new("0", name, exe, "", "", "", "-launch").to_owned(),
+            needs_proton,
+            needs_symlinks: false,
+            game_id: None,
+            runner: None,
+            artwork: vec![],
+            legacy_app_id: None,
+        }
+    }
+
+    fn shortcuts() -> PlatformShortcuts {
+        vec![
+            (
+                "Epic".to_string(),
+                vec![
+                    game("Fortnite", "/epic/fortnite.exe", true),
+                    game("Native Game", "/epic/native", false),
+                ],
+            ),
+            ("Lutris".to_string(), vec![game("Fortnite", "lutris", false)]),
+        ]
+    }
+
+    fn code_names() -> HashMap<String, String> {
+        HashMap::from([
+            ("Epic".to_string(), "egs".to_string()),
+            ("Lutris".to_string(), "lutris".to_string()),
+        ])
+    }
+
+    #[test]
+    fn rules_match_all_their_conditions() {
+        let rule = LaunchRule {
+            platform: Some("egs".to_string()),
+            needs_proton: Some(true),
+            launch_options: Some("mangohud %command% {launch_options}".to_string()),
+            ..Default::default()
+        };
+        let overrides = launch_rule_overrides(&[rule], &shortcuts(), &code_names());
+        assert_eq!(1, overrides.len());
+        let app_id = crate::sync::calculate_app_id("Fortnite", "/epic/fortnite.exe");
+        let mut shortcut = Shortcut::new("0", "Fortnite", "/epic/fortnite.exe", "", "", "", "-launch")
+            .to_owned();
+        overrides[&app_id].apply(&mut shortcut);
+        assert_eq!("mangohud %command% -launch", shortcut.launch_options);
+    }
+
+    #[test]
+    fn rules_are_applied_in_order() {
+        let rules = [
+            LaunchRule {
+                name_regex: Some("^Fort".to_string()),
+                launch_options: Some("{launch_options} -nolauncher".to_string()),
+                ..Default::default()
+            },
+            LaunchRule {
+                platform: Some("Lutris".to_string()),
+                launch_options: Some("DXVK_HUD=fps %command% {launch_options}".to_string()),
+                ..Default::default()
+            },
+            LaunchRule {
+                enabled: false,
+                launch_options: Some("disabled".to_string()),
+                ..Default::default()
+            },
+        ];
+        let preview = preview_launch_rules(&rules, &shortcuts(), &code_names());
+        assert_eq!(2, preview[0].games.len());
+        assert_eq!(1, preview[1].games.len());
+
+        let overrides = launch_rule_overrides(&rules, &shortcuts(), &code_names());
+        let app_id = crate::sync::calculate_app_id("Fortnite", "lutris");
+        let mut shortcut = Shortcut::new("0", "Fortnite", "lutris", "", "", "", "-launch").to_owned();
+        overrides[&app_id].apply(&mut shortcut);
+        assert_eq!(
+            "DXVK_HUD=fps %command% -launch -nolauncher",
+            shortcut.launch_options
+        );
+    }
+
+    #[test]
+    fn invalid_regex_is_reported() {
+        let rule = LaunchRule {
+            name_regex: Some("(".to_string()),
+            ..Default::default()
+        };
+        let preview = preview_launch_rules(std::slice::from_ref(&rule), &shortcuts(), &code_names());
+        assert!(preview[0].error.is_some());
+        assert!(launch_rule_overrides(&[rule], &shortcuts(), &code_names()).is_empty());
+    }
+}
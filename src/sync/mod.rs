@@ -1,5 +1,16 @@
 #[cfg(target_family = "unix")]
 pub mod symlinks;
+mod adopted;
+mod app_ids;
+mod collection_rules;
+mod duplicates;
+mod launch_rules;
+mod overrides;
+mod preview;
+#[cfg(target_family = "unix")]
+mod proton;
+mod report;
+mod runner;
 mod synchronization;
 
 pub use synchronization::download_images;
@@ -7,3 +18,14 @@ pub use synchronization::download_images;
 pub use synchronization::IsBoilRShortcut;
 pub use synchronization::SyncProgress;
 pub use synchronization::*;
+pub use adopted::*;
+pub use app_ids::*;
+pub use collection_rules::*;
+pub use duplicates::*;
+pub use launch_rules::*;
+pub use overrides::*;
+pub use preview::*;
+#[cfg(target_family = "unix")]
+pub use proton::*;
+pub use report::*;
+pub use runner::*;
@@ -0,0 +1,269 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use steam_shortcuts_util::shortcut::ShortcutOwned;
+
+use crate::config::{get_overrides_file, load_json_or_default, save_json};
+
+use super::{game_key, rename_game_keys, PlatformShortcuts};
+
+/// Values that replace what a platform found for a game.
+/// `{exe}` and `{launch_options}` are replaced with the values the platform found,
+/// so `gamemoderun {exe}` or `{launch_options} -dx11` keep working when the game is updated.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct GameOverride {
+    pub exe: Option<String>,
+    pub start_dir: Option<String>,
+    pub launch_options: Option<String>,
+    pub icon: Option<String>,
+    /// The compatibility tool the game runs with, also for games the platform does not run with proton
+    pub compat_tool: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverrideField {
+    Exe,
+    StartDir,
+    LaunchOptions,
+    Icon,
+    CompatTool,
+}
+
+pub const OVERRIDE_FIELDS: [OverrideField; 5] = [
+    OverrideField::Exe,
+    OverrideField::StartDir,
+    OverrideField::LaunchOptions,
+    OverrideField::Icon,
+    OverrideField::CompatTool,
+];
+
+impl OverrideField {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OverrideField::Exe => "exe",
+            OverrideField::StartDir => "start-dir",
+            OverrideField::LaunchOptions => "launch-options",
+            OverrideField::Icon => "icon",
+            OverrideField::CompatTool => "compat-tool",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        OVERRIDE_FIELDS.into_iter().find(|field| field.name() == name)
+    }
+}
+
+impl GameOverride {
+    pub fn is_empty(&self) -> bool {
+        self == &GameOverride::default()
+    }
+
+    pub fn get(&self, field: OverrideField) -> &Option<String> {
+        match field {
+            OverrideField::Exe => &self.exe,
+            OverrideField::StartDir => &self.start_dir,
+            OverrideField::LaunchOptions => &self.launch_options,
+            OverrideField::Icon => &self.icon,
+            OverrideField::CompatTool => &self.compat_tool,
+        }
+    }
+
+    pub fn get_mut(&mut self, field: OverrideField) -> &mut Option<String> {
+        match field {
+            OverrideField::Exe => &mut self.exe,
+            OverrideField::StartDir => &mut self.start_dir,
+            OverrideField::LaunchOptions => &mut self.launch_options,
+            OverrideField::Icon => &mut self.icon,
+            OverrideField::CompatTool => &mut self.compat_tool,
+        }
+    }
+
+    /// Replaces the fields of the shortcut, this does not change the app id.
+    /// The compatibility tool is not part of the shortcut, it is set up with the proton mappings.
+    pub fn apply(&self, shortcut: &mut ShortcutOwned) {
+        let fill = |template: &str| fill_template(template, &shortcut.exe, &shortcut.launch_options);
+        let exe = self.exe.as_deref().map(fill);
+        let start_dir = self.start_dir.as_deref().map(fill);
+        let launch_options = self.launch_options.as_deref().map(fill);
+        let icon = self.icon.as_deref().map(fill);
+        if let Some(exe) = exe {
+            shortcut.exe = exe;
+        }
+        if let Some(start_dir) = start_dir {
+            shortcut.start_dir = start_dir;
+        }
+        if let Some(launch_options) = launch_options {
+            shortcut.launch_options = merge_commands(&launch_options);
+        }
+        if let Some(icon) = icon {
+            shortcut.icon = icon;
+        }
+    }
+
+    /// Combines two overrides into one, `next` is applied to the result of this override
+    pub fn then(&self, next: &GameOverride) -> GameOverride {
+        let exe = self.exe.as_deref().unwrap_or("{exe}");
+        let launch_options = self.launch_options.as_deref().unwrap_or("{launch_options}");
+        let fill = |template: &Option<String>, own: &Option<String>| match template {
+            Some(template) => Some(fill_template(template, exe, launch_options)),
+            None => own.clone(),
+        };
+        GameOverride {
+            exe: fill(&next.exe, &self.exe),
+            start_dir: fill(&next.start_dir, &self.start_dir),
+            launch_options: fill(&next.launch_options, &self.launch_options),
+            icon: fill(&next.icon, &self.icon),
+            compat_tool: next.compat_tool.clone().or_else(|| self.compat_tool.clone()),
+        }
+    }
+}
+
+/// Replaces `{exe}` and `{launch_options}` in one pass, so the values are never replaced again
+fn fill_template(template: &str, exe: &str, launch_options: &str) -> String {
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let (before, after) = rest.split_at(start);
+        result.push_str(before);
+        if let Some(after) = after.strip_prefix("{exe}") {
+            result.push_str(exe);
+            rest = after;
+        } else if let Some(after) = after.strip_prefix("{launch_options}") {
+            result.push_str(launch_options);
+            rest = after;
+        } else {
+            result.push('{');
+            rest = after.get(1..).unwrap_or_default();
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Steam only replaces one `%command%`, so when several templates wrap the command
+/// everything before the last `%command%` is kept as the prefix,
+/// `A=1 %command% mangohud %command% -x` becomes `A=1 mangohud %command% -x`
+fn merge_commands(launch_options: &str) -> String {
+    let mut parts: Vec<&str> = launch_options.split("%command%").collect();
+    let Some(arguments) = parts.pop() else {
+        return launch_options.trim().to_string();
+    };
+    if parts.len() < 2 {
+        return launch_options.trim().to_string();
+    }
+    let prefix: Vec<&str> = parts
+        .iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect();
+    format!("{} %command% {}", prefix.join(" "), arguments.trim())
+        .trim()
+        .to_string()
+}
+
+/// The overrides of all games, keyed by the platform and the id of the game on that platform
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GameOverrides {
+    games: BTreeMap<String, GameOverride>,
+}
+
+impl GameOverrides {
+    pub fn load() -> Self {
+        load_json_or_default(&get_overrides_file(), "game overrides")
+    }
+
+    pub fn save(&self) -> eyre::Result<()> {
+        save_json(&get_overrides_file(), self)
+    }
+
+    pub fn get(&self, game: &str) -> Option<&GameOverride> {
+        self.games.get(game)
+    }
+
+    /// Sets the override of a game, empty overrides are removed
+    pub fn set(&mut self, game: &str, game_override: GameOverride) {
+        if game_override.is_empty() {
+            self.games.remove(game);
+        } else {
+            self.games.insert(game.to_string(), game_override);
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &GameOverride)> {
+        self.games.iter()
+    }
+
+    /// Moves the override kept under an old key of a game to its key now, returns true if any moved
+    pub fn rename_games(&mut self, renamed: &[(String, String)]) -> bool {
+        rename_game_keys(&mut self.games, renamed)
+    }
+
+    /// The overrides of the games found, keyed by the app id the platform gave them
+    pub fn for_found_games(&self, shortcuts: &PlatformShortcuts) -> HashMap<u32, GameOverride> {
+        shortcuts
+            .iter()
+            .flat_map(|(platform, infos)| {
+                infos.iter().filter_map(move |info| {
+                    self.games
+                        .get(&game_key(platform, info))
+                        .map(|game_override| (info.shortcut.app_id, game_override.clone()))
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use steam_shortcuts_util::Shortcut;
+
+    use super::*;
+
+    #[test]
+    fn fills_in_templates() {
+        let mut shortcut =
+            Shortcut::new("0", "Game", "/games/game", "/games", "", "", "-fullscreen").to_owned();
+        let game_override = GameOverride {
+            exe: Some("gamemoderun".to_string()),
+            launch_options: Some("{exe} {launch_options} -dx11".to_string()),
+            ..Default::default()
+        };
+        game_override.apply(&mut shortcut);
+        assert_eq!("gamemoderun", shortcut.exe);
+        assert_eq!("/games/game -fullscreen -dx11", shortcut.launch_options);
+        assert_eq!("/games", shortcut.start_dir);
+    }
+
+    #[test]
+    fn empty_overrides_are_removed() {
+        let mut overrides = GameOverrides::default();
+        let game_override = GameOverride {
+            icon: Some("/icon.png".to_string()),
+            ..Default::default()
+        };
+        overrides.set("Heroic/game", game_override.clone());
+        assert_eq!(Some(&game_override), overrides.get("Heroic/game"));
+        overrides.set("Heroic/game", GameOverride::default());
+        assert_eq!(None, overrides.get("Heroic/game"));
+    }
+
+    #[test]
+    fn combined_overrides_wrap_the_command_once() {
+        let mut shortcut =
+            Shortcut::new("0", "Game", "/games/game", "/games", "", "", "-fullscreen").to_owned();
+        let mangohud = GameOverride {
+            launch_options: Some("mangohud %command% {launch_options}".to_string()),
+            ..Default::default()
+        };
+        let dxvk = GameOverride {
+            launch_options: Some("DXVK_HUD=fps %command% {launch_options} -dx11".to_string()),
+            ..Default::default()
+        };
+        mangohud.then(&dxvk).apply(&mut shortcut);
+        assert_eq!(
+            "DXVK_HUD=fps mangohud %command% -fullscreen -dx11",
+            shortcut.launch_options
+        );
+    }
+}
@@ -0,0 +1,629 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+use steam_shortcuts_util::shortcut::ShortcutOwned;
+
+use crate::{
+    settings::Settings,
+    steam::{
+        get_boilr_collections, get_existing_collections, get_import_users, get_shortcuts_for_user,
+        Collection, SteamInstall,
+    },
+};
+
+use super::{
+    adopted_collection_games, collection_games, find_duplicates, get_kept_shortcuts, imported_games, installed_collection_games, kept_collection_games,
+    merge_shortcuts, platform_code_names, prepare_shortcuts, resolve_duplicates, resolve_overrides,
+    shortcuts_for_user, CollectionRules, DuplicateDecisions, DuplicateShortcut, KeepShortcuts, PlatformShortcuts,
+};
+
+/// What an import would change, computed without writing anything
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SyncPreview {
+    pub users: Vec<UserPreview>,
+    pub proton: ProtonPreview,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UserPreview {
+    pub user_id: String,
+    pub persona_name: Option<String>,
+    pub shortcuts_before: usize,
+    pub shortcuts_after: usize,
+    pub shortcut_changes: Vec<ShortcutChange>,
+    pub collection_changes: Vec<CollectionChange>,
+    /// Set if the current collections could not be read, for instance because Steam is running
+    pub collections_error: Option<String>,
+    /// Shortcuts not made by BoilR for games it imports, without a decision
+    pub duplicates: Vec<DuplicateShortcut>,
+}
+
+impl UserPreview {
+    pub fn display_name(&self) -> String {
+        match &self.persona_name {
+            Some(name) => format!("{name} ({})", self.user_id),
+            None => self.user_id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProtonPreview {
+    pub mapping_changes: Vec<ProtonMappingChange>,
+    pub error: Option<String>,
+}
+
+/// A game that would get a compatibility tool, `old_tool` is set if it already had another one
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProtonMappingChange {
+    pub app_id: u32,
+    pub name: String,
+    pub tool: String,
+    pub old_tool: Option<String>,
+}
+
+impl fmt::Display for ProtonMappingChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.old_tool {
+            Some(old_tool) => write!(
+                f,
+                "~ proton for {} ({}): {old_tool} -> {}",
+                self.name, self.app_id, self.tool
+            ),
+            None => write!(
+                f,
+                "+ proton mapping for {} ({}): {}",
+                self.name, self.app_id, self.tool
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum ShortcutChange {
+    Added {
+        app_id: u32,
+        name: String,
+    },
+    Removed {
+        app_id: u32,
+        name: String,
+    },
+    Changed {
+        old_app_id: u32,
+        app_id: u32,
+        name: String,
+        fields: Vec<FieldChange>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum CollectionChange {
+    Added {
+        name: String,
+        games: usize,
+    },
+    Removed {
+        name: String,
+    },
+    Changed {
+        name: String,
+        games_added: usize,
+        games_removed: usize,
+    },
+    /// A rule adds games to a collection made in Steam, but the user has no collection with that name
+    NotFound {
+        name: String,
+    },
+}
+
+impl SyncPreview {
+    pub fn has_changes(&self) -> bool {
+        !self.proton.mapping_changes.is_empty()
+            || self
+                .users
+                .iter()
+                .any(|u| !u.shortcut_changes.is_empty() || !u.collection_changes.is_empty())
+    }
+}
+
+/// Computes what `run_sync` would change for every Steam user, without writing anything
+pub fn preview_sync(
+    settings: &Settings,
+    shortcuts_to_import: &PlatformShortcuts,
+    failed_platforms: &[String],
+    renames: &HashMap<u32, String>,
+) -> eyre::Result<SyncPreview> {
+    let overrides = resolve_overrides(settings, shortcuts_to_import);
+    let code_names = platform_code_names();
+    let collection_rules = CollectionRules::from_settings(settings);
+    let duplicate_decisions = DuplicateDecisions::load();
+    let steam_games = installed_collection_games(settings);
+
+    let install = SteamInstall::find(&settings.steam)?;
+    let mut users = vec![];
+    for user in get_import_users(&settings.steam, &install)? {
+        let mut shortcut_info = match get_shortcuts_for_user(&user) {
+            Ok(info) => info,
+            Err(_) => continue,
+        };
+        let user_settings = settings.steam.user_settings(&user.user_id);
+        let user_shortcuts = shortcuts_for_user(shortcuts_to_import, &user_settings, &code_names);
+        let mut all_shortcuts = prepare_shortcuts(settings, &user_shortcuts, renames, &overrides);
+        let before = shortcut_info.shortcuts.clone();
+        let imported = imported_games(settings, &user_shortcuts, renames, &overrides);
+        let duplicates = find_duplicates(&user.user_id, &shortcut_info.shortcuts, &imported);
+        let resolution = resolve_duplicates(
+            &mut shortcut_info.shortcuts,
+            &mut all_shortcuts,
+            &duplicates,
+            &duplicate_decisions,
+        );
+        merge_shortcuts(
+            &mut shortcut_info,
+            &all_shortcuts,
+            KeepShortcuts::OfFailedPlatforms(failed_platforms),
+            &settings.steam.field_ownership,
+            &[],
+        );
+        let mut games = collection_games(settings, &user_shortcuts, renames, &overrides);
+        let kept = get_kept_shortcuts(
+            &shortcut_info.shortcuts,
+            KeepShortcuts::OfFailedPlatforms(failed_platforms),
+        );
+        games.extend(kept_collection_games(&kept));
+        games.extend(adopted_collection_games(&shortcut_info.shortcuts));
+        games.extend(steam_games.iter().cloned());
+        games.retain(|game| !resolution.skipped.contains(&game.app_id));
+        let new_collections = collection_rules.collections(&games);
+        let mut user_preview = UserPreview {
+            user_id: user.user_id.clone(),
+            persona_name: user.persona_name.clone(),
+            shortcuts_before: before.len(),
+            shortcuts_after: shortcut_info.shortcuts.len(),
+            shortcut_changes: diff_shortcuts(&before, &shortcut_info.shortcuts),
+            duplicates: duplicates
+                .into_iter()
+                .filter(|duplicate| duplicate_decisions.get(&duplicate.game).is_none())
+                .collect(),
+            ..Default::default()
+        };
+        if settings.steam.create_collections {
+            let current = get_boilr_collections(&install, &user.user_id).and_then(|mut current| {
+                current.extend(get_existing_collections(&install, &user.user_id)?);
+                Ok(current)
+            });
+            match current {
+                Ok(current) => {
+                    user_preview.collection_changes = diff_collections(&current, &new_collections)
+                }
+                Err(err) => {
+                    user_preview.collections_error = Some(err.to_string());
+                    user_preview.collection_changes = diff_collections(&[], &new_collections);
+                }
+            }
+        }
+        users.push(user_preview);
+    }
+
+    Ok(SyncPreview {
+        users,
+        proton: preview_proton(&install, settings, shortcuts_to_import, renames, &overrides),
+    })
+}
+
+#[cfg(target_family = "unix")]
+fn preview_proton(
+    install: &SteamInstall,
+    settings: &Settings,
+    shortcuts_to_import: &PlatformShortcuts,
+    renames: &HashMap<u32, String>,
+    overrides: &HashMap<u32, super::GameOverride>,
+) -> ProtonPreview {
+    let (mappings, names): (Vec<_>, Vec<_>) =
+        super::proton_mappings(settings, shortcuts_to_import, renames, overrides)
+            .into_iter()
+            .unzip();
+    let names: HashMap<u32, String> = mappings
+        .iter()
+        .map(|mapping| mapping.app_id)
+        .zip(names)
+        .collect();
+    match crate::steam::get_proton_changes(install, &mappings) {
+        Ok(changes) => ProtonPreview {
+            mapping_changes: changes
+                .into_iter()
+                .map(|(mapping, old_tool)| ProtonMappingChange {
+                    app_id: mapping.app_id,
+                    name: names.get(&mapping.app_id).cloned().unwrap_or_default(),
+                    tool: mapping.tool,
+                    old_tool,
+                })
+                .collect(),
+            error: None,
+        },
+        //Nothing needs proton, so a missing proton config does not matter
+        Err(_) if mappings.is_empty() => ProtonPreview::default(),
+        Err(err) => ProtonPreview {
+            mapping_changes: vec![],
+            error: Some(err.to_string()),
+        },
+    }
+}
+
+#[cfg(not(target_family = "unix"))]
+fn preview_proton(
+    _install: &SteamInstall,
+    _settings: &Settings,
+    _shortcuts_to_import: &PlatformShortcuts,
+    _renames: &HashMap<u32, String>,
+    _overrides: &HashMap<u32, super::GameOverride>,
+) -> ProtonPreview {
+    ProtonPreview::default()
+}
+
+/// Compares two lists of shortcuts.
+/// Shortcuts are matched on app id first, then on executable (a rename) and finally on name (a new executable)
+pub fn diff_shortcuts(before: &[ShortcutOwned], after: &[ShortcutOwned]) -> Vec<ShortcutChange> {
+    let mut changes = vec![];
+    let mut removed: Vec<&ShortcutOwned> = vec![];
+    for old in before {
+        match after.iter().find(|new| new.app_id == old.app_id) {
+            Some(new) => {
+                let fields = diff_fields(old, new);
+                if !fields.is_empty() {
+                    changes.push(ShortcutChange::Changed {
+                        old_app_id: old.app_id,
+                        app_id: new.app_id,
+                        name: new.app_name.clone(),
+                        fields,
+                    });
+                }
+            }
+            None => removed.push(old),
+        }
+    }
+    let mut added: Vec<&ShortcutOwned> = after
+        .iter()
+        .filter(|new| !before.iter().any(|old| old.app_id == new.app_id))
+        .collect();
+
+    let matchers: [fn(&ShortcutOwned, &ShortcutOwned) -> bool; 2] = [
+        |old, new| old.exe == new.exe,
+        |old, new| old.app_name == new.app_name,
+    ];
+    for matcher in matchers {
+        removed.retain(|old| match added.iter().position(|new| matcher(old, new)) {
+            Some(index) => {
+                let new = added.remove(index);
+                changes.push(ShortcutChange::Changed {
+                    old_app_id: old.app_id,
+                    app_id: new.app_id,
+                    name: new.app_name.clone(),
+                    fields: diff_fields(old, new),
+                });
+                false
+            }
+            None => true,
+        });
+    }
+
+    changes.extend(removed.into_iter().map(|old| ShortcutChange::Removed {
+        app_id: old.app_id,
+        name: old.app_name.clone(),
+    }));
+    changes.extend(added.into_iter().map(|new| ShortcutChange::Added {
+        app_id: new.app_id,
+        name: new.app_name.clone(),
+    }));
+    changes
+}
+
+fn diff_fields(old: &ShortcutOwned, new: &ShortcutOwned) -> Vec<FieldChange> {
+    let fields: [(&'static str, &String, &String); 4] = [
+        ("name", &old.app_name, &new.app_name),
+        ("exe", &old.exe, &new.exe),
+        ("start dir", &old.start_dir, &new.start_dir),
+        ("launch options", &old.launch_options, &new.launch_options),
+    ];
+    fields
+        .into_iter()
+        .filter(|(_, old, new)| old != new)
+        .map(|(field, old, new)| FieldChange {
+            field,
+            old: old.clone(),
+            new: new.clone(),
+        })
+        .collect()
+}
+
+/// Compares the collections BoilR owns, and lists the games that would be added to existing collections.
+/// `before` holds both the collections BoilR owns and the existing collections of the user.
+pub fn diff_collections(before: &[Collection], after: &[Collection]) -> Vec<CollectionChange> {
+    let mut changes = vec![];
+    for new in after.iter().filter(|new| new.existing) {
+        let old = before
+            .iter()
+            .find(|old| old.existing && old.name.eq_ignore_ascii_case(&new.name));
+        match old {
+            Some(old) => {
+                let games_added = new
+                    .game_ids
+                    .iter()
+                    .filter(|id| !old.game_ids.contains(id))
+                    .count();
+                if games_added > 0 {
+                    changes.push(CollectionChange::Changed {
+                        name: old.name.clone(),
+                        games_added,
+                        games_removed: 0,
+                    });
+                }
+            }
+            None => changes.push(CollectionChange::NotFound {
+                name: new.name.clone(),
+            }),
+        }
+    }
+    let before: Vec<&Collection> = before.iter().filter(|old| !old.existing).collect();
+    let after: Vec<&Collection> = after.iter().filter(|new| !new.existing).collect();
+    for new in &after {
+        match before.iter().find(|old| old.name == new.name) {
+            Some(old) => {
+                let games_added = new
+                    .game_ids
+                    .iter()
+                    .filter(|id| !old.game_ids.contains(id))
+                    .count();
+                let games_removed = old
+                    .game_ids
+                    .iter()
+                    .filter(|id| !new.game_ids.contains(id))
+                    .count();
+                if games_added > 0 || games_removed > 0 {
+                    changes.push(CollectionChange::Changed {
+                        name: new.name.clone(),
+                        games_added,
+                        games_removed,
+                    });
+                }
+            }
+            None => changes.push(CollectionChange::Added {
+                name: new.name.clone(),
+                games: new.game_ids.len(),
+            }),
+        }
+    }
+    changes.extend(
+        before
+            .iter()
+            .filter(|old| !after.iter().any(|new| new.name == old.name))
+            .map(|old| CollectionChange::Removed {
+                name: old.name.clone(),
+            }),
+    );
+    changes
+}
+
+impl fmt::Display for ShortcutChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShortcutChange::Added { app_id, name } => write!(f, "+ {name} ({app_id})"),
+            ShortcutChange::Removed { app_id, name } => write!(f, "- {name} ({app_id})"),
+            ShortcutChange::Changed {
+                old_app_id,
+                app_id,
+                name,
+                fields,
+            } => {
+                if old_app_id == app_id {
+                    write!(f, "~ {name} ({app_id})")?;
+                } else {
+                    write!(f, "~ {name} ({old_app_id} -> {app_id})")?;
+                }
+                for field in fields {
+                    write!(
+                        f,
+                        "\n    {}: {:?} -> {:?}",
+                        field.field, field.old, field.new
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for CollectionChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CollectionChange::Added { name, games } => {
+                write!(f, "+ collection {name} ({games} games)")
+            }
+            CollectionChange::Removed { name } => write!(f, "- collection {name}"),
+            CollectionChange::NotFound { name } => write!(
+                f,
+                "! collection {name} not found, create it in Steam to add games to it"
+            ),
+            CollectionChange::Changed {
+                name,
+                games_added,
+                games_removed,
+            } => write!(
+                f,
+                "~ collection {name} (+{games_added} / -{games_removed} games)"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for SyncPreview {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for user in &self.users {
+            writeln!(
+                f,
+                "Steam user {}: {} shortcuts -> {} shortcuts",
+                user.display_name(),
+                user.shortcuts_before,
+                user.shortcuts_after
+            )?;
+            for change in &user.shortcut_changes {
+                writeln!(f, "  {change}")?;
+            }
+            for change in &user.collection_changes {
+                writeln!(f, "  {change}")?;
+            }
+            if let Some(err) = &user.collections_error {
+                writeln!(f, "  Could not read current collections: {err}")?;
+            }
+            for duplicate in &user.duplicates {
+                writeln!(f, "  ? {duplicate}")?;
+            }
+        }
+        for change in &self.proton.mapping_changes {
+            writeln!(f, "{change}")?;
+        }
+        if let Some(err) = &self.proton.error {
+            writeln!(f, "Could not read proton mappings: {err}")?;
+        }
+        if !self.has_changes() {
+            writeln!(f, "No changes")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap and panic in tests
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
+    #![allow(clippy::panic)]
+    use steam_shortcuts_util::Shortcut;
+
+    use super::*;
+
+    fn shortcut(name: &str, exe: &str, launch_options: &str) -> ShortcutOwned {
+        Shortcut::new("0", name, exe, "", "", "", launch_options).to_owned()
+    }
+
+    #[test]
+    fn finds_added_and_removed() {
+        let before = vec![shortcut("Old", "/old", "")];
+        let after = vec![shortcut("New", "/new", "")];
+        let changes = diff_shortcuts(&before, &after);
+        assert_eq!(2, changes.len());
+        assert!(matches!(
+            &changes[..],
+            [ShortcutChange::Removed { .. }, ShortcutChange::Added { .. }]
+        ));
+    }
+
+    #[test]
+    fn finds_renames() {
+        let before = vec![shortcut("Old name", "/game", "")];
+        let after = vec![shortcut("New name", "/game", "")];
+        let changes = diff_shortcuts(&before, &after);
+        match &changes[..] {
+            [ShortcutChange::Changed { fields, .. }] => {
+                assert_eq!(1, fields.len());
+                assert_eq!("name", fields[0].field);
+            }
+            _ => panic!("Expected a single change, got {changes:?}"),
+        }
+    }
+
+    #[test]
+    fn finds_changed_exe_and_options() {
+        let before = vec![shortcut("Game", "/old/game", "")];
+        let after = vec![shortcut("Game", "/new/game", "-dx11")];
+        let changes = diff_shortcuts(&before, &after);
+        match &changes[..] {
+            [ShortcutChange::Changed { fields, .. }] => {
+                let names: Vec<&str> = fields.iter().map(|f| f.field).collect();
+                assert_eq!(vec!["exe", "launch options"], names);
+            }
+            _ => panic!("Expected a single change, got {changes:?}"),
+        }
+    }
+
+    #[test]
+    fn unchanged_shortcuts_are_not_reported() {
+        let before = vec![shortcut("Game", "/game", "")];
+        let changes = diff_shortcuts(&before, &before.clone());
+        assert!(changes.is_empty());
+    }
+
+    fn collection(name: &str, game_ids: Vec<usize>, existing: bool) -> Collection {
+        Collection {
+            name: name.to_string(),
+            game_ids,
+            existing,
+        }
+    }
+
+    #[test]
+    fn diffs_collections() {
+        let before = vec![
+            collection("Heroic", vec![1, 2], false),
+            collection("Itch", vec![3], false),
+        ];
+        let after = vec![
+            collection("Heroic", vec![2, 4, 5], false),
+            collection("Lutris", vec![6], false),
+        ];
+        let changes = diff_collections(&before, &after);
+        assert_eq!(
+            vec![
+                CollectionChange::Changed {
+                    name: "Heroic".to_string(),
+                    games_added: 2,
+                    games_removed: 1
+                },
+                CollectionChange::Added {
+                    name: "Lutris".to_string(),
+                    games: 1
+                },
+                CollectionChange::Removed {
+                    name: "Itch".to_string()
+                },
+            ],
+            changes
+        );
+    }
+
+    #[test]
+    fn only_adds_to_existing_collections() {
+        let before = vec![
+            collection("Favorites", vec![1, 2, 3], true),
+            collection("Retro", vec![7], true),
+        ];
+        let after = vec![
+            collection("favorites", vec![3, 4], true),
+            collection("Retro", vec![7], true),
+            collection("Co-op", vec![5], true),
+        ];
+        let changes = diff_collections(&before, &after);
+        assert_eq!(
+            vec![
+                CollectionChange::Changed {
+                    name: "Favorites".to_string(),
+                    games_added: 1,
+                    games_removed: 0
+                },
+                CollectionChange::NotFound {
+                    name: "Co-op".to_string()
+                },
+            ],
+            changes
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::{get_proton_tools_file, load_json_or_default, save_json}, settings::Settings, steam::ProtonMapping};
+
+use super::{
+    customize_shortcut, platform_code_names, AdoptedShortcuts, GameOverride, PlatformShortcuts,
+};
+
+/// The compatibility tool of every game that needs proton or has a tool chosen in its overrides.
+/// The mappings use the app id the shortcut gets after renames and overrides, together with the name of the game.
+/// Adopted shortcuts with a chosen tool are mapped as well.
+/// Games that still have the tool BoilR gave them get the tool the settings choose now.
+pub fn proton_mappings(
+    settings: &Settings,
+    shortcuts: &PlatformShortcuts,
+    renames: &HashMap<u32, String>,
+    overrides: &HashMap<u32, GameOverride>,
+) -> Vec<(ProtonMapping, String)> {
+    let code_names = platform_code_names();
+    let written_tools = WrittenProtonTools::load();
+    let mut mappings = vec![];
+    for (platform, infos) in shortcuts {
+        let code_name = code_names.get(platform).map_or("", String::as_str);
+        let platform_tool = settings.steam.proton.tool_for(platform, code_name);
+        for info in infos {
+            if settings.blacklisted_games.contains(&info.shortcut.app_id) {
+                continue;
+            }
+            let chosen_tool = overrides
+                .get(&info.shortcut.app_id)
+                .and_then(|game_override| game_override.compat_tool.clone());
+            if chosen_tool.is_none() && !info.needs_proton {
+                continue;
+            }
+            let mut shortcut = info.shortcut.clone();
+            customize_shortcut(&mut shortcut, renames, overrides);
+            mappings.push((
+                ProtonMapping {
+                    app_id: shortcut.app_id,
+                    replace: chosen_tool.is_some(),
+                    tool: chosen_tool.unwrap_or_else(|| platform_tool.to_string()),
+                    written_tool: written_tools.get(shortcut.app_id).map(str::to_string),
+                },
+                shortcut.app_name,
+            ));
+        }
+    }
+    mappings.extend(AdoptedShortcuts::load().proton_mappings());
+    mappings
+}
+
+/// The compatibility tools BoilR gave games by their app id, saved to the config folder
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct WrittenProtonTools {
+    tools: BTreeMap<u32, String>,
+}
+
+impl WrittenProtonTools {
+    pub fn load() -> Self {
+        load_json_or_default(&get_proton_tools_file(), "the proton tools BoilR set")
+    }
+
+    pub fn save(&self) -> eyre::Result<()> {
+        save_json(&get_proton_tools_file(), self)
+    }
+
+    pub fn get(&self, app_id: u32) -> Option<&str> {
+        self.tools.get(&app_id).map(String::as_str)
+    }
+
+    /// Remembers the tools BoilR just wrote
+    pub fn record(&mut self, mappings: &[ProtonMapping]) {
+        for mapping in mappings {
+            self.tools.insert(mapping.app_id, mapping.tool.clone());
+        }
+    }
+
+    /// Follows games whose app id changed
+    pub fn move_app_ids(&mut self, changes: &[(u32, u32)]) {
+        for (old_app_id, new_app_id) in changes {
+            if let Some(tool) = self.tools.remove(old_app_id) {
+                self.tools.entry(*new_app_id).or_insert(tool);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, app_ids: &[u32]) {
+        for app_id in app_ids {
+            self.tools.remove(app_id);
+        }
+    }
+}
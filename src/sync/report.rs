@@ -0,0 +1,236 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use steam_shortcuts_util::shortcut::ShortcutOwned;
+
+use crate::{
+    config::get_sync_report_file, platforms::ShortcutToImport, steam::MultiLibraryInstall,
+    steamgriddb::ImageDownload,
+};
+
+use super::{AppIdChange, DuplicateShortcut, IsBoilRShortcut};
+
+/// A machine readable summary of a sync run, saved to the config folder after every import
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+    pub started_at: String,
+    pub finished_at: String,
+    pub success: bool,
+    pub error: Option<String>,
+    pub platforms: Vec<PlatformReport>,
+    /// Games that were found, but not imported because they are blacklisted
+    pub skipped_app_ids: Vec<u32>,
+    /// Games whose app id changed, their images and proton settings were moved to the new id
+    pub app_id_changes: Vec<AppIdChange>,
+    pub users: Vec<UserReport>,
+    /// Steam games with a manifest in more than one library
+    pub steam_games_in_several_libraries: Vec<MultiLibraryInstall>,
+    pub images_downloaded: Vec<ImageReport>,
+    pub images_failed: Vec<ImageReport>,
+    pub timings: SyncTimings,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlatformReport {
+    pub name: String,
+    pub games: Vec<GameReport>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GameReport {
+    pub app_id: u32,
+    pub name: String,
+    pub needs_proton: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserReport {
+    pub user_id: String,
+    pub shortcuts_before: usize,
+    pub shortcuts_after: usize,
+    pub collections_error: Option<String>,
+    /// Steam was running, the collections are written once it exits, see [`crate::steam::PendingCollections`]
+    pub collections_queued: bool,
+    /// BoilR shortcuts that were removed, because their game is no longer found
+    pub removed: Vec<RemovedGame>,
+    /// Shortcuts that were kept, because their platform failed to find games
+    pub kept_from_failed_platforms: usize,
+    pub images_removed: Vec<String>,
+    /// Shortcuts not made by BoilR for games it imports, that the user has not decided about yet
+    pub duplicates: Vec<DuplicateShortcut>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemovedGame {
+    pub app_id: u32,
+    pub name: String,
+    pub platform: Option<String>,
+}
+
+impl From<&ShortcutOwned> for RemovedGame {
+    fn from(shortcut: &ShortcutOwned) -> Self {
+        Self {
+            app_id: shortcut.app_id,
+            name: shortcut.app_name.clone(),
+            platform: shortcut.owner().map(|o| o.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImageReport {
+    pub app_name: String,
+    pub image_type: String,
+    pub url: String,
+    pub path: PathBuf,
+    pub error: Option<String>,
+}
+
+/// Durations in milliseconds
+#[derive(Debug, Default, Serialize)]
+pub struct SyncTimings {
+    pub find_games_ms: Option<u64>,
+    pub write_shortcuts_ms: u64,
+    pub download_images_ms: u64,
+    pub total_ms: u64,
+}
+
+impl SyncReport {
+    pub fn new() -> Self {
+        Self {
+            started_at: now(),
+            started: Some(Instant::now()),
+            ..Default::default()
+        }
+    }
+
+    pub fn add_platform(&mut self, name: &str, result: &eyre::Result<Vec<ShortcutToImport>>) {
+        let (games, error) = match result {
+            Ok(shortcuts) => (
+                shortcuts
+                    .iter()
+                    .map(|s| GameReport {
+                        app_id: s.shortcut.app_id,
+                        name: s.shortcut.app_name.clone(),
+                        needs_proton: s.needs_proton,
+                    })
+                    .collect(),
+                None,
+            ),
+            Err(err) => (vec![], Some(err.to_string())),
+        };
+        self.platforms.push(PlatformReport {
+            name: name.to_string(),
+            games,
+            error,
+        });
+    }
+
+    pub fn add_images(&mut self, downloads: Vec<ImageDownload>) {
+        for download in downloads {
+            let image = ImageReport {
+                app_name: download.app_name,
+                image_type: download.image_type.name().to_string(),
+                url: download.url,
+                path: download.path,
+                error: download.error,
+            };
+            if image.error.is_some() {
+                self.images_failed.push(image);
+            } else {
+                self.images_downloaded.push(image);
+            }
+        }
+    }
+
+    pub fn finish(&mut self, result: &eyre::Result<()>) {
+        self.finished_at = now();
+        self.success = result.is_ok();
+        self.error = result.as_ref().err().map(|e| e.to_string());
+        if let Some(started) = self.started {
+            self.timings.total_ms = millis(started.elapsed());
+        }
+    }
+
+    pub fn to_json(&self) -> eyre::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn save(&self) -> eyre::Result<PathBuf> {
+        let path = get_sync_report_file();
+        std::fs::write(&path, self.to_json()?)?;
+        Ok(path)
+    }
+}
+
+pub fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+fn now() -> String {
+    OffsetDateTime::now_utc()
+        .format(&Rfc3339)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap and index in tests
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
+    use steam_shortcuts_util::Shortcut;
+
+    use crate::steamgriddb::ImageType;
+
+    use super::*;
+
+    #[test]
+    fn reports_platforms_and_images() {
+        let shortcut = Shortcut::new("0", "Game", "/game", "", "", "", "").to_owned();
+        let app_id = shortcut.app_id;
+        let mut report = SyncReport::new();
+        report.add_platform(
+            "Heroic",
+            &Ok(vec![ShortcutToImport {
+                shortcut,
+                needs_proton: true,
+                needs_symlinks: false,
+                game_id: None,
+                runner: None,
+                artwork: vec![],
+                legacy_app_id: None,
+            }]),
+        );
+        report.add_platform("Itch", &Err(eyre::eyre!("Itch not found")));
+        report.add_images(vec![
+            ImageDownload {
+                app_name: "Game".to_string(),
+                image_type: ImageType::Grid,
+                url: "https://example.com/grid.png".to_string(),
+                path: PathBuf::from("grid.png"),
+                error: None,
+            },
+            ImageDownload {
+                app_name: "Game".to_string(),
+                image_type: ImageType::Hero,
+                url: "https://example.com/hero.png".to_string(),
+                path: PathBuf::from("hero.png"),
+                error: Some("timeout".to_string()),
+            },
+        ]);
+        report.finish(&Ok(()));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["success"], true);
+        assert_eq!(json["platforms"][0]["games"][0]["app_id"], app_id);
+        assert_eq!(json["platforms"][1]["error"], "Itch not found");
+        assert_eq!(json["images_downloaded"][0]["image_type"], "Grid");
+        assert_eq!(json["images_failed"][0]["url"], "https://example.com/hero.png");
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use futures::executor::block_on;
+use tokio::sync::watch::Sender;
+
+use crate::{
+    backup::{create_snapshot_with_retention, restore_snapshot, SnapshotPart},
+    platforms::{GamesPlatform, ShortcutToImport},
+    settings::Settings,
+    steam::{apply_pending_collections, is_collections_locked, PendingCollections, SteamInstall},
+    steamgriddb::copy_platform_images,
+};
+
+use super::{
+    download_images, fix_all_shortcut_icons, follow_app_id_changes, get_platform_shortcuts,
+    migrate_legacy_game_keys, millis, platform_artwork, resolve_overrides, sync_shortcuts,
+    KeepShortcuts, SyncProgress, SyncReport,
+};
+
+pub type PlatformShortcuts = Vec<(String, Vec<ShortcutToImport>)>;
+
+/// Finds the games of all enabled platforms, one thread per platform.
+/// The result contains the platform name together with what the platform found.
+pub fn fetch_platform_shortcuts(
+    platforms: &[Box<dyn GamesPlatform>],
+) -> Vec<(String, eyre::Result<Vec<ShortcutToImport>>)> {
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = platforms
+            .iter()
+            .filter(|platform| platform.enabled())
+            .map(|platform| {
+                let name = platform.name().to_string();
+                let platform = platform.clone();
+                (name, scope.spawn(move || get_platform_shortcuts(platform)))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|(name, handle)| {
+                let result = handle
+                    .join()
+                    .unwrap_or_else(|_| Err(eyre::eyre!("Finding games for {name} crashed")));
+                (name, result)
+            })
+            .collect()
+    })
+}
+
+/// Runs a full import: proton setup, backup, writing shortcuts, downloading images and fixing icons.
+/// This is blocking and must be called from within a tokio runtime, since images are downloaded with reqwest.
+/// The outcome is recorded in the report, which is saved to the config folder afterwards.
+/// The shortcuts of `failed_platforms` are kept as they are, since their games could not be found.
+pub fn run_sync(
+    settings: &Settings,
+    shortcuts_to_import: PlatformShortcuts,
+    failed_platforms: &[String],
+    renames: &HashMap<u32, String>,
+    sender: &mut Option<Sender<SyncProgress>>,
+    report: &mut SyncReport,
+) -> eyre::Result<()> {
+    let result = sync_with_report(
+        settings,
+        shortcuts_to_import,
+        failed_platforms,
+        renames,
+        sender,
+        report,
+    );
+    if let (Err(err), Some(sender)) = (&result, sender) {
+        let _ = sender.send(SyncProgress::Failed {
+            error: err.to_string(),
+        });
+    }
+    report.finish(&result);
+    match report.save() {
+        Ok(path) => log!("Saved sync report to {path:?}"),
+        Err(err) => eprintln!("Could not save sync report: {err}"),
+    }
+    result
+}
+
+fn sync_with_report(
+    settings: &Settings,
+    shortcuts_to_import: PlatformShortcuts,
+    failed_platforms: &[String],
+    renames: &HashMap<u32, String>,
+    sender: &mut Option<Sender<SyncProgress>>,
+    report: &mut SyncReport,
+) -> eyre::Result<()> {
+    if let Some(sender) = sender {
+        let _ = sender.send(SyncProgress::Starting);
+    }
+    let install = &SteamInstall::find(&settings.steam)?;
+    let stopped_steam = if settings.steam.stop_steam {
+        crate::steam::ensure_steam_stopped(&settings.steam, install)?
+    } else {
+        None
+    };
+
+    let snapshot = create_snapshot_with_retention(settings, install, None);
+    if let Err(err) = &snapshot {
+        eprintln!("Could not back up the shortcuts: {err:?}");
+    }
+
+    //Settings kept under the keys of older versions follow the game before its app id change is followed
+    migrate_legacy_game_keys(&shortcuts_to_import);
+    //Images and proton settings are moved before proton is set up for the new app ids
+    let overrides = resolve_overrides(settings, &shortcuts_to_import);
+    let mut settings = settings.clone();
+    let mut renames = renames.clone();
+    report.app_id_changes =
+        follow_app_id_changes(&mut settings, install, &mut renames, &shortcuts_to_import, &overrides);
+    let settings = &settings;
+    let renames = &renames;
+
+    #[cfg(target_family = "unix")]
+    setup_proton(settings, install, &shortcuts_to_import, renames, &overrides);
+
+    report.skipped_app_ids = shortcuts_to_import
+        .iter()
+        .flat_map(|(_, infos)| infos.iter())
+        .map(|info| info.shortcut.app_id)
+        .filter(|app_id| settings.blacklisted_games.contains(app_id))
+        .collect();
+    let start_time = Instant::now();
+    let usersinfo = match sync_shortcuts(
+        settings,
+        install,
+        &shortcuts_to_import,
+        KeepShortcuts::OfFailedPlatforms(failed_platforms),
+        sender,
+        renames,
+        &overrides,
+        report,
+    ) {
+        Ok(usersinfo) => usersinfo,
+        Err(err) => {
+            let Ok(snapshot) = &snapshot else {
+                return Err(err);
+            };
+            //Proton, the images and the stores of BoilR already follow the new app ids, so they are rolled back as well
+            let parts = [
+                SnapshotPart::Shortcuts,
+                SnapshotPart::Images,
+                SnapshotPart::Proton,
+                SnapshotPart::Config,
+            ];
+            return match restore_snapshot(install, snapshot, &parts) {
+                Ok(()) => Err(err.wrap_err(format!(
+                    "Importing failed, the shortcuts, images, proton settings and BoilR config were restored from backup {}",
+                    snapshot.id
+                ))),
+                Err(restore_err) => Err(err.wrap_err(format!(
+                    "Importing failed and restoring backup {} failed as well: {restore_err:?}",
+                    snapshot.id
+                ))),
+            };
+        }
+    };
+    report.timings.write_shortcuts_ms = millis(start_time.elapsed());
+    apply_other_pending_collections(install);
+    #[cfg(target_family = "unix")]
+    remove_proton_of_removed_shortcuts(install, report);
+    let start_time = Instant::now();
+    //The art of the platforms goes first, so SteamGridDB is only searched for the images that are still missing
+    let artwork = platform_artwork(&shortcuts_to_import, renames, &overrides);
+    report.add_images(block_on(copy_platform_images(settings, &usersinfo, &artwork)));
+    let task = download_images(settings, &usersinfo, sender);
+    report.add_images(block_on(task));
+    report.timings.download_images_ms = millis(start_time.elapsed());
+    //Run a second time to fix up shortcuts after images are downloaded
+    if let Err(e) = fix_all_shortcut_icons(settings, install) {
+        eprintln!("Could not fix shortcuts with error {e}");
+    }
+
+    if let Some(sender) = sender {
+        let _ = sender.send(SyncProgress::Done);
+    }
+    if settings.steam.start_steam {
+        crate::steam::ensure_steam_started(install, stopped_steam.as_ref());
+    }
+    Ok(())
+}
+
+/// Writes the collections still pending for users this import did not write collections for
+fn apply_other_pending_collections(install: &SteamInstall) {
+    if PendingCollections::load().is_empty() {
+        return;
+    }
+    match apply_pending_collections(install) {
+        Ok(users) if !users.is_empty() => {
+            log!("Wrote the pending collections of {} user(s)", users.len())
+        }
+        Ok(_) => (),
+        Err(err) if is_collections_locked(&err) => (),
+        Err(err) => eprintln!("Could not write pending collections: {err}"),
+    }
+}
+
+#[cfg(target_family = "unix")]
+fn setup_proton(
+    settings: &Settings,
+    install: &SteamInstall,
+    shortcuts_to_import: &PlatformShortcuts,
+    renames: &HashMap<u32, String>,
+    overrides: &HashMap<u32, super::GameOverride>,
+) {
+    for (name, shortcuts) in shortcuts_to_import {
+        for shortcut_info in shortcuts {
+            if shortcut_info.needs_proton {
+                super::symlinks::ensure_links_folder_created(name);
+            }
+
+            if shortcut_info.needs_symlinks {
+                super::symlinks::create_sym_links(&shortcut_info.shortcut);
+            }
+        }
+    }
+    let mappings: Vec<crate::steam::ProtonMapping> =
+        super::proton_mappings(settings, shortcuts_to_import, renames, overrides)
+            .into_iter()
+            .map(|(mapping, _)| mapping)
+            .collect();
+    let result = crate::steam::get_proton_changes(install, &mappings).and_then(|changes| {
+        crate::steam::setup_proton_games(install, &mappings)?;
+        Ok(changes)
+    });
+    match result {
+        Ok(changes) if !changes.is_empty() => {
+            let written: Vec<_> = changes.into_iter().map(|(mapping, _)| mapping).collect();
+            let mut written_tools = super::WrittenProtonTools::load();
+            written_tools.record(&written);
+            if let Err(err) = written_tools.save() {
+                eprintln!("Could not save the proton tools BoilR set: {err:?}");
+            }
+        }
+        Ok(_) => {}
+        Err(err) => eprintln!("failed to save proton settings: {err:?}"),
+    }
+}
+
+/// Removes the proton mappings of the shortcuts BoilR removed from every user.
+/// The mapping is shared by all users, so it stays while another user still has the shortcut.
+#[cfg(target_family = "unix")]
+fn remove_proton_of_removed_shortcuts(install: &SteamInstall, report: &SyncReport) {
+    let mut removed: Vec<u32> = report
+        .users
+        .iter()
+        .flat_map(|user| user.removed.iter().map(|game| game.app_id))
+        .collect();
+    if removed.is_empty() {
+        return;
+    }
+    match shortcut_app_ids(install) {
+        Ok(app_ids) => removed.retain(|app_id| !app_ids.contains(app_id)),
+        Err(err) => {
+            eprintln!("Could not read the shortcuts, the proton settings of removed shortcuts are kept: {err:?}");
+            return;
+        }
+    }
+    if let Err(err) = crate::steam::remove_compat_tool_mappings(install, &removed) {
+        eprintln!("Could not remove the proton settings of removed shortcuts: {err:?}");
+        return;
+    }
+    if !removed.is_empty() {
+        let mut written_tools = super::WrittenProtonTools::load();
+        written_tools.remove(&removed);
+        if let Err(err) = written_tools.save() {
+            eprintln!("Could not save the proton tools BoilR set: {err:?}");
+        }
+    }
+}
+
+/// The app ids of the shortcuts of every Steam user
+#[cfg(target_family = "unix")]
+fn shortcut_app_ids(install: &SteamInstall) -> eyre::Result<std::collections::HashSet<u32>> {
+    let mut app_ids = std::collections::HashSet::new();
+    for user in crate::steam::get_install_users(install)? {
+        if user.shortcut_path.is_none() {
+            continue;
+        }
+        let shortcut_info = crate::steam::get_shortcuts_for_user(&user)?;
+        app_ids.extend(shortcut_info.shortcuts.iter().map(|shortcut| shortcut.app_id));
+    }
+    Ok(app_ids)
+}
+
+#[cfg(test)]
+#[cfg(target_family = "unix")]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    use steam_shortcuts_util::{shortcuts_to_bytes, Shortcut};
+
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::steam::SteamInstallKind;
+
+    #[test]
+    fn finds_shortcuts_of_every_user() {
+        let dir = tempfile::tempdir().unwrap();
+        let game = Shortcut::new("0", "Game", "/games/game", "", "", "", "");
+        let other = Shortcut::new("0", "Other", "/games/other", "", "", "", "");
+        for (user_id, shortcut) in [("1", &game), ("2", &other)] {
+            let config = dir.path().join("userdata").join(user_id).join("config");
+            std::fs::create_dir_all(&config).unwrap();
+            std::fs::write(config.join("shortcuts.vdf"), shortcuts_to_bytes(&vec![shortcut.clone()])).unwrap();
+        }
+        std::fs::create_dir_all(dir.path().join("userdata").join("3")).unwrap();
+        let install = SteamInstall {
+            kind: SteamInstallKind::Custom,
+            path: dir.path().to_path_buf(),
+        };
+
+        let app_ids = shortcut_app_ids(&install).unwrap();
+        assert_eq!(HashSet::from([game.app_id, other.app_id]), app_ids);
+    }
+}
@@ -43,7 +43,7 @@ pub fn create_sym_links(shortcut: &ShortcutOwned) -> ShortcutOwned {
             new_shortcut
         }
         _ => {
-            println!("Could not create symlinks for game: {}", shortcut.app_name);
+            log!("Could not create symlinks for game: {}", shortcut.app_name);
             shortcut.clone()
         }
     }
@@ -53,7 +53,7 @@ pub fn ensure_links_folder_created(name: &str) {
     let boilr_links_path = get_boilr_links_path();
     if !boilr_links_path.exists() {
         if let Err(e) = std::fs::create_dir_all(&boilr_links_path) {
-            println!(
+            log!(
                 "Could not create links folder for symlinks at path: {boilr_links_path:?} , error: {e:?} , you can try to disable creating symlinks for platform {name}"
             );
         }
@@ -1,20 +1,29 @@
 use eframe::epaint::ahash::HashSet;
 use steam_shortcuts_util::{
-    calculate_app_id_for_shortcut, shortcut::ShortcutOwned, shortcuts_to_bytes, Shortcut,
+    calculate_app_id_for_shortcut, parse_shortcuts, shortcut::ShortcutOwned, shortcuts_to_bytes,
+    Shortcut,
 };
 use tokio::sync::watch::Sender;
 
 use crate::{
-    platforms::{GamesPlatform, ShortcutToImport},
+    platforms::{GamesPlatform, PlatformImage, ShortcutToImport},
     settings::Settings,
     steam::{
-        get_shortcuts_for_user, get_shortcuts_paths, write_collections, Collection, ShortcutInfo,
-        SteamUsersInfo,
+        get_game_manifests, get_import_users, get_install_users, get_shortcuts_for_user, get_shortcuts_paths,
+        multi_library_installs, unique_installs, write_or_queue_collections,
+        CollectionsWrite, FieldOwnership, ShortcutInfo, SteamInstall, SteamUserSettings, SteamUsersInfo,
     },
-    steamgriddb::{download_images_for_users, ImageType},
+    steamgriddb::{download_images_for_users, ImageDownload, ImageType},
 };
 
-use std::{collections::HashMap, error::Error};
+use std::collections::HashMap;
+
+use super::{
+    adopted_collection_games, collection_games, find_duplicates, imported_games,
+    kept_collection_games, move_grid_images, platform_code_names, resolve_duplicates,
+    steam_collection_games, AdoptedShortcuts, AppIdChange, CollectionRules, DuplicateDecisions, GameOverride,
+    PlatformShortcuts, RemovedGame, SyncReport, UserReport, ADOPTED_PLATFORM,
+};
 
 use std::{fs::File, io::Write, path::Path};
 
@@ -27,65 +36,79 @@ pub enum SyncProgress {
     FindingImages,
     DownloadingImages { to_download: usize },
     Done,
+    Failed { error: String },
 }
 
+/// Stops BoilR from managing a shortcut, adopted shortcuts get back the dev kit game id they had before
 pub fn disconnect_shortcut(settings: &Settings, app_id: u32) -> Result<(), String> {
     let mut userinfo_shortcuts = get_shortcuts_paths(&settings.steam)
         .map_err(|e| format!("Getting shortcut paths failed: {e}"))?;
+    let mut adopted_shortcuts = AdoptedShortcuts::load();
+    let origin = adopted_shortcuts
+        .get(app_id)
+        .map(|adopted| adopted.origin.clone())
+        .unwrap_or_default();
 
     for user in userinfo_shortcuts.iter_mut() {
         let shortcut_info = get_shortcuts_for_user(user);
         if let Ok(mut shortcut_info) = shortcut_info {
             for shortcut in shortcut_info.shortcuts.iter_mut() {
                 if shortcut.app_id == app_id {
-                    shortcut.dev_kit_game_id = "".to_string();
+                    shortcut.dev_kit_game_id = origin.clone();
                     shortcut.tags.retain(|s| s != BOILR_TAG);
                 }
             }
-            save_shortcuts(&shortcut_info.shortcuts, Path::new(&shortcut_info.path));
+            save_shortcuts(&shortcut_info.shortcuts, Path::new(&shortcut_info.path))
+                .map_err(|e| e.to_string())?;
         }
     }
+    if adopted_shortcuts.remove(app_id).is_some() {
+        adopted_shortcuts.save().map_err(|e| e.to_string())?;
+    }
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn sync_shortcuts(
     settings: &Settings,
-    platform_shortcuts: &[(String, Vec<ShortcutOwned>)],
+    install: &SteamInstall,
+    platform_shortcuts: &PlatformShortcuts,
+    keep: KeepShortcuts,
     sender: &mut Option<Sender<SyncProgress>>,
     renames: &HashMap<u32, String>,
+    overrides: &HashMap<u32, GameOverride>,
+    report: &mut SyncReport,
 ) -> eyre::Result<Vec<SteamUsersInfo>> {
-    let mut userinfo_shortcuts = get_shortcuts_paths(&settings.steam)?;
-    let mut all_shortcuts: Vec<ShortcutOwned> = platform_shortcuts
-        .iter()
-        .flat_map(|s| s.1.clone())
-        .filter(|s| !settings.blacklisted_games.contains(&s.app_id))
-        .collect();
-    for shortcut in &mut all_shortcuts {
-        shortcut.dev_kit_game_id = BOILR_TAG.to_string();
-    }
+    let mut userinfo_shortcuts = get_import_users(&settings.steam, install)?;
     if let Some(sender) = &sender {
-        let _ = sender.send(SyncProgress::FoundGames {
-            games_found: all_shortcuts.len(),
-        });
+        let games_found = platform_shortcuts
+            .iter()
+            .flat_map(|(_, infos)| infos.iter())
+            .filter(|info| !settings.blacklisted_games.contains(&info.shortcut.app_id))
+            .count();
+        let _ = sender.send(SyncProgress::FoundGames { games_found });
     }
-    for shortcut in &mut all_shortcuts {
-        if let Some(rename) = renames.get(&shortcut.app_id) {
-            shortcut.app_name = rename.clone();
-            let new_shortcut = Shortcut::new(
-                "0",
-                shortcut.app_name.as_str(),
-                &shortcut.exe,
-                "",
-                "",
-                "",
-                "",
-            );
-            shortcut.app_id = calculate_app_id_for_shortcut(&new_shortcut);
-        }
-        println!("Appid: {} name: {}", shortcut.app_id, shortcut.app_name);
+    let code_names = platform_code_names();
+    let collection_rules = CollectionRules::from_settings(settings);
+    let manifests = get_game_manifests(install);
+    for game in multi_library_installs(&manifests) {
+        let libraries: Vec<String> = game.libraries.iter().map(|l| l.display().to_string()).collect();
+        log!(
+            "{} ({}) is installed in several Steam libraries: {}",
+            game.name,
+            game.appid,
+            libraries.join(", ")
+        );
+        report.steam_games_in_several_libraries.push(game);
     }
-    println!("Found {} user(s)", userinfo_shortcuts.len());
+    let steam_games = if settings.steam.collect_installed_games {
+        steam_collection_games(&unique_installs(manifests))
+    } else {
+        vec![]
+    };
+    let duplicate_decisions = DuplicateDecisions::load();
+    log!("Found {} user(s)", userinfo_shortcuts.len());
     let ok_shorcuts = userinfo_shortcuts.iter_mut().filter_map(|user|{
         let shortcut_info = get_shortcuts_for_user(user).ok();
         shortcut_info.map(|shortcut_info| {
@@ -94,49 +117,385 @@ pub fn sync_shortcuts(
     });
     for (user,mut shortcut_info) in ok_shorcuts {
         let start_time = std::time::Instant::now();
-        println!(
+        log!(
             "Found {} shortcuts for user: {}",
             shortcut_info.shortcuts.len(),
             user.user_id
         );
 
-        remove_old_shortcuts(&mut shortcut_info);
-        remove_shortcuts_with_same_appid(&mut shortcut_info, &all_shortcuts);
+        let user_settings = settings.steam.user_settings(&user.user_id);
+        let user_shortcuts = shortcuts_for_user(platform_shortcuts, &user_settings, &code_names);
+        let mut all_shortcuts = prepare_shortcuts(settings, &user_shortcuts, renames, overrides);
+        let shortcuts_before = shortcut_info.shortcuts.len();
+        let imported = imported_games(settings, &user_shortcuts, renames, overrides);
+        let duplicates = find_duplicates(&user.user_id, &shortcut_info.shortcuts, &imported);
+        let resolution = resolve_duplicates(
+            &mut shortcut_info.shortcuts,
+            &mut all_shortcuts,
+            &duplicates,
+            &duplicate_decisions,
+        );
+        if !resolution.adopted.is_empty() {
+            move_grid_images(user, &resolution.adopted);
+            //Proton is set up before this, the tool the adopted shortcut had replaces the default one
+            #[cfg(target_family = "unix")]
+            if let Err(err) = crate::steam::move_compat_tool_mappings(install, &resolution.adopted, true) {
+                eprintln!("Could not move the proton settings of adopted shortcuts: {err}");
+            }
+        }
+        let duplicates: Vec<_> = duplicates
+            .into_iter()
+            .filter(|duplicate| duplicate_decisions.get(&duplicate.game).is_none())
+            .collect();
+        for duplicate in &duplicates {
+            log!("{duplicate}, choose to adopt, skip or keep both on the import page");
+        }
+        let removed = merge_shortcuts(
+            &mut shortcut_info,
+            &all_shortcuts,
+            keep,
+            &settings.steam.field_ownership,
+            &report.app_id_changes,
+        );
+        let kept = get_kept_shortcuts(&shortcut_info.shortcuts, keep);
 
-        shortcut_info.shortcuts.extend(all_shortcuts.clone());
+        save_shortcuts(&shortcut_info.shortcuts, Path::new(&shortcut_info.path))?;
 
-        save_shortcuts(&shortcut_info.shortcuts, Path::new(&shortcut_info.path));
+        let mut images_removed = vec![];
+        if settings.steamgrid_db.remove_images_of_uninstalled_games {
+            for shortcut in &removed {
+                let blacklisted = settings.blacklisted_games.contains(&shortcut.app_id)
+                    || user_settings.blacklisted_games.contains(&shortcut.app_id);
+                if !blacklisted {
+                    images_removed.extend(remove_grid_images(user, shortcut.app_id));
+                }
+            }
+        }
 
+        let mut collections_error = None;
+        let mut collections_queued = false;
         if settings.steam.create_collections {
-            match write_shortcut_collections(&user.user_id, platform_shortcuts) {
-                Ok(_) => (),
-                Err(_e) => eprintln!("Could not write collections, make sure steam is shut down"),
+            let mut games = collection_games(settings, &user_shortcuts, renames, overrides);
+            games.extend(kept_collection_games(&kept));
+            games.extend(adopted_collection_games(&shortcut_info.shortcuts));
+            games.extend(steam_games.iter().cloned());
+            games.retain(|game| !resolution.skipped.contains(&game.app_id));
+            let collections = collection_rules.collections(&games);
+            log!("Writing {} collections ", collections.len());
+            match write_or_queue_collections(install, &user.user_id, &collections) {
+                Ok(CollectionsWrite::Written) => (),
+                Ok(CollectionsWrite::Queued) => {
+                    log!("Steam is running, the collections are written when it exits or on the next import");
+                    collections_queued = true;
+                }
+                Err(e) => {
+                    eprintln!("Could not write collections: {e}");
+                    collections_error = Some(e.to_string());
+                }
             }
         }
+        report.users.push(UserReport {
+            user_id: user.user_id.clone(),
+            shortcuts_before,
+            shortcuts_after: shortcut_info.shortcuts.len(),
+            collections_error,
+            collections_queued,
+            removed: removed.iter().map(RemovedGame::from).collect(),
+            kept_from_failed_platforms: kept.iter().map(|(_, s)| s.len()).sum(),
+            images_removed,
+            duplicates,
+        });
 
         let duration = start_time.elapsed();
-        println!("Finished synchronizing games in: {duration:?}");
+        log!("Finished synchronizing games in: {duration:?}");
     }
     Ok(userinfo_shortcuts)
 }
 
+/// The shortcuts found by the platforms that a Steam user gets, without their blacklisted games and disabled platforms
+pub(crate) fn shortcuts_for_user(
+    platform_shortcuts: &PlatformShortcuts,
+    user_settings: &SteamUserSettings,
+    code_names: &HashMap<String, String>,
+) -> PlatformShortcuts {
+    platform_shortcuts
+        .iter()
+        .filter(|(platform, _)| {
+            let code_name = code_names.get(platform).map_or("", String::as_str);
+            user_settings.imports_platform(platform, code_name)
+        })
+        .map(|(platform, infos)| {
+            let infos = infos
+                .iter()
+                .filter(|info| !user_settings.blacklisted_games.contains(&info.shortcut.app_id))
+                .cloned()
+                .collect();
+            (platform.clone(), infos)
+        })
+        .collect()
+}
+
+/// Tags, renames, overrides and filters the shortcuts found by the platforms, so they are ready to be written
+pub(crate) fn prepare_shortcuts(
+    settings: &Settings,
+    platform_shortcuts: &PlatformShortcuts,
+    renames: &HashMap<u32, String>,
+    overrides: &HashMap<u32, GameOverride>,
+) -> Vec<ShortcutOwned> {
+    let mut all_shortcuts: Vec<ShortcutOwned> = platform_shortcuts
+        .iter()
+        .flat_map(|(platform, infos)| {
+            infos.iter().map(move |info| {
+                let mut shortcut = info.shortcut.clone();
+                shortcut.dev_kit_game_id = owner_tag(platform);
+                shortcut
+            })
+        })
+        .filter(|s| !settings.blacklisted_games.contains(&s.app_id))
+        .collect();
+    for shortcut in &mut all_shortcuts {
+        customize_shortcut(shortcut, renames, overrides);
+        log!("Appid: {} name: {}", shortcut.app_id, shortcut.app_name);
+    }
+    all_shortcuts
+}
+
+/// The art the platforms have for their games, by the app id the shortcut gets after renames and overrides
+pub(crate) fn platform_artwork(
+    platform_shortcuts: &PlatformShortcuts,
+    renames: &HashMap<u32, String>,
+    overrides: &HashMap<u32, GameOverride>,
+) -> HashMap<u32, Vec<PlatformImage>> {
+    platform_shortcuts
+        .iter()
+        .flat_map(|(_, infos)| infos.iter())
+        .filter(|info| !info.artwork.is_empty())
+        .map(|info| {
+            let mut shortcut = info.shortcut.clone();
+            customize_shortcut(&mut shortcut, renames, overrides);
+            (shortcut.app_id, info.artwork.clone())
+        })
+        .collect()
+}
+
+/// Applies the rename and override of a shortcut found by a platform,
+/// the app id is calculated again if the name or target changed
+pub(crate) fn customize_shortcut(
+    shortcut: &mut ShortcutOwned,
+    renames: &HashMap<u32, String>,
+    overrides: &HashMap<u32, GameOverride>,
+) {
+    let found_app_id = shortcut.app_id;
+    let found_exe = shortcut.exe.clone();
+    if let Some(game_override) = overrides.get(&found_app_id) {
+        game_override.apply(shortcut);
+    }
+    let renamed = match renames.get(&found_app_id) {
+        Some(rename) => {
+            shortcut.app_name = rename.clone();
+            true
+        }
+        None => false,
+    };
+    if renamed || shortcut.exe != found_exe {
+        shortcut.app_id = calculate_app_id(&shortcut.app_name, &shortcut.exe);
+    }
+}
+
+/// The app id Steam gives a shortcut with this name and target
+pub(crate) fn calculate_app_id(name: &str, exe: &str) -> u32 {
+    let shortcut = Shortcut::new("0", name, exe, "", "", "", "");
+    calculate_app_id_for_shortcut(&shortcut)
+}
+
+/// The BoilR shortcuts an import leaves as they are, when their game was not found again
+#[derive(Debug, Clone, Copy)]
+pub enum KeepShortcuts<'a> {
+    /// Every platform was asked for its games, the shortcuts of the ones that failed are kept
+    OfFailedPlatforms(&'a [String]),
+}
+
+impl KeepShortcuts<'_> {
+    /// If a BoilR shortcut owned by `owner` is kept, adopted shortcuts are always kept
+    fn keeps(&self, owner: Option<&str>) -> bool {
+        match (self, owner) {
+            (_, Some(ADOPTED_PLATFORM)) => true,
+            (KeepShortcuts::OfFailedPlatforms(failed), Some(owner)) => {
+                failed.iter().any(|platform| platform == owner)
+            }
+            (KeepShortcuts::OfFailedPlatforms(_), None) => false,
+        }
+    }
+}
+
+/// Replaces the BoilR shortcuts of a user with the new shortcuts.
+/// Fields the user owns are kept from the existing shortcut with the same app id.
+/// Adopted shortcuts and shortcuts owned by a platform that failed to find its games are kept as they are,
+/// the BoilR shortcuts that are gone for good are returned.
+pub(crate) fn merge_shortcuts(
+    shortcut_info: &mut ShortcutInfo,
+    all_shortcuts: &[ShortcutOwned],
+    keep: KeepShortcuts,
+    ownership: &FieldOwnership,
+    app_id_changes: &[AppIdChange],
+) -> Vec<ShortcutOwned> {
+    let new_app_ids: HashSet<u32> = all_shortcuts.iter().map(|s| s.app_id).collect();
+    //Games whose app id changed are still the same shortcut, so they keep what the user set on them
+    let new_app_id_of: HashMap<u32, u32> = app_id_changes
+        .iter()
+        .filter(|change| change.old.app_id != change.new.app_id)
+        .filter(|change| new_app_ids.contains(&change.new.app_id))
+        .map(|change| (change.old.app_id, change.new.app_id))
+        .collect();
+    let mut existing: HashMap<u32, ShortcutOwned> = shortcut_info
+        .shortcuts
+        .iter()
+        .filter_map(|s| new_app_id_of.get(&s.app_id).map(|new_app_id| (*new_app_id, s.clone())))
+        .collect();
+    existing.extend(
+        shortcut_info
+            .shortcuts
+            .iter()
+            .filter(|s| new_app_ids.contains(&s.app_id))
+            .map(|s| (s.app_id, s.clone())),
+    );
+    let (old_shortcuts, mut shortcuts): (Vec<_>, Vec<_>) = std::mem::take(&mut shortcut_info.shortcuts)
+        .into_iter()
+        .partition(|shortcut| shortcut.is_boilr_shortcut());
+    let mut removed = vec![];
+    for shortcut in old_shortcuts {
+        if new_app_ids.contains(&shortcut.app_id) || new_app_id_of.contains_key(&shortcut.app_id) {
+            continue;
+        }
+        if keep.keeps(shortcut.owner()) {
+            shortcuts.push(shortcut);
+        } else {
+            removed.push(shortcut);
+        }
+    }
+    shortcuts.retain(|shortcut| !new_app_ids.contains(&shortcut.app_id));
+    shortcuts.extend(all_shortcuts.iter().map(|new| match existing.get(&new.app_id) {
+        Some(old) => merge_metadata(old, new, ownership),
+        None => new.clone(),
+    }));
+    shortcut_info.shortcuts = shortcuts;
+    removed
+}
+
+/// Takes the fields BoilR owns from the new shortcut and the rest from the shortcut already in Steam
+fn merge_metadata(
+    old: &ShortcutOwned,
+    new: &ShortcutOwned,
+    ownership: &FieldOwnership,
+) -> ShortcutOwned {
+    let mut merged = new.clone();
+    if !ownership.start_dir {
+        merged.start_dir = old.start_dir.clone();
+    }
+    if !ownership.launch_options {
+        merged.launch_options = old.launch_options.clone();
+    }
+    //The icon is filled in from the downloaded images later, so an empty icon is not a change
+    if !ownership.icon || new.icon.is_empty() {
+        merged.icon = old.icon.clone();
+    }
+    if !ownership.tags {
+        merged.tags = old.tags.clone();
+        for tag in &new.tags {
+            if !merged.tags.contains(tag) {
+                merged.tags.push(tag.clone());
+            }
+        }
+    }
+    if !ownership.is_hidden {
+        merged.is_hidden = old.is_hidden;
+    }
+    if !ownership.allow_overlay {
+        merged.allow_overlay = old.allow_overlay;
+    }
+    if !ownership.allow_desktop_config {
+        merged.allow_desktop_config = old.allow_desktop_config;
+    }
+    if !ownership.open_vr {
+        merged.open_vr = old.open_vr;
+    }
+    if !ownership.last_play_time {
+        merged.last_play_time = old.last_play_time;
+    }
+    merged
+}
+
+/// The BoilR shortcuts that were kept because their platform was not imported, grouped by platform.
+/// Adopted shortcuts are not included.
+pub(crate) fn get_kept_shortcuts(
+    shortcuts: &[ShortcutOwned],
+    keep: KeepShortcuts,
+) -> Vec<(String, Vec<ShortcutOwned>)> {
+    let mut kept: Vec<(String, Vec<ShortcutOwned>)> = vec![];
+    let kept_shortcuts = shortcuts.iter().filter(|s| {
+        s.is_boilr_shortcut() && s.owner() != Some(ADOPTED_PLATFORM) && keep.keeps(s.owner())
+    });
+    for shortcut in kept_shortcuts {
+        let platform = shortcut.owner().unwrap_or_default();
+        match kept.iter_mut().find(|(kept_platform, _)| kept_platform == platform) {
+            Some((_, platform_shortcuts)) => platform_shortcuts.push(shortcut.clone()),
+            None => kept.push((platform.to_string(), vec![shortcut.clone()])),
+        }
+    }
+    kept
+}
+
+/// Removes the images in the grid folder of the user for a shortcut, returns the removed files
+fn remove_grid_images(user: &SteamUsersInfo, app_id: u32) -> Vec<String> {
+    let grid_folder = Path::new(&user.steam_user_data_folder)
+        .join("config")
+        .join("grid");
+    let names: Vec<String> = ImageType::all()
+        .iter()
+        .map(|image_type| image_type.file_name_no_extension(app_id))
+        .collect();
+    let Ok(files) = std::fs::read_dir(&grid_folder) else {
+        return vec![];
+    };
+    let mut removed = vec![];
+    for file in files.flatten() {
+        let path = file.path();
+        let is_image = path
+            .file_stem()
+            .map(|stem| names.iter().any(|name| stem.to_string_lossy() == *name))
+            .unwrap_or(false);
+        if is_image {
+            match std::fs::remove_file(&path) {
+                Ok(_) => removed.push(path.to_string_lossy().to_string()),
+                Err(err) => eprintln!("Could not remove image {path:?}: {err}"),
+            }
+        }
+    }
+    removed
+}
+
 pub async fn download_images(
     settings: &Settings,
     userinfo_shortcuts: &[SteamUsersInfo],
     sender: &mut Option<Sender<SyncProgress>>,
-) {
+) -> Vec<ImageDownload> {
+    let mut downloads = vec![];
     if settings.steamgrid_db.enabled {
-        download_images_for_users(settings, userinfo_shortcuts,  sender).await;
+        downloads.extend(download_images_for_users(settings, userinfo_shortcuts,  sender).await);
         if settings.steamgrid_db.prefer_animated{
             let mut set = settings.clone();
             set.steamgrid_db.prefer_animated = false;
-            download_images_for_users(&set, userinfo_shortcuts,  sender).await;
+            downloads.extend(download_images_for_users(&set, userinfo_shortcuts,  sender).await);
         }
     }
+    downloads
 }
 
 pub trait IsBoilRShortcut {
     fn is_boilr_shortcut(&self) -> bool;
+    /// The name of the platform that created the shortcut,
+    /// shortcuts made by older versions of BoilR do not have one
+    fn owner(&self) -> Option<&str>;
 }
 
 impl IsBoilRShortcut for ShortcutOwned {
@@ -144,26 +503,22 @@ impl IsBoilRShortcut for ShortcutOwned {
         let boilr_tag = BOILR_TAG.to_string();
         self.tags.contains(&boilr_tag) || self.dev_kit_game_id.starts_with(&boilr_tag)
     }
-}
 
-fn remove_shortcuts_with_same_appid(
-    shortcut_info: &mut ShortcutInfo,
-    new_shortcuts: &[ShortcutOwned],
-) {
-    let app_ids: HashSet<u32> = new_shortcuts.iter().map(|s| s.app_id).collect();
-    shortcut_info
-        .shortcuts
-        .retain(|shortcut| !app_ids.contains(&shortcut.app_id));
+    fn owner(&self) -> Option<&str> {
+        self.dev_kit_game_id
+            .strip_prefix(BOILR_TAG)
+            .and_then(|rest| rest.strip_prefix('-'))
+            .filter(|owner| !owner.is_empty())
+    }
 }
 
-fn remove_old_shortcuts(shortcut_info: &mut ShortcutInfo) {
-    shortcut_info
-        .shortcuts
-        .retain(|shortcut| !shortcut.is_boilr_shortcut());
+/// The dev kit game id of a shortcut, records which platform owns the shortcut
+pub(super) fn owner_tag(platform: &str) -> String {
+    format!("{BOILR_TAG}-{platform}")
 }
 
-pub fn fix_all_shortcut_icons(settings: &Settings) -> eyre::Result<()> {
-    let mut userinfo_shortcuts = get_shortcuts_paths(&settings.steam)
+pub fn fix_all_shortcut_icons(settings: &Settings, install: &SteamInstall) -> eyre::Result<()> {
+    let mut userinfo_shortcuts = get_install_users(install)
         .map_err(|e| eyre::format_err!("Could not find steam shortcuts; {e}"))?;
     for user in userinfo_shortcuts.iter_mut() {
         let shortcut_info = get_shortcuts_for_user(user);
@@ -174,7 +529,7 @@ pub fn fix_all_shortcut_icons(settings: &Settings) -> eyre::Result<()> {
                 settings.steam.optimize_for_big_picture,
             );
             if changes {
-                save_shortcuts(&shortcut_info.shortcuts, Path::new(&shortcut_info.path));
+                save_shortcuts(&shortcut_info.shortcuts, Path::new(&shortcut_info.path))?;
             }
         }
     }
@@ -211,24 +566,6 @@ fn fix_shortcut_icons(
     has_changes
 }
 
-fn write_shortcut_collections<S: AsRef<str>>(
-    steam_id: S,
-    platform_results: &[(String, Vec<ShortcutOwned>)],
-) -> Result<(), Box<dyn Error>> {
-    let mut collections = vec![];
-
-    for (name, shortcuts) in platform_results {
-        let game_ids = shortcuts.iter().map(|s| (s.app_id as usize)).collect();
-        collections.push(Collection {
-            name: name.clone(),
-            game_ids,
-        });
-    }
-    println!("Writing {} collections ", collections.len());
-    write_collections(steam_id.as_ref(), &collections)?;
-    Ok(())
-}
-
 pub fn get_platform_shortcuts(
     platform: Box<dyn GamesPlatform>,
 ) -> eyre::Result<Vec<ShortcutToImport>> {
@@ -239,29 +576,260 @@ pub fn get_platform_shortcuts(
     }
 }
 
-fn save_shortcuts(shortcuts: &[ShortcutOwned], path: &Path) {
+/// Writes the shortcuts to a temporary file next to `path`, checks that they can be read back
+/// and then renames the file in place, so Steam never sees a half written file.
+pub(super) fn save_shortcuts(shortcuts: &[ShortcutOwned], path: &Path) -> eyre::Result<()> {
     let mut shortcuts_refs = vec![];
     for shortcut in shortcuts {
         shortcuts_refs.push(shortcut.borrow());
     }
     let new_content = shortcuts_to_bytes(&shortcuts_refs);
-    match File::create(path) {
-        Ok(mut file) => match file.write_all(new_content.as_slice()) {
-            Ok(_) => {
-                println!("Saved {} shortcuts", shortcuts.len())
-            }
-            Err(e) => println!(
-                "Failed to save shortcuts to {} error: {}",
-                path.to_string_lossy(),
-                e
-            ),
-        },
+    let temp_path = path.with_extension("vdf.tmp");
+    let result = write_verified(&temp_path, &new_content, shortcuts)
+        .and_then(|_| Ok(std::fs::rename(&temp_path, path)?));
+    match result {
+        Ok(_) => {
+            log!("Saved {} shortcuts", shortcuts.len());
+            Ok(())
+        }
         Err(e) => {
-            println!(
+            let _ = std::fs::remove_file(&temp_path);
+            Err(eyre::format_err!(
                 "Failed to save shortcuts to {} error: {}",
                 path.to_string_lossy(),
                 e
-            );
+            ))
+        }
+    }
+}
+
+fn write_verified(path: &Path, content: &[u8], shortcuts: &[ShortcutOwned]) -> eyre::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(content)?;
+    file.sync_all()?;
+    drop(file);
+    let written = std::fs::read(path)?;
+    verify_shortcuts(&written, shortcuts)
+}
+
+/// Checks that the content parses back into the expected shortcuts
+fn verify_shortcuts(content: &[u8], expected: &[ShortcutOwned]) -> eyre::Result<()> {
+    let parsed = parse_shortcuts(content)
+        .map_err(|e| eyre::format_err!("Written shortcuts could not be parsed: {:?}", e))?;
+    if parsed.len() != expected.len() {
+        return Err(eyre::format_err!(
+            "Expected {} shortcuts to be written, but found {}",
+            expected.len(),
+            parsed.len()
+        ));
+    }
+    for (parsed, expected) in parsed.iter().zip(expected) {
+        if parsed.app_id != expected.app_id
+            || parsed.app_name != expected.app_name
+            || parsed.exe != expected.exe
+        {
+            return Err(eyre::format_err!(
+                "Shortcut {} was not written correctly",
+                expected.app_name
+            ));
         }
     }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    //Okay to unwrap in tests
+    #![allow(clippy::unwrap_used)]
+    #![allow(clippy::indexing_slicing)]
+    use super::*;
+    use crate::sync::KnownAppId;
+
+    fn shortcuts() -> Vec<ShortcutOwned> {
+        vec![
+            Shortcut::new("0", "Game", "/games/game", "/games", "", "", "").to_owned(),
+            Shortcut::new("0", "Other", "/games/other", "/games", "", "", "-x").to_owned(),
+        ]
+    }
+
+    #[test]
+    fn saves_shortcuts_without_leaving_temp_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shortcuts.vdf");
+        std::fs::write(&path, b"old content").unwrap();
+        save_shortcuts(&shortcuts(), &path).unwrap();
+
+        let content = std::fs::read(&path).unwrap();
+        assert_eq!(2, parse_shortcuts(&content).unwrap().len());
+        let files = std::fs::read_dir(dir.path()).unwrap().count();
+        assert_eq!(1, files);
+    }
+
+    #[test]
+    fn verification_fails_for_truncated_content() {
+        let shortcuts = shortcuts();
+        let refs: Vec<Shortcut> = shortcuts.iter().map(|s| s.borrow()).collect();
+        let content = shortcuts_to_bytes(&refs);
+        assert!(verify_shortcuts(&content, &shortcuts).is_ok());
+        assert!(verify_shortcuts(&content[..content.len() / 2], &shortcuts).is_err());
+        assert!(verify_shortcuts(&content, &shortcuts[..1]).is_err());
+    }
+
+    fn boilr_shortcut(name: &str, platform: &str) -> ShortcutOwned {
+        let mut shortcut = Shortcut::new("0", name, name, "", "", "", "").to_owned();
+        shortcut.dev_kit_game_id = owner_tag(platform);
+        shortcut
+    }
+
+    #[test]
+    fn merge_keeps_shortcuts_of_failed_platforms_and_adopted_shortcuts() {
+        let manual = Shortcut::new("0", "Manual", "/manual", "", "", "", "").to_owned();
+        let mut legacy = Shortcut::new("0", "Legacy", "/legacy", "", "", "", "").to_owned();
+        legacy.dev_kit_game_id = BOILR_TAG.to_string();
+        let mut shortcut_info = ShortcutInfo {
+            path: "shortcuts.vdf".into(),
+            shortcuts: vec![
+                manual,
+                legacy,
+                boilr_shortcut("Installed", "Heroic"),
+                boilr_shortcut("Uninstalled", "Heroic"),
+                boilr_shortcut("Lutris game", "Lutris"),
+                boilr_shortcut("Adopted game", ADOPTED_PLATFORM),
+            ],
+        };
+        let new_shortcuts = vec![boilr_shortcut("Installed", "Heroic")];
+
+        let failed = ["Lutris".to_string()];
+        let removed = merge_shortcuts(
+            &mut shortcut_info,
+            &new_shortcuts,
+            KeepShortcuts::OfFailedPlatforms(&failed),
+            &FieldOwnership::default(),
+            &[],
+        );
+
+        let names: Vec<&str> = shortcut_info
+            .shortcuts
+            .iter()
+            .map(|s| s.app_name.as_str())
+            .collect();
+        assert_eq!(vec!["Manual", "Lutris game", "Adopted game", "Installed"], names);
+        let removed: Vec<&str> = removed.iter().map(|s| s.app_name.as_str()).collect();
+        assert_eq!(vec!["Legacy", "Uninstalled"], removed);
+
+        let kept = get_kept_shortcuts(&shortcut_info.shortcuts, KeepShortcuts::OfFailedPlatforms(&failed));
+        assert_eq!(1, kept.len());
+        assert_eq!("Lutris", kept[0].0);
+        assert_eq!("Lutris game", kept[0].1[0].app_name);
+    }
+
+    #[test]
+    fn merge_keeps_fields_owned_by_the_user() {
+        let mut old = boilr_shortcut("Game", "Heroic");
+        old.is_hidden = true;
+        old.allow_overlay = false;
+        old.last_play_time = 1234;
+        old.tags = vec!["Favorite".to_string()];
+        old.launch_options = "-old".to_string();
+        old.icon = "/grid/icon.png".to_string();
+        let mut new = boilr_shortcut("Game", "Heroic");
+        new.allow_overlay = true;
+        new.tags = vec!["Installed".to_string()];
+        new.launch_options = "-new".to_string();
+        let mut shortcut_info = ShortcutInfo {
+            path: "shortcuts.vdf".into(),
+            shortcuts: vec![old],
+        };
+
+        merge_shortcuts(
+            &mut shortcut_info,
+            &[new.clone()],
+            KeepShortcuts::OfFailedPlatforms(&[]),
+            &FieldOwnership::default(),
+            &[],
+        );
+        let merged = &shortcut_info.shortcuts[0];
+        assert!(merged.is_hidden);
+        assert!(!merged.allow_overlay);
+        assert_eq!(1234, merged.last_play_time);
+        assert_eq!(vec!["Favorite", "Installed"], merged.tags);
+        assert_eq!("-new", merged.launch_options);
+        assert_eq!("/grid/icon.png", merged.icon);
+
+        let mut old = boilr_shortcut("Game", "Heroic");
+        old.allow_overlay = false;
+        old.launch_options = "-old".to_string();
+        let ownership = FieldOwnership {
+            allow_overlay: true,
+            launch_options: false,
+            ..Default::default()
+        };
+        let merged = merge_metadata(&old, &new, &ownership);
+        assert!(merged.allow_overlay);
+        assert_eq!("-old", merged.launch_options);
+    }
+
+    #[test]
+    fn merge_follows_app_id_changes() {
+        let mut old = boilr_shortcut("Game", "Heroic");
+        old.is_hidden = true;
+        old.allow_overlay = true;
+        old.last_play_time = 1234;
+        old.tags = vec!["Favorite".to_string()];
+        let new = boilr_shortcut("Renamed game", "Heroic");
+        let change = AppIdChange {
+            game: "Heroic/game".to_string(),
+            name: new.app_name.clone(),
+            old: KnownAppId {
+                found_app_id: old.app_id,
+                app_id: old.app_id,
+            },
+            new: KnownAppId {
+                found_app_id: new.app_id,
+                app_id: new.app_id,
+            },
+        };
+        let mut shortcut_info = ShortcutInfo {
+            path: "shortcuts.vdf".into(),
+            shortcuts: vec![old],
+        };
+
+        let removed = merge_shortcuts(
+            &mut shortcut_info,
+            std::slice::from_ref(&new),
+            KeepShortcuts::OfFailedPlatforms(&[]),
+            &FieldOwnership::default(),
+            &[change],
+        );
+        assert!(removed.is_empty());
+        assert_eq!(1, shortcut_info.shortcuts.len());
+        let merged = &shortcut_info.shortcuts[0];
+        assert_eq!(new.app_id, merged.app_id);
+        assert_eq!("Renamed game", merged.app_name);
+        assert!(merged.is_hidden);
+        assert!(merged.allow_overlay);
+        assert_eq!(1234, merged.last_play_time);
+        assert_eq!(vec!["Favorite"], merged.tags);
+    }
+
+    #[test]
+    fn reads_owner_of_shortcut() {
+        assert_eq!(Some("Mini Galaxy"), boilr_shortcut("Game", "Mini Galaxy").owner());
+        let mut legacy = boilr_shortcut("Game", "");
+        assert_eq!(None, legacy.owner());
+        legacy.dev_kit_game_id = BOILR_TAG.to_string();
+        assert!(legacy.is_boilr_shortcut());
+        assert_eq!(None, legacy.owner());
+    }
+
+    #[test]
+    fn failed_save_keeps_old_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("shortcuts.vdf");
+        std::fs::write(&path, b"old shortcuts").unwrap();
+        //The temporary file can not be created when there is a folder in its place
+        std::fs::create_dir(path.with_extension("vdf.tmp")).unwrap();
+        assert!(save_shortcuts(&shortcuts(), &path).is_err());
+        assert_eq!(b"old shortcuts".to_vec(), std::fs::read(&path).unwrap());
+    }
 }
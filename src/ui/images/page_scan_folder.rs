@@ -9,6 +9,9 @@ use std::collections::HashMap;
 use futures::executor::block_on;
 use std::path::Path;
 
+/// The platform name the shortcuts of scanned folders get
+const SCANNED_FOLDER: &str = "ScannedFolder";
+
 pub struct ScanFolderState {
     pub folder: Option<PathBuf>,
     pub found_games: Vec<ScannedGame>,
@@ -100,7 +103,7 @@ pub fn render_scan_games_folder(
                     app_id: 0,
                 }
             }).collect();
-            let platform_shortcuts = vec![("ScannedFolder".to_string(), shortcuts)];
+            let platform_shortcuts = vec![(SCANNED_FOLDER.to_string(), shortcuts)];
             let settings = match Settings::new() {
                 Ok(s) => s,
                 Err(e) => {
@@ -108,14 +111,23 @@ pub fn render_scan_games_folder(
                     return;
                 }
             };
+            let install = match crate::steam::SteamInstall::find(&settings.steam) {
+                Ok(install) => install,
+                Err(e) => {
+                    state.import_status = Some(format!("Could not find Steam: {e}"));
+                    return;
+                }
+            };
             let renames: HashMap<u32, String> = HashMap::new();
             state.import_status = Some("Importing shortcuts into Steam...".to_string());
             let mut sender = None;
-            match sync::sync_shortcuts(&settings, &platform_shortcuts, &mut sender, &renames) {
+            //The other platforms were not asked for their games, so their shortcuts are kept
+            let other_platforms: Vec<String> = sync::platform_code_names().into_keys().collect();
+            match sync::sync_shortcuts(&settings, &install, &platform_shortcuts, sync::KeepShortcuts::OfFailedPlatforms(&other_platforms), &mut sender, &renames, &HashMap::new(), &mut sync::SyncReport::new()) {
                 Ok(usersinfo) => {
                     state.import_status = Some("Downloading artwork from SteamGridDB...".to_string());
                     block_on(sync::download_images(&settings, &usersinfo, &mut sender));
-                    if let Err(e) = sync::fix_all_shortcut_icons(&settings) {
+                    if let Err(e) = sync::fix_all_shortcut_icons(&settings, &install) {
                         state.import_status = Some(format!("Could not fix shortcuts: {e}"));
                     } else {
                         state.import_status = Some("Scan/import/artwork complete.".to_string());
@@ -5,6 +5,7 @@ use steamgriddb_api::images::MimeTypes;
 
 #[derive(Clone, Debug)]
 pub struct PossibleImage {
+    #[allow(dead_code)]
     pub thumbnail_path: PathBuf,
     pub thumbnail_url: String,
     pub mime: MimeTypes,
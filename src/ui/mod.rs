@@ -1,15 +1,21 @@
 mod defines;
 mod ui_backup;
+mod ui_collection_rules;
 mod ui_disconnect;
 mod ui_import_games;
+mod ui_launch_rules;
+mod ui_overrides;
 mod ui_settings;
+mod ui_steam_users;
 mod uiapp;
 mod components;
 
 pub use defines::*;
 pub use ui_backup::*;
+pub use ui_collection_rules::*;
 pub use ui_disconnect::*;
 pub use ui_import_games::*;
+pub use ui_overrides::*;
 pub use ui_settings::*;
 pub use uiapp::*;
 pub mod images;
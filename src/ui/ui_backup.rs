@@ -1,41 +1,89 @@
-use std::path::{Path, PathBuf};
-
 use egui::ScrollArea;
-use time::format_description;
 
 use crate::{
-    config::get_backups_flder,
-    steam::{get_shortcuts_paths, SteamSettings},
+    backup::{
+        create_snapshot, create_snapshot_with_retention, delete_snapshot, diff_snapshot,
+        load_snapshots, restore_snapshot, set_snapshot_label, Snapshot, SnapshotDiff,
+        SnapshotPart, ALL_PARTS,
+    },
+    steam::SteamInstall,
 };
 
 use super::MyEguiApp;
 
-#[derive(Default)]
 pub struct BackupState {
-    pub available_backups: Option<Vec<PathBuf>>,
-    pub last_restore: Option<PathBuf>,
+    pub available_backups: Option<Vec<Snapshot>>,
+    pub selected: Option<String>,
+    pub selected_diff: Option<eyre::Result<Vec<SnapshotDiff>>>,
+    pub new_label: String,
+    pub edit_label: String,
+    pub restore_parts: Vec<SnapshotPart>,
+    pub status: Option<String>,
+}
+
+impl Default for BackupState {
+    fn default() -> Self {
+        Self {
+            available_backups: None,
+            selected: None,
+            selected_diff: None,
+            new_label: String::new(),
+            edit_label: String::new(),
+            restore_parts: ALL_PARTS.to_vec(),
+            status: None,
+        }
+    }
+}
+
+enum BackupAction {
+    Select(String),
+    Restore,
+    SaveLabel,
+    Delete,
 }
 
 impl MyEguiApp {
     pub fn render_backup(&mut self, ui: &mut egui::Ui) {
         ui.heading("Backups");
-        ui.label("Here you can restore backed up shortcuts files");
-        ui.label("Click a backup to restore it, your current shortcuts will be backed up first");
+        ui.label("A backup is made every time you import, it contains your shortcuts, the images of BoilR shortcuts, collections, proton settings and the BoilR configuration");
+        ui.label("Old backups are removed as configured in the settings, labeled backups are always kept");
         ui.add_space(15.0);
 
-        if let Some(last_restore) = self.backup_state.last_restore.as_ref() {
-            ui.heading(format!("Last restored {last_restore:?}"));
+        if let Some(status) = self.backup_state.status.as_ref() {
+            ui.heading(status);
         }
 
-        if ui.button("Click here to create a new backup").clicked() {
-            backup_shortcuts(&self.settings.steam);
-            self.backup_state.available_backups = None;
-        }
+        ui.horizontal(|ui| {
+            ui.label("Label:");
+            ui.text_edit_singleline(&mut self.backup_state.new_label);
+            if ui.button("Create a new backup").clicked() {
+                let label = std::mem::take(&mut self.backup_state.new_label);
+                let result = SteamInstall::find(&self.settings.steam).and_then(|install| {
+                    create_snapshot_with_retention(&self.settings, &install, Some(label))
+                });
+                self.backup_state.status = Some(match result {
+                    Ok(snapshot) => format!("Created backup {}", snapshot.id),
+                    Err(err) => format!("Could not create backup: {err}"),
+                });
+                self.backup_state.available_backups = None;
+            }
+        });
 
         let available_backups = self
             .backup_state
             .available_backups
-            .get_or_insert_with(load_backups);
+            .get_or_insert_with(load_snapshots)
+            .clone();
+
+        let mut action = None;
+        let selected = self
+            .backup_state
+            .selected
+            .as_ref()
+            .and_then(|id| available_backups.iter().find(|s| &s.id == id));
+        if let Some(snapshot) = selected {
+            action = self.render_selected_backup(ui, snapshot);
+        }
 
         if available_backups.is_empty() {
             ui.label("No backups found, they will be created every time you run import");
@@ -44,99 +92,143 @@ impl MyEguiApp {
                 .stick_to_right(true)
                 .auto_shrink([false, true])
                 .show(ui, |ui| {
-                    for backup_path in available_backups {
+                    for snapshot in &available_backups {
+                        let is_selected = selected.map(|s| s.id == snapshot.id).unwrap_or(false);
                         if ui
-                            .button(backup_path.to_string_lossy().to_string())
+                            .selectable_label(is_selected, snapshot.title())
+                            .on_hover_text(snapshot.summary())
                             .clicked()
                         {
-                            //Restore
-                            backup_shortcuts(&self.settings.steam);
-                            if restore_backup(&self.settings.steam, backup_path.as_path()) {
-                                self.backup_state.last_restore = Some(backup_path.clone());
-                            }
+                            action = Some(BackupAction::Select(snapshot.id.clone()));
                         }
                     }
                 });
         }
+
+        if let Some(action) = action {
+            self.handle_backup_action(action, selected.cloned());
+        }
     }
-}
 
-pub fn restore_backup(steam_settings: &SteamSettings, shortcut_path: &Path) -> bool {
-    let file_name = shortcut_path.file_name();
-    let paths = get_shortcuts_paths(steam_settings);
-    if let (Ok(paths), Some(file_name)) = (paths, file_name) {
-        for user in paths {
-            if let Some(user_shortcut_path) = user.shortcut_path {
-                if file_name.to_string_lossy().starts_with(&user.user_id) {
-                    match std::fs::copy(shortcut_path, Path::new(&user_shortcut_path)) {
-                        Ok(_) => {
-                            println!("Restored shortcut to path : {user_shortcut_path}");
-                        }
-                        Err(err) => {
-                            eprintln!(
-                                "Failed to restored shortcut to path : {user_shortcut_path} gave error: {err:?}"
-                            );
-                        }
+    fn render_selected_backup(
+        &mut self,
+        ui: &mut egui::Ui,
+        snapshot: &Snapshot,
+    ) -> Option<BackupAction> {
+        let mut action = None;
+        let state = &mut self.backup_state;
+        ui.heading(snapshot.title());
+        ui.label(format!("Contains {}", snapshot.summary()));
+        match &state.selected_diff {
+            Some(Ok(diffs)) => {
+                for diff in diffs {
+                    let mut lines: Vec<String> = diff
+                        .shortcut_changes
+                        .iter()
+                        .map(|change| change.to_string())
+                        .collect();
+                    if diff.images_missing > 0 {
+                        lines.push(format!("+ {} images", diff.images_missing));
                     }
-                    return true;
+                    let summary = if lines.is_empty() {
+                        "no changes".to_string()
+                    } else {
+                        format!("{} changes", lines.len())
+                    };
+                    ui.collapsing(
+                        format!("Restoring for user {} makes {summary}", diff.user_id),
+                        |ui| {
+                            for line in lines {
+                                ui.label(line);
+                            }
+                        },
+                    );
                 }
             }
+            Some(Err(err)) => {
+                ui.label("Could not compare the backup with the current shortcuts")
+                    .on_hover_text(format!("Error message: {err}"));
+            }
+            None => {}
         }
-    }
-    false
-}
-
-pub fn load_backups() -> Vec<PathBuf> {
-    let backup_folder = get_backups_flder();
-    let files = std::fs::read_dir(backup_folder);
-    let mut result = vec![];
-    if let Ok(files) = files {
-        for file in files.flatten() {
-            if file
-                .path()
-                .extension()
-                .unwrap_or_default()
-                .to_string_lossy()
-                == "vdf"
+        ui.horizontal(|ui| {
+            ui.label("Label:");
+            ui.text_edit_singleline(&mut state.edit_label);
+            if ui.button("Save label").clicked() {
+                action = Some(BackupAction::SaveLabel);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Restore:");
+            for part in ALL_PARTS {
+                let mut checked = state.restore_parts.contains(&part);
+                if ui.checkbox(&mut checked, part.name()).changed() {
+                    if checked {
+                        state.restore_parts.push(part);
+                    } else {
+                        state.restore_parts.retain(|p| *p != part);
+                    }
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .button("Restore this backup")
+                .on_hover_text("Your current setup will be backed up first")
+                .clicked()
             {
-                result.push(file.path().to_path_buf());
+                action = Some(BackupAction::Restore);
             }
-        }
+            if ui.button("Delete this backup").clicked() {
+                action = Some(BackupAction::Delete);
+            }
+        });
+        ui.add_space(15.0);
+        action
     }
-    result.sort();
-    result.reverse();
-    result
-}
-
-const DATE_FORMAT: &str = "[year]-[month]-[day]-[hour]-[minute]-[second]";
 
-pub fn backup_shortcuts(steam_settings: &SteamSettings) {
-    use time::OffsetDateTime;
-
-    let backup_folder = get_backups_flder();
-    let paths = get_shortcuts_paths(steam_settings);
-    let date = OffsetDateTime::now_utc();
-    let format = format_description::parse(DATE_FORMAT);
-    if let Ok(format) = format{
-    let date_string = date.format(&format);
-    if let (Ok(date_string),Ok(user_infos)) = (date_string,paths) {
-        for user_info in user_infos {
-            if let Some(shortcut_path) = user_info.shortcut_path {
-                let new_path = backup_folder.join(format!(
-                    "{}-{}-shortcuts.vdf",
-                    user_info.user_id, date_string
-                ));
-                match std::fs::copy(shortcut_path, &new_path) {
-                    Ok(_) => {
-                        println!("Backed up shortcut at: {new_path:?}");
-                    }
-                    Err(err) => {
-                        eprintln!(
-                            "Failed to backup shortcut at: {new_path:?}, error: {err:?}"
-                        );
-                    }
+    fn handle_backup_action(&mut self, action: BackupAction, selected: Option<Snapshot>) {
+        let state = &mut self.backup_state;
+        match (action, selected) {
+            (BackupAction::Select(id), _) => {
+                let snapshot = state
+                    .available_backups
+                    .as_ref()
+                    .and_then(|backups| backups.iter().find(|s| s.id == id));
+                if let Some(snapshot) = snapshot {
+                    state.edit_label = snapshot.manifest.label.clone().unwrap_or_default();
+                    state.selected_diff = Some(diff_snapshot(&self.settings.steam, snapshot));
                 }
+                state.selected = Some(id);
+            }
+            (BackupAction::Restore, Some(snapshot)) => {
+                let result = SteamInstall::find(&self.settings.steam).and_then(|install| {
+                    create_snapshot(&install, None)?;
+                    restore_snapshot(&install, &snapshot, &state.restore_parts)
+                });
+                state.status = Some(match result {
+                    Ok(_) => format!("Restored {}", snapshot.title()),
+                    Err(err) => format!("Could not restore {}: {err}", snapshot.title()),
+                });
+                state.available_backups = None;
+                state.selected_diff = Some(diff_snapshot(&self.settings.steam, &snapshot));
             }
-        }}
+            (BackupAction::SaveLabel, Some(mut snapshot)) => {
+                let label = Some(state.edit_label.clone());
+                if let Err(err) = set_snapshot_label(&mut snapshot, label) {
+                    state.status = Some(format!("Could not save label: {err}"));
+                }
+                state.available_backups = None;
+            }
+            (BackupAction::Delete, Some(snapshot)) => {
+                if let Err(err) = delete_snapshot(&snapshot) {
+                    state.status = Some(format!("Could not delete backup: {err}"));
+                }
+                state.selected = None;
+                state.selected_diff = None;
+                state.available_backups = None;
+            }
+            _ => {}
+        }
     }
 }
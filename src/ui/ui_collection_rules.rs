@@ -0,0 +1,290 @@
+use std::collections::HashMap;
+
+use eframe::egui;
+use tokio::sync::watch;
+
+use crate::{
+    steam::{wait_for_pending_collections, PendingCollections, SteamInstall, PENDING_COLLECTIONS_POLL},
+    sync::{self, CollectionRule, CollectionRules, CollectionTags},
+};
+
+use super::{
+    get_all_games, ui_launch_rules::optional_text, ui_settings::SECTION_SPACING, MyEguiApp,
+};
+
+/// The tags of a game that are being edited on the import page
+pub struct TagEdit {
+    pub game: String,
+    text: String,
+}
+
+impl TagEdit {
+    pub fn new(game: String, tags: &[String]) -> Self {
+        Self {
+            game,
+            text: tags.join(", "),
+        }
+    }
+
+    fn tags(&self) -> Vec<String> {
+        self.text.split(',').map(str::to_string).collect()
+    }
+}
+
+/// The collections waiting for Steam to exit before they can be written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PendingCollectionsStatus {
+    Nothing,
+    Waiting { users: usize },
+    Written { users: usize },
+    Failed { error: String },
+}
+
+impl MyEguiApp {
+    /// Starts waiting for Steam to exit if there are collections to write
+    pub(crate) fn start_collections_watcher(&mut self) {
+        let pending = PendingCollections::load();
+        if pending.is_empty()
+            || matches!(
+                *self.pending_collections.borrow(),
+                PendingCollectionsStatus::Waiting { .. }
+            )
+        {
+            return;
+        }
+        let (sender, reciever) = watch::channel(PendingCollectionsStatus::Waiting {
+            users: pending.users().count(),
+        });
+        self.pending_collections = reciever;
+        let steam_settings = self.settings.steam.clone();
+        self.rt.spawn_blocking(move || {
+            let result = SteamInstall::find(&steam_settings).and_then(|install| {
+                wait_for_pending_collections(&install, PENDING_COLLECTIONS_POLL, |pending| {
+                    let _ = sender.send(PendingCollectionsStatus::Waiting {
+                        users: pending.users().count(),
+                    });
+                })
+            });
+            let status = match result {
+                Ok(users) => PendingCollectionsStatus::Written { users: users.len() },
+                Err(err) => PendingCollectionsStatus::Failed {
+                    error: err.to_string(),
+                },
+            };
+            let _ = sender.send(status);
+        });
+    }
+
+    pub(crate) fn render_pending_collections(&self, ui: &mut egui::Ui) {
+        match &*self.pending_collections.borrow() {
+            PendingCollectionsStatus::Nothing => {}
+            PendingCollectionsStatus::Waiting { users } => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Waiting for Steam to exit to write the collections of {users} user(s)"));
+                })
+                .response
+                .on_hover_text("Steam keeps its collections locked while it runs, they are also written by the next import");
+            }
+            PendingCollectionsStatus::Written { users } => {
+                ui.label(format!("Wrote the pending collections of {users} user(s)"));
+            }
+            PendingCollectionsStatus::Failed { error } => {
+                ui.label("Could not write the pending collections")
+                    .on_hover_text(format!("Error message: {error}"));
+            }
+        }
+    }
+
+    pub(crate) fn render_collection_rules_settings(&mut self, ui: &mut egui::Ui) {
+        if !self.settings.steam.create_collections {
+            return;
+        }
+        ui.heading("Collection rules");
+        ui.label("Put the games matching a rule in a Steam collection, without rules every platform gets its own collection");
+        ui.label("{platform}, {runner} and {tag} in the collection name are replaced with the values of each game, e.g. \"Lutris - {runner}\"");
+        let mut platform_names: Vec<String> = self
+            .platforms
+            .iter()
+            .map(|platform| platform.name().to_string())
+            .collect();
+        if self.settings.steam.collect_installed_games {
+            platform_names.push(sync::STEAM_GAMES_PLATFORM.to_string());
+        }
+        ui.checkbox(
+            &mut self.settings.steam.collect_installed_games,
+            "Put installed Steam games in the collections too",
+        )
+        .on_hover_text("The games Steam installed have the platform \"Steam\" and can be matched by library folder");
+        let all_tags = self.collection_tags.all_tags();
+        let mut removed = None;
+        for (index, rule) in self.settings.collection_rules.iter_mut().enumerate() {
+            ui.push_id(index, |ui| {
+                ui.group(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut rule.enabled, "");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut rule.name).hint_text("Collection name"),
+                        );
+                        if ui.button("Remove").clicked() {
+                            removed = Some(index);
+                        }
+                    });
+                    render_rule(ui, rule, &platform_names, &all_tags);
+                });
+            });
+        }
+        if let Some(index) = removed {
+            self.settings.collection_rules.remove(index);
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Add rule").clicked() {
+                self.settings.collection_rules.push(CollectionRule::default());
+            }
+            if self.settings.collection_rules.is_empty()
+                && ui
+                    .button("Start from the platform collections")
+                    .on_hover_text("Adds the rule BoilR uses when there are none, so more rules can be added next to it")
+                    .clicked()
+            {
+                self.settings
+                    .collection_rules
+                    .push(CollectionRule::per_platform());
+            }
+        });
+        ui.collapsing("Preview collections", |ui| {
+            self.render_collection_rules_preview(ui);
+        });
+        ui.add_space(SECTION_SPACING);
+    }
+
+    fn render_collection_rules_preview(&mut self, ui: &mut egui::Ui) {
+        let games = get_all_games(&self.games_to_sync);
+        if games.is_empty() {
+            ui.label("Find games on the import page to see which collections the rules make");
+            return;
+        }
+        let code_names: HashMap<String, String> = self
+            .platforms
+            .iter()
+            .map(|platform| (platform.name().to_string(), platform.code_name().to_string()))
+            .collect();
+        let rules = CollectionRules::new(
+            &self.settings.collection_rules,
+            self.collection_tags.clone(),
+            code_names,
+        );
+        let mut games =
+            sync::collection_games(&self.settings, &games, &self.rename_map, &HashMap::new());
+        if self.settings.steam.collect_installed_games {
+            let steam_games = self
+                .steam_collection_games
+                .get_or_insert_with(|| sync::installed_collection_games(&self.settings));
+            games.extend(steam_games.iter().cloned());
+        }
+        for collection in rules.preview(&games) {
+            let kind = if collection.existing {
+                "existing collection"
+            } else {
+                "collection"
+            };
+            ui.collapsing(
+                format!("{} ({kind}, {} games)", collection.name, collection.games.len()),
+                |ui| {
+                    for game in collection.games {
+                        ui.label(format!("{} ({})", game.name, game.platform));
+                    }
+                },
+            );
+        }
+    }
+}
+
+fn render_rule(
+    ui: &mut egui::Ui,
+    rule: &mut CollectionRule,
+    platform_names: &[String],
+    all_tags: &[String],
+) {
+    egui::Grid::new("collection_rule").num_columns(2).show(ui, |ui| {
+        ui.label("Platform");
+        egui::ComboBox::from_id_salt("platform")
+            .selected_text(rule.platform.as_deref().unwrap_or("Any"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut rule.platform, None, "Any");
+                for name in platform_names {
+                    ui.selectable_value(&mut rule.platform, Some(name.clone()), name);
+                }
+            });
+        ui.end_row();
+        optional_text(ui, "Runner", &mut rule.runner, "Any runner, e.g. wine, legendary or gog");
+        ui.label("Runs with Proton");
+        let selected = match rule.needs_proton {
+            None => "Any",
+            Some(true) => "Yes",
+            Some(false) => "No",
+        };
+        egui::ComboBox::from_id_salt("needs_proton")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut rule.needs_proton, None, "Any");
+                ui.selectable_value(&mut rule.needs_proton, Some(true), "Yes");
+                ui.selectable_value(&mut rule.needs_proton, Some(false), "No");
+            });
+        ui.end_row();
+        optional_text(ui, "Name matches", &mut rule.name_regex, "Any name, e.g. ^Fortnite$");
+        ui.label("Tag");
+        egui::ComboBox::from_id_salt("tag")
+            .selected_text(rule.tag.as_deref().unwrap_or("Any"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut rule.tag, None, "Any");
+                for tag in all_tags {
+                    ui.selectable_value(&mut rule.tag, Some(tag.clone()), tag);
+                }
+            });
+        ui.end_row();
+        optional_text(ui, "Library", &mut rule.library, "Any library, e.g. /run/media/mmcblk0p1");
+        ui.label("");
+        ui.checkbox(&mut rule.existing, "Add to an existing collection")
+            .on_hover_text("Adds the games to the collection with this name that you made in Steam, BoilR does not remove it or the games already in it");
+        ui.end_row();
+    });
+}
+
+/// Shows the tags of the game being edited, saves them when the user is done
+pub(crate) fn render_tag_editor(
+    ui: &mut egui::Ui,
+    tag_edit: &mut Option<TagEdit>,
+    collection_tags: &mut CollectionTags,
+) {
+    let Some(edit) = tag_edit.as_mut() else {
+        return;
+    };
+    let mut save = None;
+    ui.indent(&edit.game, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Tags");
+            ui.add(
+                egui::TextEdit::singleline(&mut edit.text)
+                    .hint_text("Comma separated, used by the collection rules"),
+            );
+            if ui.button("Save").clicked() {
+                save = Some(true);
+            }
+            if ui.button("Cancel").clicked() {
+                save = Some(false);
+            }
+        });
+    });
+    match save {
+        Some(true) => {
+            collection_tags.set(&edit.game, edit.tags());
+            *tag_edit = None;
+            if let Err(err) = collection_tags.save() {
+                eprintln!("Could not save collection tags: {err:?}");
+            }
+        }
+        Some(false) => *tag_edit = None,
+        None => {}
+    }
+}
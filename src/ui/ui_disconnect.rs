@@ -5,12 +5,15 @@ use super::MyEguiApp;
 use crate::steam::get_shortcuts_for_user;
 use crate::steam::get_shortcuts_paths;
 use crate::steam::ShortcutInfo;
+use crate::sync::adopt_shortcut;
 use crate::sync::disconnect_shortcut;
+use crate::sync::AdoptedShortcuts;
 use crate::sync::IsBoilRShortcut;
 
 #[derive(Default)]
 pub struct DisconnectState {
     pub connected_shortcuts: Option<Result<Vec<ShortcutInfo>, String>>,
+    pub adopted_shortcuts: Option<AdoptedShortcuts>,
 }
 
 impl MyEguiApp {
@@ -47,8 +50,13 @@ impl MyEguiApp {
             Ok(users) => {
                 let has_multiple_users = users.len() > 1;
                 let mut redraw = 0;
+                let mut adopted = 0;
+                let adopted_shortcuts = self
+                    .disconnect_state
+                    .adopted_shortcuts
+                    .get_or_insert_with(AdoptedShortcuts::load);
                 set_scroll_style(ui);
-                ScrollArea::vertical()
+                let adopted_changed = ScrollArea::vertical()
                     .stick_to_right(true)
                     .auto_shrink([false, true])
                     .show(ui, |ui| {
@@ -67,10 +75,50 @@ impl MyEguiApp {
                                 }
                             }
                         }
-                    });
+
+                        ui.add_space(super::SECTION_SPACING);
+                        ui.heading("Adopt shortcuts");
+                        ui.label("In this section you can let BoilR manage a shortcut you made yourself.");
+                        ui.label("BoilR downloads its images, puts it in the collections and sets its compatibility tool, but never removes it.");
+                        ui.add_space(super::SECTION_SPACING);
+                        for user in users.iter() {
+                            if has_multiple_users {
+                                ui.heading(user.path.to_string_lossy().to_string());
+                            }
+                            for shortcut in user.shortcuts.iter() {
+                                if !shortcut.is_boilr_shortcut()
+                                    && ui
+                                        .button(&shortcut.app_name)
+                                        .on_hover_text(&shortcut.exe)
+                                        .clicked()
+                                {
+                                    match adopt_shortcut(&self.settings, shortcut.app_id) {
+                                        Ok(_) => adopted = shortcut.app_id,
+                                        Err(err) => eprintln!("Could not adopt {}: {err}", shortcut.app_name),
+                                    }
+                                }
+                            }
+                        }
+                        #[cfg(target_family = "unix")]
+                        let adopted_changed =
+                            render_adopted_tools(ui, adopted_shortcuts, &self.compat_tools);
+                        #[cfg(not(target_family = "unix"))]
+                        let adopted_changed = false;
+                        adopted_changed
+                    })
+                    .inner;
+                if adopted_changed {
+                    if let Err(err) = adopted_shortcuts.save() {
+                        eprintln!("Could not save adopted shortcuts: {err}");
+                    }
+                }
                 if redraw != 0 {
                     self.disconnect_state.connected_shortcuts = None;
+                    self.disconnect_state.adopted_shortcuts = None;
                     self.settings.blacklisted_games.push(redraw);
+                } else if adopted != 0 {
+                    self.disconnect_state.connected_shortcuts = None;
+                    self.disconnect_state.adopted_shortcuts = None;
                 }
             }
             Err(msg) => {
@@ -80,6 +128,54 @@ impl MyEguiApp {
     }
 }
 
+/// Lets the user pick the compatibility tool of each adopted shortcut, returns true if one changed
+#[cfg(target_family = "unix")]
+fn render_adopted_tools(
+    ui: &mut egui::Ui,
+    adopted_shortcuts: &mut AdoptedShortcuts,
+    tools: &[crate::steam::CompatTool],
+) -> bool {
+    let adopted: Vec<(u32, String, Option<String>)> = adopted_shortcuts
+        .iter()
+        .map(|(app_id, adopted)| (*app_id, adopted.name.clone(), adopted.compat_tool.clone()))
+        .collect();
+    if adopted.is_empty() {
+        return false;
+    }
+    let mut changed = false;
+    ui.add_space(super::SECTION_SPACING);
+    ui.heading("Adopted shortcuts");
+    egui::Grid::new("adopted_tools").num_columns(2).show(ui, |ui| {
+        for (app_id, name, compat_tool) in adopted {
+            let mut tool = compat_tool.clone().unwrap_or_default();
+            ui.label(name);
+            let selected = if tool.is_empty() {
+                "Keep the tool Steam has".to_string()
+            } else {
+                tools
+                    .iter()
+                    .find(|compat_tool| compat_tool.name == tool)
+                    .map(|compat_tool| compat_tool.display_name.clone())
+                    .unwrap_or_else(|| tool.clone())
+            };
+            egui::ComboBox::from_id_salt(("adopted_tool", app_id))
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut tool, String::new(), "Keep the tool Steam has");
+                    for compat_tool in tools {
+                        ui.selectable_value(&mut tool, compat_tool.name.clone(), &compat_tool.display_name);
+                    }
+                });
+            if compat_tool.unwrap_or_default() != tool {
+                adopted_shortcuts.set_compat_tool(app_id, Some(tool));
+                changed = true;
+            }
+            ui.end_row();
+        }
+    });
+    changed
+}
+
 fn set_scroll_style(ui: &mut egui::Ui) {
     let scroll_style = ui.style_mut();
     scroll_style.visuals.extreme_bg_color = BACKGROUND_COLOR;
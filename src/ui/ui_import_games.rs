@@ -1,22 +1,20 @@
 use eframe::egui;
 use egui::ScrollArea;
-use futures::executor::block_on;
 use rfd::FileDialog;
 
-use steam_shortcuts_util::shortcut::ShortcutOwned;
 use tokio::sync::watch;
-use tokio::task::JoinHandle;
 
 use crate::config::get_renames_file;
 use crate::platforms::ShortcutToImport;
 use crate::game_scan::{scan_folder, ScannedGame};
-#[cfg(target_family = "unix")]
-use crate::steam::setup_proton_games;
 use crate::sync;
 
-use crate::sync::{download_images, SyncProgress};
+use crate::sync::{DuplicateDecision, DuplicateShortcut, SyncPreview, SyncProgress, SyncReport};
 
-use super::{all_ready, backup_shortcuts, get_all_games};
+use super::{
+    all_ready, get_all_games, get_failed_platforms, render_override_editor, render_tag_editor,
+    OverrideEdit, TagEdit,
+};
 use super::{
     ui_colors::{BACKGROUND_COLOR, EXTRA_BACKGROUND_COLOR},
     MyEguiApp,
@@ -75,6 +73,11 @@ impl MyEguiApp {
             ui.add_space(SECTION_SPACING);
             // === END: Scan Games Folder Section ===
             
+            self.render_preview(ui);
+            self.render_pending_collections(ui);
+            self.render_duplicates(ui);
+            ui.add_space(SECTION_SPACING);
+
             ui.label("Select the games you want to import into steam");
             for (name,status) in &self.games_to_sync{
                 ui.heading(name);
@@ -94,6 +97,7 @@ impl MyEguiApp {
                                 }
                                 for shortcut_to_import in shortcuts {
                                     let shortcut = &shortcut_to_import.shortcut;
+                                    let game_key = sync::game_key(name, shortcut_to_import);
                                     let mut import_game = !self.settings.blacklisted_games.contains(&shortcut.app_id);
                                     ui.horizontal(|ui|{
                                         if self.current_edit == Option::Some(shortcut.app_id){
@@ -127,12 +131,32 @@ impl MyEguiApp {
                                                     self.settings.blacklisted_games.retain(|id| *id != shortcut.app_id);
                                                 }
                                             }
+                                            let game_override = self.overrides.get(&game_key);
+                                            if game_override.is_some(){
+                                                ui.label("(customized)");
+                                            }
+                                            if ui.small_button("Customize").on_hover_text("Override the executable, start directory, launch options, icon or compatibility tool of this game").clicked(){
+                                                self.override_edit = Some(OverrideEdit::new(game_key.clone(), game_override));
+                                            }
+                                            let tags = self.collection_tags.get(&game_key);
+                                            if !tags.is_empty(){
+                                                ui.label(format!("[{}]", tags.join(", ")));
+                                            }
+                                            if ui.small_button("Tags").on_hover_text("Tag this game, collection rules can put tagged games in a collection").clicked(){
+                                                self.tag_edit = Some(TagEdit::new(game_key.clone(), tags));
+                                            }
                                         }
                                     });
+                                    if self.override_edit.as_ref().is_some_and(|edit| edit.game == game_key){
+                                        render_override_editor(ui, &mut self.override_edit, &mut self.overrides, shortcut_to_import, &self.compat_tools);
+                                    }
+                                    if self.tag_edit.as_ref().is_some_and(|edit| edit.game == game_key){
+                                        render_tag_editor(ui, &mut self.tag_edit, &mut self.collection_tags);
+                                    }
                                 }
                             },
                             Err(err) => {
-                                ui.label("Failed finding games").on_hover_text(format!("Error message: {err}"));
+                                ui.label("Failed finding games, the shortcuts from the last import are kept").on_hover_text(format!("Error message: {err}"));
                             },
                         };
                     },
@@ -145,6 +169,128 @@ impl MyEguiApp {
         });
     }
 
+    fn render_preview(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Preview changes")
+            .id_salt("import_preview")
+            .show(ui, |ui| {
+                ui.label("See what an import would change in Steam, nothing is written");
+                let games_ready = all_ready(&self.games_to_sync);
+                let mut refresh = false;
+                match &*self.preview.borrow() {
+                    FetchStatus::NeedsFetched => {
+                        refresh = preview_button(ui, "Preview changes", games_ready);
+                    }
+                    FetchStatus::Fetching => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Comparing with the current shortcuts");
+                        });
+                    }
+                    FetchStatus::Fetched(preview) => {
+                        refresh = preview_button(ui, "Refresh preview", games_ready);
+                        match preview {
+                            Ok(preview) => render_sync_preview(ui, preview),
+                            Err(err) => {
+                                ui.label("Could not preview the import")
+                                    .on_hover_text(format!("Error message: {err}"));
+                            }
+                        }
+                    }
+                }
+                if refresh {
+                    self.preview_sync_async();
+                }
+            });
+    }
+
+    fn render_duplicates(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Duplicate shortcuts")
+            .id_salt("import_duplicates")
+            .show(ui, |ui| {
+                ui.label("Shortcuts you made yourself for games BoilR imports");
+                let mut refresh = false;
+                let mut decided = None;
+                match &*self.duplicates.borrow() {
+                    FetchStatus::NeedsFetched => {
+                        refresh = ui.button("Find duplicates").clicked();
+                    }
+                    FetchStatus::Fetching => {
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.label("Comparing with the current shortcuts");
+                        });
+                    }
+                    FetchStatus::Fetched(duplicates) => {
+                        refresh = ui.button("Find duplicates again").clicked();
+                        match duplicates {
+                            Ok(duplicates) => {
+                                if duplicates.is_empty() {
+                                    ui.label("No duplicates found");
+                                }
+                                for duplicate in duplicates {
+                                    if let Some(decision) =
+                                        render_duplicate(ui, duplicate, &self.duplicate_decisions)
+                                    {
+                                        decided = Some((duplicate.game.clone(), decision));
+                                    }
+                                }
+                            }
+                            Err(err) => {
+                                ui.label("Could not look for duplicates")
+                                    .on_hover_text(format!("Error message: {err}"));
+                            }
+                        }
+                    }
+                }
+                if let Some((game, decision)) = decided {
+                    self.duplicate_decisions.set(&game, decision);
+                    if let Err(err) = self.duplicate_decisions.save() {
+                        eprintln!("Could not save duplicate decisions: {err}");
+                    }
+                    self.preview = watch::channel(FetchStatus::NeedsFetched).1;
+                }
+                if refresh {
+                    self.find_duplicates_async();
+                }
+            });
+    }
+
+    fn find_duplicates_async(&mut self) {
+        if !all_ready(&self.games_to_sync) {
+            return;
+        }
+        let (sender, reciever) = watch::channel(FetchStatus::Fetching);
+        self.duplicates = reciever;
+        let settings = self.settings.clone();
+        let renames = self.rename_map.clone();
+        let shortcuts_to_import = get_all_games(&self.games_to_sync);
+        self.rt.spawn_blocking(move || {
+            let duplicates = sync::find_all_duplicates(&settings, &shortcuts_to_import, &renames);
+            let _ = sender.send(FetchStatus::Fetched(duplicates));
+        });
+    }
+
+    fn preview_sync_async(&mut self) {
+        if !all_ready(&self.games_to_sync) {
+            return;
+        }
+        let (sender, reciever) = watch::channel(FetchStatus::Fetching);
+        self.preview = reciever;
+        let settings = self.settings.clone();
+        let renames = self.rename_map.clone();
+        let shortcuts_to_import = get_all_games(&self.games_to_sync);
+        let failed_platforms = get_failed_platforms(&self.games_to_sync);
+        self.rt.spawn_blocking(move || {
+            let preview = sync::preview_sync(
+                &settings,
+                &shortcuts_to_import,
+                &failed_platforms,
+                &renames,
+            );
+            let _ = sender.send(FetchStatus::Fetched(preview));
+        });
+    }
+
     /// Add scanned games to the import system so they use the same workflow
     fn add_scanned_games_to_import(&mut self, scanned_games: Vec<ScannedGame>) {
         use steam_shortcuts_util::shortcut::Shortcut;
@@ -169,6 +315,10 @@ impl MyEguiApp {
                 shortcut: shortcut_owned,
                 needs_proton: false,
                 needs_symlinks: false,
+                game_id: None,
+                runner: None,
+                artwork: vec![],
+                legacy_app_id: None,
             }
         }).collect();
         
@@ -179,95 +329,127 @@ impl MyEguiApp {
         println!("Added {} scanned games to import system", shortcuts.len());
     }
 
-    pub fn run_sync_blocking(&mut self) -> eyre::Result<()> {
-        self.run_sync(true)
-    }
-
     pub fn run_sync_async(&mut self) {
-        let _ = self.run_sync(false);
-    }
-    fn run_sync(&mut self, wait: bool) -> eyre::Result<()> {
+        self.preview = watch::channel(FetchStatus::NeedsFetched).1;
         let (sender, reciever) = watch::channel(SyncProgress::NotStarted);
         let settings = self.settings.clone();
-        if settings.steam.stop_steam {
-            crate::steam::ensure_steam_stopped();
-        }
-
         self.status_reciever = reciever;
         let renames = self.rename_map.clone();
-        let all_ready = all_ready(&self.games_to_sync);
-        let _ = sender.send(SyncProgress::Starting);
-        if all_ready {
+        if all_ready(&self.games_to_sync) {
             let shortcuts_to_import = get_all_games(&self.games_to_sync);
-            let handle: JoinHandle<eyre::Result<()>> = self.rt.spawn_blocking(move || {
-                #[cfg(target_family = "unix")]
-                setup_proton(shortcuts_to_import.iter());
-
-                let import_games = to_shortcut_owned(shortcuts_to_import);
-
-                let mut some_sender = Some(sender);
-                backup_shortcuts(&settings.steam);
-                let usersinfo =
-                    sync::sync_shortcuts(&settings, &import_games, &mut some_sender, &renames)?;
-                let task = download_images(&settings, &usersinfo, &mut some_sender);
-                block_on(task);
-                //Run a second time to fix up shortcuts after images are downloaded
-                if let Err(e) = sync::fix_all_shortcut_icons(&settings) {
-                    eprintln!("Could not fix shortcuts with error {e}");
-                }
-
-                if let Some(sender) = some_sender {
-                    let _ = sender.send(SyncProgress::Done);
+            let failed_platforms = get_failed_platforms(&self.games_to_sync);
+            let mut report = SyncReport::new();
+            for (name, status) in &self.games_to_sync {
+                if let FetchStatus::Fetched(result) = &*status.borrow() {
+                    report.add_platform(name, result);
                 }
-                if settings.steam.start_steam {
-                    crate::steam::ensure_steam_started(&settings.steam);
+            }
+            self.rt.spawn_blocking(move || {
+                let mut some_sender = Some(sender);
+                if let Err(err) = sync::run_sync(
+                    &settings,
+                    shortcuts_to_import,
+                    &failed_platforms,
+                    &renames,
+                    &mut some_sender,
+                    &mut report,
+                ) {
+                    eprintln!("Failed to import games: {err:?}");
                 }
-                Ok(())
             });
-            if wait {
-                self.rt.block_on(handle)??;
-            }
         }
-        Ok(())
     }
 }
 
-fn to_shortcut_owned(
-    shortcuts_to_import: Vec<(String, Vec<ShortcutToImport>)>,
-) -> Vec<(String, Vec<ShortcutOwned>)> {
-    let mut import_games = vec![];
-    for (name, infos) in shortcuts_to_import {
-        let mut shortcuts = vec![];
-        for info in infos {
-            shortcuts.push(info.shortcut);
+/// Shows a duplicate with its decision, returns the decision the user picked, `Some(None)` asks again
+fn render_duplicate(
+    ui: &mut egui::Ui,
+    duplicate: &DuplicateShortcut,
+    decisions: &sync::DuplicateDecisions,
+) -> Option<Option<DuplicateDecision>> {
+    let current = decisions.get(&duplicate.game);
+    let mut picked = None;
+    ui.horizontal(|ui| {
+        ui.label(format!("Steam user {}: {duplicate}", duplicate.user_id))
+            .on_hover_text(format!("Shortcut target: {}", duplicate.shortcut_exe));
+        for (decision, label, hover) in [
+            (
+                DuplicateDecision::Adopt,
+                "Adopt",
+                "BoilR takes over your shortcut, it keeps its images",
+            ),
+            (
+                DuplicateDecision::Skip,
+                "Skip",
+                "Keep your shortcut and do not import this game",
+            ),
+            (
+                DuplicateDecision::KeepBoth,
+                "Keep both",
+                "Keep your shortcut and import the game next to it",
+            ),
+        ] {
+            if ui
+                .selectable_label(current == Some(decision), label)
+                .on_hover_text(hover)
+                .clicked()
+            {
+                picked = Some(Some(decision));
+            }
         }
-        import_games.push((name, shortcuts));
-    }
-    import_games
+        if current.is_some() && ui.small_button("Ask again").clicked() {
+            picked = Some(None);
+        }
+    });
+    picked
 }
 
-#[cfg(target_family = "unix")]
-fn setup_proton<'a, I>(shortcut_infos: I)
-where
-    I: IntoIterator<Item = &'a (String, Vec<ShortcutToImport>)>,
-{
-    let mut shortcuts_to_proton = vec![];
+/// The preview needs every platform to have found its games, until then the button is disabled
+fn preview_button(ui: &mut egui::Ui, text: &str, games_ready: bool) -> bool {
+    let clicked = ui
+        .add_enabled(games_ready, egui::Button::new(text))
+        .on_disabled_hover_text("Still finding games")
+        .clicked();
+    if !games_ready {
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label("Still finding games");
+        });
+    }
+    clicked
+}
 
-    for (name, shortcuts) in shortcut_infos {
-        for shortcut_info in shortcuts {
-            if shortcut_info.needs_proton {
-                crate::sync::symlinks::ensure_links_folder_created(name);
+fn render_sync_preview(ui: &mut egui::Ui, preview: &SyncPreview) {
+    if !preview.has_changes() {
+        ui.label("No changes");
+    }
+    for user in &preview.users {
+        ui.label(format!(
+            "Steam user {}: {} shortcuts -> {} shortcuts",
+            user.user_id, user.shortcuts_before, user.shortcuts_after
+        ));
+        ui.indent(&user.user_id, |ui| {
+            for change in &user.shortcut_changes {
+                ui.label(change.to_string());
             }
-            if shortcut_info.needs_proton {
-                shortcuts_to_proton.push(format!("{}", shortcut_info.shortcut.app_id));
+            for change in &user.collection_changes {
+                ui.label(change.to_string());
             }
-
-            if shortcut_info.needs_symlinks {
-                crate::sync::symlinks::create_sym_links(&shortcut_info.shortcut);
+            if let Some(err) = &user.collections_error {
+                ui.label("Could not read the current collections, is Steam running?")
+                    .on_hover_text(format!("Error message: {err}"));
             }
-        }
-        if let Err(err) = setup_proton_games(&shortcuts_to_proton){
-            eprintln!("failed to save proton settings: {err:?}");
-        }
+            for duplicate in &user.duplicates {
+                ui.label(format!("? {duplicate}"))
+                    .on_hover_text("Decide what to do with it under duplicate shortcuts");
+            }
+        });
+    }
+    for change in &preview.proton.mapping_changes {
+        ui.label(change.to_string());
+    }
+    if let Some(err) = &preview.proton.error {
+        ui.label("Could not read the proton mappings")
+            .on_hover_text(format!("Error message: {err}"));
     }
 }
@@ -0,0 +1,124 @@
+use std::collections::HashMap;
+
+use eframe::egui;
+
+use crate::sync::{self, LaunchRule};
+
+use super::{get_all_games, ui_settings::SECTION_SPACING, MyEguiApp};
+
+impl MyEguiApp {
+    pub(crate) fn render_launch_rules_settings(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Launch rules");
+        ui.label("Change the executable or launch options of every game matching a rule, rules are applied in order before the overrides of a game");
+        ui.label("{exe} and {launch_options} are replaced with what the platform found, e.g. \"mangohud %command% {launch_options}\"");
+        let platform_names: Vec<String> = self
+            .platforms
+            .iter()
+            .map(|platform| platform.name().to_string())
+            .collect();
+        let mut removed = None;
+        for (index, rule) in self.settings.launch_rules.iter_mut().enumerate() {
+            ui.push_id(index, |ui| {
+                ui.group(|ui| {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut rule.enabled, "");
+                        ui.add(egui::TextEdit::singleline(&mut rule.name).hint_text("Rule name"));
+                        if ui.button("Remove").clicked() {
+                            removed = Some(index);
+                        }
+                    });
+                    render_rule(ui, rule, &platform_names);
+                });
+            });
+        }
+        if let Some(index) = removed {
+            self.settings.launch_rules.remove(index);
+        }
+        if ui.button("Add rule").clicked() {
+            self.settings.launch_rules.push(LaunchRule {
+                name: format!("Rule {}", self.settings.launch_rules.len() + 1),
+                ..Default::default()
+            });
+        }
+        if !self.settings.launch_rules.is_empty() {
+            ui.collapsing("Preview launch rules", |ui| {
+                self.render_launch_rules_preview(ui);
+            });
+        }
+        ui.add_space(SECTION_SPACING);
+    }
+
+    fn render_launch_rules_preview(&self, ui: &mut egui::Ui) {
+        let games = get_all_games(&self.games_to_sync);
+        if games.is_empty() {
+            ui.label("Find games on the import page to see which games the rules match");
+            return;
+        }
+        let code_names: HashMap<String, String> = self
+            .platforms
+            .iter()
+            .map(|platform| (platform.name().to_string(), platform.code_name().to_string()))
+            .collect();
+        for rule in sync::preview_launch_rules(&self.settings.launch_rules, &games, &code_names) {
+            match rule.error {
+                Some(error) => {
+                    ui.label(format!("{}: {error}", rule.rule));
+                }
+                None => {
+                    ui.label(format!("{}: {} game(s)", rule.rule, rule.games.len()));
+                }
+            }
+            for game in rule.games {
+                ui.label(format!(
+                    "    {} ({}): {} {}",
+                    game.name, game.platform, game.exe, game.launch_options
+                ));
+            }
+        }
+    }
+}
+
+fn render_rule(ui: &mut egui::Ui, rule: &mut LaunchRule, platform_names: &[String]) {
+    egui::Grid::new("launch_rule").num_columns(2).show(ui, |ui| {
+        ui.label("Platform");
+        egui::ComboBox::from_id_salt("platform")
+            .selected_text(rule.platform.as_deref().unwrap_or("Any"))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut rule.platform, None, "Any");
+                for name in platform_names {
+                    ui.selectable_value(&mut rule.platform, Some(name.clone()), name);
+                }
+            });
+        ui.end_row();
+        ui.label("Runs with Proton");
+        let selected = match rule.needs_proton {
+            None => "Any",
+            Some(true) => "Yes",
+            Some(false) => "No",
+        };
+        egui::ComboBox::from_id_salt("needs_proton")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut rule.needs_proton, None, "Any");
+                ui.selectable_value(&mut rule.needs_proton, Some(true), "Yes");
+                ui.selectable_value(&mut rule.needs_proton, Some(false), "No");
+            });
+        ui.end_row();
+        optional_text(ui, "Name matches", &mut rule.name_regex, "Any name, e.g. ^Fortnite$");
+        optional_text(ui, "Executable matches", &mut rule.exe_regex, "Any executable");
+        optional_text(ui, "Executable", &mut rule.exe, "{exe}");
+        optional_text(ui, "Launch options", &mut rule.launch_options, "{launch_options}");
+    });
+}
+
+pub(super) fn optional_text(ui: &mut egui::Ui, label: &str, value: &mut Option<String>, hint: &str) {
+    ui.label(label);
+    let mut text = value.clone().unwrap_or_default();
+    if ui
+        .add(egui::TextEdit::singleline(&mut text).hint_text(hint))
+        .changed()
+    {
+        *value = if text.is_empty() { None } else { Some(text) };
+    }
+    ui.end_row();
+}
@@ -0,0 +1,132 @@
+use eframe::egui;
+
+use crate::platforms::ShortcutToImport;
+use crate::steam::CompatTool;
+use crate::sync::{GameOverride, GameOverrides, OverrideField, OVERRIDE_FIELDS};
+
+/// The override of a game that is being edited on the import page
+pub struct OverrideEdit {
+    pub game: String,
+    values: Vec<(OverrideField, String)>,
+}
+
+impl OverrideEdit {
+    pub fn new(game: String, game_override: Option<&GameOverride>) -> Self {
+        let values = OVERRIDE_FIELDS
+            .iter()
+            .map(|field| {
+                let value = game_override
+                    .and_then(|o| o.get(*field).clone())
+                    .unwrap_or_default();
+                (*field, value)
+            })
+            .collect();
+        Self { game, values }
+    }
+
+    fn to_override(&self) -> GameOverride {
+        let mut game_override = GameOverride::default();
+        for (field, value) in &self.values {
+            if !value.trim().is_empty() {
+                *game_override.get_mut(*field) = Some(value.clone());
+            }
+        }
+        game_override
+    }
+}
+
+enum OverrideAction {
+    Save,
+    Clear,
+    Cancel,
+}
+
+/// Shows the fields of the override being edited, saves it when the user is done
+pub(crate) fn render_override_editor(
+    ui: &mut egui::Ui,
+    override_edit: &mut Option<OverrideEdit>,
+    overrides: &mut GameOverrides,
+    shortcut_to_import: &ShortcutToImport,
+    compat_tools: &[CompatTool],
+) {
+    let Some(edit) = override_edit.as_mut() else {
+        return;
+    };
+    let found = &shortcut_to_import.shortcut;
+    let mut action = None;
+    let id = edit.game.clone();
+    ui.indent(&id, |ui| {
+        ui.label("Leave a field empty to use what the platform found, {exe} and {launch_options} are replaced with the values the platform found");
+        egui::Grid::new(&id).num_columns(2).show(ui, |ui| {
+            for (field, value) in edit.values.iter_mut() {
+                let found_value = match field {
+                    OverrideField::Exe => found.exe.as_str(),
+                    OverrideField::StartDir => found.start_dir.as_str(),
+                    OverrideField::LaunchOptions => found.launch_options.as_str(),
+                    OverrideField::Icon => found.icon.as_str(),
+                    OverrideField::CompatTool => "The tool of the platform",
+                };
+                ui.label(field_label(*field));
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(value).hint_text(found_value));
+                    if *field == OverrideField::CompatTool && !compat_tools.is_empty() {
+                        egui::ComboBox::from_id_salt("compat_tool")
+                            .selected_text("Installed tools")
+                            .show_ui(ui, |ui| {
+                                for tool in compat_tools {
+                                    ui.selectable_value(value, tool.name.clone(), &tool.display_name);
+                                }
+                            });
+                    }
+                });
+                ui.end_row();
+            }
+        });
+        let mut result = found.clone();
+        edit.to_override().apply(&mut result);
+        ui.label(format!(
+            "Steam will run: {} {}",
+            result.exe, result.launch_options
+        ));
+        ui.horizontal(|ui| {
+            if ui.button("Save").clicked() {
+                action = Some(OverrideAction::Save);
+            }
+            if ui.button("Clear").clicked() {
+                action = Some(OverrideAction::Clear);
+            }
+            if ui.button("Cancel").clicked() {
+                action = Some(OverrideAction::Cancel);
+            }
+        });
+    });
+    let game_override = match action {
+        Some(OverrideAction::Save) => edit.to_override(),
+        Some(OverrideAction::Clear) => GameOverride::default(),
+        Some(OverrideAction::Cancel) => {
+            *override_edit = None;
+            return;
+        }
+        None => return,
+    };
+    let game = edit.game.clone();
+    *override_edit = None;
+    save_override(overrides, &game, game_override);
+}
+
+fn save_override(overrides: &mut GameOverrides, game: &str, game_override: GameOverride) {
+    overrides.set(game, game_override);
+    if let Err(err) = overrides.save() {
+        eprintln!("Could not save game overrides: {err:?}");
+    }
+}
+
+fn field_label(field: OverrideField) -> &'static str {
+    match field {
+        OverrideField::Exe => "Executable",
+        OverrideField::StartDir => "Start directory",
+        OverrideField::LaunchOptions => "Launch options",
+        OverrideField::Icon => "Icon",
+        OverrideField::CompatTool => "Compatibility tool",
+    }
+}
@@ -2,6 +2,8 @@ use copypasta::ClipboardProvider;
 use eframe::egui;
 use egui::ScrollArea;
 
+use crate::steam::SteamInstall;
+
 use super::{
     ui_colors::{BACKGROUND_COLOR, EXTRA_BACKGROUND_COLOR},
     MyEguiApp,
@@ -30,6 +32,14 @@ impl MyEguiApp {
 
                 self.render_steam_settings(ui);
 
+                self.render_steam_users_settings(ui);
+
+                self.render_launch_rules_settings(ui);
+
+                self.render_collection_rules_settings(ui);
+
+                self.render_backup_settings(ui);
+
                 for platform in &mut self.platforms {
                     platform.render_ui(ui);
                     ui.add_space(SECTION_SPACING);
@@ -57,22 +67,130 @@ impl MyEguiApp {
                 }
             }
         });
+        match SteamInstall::find(&self.settings.steam) {
+            Ok(install) if install.path.exists() => {
+                ui.label(format!("Using {} Steam at {}", install.kind.name(), install.path.display()));
+            }
+            _ => {
+                ui.label("Steam was not found, set its location above");
+            }
+        }
         ui.checkbox(
             &mut self.settings.steam.create_collections,
             "Create collections",
         )
-        .on_hover_text("Tries to create a games collection for each platform");
+        .on_hover_text("Tries to create a games collection for each platform, or the collections of the collection rules");
         ui.checkbox(&mut self.settings.steam.optimize_for_big_picture, "Optimize for big picture").on_hover_text("Set icons to be larger horizontal images, this looks nice in steam big picture mode, but a bit off in desktop mode");
         ui.checkbox(
             &mut self.settings.steam.stop_steam,
             "Stop Steam before import",
         )
         .on_hover_text("Stops Steam if it is running when import starts");
+        if self.settings.steam.stop_steam {
+            ui.horizontal(|ui| {
+                ui.label("Seconds to wait for Steam to shut down");
+                ui.add(egui::DragValue::new(&mut self.settings.steam.shutdown_timeout).range(1..=600))
+                    .on_hover_text("Steam is asked to shut down, if it is still running after this it is stopped forcefully");
+            });
+        }
         ui.checkbox(
             &mut self.settings.steam.start_steam,
             "Start Steam after import",
         )
-        .on_hover_text("Starts Steam is it is not running after the import");
+        .on_hover_text("Starts Steam is it is not running after the import, in Big Picture mode if it was running in it before");
+        ui.collapsing("Fields BoilR overwrites on import", |ui| {
+            ui.label("Unchecked fields keep what you changed in Steam");
+            let ownership = &mut self.settings.steam.field_ownership;
+            egui::Grid::new("field_ownership").show(ui, |ui| {
+                ui.checkbox(&mut ownership.start_dir, "Start directory");
+                ui.checkbox(&mut ownership.launch_options, "Launch options");
+                ui.checkbox(&mut ownership.icon, "Icon");
+                ui.end_row();
+                ui.checkbox(&mut ownership.tags, "Tags");
+                ui.checkbox(&mut ownership.is_hidden, "Hidden");
+                ui.checkbox(&mut ownership.allow_overlay, "Steam overlay");
+                ui.end_row();
+                ui.checkbox(&mut ownership.allow_desktop_config, "Desktop configuration");
+                ui.checkbox(&mut ownership.open_vr, "VR library");
+                ui.checkbox(&mut ownership.last_play_time, "Last played");
+                ui.end_row();
+            });
+        });
+        #[cfg(target_family = "unix")]
+        ui.collapsing("Proton", |ui| {
+            self.render_proton_settings(ui);
+        });
+        ui.add_space(SECTION_SPACING);
+    }
+
+    #[cfg(target_family = "unix")]
+    fn render_proton_settings(&mut self, ui: &mut egui::Ui) {
+        ui.label("The compatibility tool games that need Proton run with, a tool chosen when customizing a game is used before these");
+        let proton = &mut self.settings.steam.proton;
+        let tools = &self.compat_tools;
+        egui::Grid::new("proton_tools").num_columns(2).show(ui, |ui| {
+            ui.label("Default");
+            let selected = tool_display_name(tools, &proton.default_tool);
+            egui::ComboBox::from_id_salt("default_tool")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    if !tools.iter().any(|tool| tool.name == "proton_experimental") {
+                        ui.selectable_value(
+                            &mut proton.default_tool,
+                            "proton_experimental".to_string(),
+                            "Proton - Experimental",
+                        );
+                    }
+                    for tool in tools {
+                        ui.selectable_value(&mut proton.default_tool, tool.name.clone(), &tool.display_name);
+                    }
+                });
+            ui.end_row();
+            for platform in &self.platforms {
+                let code_name = platform.code_name().to_string();
+                let mut tool = proton.platform_tools.get(&code_name).cloned().unwrap_or_default();
+                ui.label(platform.name());
+                let selected = if tool.is_empty() {
+                    "Default".to_string()
+                } else {
+                    tool_display_name(tools, &tool)
+                };
+                egui::ComboBox::from_id_salt(&code_name)
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut tool, String::new(), "Default");
+                        for compat_tool in tools {
+                            ui.selectable_value(&mut tool, compat_tool.name.clone(), &compat_tool.display_name);
+                        }
+                    });
+                if tool.is_empty() {
+                    proton.platform_tools.remove(&code_name);
+                } else {
+                    proton.platform_tools.insert(code_name, tool);
+                }
+                ui.end_row();
+            }
+        });
+        if tools.is_empty() {
+            ui.label("No compatibility tools were found in the Steam libraries or compatibilitytools.d");
+        }
+    }
+
+    fn render_backup_settings(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Backups");
+        ui.horizontal(|ui| {
+            ui.label("Backups to keep:");
+            ui.add(egui::DragValue::new(&mut self.settings.backup.keep_last).range(0..=1000));
+        })
+        .response
+        .on_hover_text("The oldest backups are removed when there are more than this, 0 keeps all of them");
+        ui.horizontal(|ui| {
+            ui.label("Days to keep backups:");
+            ui.add(egui::DragValue::new(&mut self.settings.backup.keep_days).range(0..=3650));
+        })
+        .response
+        .on_hover_text("Backups older than this are removed, 0 keeps them forever");
+        ui.label("Labeled backups are never removed");
         ui.add_space(SECTION_SPACING);
     }
 
@@ -116,9 +234,28 @@ impl MyEguiApp {
             ui.checkbox(
                 &mut self.settings.steamgrid_db.only_download_boilr_images,
                 "Only download images for BoilR shortcuts",
-            );
+            )
+            .on_hover_text("Shortcuts adopted on the disconnect page count as BoilR shortcuts");
             ui.checkbox(&mut self.settings.steamgrid_db.allow_nsfw, "Allow NSFW images");
+            ui.checkbox(
+                &mut self.settings.steamgrid_db.remove_images_of_uninstalled_games,
+                "Remove images of uninstalled games",
+            )
+            .on_hover_text("Deletes the images of a shortcut when BoilR removes it, because the game is no longer installed");
         }
+        ui.checkbox(
+            &mut self.settings.steamgrid_db.use_platform_images,
+            "Use images from the platforms",
+        )
+        .on_hover_text("Uses the covers and icons Itch, Heroic, GOG Galaxy and Uplay have for a game, SteamGridDB is only searched for the images they do not have");
         ui.add_space(SECTION_SPACING);
     }
 }
+
+#[cfg(target_family = "unix")]
+fn tool_display_name(tools: &[crate::steam::CompatTool], name: &str) -> String {
+    tools
+        .iter()
+        .find(|tool| tool.name == name)
+        .map_or_else(|| name.to_string(), |tool| tool.display_name.clone())
+}
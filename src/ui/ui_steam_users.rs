@@ -0,0 +1,106 @@
+use eframe::egui;
+
+use crate::steam::{SteamUserSettings, SteamUsersInfo};
+
+use super::{get_all_games, ui_settings::SECTION_SPACING, MyEguiApp};
+
+impl MyEguiApp {
+    pub(crate) fn render_steam_users_settings(&mut self, ui: &mut egui::Ui) {
+        if self.steam_users.is_empty() {
+            return;
+        }
+        ui.heading("Steam users");
+        ui.label("Choose which Steam users get the imported games, and which platforms and games each of them gets");
+        let platforms: Vec<(String, String)> = self
+            .platforms
+            .iter()
+            .map(|platform| (platform.name().to_string(), platform.code_name().to_string()))
+            .collect();
+        let games: Vec<(u32, String)> = get_all_games(&self.games_to_sync)
+            .iter()
+            .flat_map(|(_, infos)| infos.iter())
+            .map(|info| (info.shortcut.app_id, info.shortcut.app_name.clone()))
+            .collect();
+        for user in &self.steam_users {
+            let mut user_settings = self.settings.steam.user_settings(&user.user_id);
+            ui.push_id(&user.user_id, |ui| {
+                ui.group(|ui| {
+                    render_user(ui, user, &mut user_settings, &platforms, &games);
+                });
+            });
+            if user_settings == SteamUserSettings::default() {
+                self.settings.steam.users.remove(&user.user_id);
+            } else {
+                self.settings
+                    .steam
+                    .users
+                    .insert(user.user_id.clone(), user_settings);
+            }
+        }
+        ui.add_space(SECTION_SPACING);
+    }
+}
+
+fn render_user(
+    ui: &mut egui::Ui,
+    user: &SteamUsersInfo,
+    user_settings: &mut SteamUserSettings,
+    platforms: &[(String, String)],
+    games: &[(u32, String)],
+) {
+    ui.checkbox(
+        &mut user_settings.enabled,
+        format!("Import games for {}", user.display_name()),
+    );
+    if !user_settings.enabled {
+        return;
+    }
+    ui.collapsing("Platforms and games", |ui| {
+        ui.horizontal_wrapped(|ui| {
+            for (name, code_name) in platforms {
+                let mut enabled = user_settings.imports_platform(name, code_name);
+                if ui.checkbox(&mut enabled, name).changed() {
+                    if enabled {
+                        user_settings.disabled_platforms.retain(|disabled| {
+                            !disabled.eq_ignore_ascii_case(name)
+                                && !disabled.eq_ignore_ascii_case(code_name)
+                        });
+                    } else {
+                        user_settings.disabled_platforms.push(code_name.clone());
+                    }
+                }
+            }
+        });
+        let mut removed = None;
+        for app_id in &user_settings.blacklisted_games {
+            let name = games
+                .iter()
+                .find(|(id, _)| id == app_id)
+                .map_or_else(|| app_id.to_string(), |(_, name)| name.clone());
+            ui.horizontal(|ui| {
+                ui.label(format!("Not imported: {name}"));
+                if ui.button("Import again").clicked() {
+                    removed = Some(*app_id);
+                }
+            });
+        }
+        if let Some(app_id) = removed {
+            user_settings.blacklisted_games.retain(|id| *id != app_id);
+        }
+        let mut excluded = None;
+        egui::ComboBox::from_id_salt("exclude_game")
+            .selected_text("Do not import a game for this user")
+            .show_ui(ui, |ui| {
+                for (app_id, name) in games {
+                    if !user_settings.blacklisted_games.contains(app_id)
+                        && ui.selectable_label(false, name).clicked()
+                    {
+                        excluded = Some(*app_id);
+                    }
+                }
+            });
+        if let Some(app_id) = excluded {
+            user_settings.blacklisted_games.push(app_id);
+        }
+    });
+}
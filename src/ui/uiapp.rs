@@ -1,4 +1,4 @@
-use std::{collections::HashMap, error::Error, time::Duration};
+use std::collections::HashMap;
 
 use eframe::{egui, App, Frame};
 use egui::{
@@ -10,14 +10,14 @@ use tokio::{
 };
 use rfd::FileDialog;
 
-// === ADDED: Scan Games Folder UI imports ===
-// use crate::ui::images::page_scan_folder::{ScanFolderState, render_scan_games_folder};
-
 use crate::{
-    config::get_renames_file,
     platforms::{get_platforms, GamesPlatform, Platforms, ShortcutToImport},
-    settings::{save_settings, Settings},
-    sync::{self, SyncProgress},
+    settings::{get_rename_map, save_settings, Settings},
+    steam::{get_compat_tools, get_shortcuts_paths, CompatTool, SteamUsersInfo},
+    sync::{
+        self, CollectionTags, DuplicateDecisions, DuplicateShortcut, GameOverrides, SyncPreview,
+        SyncProgress,
+    },
 };
 
 use super::{
@@ -28,7 +28,7 @@ use super::{
     },
     ui_images::get_logo_icon,
     ui_import_games::FetchStatus,
-    BackupState, DisconnectState,
+    BackupState, DisconnectState, OverrideEdit, PendingCollectionsStatus, TagEdit,
 };
 
 const SECTION_SPACING: f32 = 25.0;
@@ -55,6 +55,15 @@ pub(crate) fn get_all_games(games: &GamesToSync) -> Vec<(String, Vec<ShortcutToI
         .collect()
 }
 
+/// The names of the platforms that could not find their games
+pub(crate) fn get_failed_platforms(games: &GamesToSync) -> Vec<String> {
+    games
+        .iter()
+        .filter(|(_name, rx)| matches!(&*rx.borrow(), FetchStatus::Fetched(Err(_))))
+        .map(|(name, _rx)| name.to_owned())
+        .collect()
+}
+
 pub struct MyEguiApp {
     selected_menu: Menues,
     pub(crate) settings: Settings,
@@ -66,7 +75,19 @@ pub struct MyEguiApp {
     pub(crate) disconnect_state: DisconnectState,
     pub(crate) rename_map: HashMap<u32, String>,
     pub(crate) current_edit: Option<u32>,
+    pub(crate) overrides: GameOverrides,
+    pub(crate) override_edit: Option<OverrideEdit>,
+    pub(crate) collection_tags: CollectionTags,
+    pub(crate) tag_edit: Option<TagEdit>,
+    pub(crate) compat_tools: Vec<CompatTool>,
+    pub(crate) steam_users: Vec<SteamUsersInfo>,
     pub(crate) platforms: Platforms,
+    pub(crate) preview: Receiver<FetchStatus<eyre::Result<SyncPreview>>>,
+    pub(crate) pending_collections: Receiver<PendingCollectionsStatus>,
+    pub(crate) duplicates: Receiver<FetchStatus<eyre::Result<Vec<DuplicateShortcut>>>>,
+    pub(crate) duplicate_decisions: DuplicateDecisions,
+    /// The installed Steam games for the collections preview, read the first time they are needed
+    pub(crate) steam_collection_games: Option<Vec<sync::CollectionGame>>,
 }
 
 impl MyEguiApp {
@@ -75,7 +96,9 @@ impl MyEguiApp {
         let settings = Settings::new()?;
         let platforms = get_platforms();
         let games_to_sync = create_games_to_sync(&mut runtime, &platforms);
-        Ok(Self {
+        let compat_tools = get_compat_tools(&settings.steam);
+        let steam_users = get_shortcuts_paths(&settings.steam).unwrap_or_default();
+        let mut app = Self {
             selected_menu: Menues::Import,
             settings,
             rt: runtime,
@@ -86,11 +109,35 @@ impl MyEguiApp {
             disconnect_state: DisconnectState::default(),
             rename_map: get_rename_map(),
             current_edit: Option::None,
+            overrides: GameOverrides::load(),
+            override_edit: Option::None,
+            collection_tags: CollectionTags::load(),
+            tag_edit: Option::None,
+            compat_tools,
+            steam_users,
+            preview: watch::channel(FetchStatus::NeedsFetched).1,
             platforms,
-        })
+            pending_collections: watch::channel(PendingCollectionsStatus::Nothing).1,
+            duplicates: watch::channel(FetchStatus::NeedsFetched).1,
+            duplicate_decisions: DuplicateDecisions::load(),
+            steam_collection_games: None,
+        };
+        app.start_collections_watcher();
+        Ok(app)
     }
 
     fn render_import_button(&mut self, ui: &mut egui::Ui) {
+        //The import can move renames and blacklisted games to new app ids
+        let sync_finished = self.status_reciever.has_changed().unwrap_or(false)
+            && matches!(*self.status_reciever.borrow_and_update(), SyncProgress::Done);
+        if sync_finished {
+            if let Ok(settings) = Settings::new() {
+                self.settings.blacklisted_games = settings.blacklisted_games;
+                self.settings.steam.users = settings.steam.users;
+            }
+            self.rename_map = get_rename_map();
+            self.start_collections_watcher();
+        }
         let (status_string, syncing) = match &*self.status_reciever.borrow() {
             SyncProgress::NotStarted => ("".to_string(), false),
             SyncProgress::Starting => ("Starting Import".to_string(), true),
@@ -102,6 +149,7 @@ impl MyEguiApp {
                 (format!("Downloading {to_download} images "), true)
             }
             SyncProgress::Done => ("Done importing games".to_string(), false),
+            SyncProgress::Failed { error } => (format!("Import failed: {error}"), false),
         };
         if syncing {
             ui.ctx().request_repaint();
@@ -137,6 +185,7 @@ impl MyEguiApp {
         }
     }
 
+    #[allow(dead_code)]
     fn render_scan_folder(&mut self, ui: &mut egui::Ui) {
         ui.heading("Scan Games Folder");
         
@@ -203,17 +252,6 @@ impl MyEguiApp {
     }
 }
 
-fn get_rename_map() -> HashMap<u32, String> {
-    try_get_rename_map().unwrap_or_default()
-}
-
-fn try_get_rename_map() -> Result<HashMap<u32, String>, Box<dyn Error>> {
-    let rename_map = get_renames_file();
-    let file_content = std::fs::read_to_string(rename_map)?;
-    let deserialized = serde_json::from_str(&file_content)?;
-    Ok(deserialized)
-}
-
 // === CHANGED: Menues enum, remove ScanFolder ===
 #[derive(PartialEq, Clone, Default)]
 enum Menues {
@@ -380,15 +418,6 @@ fn setup(ctx: &egui::Context) {
     ctx.set_style(style);
     egui_extras::install_image_loaders(ctx);
 }
-pub fn run_sync() -> eyre::Result<()> {
-    let mut app = MyEguiApp::new()?;
-    while !all_ready(&app.games_to_sync) {
-        println!("Finding games, trying again in 500ms");
-        std::thread::sleep(Duration::from_secs_f32(0.5));
-    }
-    app.run_sync_blocking()
-}
-
 pub fn run_ui(args: Vec<String>) -> eyre::Result<()> {
     let app = MyEguiApp::new()?;
     let no_v_sync = args.contains(&"--no-vsync".to_string());
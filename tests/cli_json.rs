@@ -0,0 +1,35 @@
+//Okay to unwrap in tests
+#![allow(clippy::unwrap_used)]
+#![allow(clippy::indexing_slicing)]
+
+use std::process::Command;
+
+#[cfg(target_family = "unix")]
+#[test]
+fn sync_json_prints_only_the_report_on_stdout() {
+    let home = tempfile::tempdir().unwrap();
+    let steam = home.path().join("steam");
+    std::fs::create_dir_all(steam.join("userdata/12345678/config")).unwrap();
+    let config_folder = home.path().join(".config/boilr");
+    std::fs::create_dir_all(&config_folder).unwrap();
+    std::fs::write(
+        config_folder.join("config.toml"),
+        format!("[steam]\nlocation = {:?}\n", steam.to_string_lossy()),
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_boilr"))
+        .args(["sync", "--json"])
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", home.path().join(".config"))
+        .env("XDG_DATA_HOME", home.path().join(".local/share"))
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let report: serde_json::Value = serde_json::from_str(&stdout)
+        .unwrap_or_else(|err| panic!("stdout is not JSON ({err}): {stdout}"));
+    assert!(report.is_object());
+    assert!(stderr.contains("Found 1 user(s)"), "{stderr}");
+}